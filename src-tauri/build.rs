@@ -1,3 +1,49 @@
+// Regenerates the TypeScript bindings for the IPC models (`src/bindings/`) on
+// every build, so the frontend always type-checks against the current Rust
+// types.
+use std::path::Path;
+
+use ts_rs::TS;
+
+#[allow(dead_code)]
+#[path = "src/models.rs"]
+mod models;
+
+const BINDINGS_DIR: &str = "../src/bindings";
+
 fn main() {
+    println!("cargo:rerun-if-changed=src/models.rs");
+    export_bindings(Path::new(BINDINGS_DIR)).expect("failed to export TypeScript bindings");
     tauri_build::build()
 }
+
+fn export_bindings(dir: &Path) -> Result<(), ts_rs::ExportError> {
+    models::User::export_all_to(dir)?;
+    models::Chat::export_all_to(dir)?;
+    models::Message::export_all_to(dir)?;
+    models::IncomingMessage::export_all_to(dir)?;
+    models::AttachmentProgress::export_all_to(dir)?;
+    models::ImageOptions::export_all_to(dir)?;
+    models::ChosenFile::export_all_to(dir)?;
+    models::PreparedImage::export_all_to(dir)?;
+    models::MediaCacheUsage::export_all_to(dir)?;
+    models::OutboxEntry::export_all_to(dir)?;
+    models::OutboxStateEvent::export_all_to(dir)?;
+    models::LiveUpdate::<()>::export_all_to(dir)?;
+    models::SearchHit::export_all_to(dir)?;
+    models::UnreadSummary::export_all_to(dir)?;
+    models::ExportFormat::export_all_to(dir)?;
+    models::ExportSummary::export_all_to(dir)?;
+    models::ImportSource::export_all_to(dir)?;
+    models::ImportReport::export_all_to(dir)?;
+    models::LogEntry::export_all_to(dir)?;
+    models::LogFilter::export_all_to(dir)?;
+    models::ConnectionSource::export_all_to(dir)?;
+    models::Settings::export_all_to(dir)?;
+    models::ProfileInfo::export_all_to(dir)?;
+    models::DeepLink::export_all_to(dir)?;
+    models::PresenceNotification::export_all_to(dir)?;
+    models::NotificationOutcome::export_all_to(dir)?;
+    models::CommandError::export_all_to(dir)?;
+    Ok(())
+}
@@ -0,0 +1,90 @@
+// Local filesystem backend
+//
+// Keeps attachments as plain files under a root directory, mirroring their
+// storage paths. Used in place of Firebase Storage when
+// `CHITCHAT_ATTACHMENTS_DIR` is set, for development and testing.
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use super::{BlobBackend, BoxFuture};
+use crate::error::{Error, Result};
+
+pub struct LocalBackend {
+    root: PathBuf,
+}
+
+impl LocalBackend {
+    pub fn new(root: PathBuf) -> Self {
+        LocalBackend { root }
+    }
+
+    fn file(&self, path: &str) -> PathBuf {
+        self.root.join(path)
+    }
+
+    /// Where an upload collects its chunks until the last one arrives.
+    fn partial(&self, path: &str) -> PathBuf {
+        self.root.join(format!("{path}.part"))
+    }
+}
+
+impl BlobBackend for LocalBackend {
+    fn start_upload<'a>(
+        &'a self,
+        path: &'a str,
+        _size: u64,
+        _mime_type: &'a str,
+    ) -> BoxFuture<'a, Result<String>> {
+        Box::pin(async move {
+            let partial = self.partial(path);
+            if let Some(dir) = partial.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            File::create(partial)?;
+            Ok(path.to_string())
+        })
+    }
+
+    fn upload_chunk<'a>(
+        &'a self,
+        upload: &'a str,
+        offset: u64,
+        chunk: Vec<u8>,
+        last: bool,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let partial = self.partial(upload);
+            let mut file = OpenOptions::new().append(true).open(&partial)?;
+            if file.metadata()?.len() != offset {
+                return Err(Error::Storage(format!(
+                    "chunk at {offset} of {upload} is out of order"
+                )));
+            }
+            file.write_all(&chunk)?;
+            if last {
+                file.sync_all()?;
+                fs::rename(&partial, self.file(upload))?;
+            }
+            Ok(())
+        })
+    }
+
+    fn read_range<'a>(
+        &'a self,
+        path: &'a str,
+        offset: u64,
+        len: u64,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        Box::pin(async move {
+            let mut file = File::open(self.file(path)).map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => Error::Storage(format!("{path} does not exist")),
+                _ => e.into(),
+            })?;
+            file.seek(SeekFrom::Start(offset))?;
+            let mut bytes = Vec::new();
+            file.take(len).read_to_end(&mut bytes)?;
+            Ok(bytes)
+        })
+    }
+}
@@ -0,0 +1,605 @@
+// File attachments
+//
+// Every file is encrypted with its own random key (ChaCha20) and uploaded in
+// fixed-size chunks to a `BlobBackend`, under a random name in the chat's
+// storage folder. The key, the file's SHA-256 and the rest of its metadata
+// travel inside the end-to-end encrypted message, so neither the backend nor
+// RTDB sees anything but ciphertext. The stream cipher keeps sizes and
+// offsets as they are, so downloads can fetch and decrypt any range: they
+// collect their chunks in the media cache's partial folder and pick up where
+// they left off after an interruption. The file only joins the cache once its
+// size and hash match the attachment's, which also authenticates the
+// decrypted contents. Both directions report progress through
+// `attachment-progress` events.
+//
+// The webview cannot name files to upload: it gets a one-time token for each
+// file the user picks or drops, and only those can be attached.
+mod firebase;
+mod local;
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::future::Future;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use chacha20::ChaCha20;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, DragDropEvent, Emitter, Manager, Runtime, Window, WindowEvent};
+
+use crate::error::{Error, Result};
+use crate::hex;
+use crate::media_cache::{self, MediaCache};
+use crate::models::{Attachment, AttachmentProgress, ChosenFile, TransferDirection};
+use crate::push_id;
+use crate::session::Credentials;
+
+use self::firebase::FirebaseStorage;
+use self::local::LocalBackend;
+
+/// Event emitted after every transferred chunk.
+pub const PROGRESS_EVENT: &str = "attachment-progress";
+
+/// Event sent to a window when a file is dropped on it, with the file as a
+/// [`ChosenFile`].
+pub const DROPPED_EVENT: &str = "attachment-dropped";
+
+/// Largest file that can be attached.
+pub const MAX_SIZE: u64 = 100 * 1024 * 1024;
+
+/// Bytes per upload or download request. Resumable uploads to Cloud Storage
+/// need a multiple of 256 KiB.
+const CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Set to a directory to keep attachments there instead of in Firebase
+/// Storage, e.g. for development and testing without a bucket.
+const LOCAL_BACKEND_ENV: &str = "CHITCHAT_ATTACHMENTS_DIR";
+
+/// Content type of every stored file, which is ciphertext whatever it was.
+const STORED_MIME_TYPE: &str = "application/octet-stream";
+
+type FileKey = [u8; 32];
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Somewhere attachment contents can be kept.
+pub trait BlobBackend: Send + Sync {
+    /// Starts uploading `size` bytes to `path`. Returns a handle identifying
+    /// the upload for [`upload_chunk`](Self::upload_chunk).
+    fn start_upload<'a>(
+        &'a self,
+        path: &'a str,
+        size: u64,
+        mime_type: &'a str,
+    ) -> BoxFuture<'a, Result<String>>;
+
+    /// Sends the next chunk, which starts at `offset`. The upload is complete
+    /// once the `last` chunk is through.
+    fn upload_chunk<'a>(
+        &'a self,
+        upload: &'a str,
+        offset: u64,
+        chunk: Vec<u8>,
+        last: bool,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// Reads up to `len` bytes of the file at `path`, starting at `offset`.
+    fn read_range<'a>(
+        &'a self,
+        path: &'a str,
+        offset: u64,
+        len: u64,
+    ) -> BoxFuture<'a, Result<Vec<u8>>>;
+}
+
+#[derive(Default)]
+pub struct Attachments {
+    http: reqwest::Client,
+    /// Files the user picked or dropped, by token.
+    chosen: Mutex<HashMap<String, PathBuf>>,
+    /// Held while a file downloads, so two requests never write the same
+    /// partial file. Keyed by hash.
+    downloads: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl Attachments {
+    /// Records a file the user picked or dropped, returning the token the
+    /// webview may attach it by.
+    pub fn choose(&self, path: PathBuf) -> ChosenFile {
+        let name = file_name(&path);
+        let token = hex::encode(&rand::random::<[u8; 16]>());
+        lock(&self.chosen).insert(token.clone(), path);
+        ChosenFile { token, name }
+    }
+
+    /// The file recorded under `token`. The token cannot be used again.
+    pub fn take(&self, token: &str) -> Result<PathBuf> {
+        lock(&self.chosen)
+            .remove(token)
+            .ok_or_else(|| Error::InvalidAttachment("no such file was chosen".into()))
+    }
+
+    /// The file recorded under `token`, which stays valid.
+    pub fn peek(&self, token: &str) -> Result<PathBuf> {
+        lock(&self.chosen)
+            .get(token)
+            .cloned()
+            .ok_or_else(|| Error::InvalidAttachment("no such file was chosen".into()))
+    }
+
+    fn download_lock(&self, sha256: &str) -> Arc<tokio::sync::Mutex<()>> {
+        let mut downloads = lock(&self.downloads);
+        // Forget the locks nobody holds or waits for
+        downloads.retain(|_, lock| Arc::strong_count(lock) > 1);
+        downloads.entry(sha256.into()).or_default().clone()
+    }
+
+    fn backend(&self, credentials: &Credentials) -> Result<Box<dyn BlobBackend>> {
+        if let Some(root) = std::env::var_os(LOCAL_BACKEND_ENV) {
+            return Ok(Box::new(LocalBackend::new(PathBuf::from(root))));
+        }
+        let bucket = credentials
+            .storage_bucket
+            .clone()
+            .filter(|bucket| !bucket.is_empty())
+            .ok_or_else(|| Error::Storage("no storage bucket configured".into()))?;
+        Ok(Box::new(FirebaseStorage::new(
+            self.http.clone(),
+            bucket,
+            credentials.id_token.clone(),
+        )))
+    }
+}
+
+/// Offers the first file dropped on a window for attaching.
+pub fn on_window_event<R: Runtime>(window: &Window<R>, event: &WindowEvent) {
+    let WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) = event else {
+        return;
+    };
+    let (Some(path), Some(attachments)) = (paths.first(), window.try_state::<Attachments>()) else {
+        return;
+    };
+    let file = attachments.choose(path.clone());
+    if let Err(e) = window.emit_to(window.label(), DROPPED_EVENT, file) {
+        tracing::warn!("failed to offer dropped file: {e}");
+    }
+}
+
+/// Encrypts and uploads the file at `path` for sending in a chat.
+pub async fn upload(
+    app: &AppHandle,
+    credentials: &Credentials,
+    chat_id: &str,
+    path: &Path,
+) -> Result<Attachment> {
+    if !push_id::is_valid_key(chat_id) {
+        return Err(Error::InvalidChatId(chat_id.into()));
+    }
+    let name = file_name(path);
+    let size = fs::metadata(path)?.len();
+    if size == 0 || size > MAX_SIZE {
+        return Err(Error::InvalidAttachment(format!(
+            "{name} is empty or larger than {} MiB",
+            MAX_SIZE / 1024 / 1024
+        )));
+    }
+
+    let hashed = path.to_path_buf();
+    let sha256 =
+        tauri::async_runtime::spawn_blocking(move || media_cache::hash_file(&hashed)).await??;
+    let key: FileKey = rand::random();
+    let attachment = Attachment {
+        mime_type: mime_type(path).into(),
+        storage_path: format!(
+            "attachments/{chat_id}/{}",
+            hex::encode(&rand::random::<[u8; 32]>())
+        ),
+        name,
+        size,
+        sha256,
+        key: BASE64.encode(key),
+        image: None,
+    };
+
+    let backend = app.state::<Attachments>().backend(credentials)?;
+    send(&*backend, path, &attachment, &key, |sent| {
+        emit_progress(app, &attachment, TransferDirection::Upload, sent)
+    })
+    .await?;
+    Ok(attachment)
+}
+
+/// Encrypts the file at `path` with `key` and uploads it as `attachment`.
+/// Leaves the upload unfinished if the file no longer matches the hash.
+async fn send(
+    backend: &dyn BlobBackend,
+    path: &Path,
+    attachment: &Attachment,
+    key: &FileKey,
+    mut progress: impl FnMut(u64),
+) -> Result<()> {
+    let size = attachment.size;
+    let upload = backend
+        .start_upload(&attachment.storage_path, size, STORED_MIME_TYPE)
+        .await?;
+    let mut file = File::open(path)?;
+    let mut cipher = cipher(key);
+    let mut hasher = Sha256::new();
+    let mut offset = 0;
+    while offset < size {
+        let mut chunk = vec![0; CHUNK_SIZE.min(size - offset) as usize];
+        file.read_exact(&mut chunk)?;
+        hasher.update(&chunk);
+        let last = offset + chunk.len() as u64 == size;
+        if last && hex::encode(&hasher.clone().finalize()) != attachment.sha256 {
+            return Err(Error::InvalidAttachment(format!(
+                "{} changed while uploading",
+                attachment.name
+            )));
+        }
+        cipher.apply_keystream(&mut chunk);
+        let len = chunk.len() as u64;
+        backend.upload_chunk(&upload, offset, chunk, last).await?;
+        offset += len;
+        progress(offset);
+    }
+    Ok(())
+}
+
+/// Downloads an attachment into the media cache, resuming an earlier
+/// partial download, and returns where it is. Fails without keeping anything
+/// if the result does not match the attachment's size and hash.
+pub async fn download(
+    app: &AppHandle,
+    credentials: &Credentials,
+    attachment: &Attachment,
+) -> Result<PathBuf> {
+    validate(attachment)?;
+    let state = app.state::<Attachments>();
+    let lock = state.download_lock(&attachment.sha256);
+    let _guard = lock.lock().await;
+
+    let sha256 = attachment.sha256.clone();
+    let cache = app.clone();
+    let cached =
+        tauri::async_runtime::spawn_blocking(move || cache.state::<MediaCache>().get(&sha256))
+            .await??;
+    if let Some(path) = cached {
+        return Ok(path);
+    }
+
+    let partial = app.state::<MediaCache>().partial_path(&attachment.sha256)?;
+    let backend = state.backend(credentials)?;
+    let fetched = fetch(&*backend, attachment, &partial, |received| {
+        emit_progress(app, attachment, TransferDirection::Download, received)
+    })
+    .await?;
+    if fetched != attachment.size {
+        let _ = fs::remove_file(&partial);
+        return Err(Error::Integrity(attachment.name.clone()));
+    }
+    // Checks the hash, which also covers what an earlier attempt fetched
+    let sha256 = attachment.sha256.clone();
+    let cache = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        cache.state::<MediaCache>().insert_file(&sha256, &partial)
+    })
+    .await?
+    .map_err(|e| match e {
+        Error::Integrity(_) => Error::Integrity(attachment.name.clone()),
+        e => e,
+    })
+}
+
+/// Downloads and decrypts the rest of `attachment` into `partial`, after
+/// whatever an earlier attempt left there. Returns how many bytes `partial`
+/// holds afterwards.
+async fn fetch(
+    backend: &dyn BlobBackend,
+    attachment: &Attachment,
+    partial: &Path,
+    mut progress: impl FnMut(u64),
+) -> Result<u64> {
+    let mut cipher = cipher(&decode_key(&attachment.key)?);
+    let mut file = OpenOptions::new().create(true).append(true).open(partial)?;
+    let mut offset = file.metadata()?.len();
+    if offset > attachment.size {
+        file.set_len(0)?;
+        offset = 0;
+    }
+
+    while offset < attachment.size {
+        let len = CHUNK_SIZE.min(attachment.size - offset);
+        let mut chunk = backend
+            .read_range(&attachment.storage_path, offset, len)
+            .await?;
+        if chunk.is_empty() || chunk.len() as u64 > len {
+            break;
+        }
+        cipher.seek(offset);
+        cipher.apply_keystream(&mut chunk);
+        file.write_all(&chunk)?;
+        offset += chunk.len() as u64;
+        progress(offset);
+    }
+    file.sync_all()?;
+    Ok(offset)
+}
+
+/// ChaCha20 keystream for a file. Every key encrypts a single file, so the
+/// nonce can stay zero.
+fn cipher(key: &FileKey) -> ChaCha20 {
+    ChaCha20::new(key.into(), &[0; 12].into())
+}
+
+fn decode_key(key: &str) -> Result<FileKey> {
+    BASE64
+        .decode(key)
+        .ok()
+        .and_then(|key| key.try_into().ok())
+        .ok_or_else(|| Error::InvalidAttachment("malformed file key".into()))
+}
+
+/// Checks metadata received from other clients before it is used to build
+/// paths.
+fn validate(attachment: &Attachment) -> Result<()> {
+    // Files are named by 64 random hex digits
+    let in_chat_folder = attachment
+        .storage_path
+        .strip_prefix("attachments/")
+        .and_then(|rest| rest.split_once('/'))
+        .is_some_and(|(chat, object)| {
+            push_id::is_valid_key(chat) && media_cache::is_sha256(object)
+        });
+    if media_cache::is_sha256(&attachment.sha256)
+        && in_chat_folder
+        && decode_key(&attachment.key).is_ok()
+        && attachment.size <= MAX_SIZE
+    {
+        Ok(())
+    } else {
+        Err(Error::InvalidAttachment(attachment.name.clone()))
+    }
+}
+
+fn emit_progress(
+    app: &AppHandle,
+    attachment: &Attachment,
+    direction: TransferDirection,
+    transferred: u64,
+) {
+    let event = AttachmentProgress {
+        sha256: attachment.sha256.clone(),
+        direction,
+        transferred,
+        total: attachment.size,
+    };
+    if let Err(e) = app.emit(PROGRESS_EVENT, event) {
+        tracing::warn!("failed to emit progress event: {e}");
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("file")
+        .to_string()
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Content type for a file name, from its extension. Only images that
+/// `images` can decode are labelled as images; others, such as HEIC, are
+/// sent as plain files.
+fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("pdf") => "application/pdf",
+        Some("txt" | "log") => "text/plain",
+        Some("md") => "text/markdown",
+        Some("csv") => "text/csv",
+        Some("json") => "application/json",
+        Some("zip") => "application/zip",
+        Some("mp3") => "audio/mpeg",
+        Some("ogg" | "opus") => "audio/ogg",
+        Some("wav") => "audio/wav",
+        Some("m4a") => "audio/mp4",
+        Some("mp4") => "video/mp4",
+        Some("mov") => "video/quicktime",
+        Some("webm") => "video/webm",
+        Some("doc") => "application/msword",
+        Some("docx") => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        Some("xls") => "application/vnd.ms-excel",
+        Some("xlsx") => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        Some("ppt") => "application/vnd.ms-powerpoint",
+        Some("pptx") => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    /// A file a bit over two chunks long, and its attachment.
+    fn sample(dir: &Path, key: &FileKey) -> (PathBuf, Vec<u8>, Attachment) {
+        let contents: Vec<u8> = (0..2 * CHUNK_SIZE + 1000)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let path = dir.join("notes.txt");
+        fs::write(&path, &contents).unwrap();
+        let attachment = Attachment {
+            name: "notes.txt".into(),
+            mime_type: "text/plain".into(),
+            size: contents.len() as u64,
+            sha256: media_cache::hash_file(&path).unwrap(),
+            storage_path: format!("attachments/-chat/{}", "0f".repeat(32)),
+            key: BASE64.encode(key),
+            image: None,
+        };
+        (path, contents, attachment)
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tauri::async_runtime::block_on(future)
+    }
+
+    #[test]
+    fn files_are_stored_encrypted_and_come_back_intact() {
+        let dir = TempDir::new();
+        let backend = LocalBackend::new(dir.join("bucket"));
+        let key: FileKey = rand::random();
+        let (path, contents, attachment) = sample(&dir, &key);
+
+        let mut sent = Vec::new();
+        block_on(send(&backend, &path, &attachment, &key, |n| sent.push(n))).unwrap();
+        assert_eq!(
+            sent,
+            [CHUNK_SIZE, 2 * CHUNK_SIZE, attachment.size],
+            "one progress report per chunk"
+        );
+        let stored = fs::read(dir.join("bucket").join(&attachment.storage_path)).unwrap();
+        assert_eq!(stored.len(), contents.len());
+        // About one byte in 256 matches by chance
+        let same = stored.iter().zip(&contents).filter(|(a, b)| a == b).count();
+        assert!(same < contents.len() / 100);
+
+        let partial = dir.join("download");
+        let fetched = block_on(fetch(&backend, &attachment, &partial, |_| {})).unwrap();
+        assert_eq!(fetched, attachment.size);
+        assert_eq!(fs::read(&partial).unwrap(), contents);
+    }
+
+    #[test]
+    fn downloads_resume_where_they_stopped() {
+        let dir = TempDir::new();
+        let backend = LocalBackend::new(dir.join("bucket"));
+        let key: FileKey = rand::random();
+        let (path, contents, attachment) = sample(&dir, &key);
+        block_on(send(&backend, &path, &attachment, &key, |_| {})).unwrap();
+
+        // Stopped mid-chunk
+        let partial = dir.join("download");
+        let stopped_at = CHUNK_SIZE as usize + 123;
+        fs::write(&partial, &contents[..stopped_at]).unwrap();
+        let mut received = Vec::new();
+        block_on(fetch(&backend, &attachment, &partial, |n| received.push(n))).unwrap();
+        assert_eq!(received.first(), Some(&(2 * CHUNK_SIZE + 123)));
+        assert_eq!(fs::read(&partial).unwrap(), contents);
+
+        // Longer than the file can be; starts over
+        fs::write(&partial, vec![0; contents.len() + 1]).unwrap();
+        block_on(fetch(&backend, &attachment, &partial, |_| {})).unwrap();
+        assert_eq!(fs::read(&partial).unwrap(), contents);
+    }
+
+    #[test]
+    fn another_key_does_not_reveal_the_file() {
+        let dir = TempDir::new();
+        let backend = LocalBackend::new(dir.join("bucket"));
+        let key: FileKey = rand::random();
+        let (path, contents, attachment) = sample(&dir, &key);
+        block_on(send(&backend, &path, &attachment, &key, |_| {})).unwrap();
+
+        let wrong = Attachment {
+            key: BASE64.encode(rand::random::<FileKey>()),
+            ..attachment
+        };
+        let partial = dir.join("download");
+        block_on(fetch(&backend, &wrong, &partial, |_| {})).unwrap();
+        // The media cache refuses it, as the hash does not match
+        assert_ne!(media_cache::hash_file(&partial).unwrap(), wrong.sha256);
+        assert_ne!(fs::read(&partial).unwrap(), contents);
+    }
+
+    #[test]
+    fn changed_files_are_not_stored() {
+        let dir = TempDir::new();
+        let backend = LocalBackend::new(dir.join("bucket"));
+        let key: FileKey = rand::random();
+        let (path, _, attachment) = sample(&dir, &key);
+        let stale = Attachment {
+            sha256: "00".repeat(32),
+            ..attachment
+        };
+
+        let result = block_on(send(&backend, &path, &stale, &key, |_| {}));
+        assert!(matches!(result, Err(Error::InvalidAttachment(_))));
+        assert!(!dir.join("bucket").join(&stale.storage_path).exists());
+    }
+
+    #[test]
+    fn metadata_from_other_clients_is_checked() {
+        let (_, _, attachment) = sample(&TempDir::new(), &rand::random());
+        assert!(validate(&attachment).is_ok());
+
+        for storage_path in [
+            format!("attachments/../{}", "0f".repeat(32)),
+            format!("attachments/-chat/{}/x", "0f".repeat(32)),
+            "attachments/-chat/notes.txt".to_string(),
+            format!("avatars/-chat/{}", "0f".repeat(32)),
+        ] {
+            let moved = Attachment {
+                storage_path: storage_path.clone(),
+                ..attachment.clone()
+            };
+            assert!(validate(&moved).is_err(), "{storage_path}");
+        }
+        let short_key = Attachment {
+            key: BASE64.encode([0; 16]),
+            ..attachment.clone()
+        };
+        assert!(validate(&short_key).is_err());
+        let huge = Attachment {
+            size: MAX_SIZE + 1,
+            ..attachment
+        };
+        assert!(validate(&huge).is_err());
+    }
+
+    #[test]
+    fn chosen_files_are_attached_once() {
+        let attachments = Attachments::default();
+        let chosen = attachments.choose(PathBuf::from("/photos/cat.jpg"));
+        assert_eq!(chosen.name, "cat.jpg");
+        assert_eq!(
+            attachments.peek(&chosen.token).unwrap(),
+            Path::new("/photos/cat.jpg")
+        );
+        assert_eq!(
+            attachments.take(&chosen.token).unwrap(),
+            Path::new("/photos/cat.jpg")
+        );
+        assert!(attachments.take(&chosen.token).is_err());
+        assert!(attachments.peek("/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn downloads_lock_per_file() {
+        let attachments = Attachments::default();
+        let a = attachments.download_lock("a");
+        let _held = a.try_lock().unwrap();
+        assert!(attachments.download_lock("a").try_lock().is_err());
+        assert!(attachments.download_lock("b").try_lock().is_ok());
+        // Only locks still in use are kept
+        let _c = attachments.download_lock("c");
+        let mut kept: Vec<String> = lock(&attachments.downloads).keys().cloned().collect();
+        kept.sort();
+        assert_eq!(kept, ["a", "c"]);
+    }
+}
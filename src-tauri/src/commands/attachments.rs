@@ -0,0 +1,97 @@
+// Commands for sending and saving file attachments
+use std::fs;
+
+use tauri::{AppHandle, State};
+use tauri_plugin_dialog::DialogExt;
+use tokio::sync::oneshot;
+
+use crate::attachments::{self, Attachments};
+use crate::error::{Error, Result};
+use crate::models::{Attachment, ChosenFile};
+use crate::session::{Credentials, Session};
+
+/// Asks for a file to attach. Resolves to `null` if the user cancels the
+/// dialog.
+#[tauri::command]
+pub async fn choose_attachment(
+    app: AppHandle,
+    attachments: State<'_, Attachments>,
+) -> Result<Option<ChosenFile>> {
+    let (picked, path) = oneshot::channel();
+    app.dialog()
+        .file()
+        .set_title("Attach a file")
+        .pick_file(move |path| {
+            let _ = picked.send(path);
+        });
+    let Some(path) = path.await.ok().flatten() else {
+        return Ok(None);
+    };
+    let path = path
+        .into_path()
+        .map_err(|e| Error::UnsupportedPath(e.to_string()))?;
+    Ok(Some(attachments.choose(path)))
+}
+
+/// Encrypts and uploads a chosen file for sending in a chat. Pass the result
+/// to `enqueue_message` to send it.
+#[tauri::command]
+pub async fn upload_attachment(
+    app: AppHandle,
+    session: State<'_, Session>,
+    attachments: State<'_, Attachments>,
+    chat_id: String,
+    token: String,
+) -> Result<Attachment> {
+    let credentials = signed_in(&session)?;
+    let path = attachments.take(&token)?;
+    attachments::upload(&app, &credentials, &chat_id, &path).await
+}
+
+/// Downloads an attachment and asks where to save it. Resolves to `null` if
+/// the user cancels the dialog.
+#[tauri::command]
+pub async fn save_attachment(
+    app: AppHandle,
+    session: State<'_, Session>,
+    attachment: Attachment,
+) -> Result<Option<String>> {
+    let credentials = signed_in(&session)?;
+    let (picked, path) = oneshot::channel();
+    app.dialog()
+        .file()
+        .set_title("Save attachment")
+        .set_file_name(&attachment.name)
+        .save_file(move |path| {
+            let _ = picked.send(path);
+        });
+    let Some(path) = path.await.ok().flatten() else {
+        return Ok(None);
+    };
+    let path = path
+        .into_path()
+        .map_err(|e| Error::UnsupportedPath(e.to_string()))?;
+
+    let cached = attachments::download(&app, &credentials, &attachment).await?;
+    fs::copy(cached, &path)?;
+    Ok(Some(path.display().to_string()))
+}
+
+/// Downloads an attachment into the media cache (if it is not there yet), so
+/// the webview can show it through the `chitchat-media` scheme.
+#[tauri::command]
+pub async fn cache_attachment(
+    app: AppHandle,
+    session: State<'_, Session>,
+    attachment: Attachment,
+) -> Result<()> {
+    let credentials = signed_in(&session)?;
+    attachments::download(&app, &credentials, &attachment).await?;
+    Ok(())
+}
+
+fn signed_in(session: &Session) -> Result<Credentials> {
+    session
+        .credentials()
+        .ok_or_else(|| Error::Storage("not signed in".into()))
+}
@@ -0,0 +1,12 @@
+// Commands for chitchat:// links
+use tauri::State;
+
+use crate::deep_link::DeepLinks;
+use crate::models::DeepLink;
+
+/// Called once the webview listens for `deep-link` events. Returns the links
+/// opened before then, such as the one the app was started for.
+#[tauri::command]
+pub fn take_deep_links(links: State<'_, DeepLinks>) -> Vec<DeepLink> {
+    links.start_listening()
+}
@@ -0,0 +1,51 @@
+// Commands for support diagnostics
+use tauri::{AppHandle, State};
+use tauri_plugin_dialog::DialogExt;
+use tokio::sync::oneshot;
+
+use crate::diagnostics::bundle;
+use crate::diagnostics::connection::ConnectionHistory;
+use crate::error::{Error, Result};
+use crate::models::ConnectionSource;
+
+/// Records a change of the webview's connection state.
+#[tauri::command]
+pub fn record_connection_state(
+    history: State<'_, ConnectionHistory>,
+    source: ConnectionSource,
+    connected: bool,
+) {
+    history.record(source, connected);
+}
+
+/// Asks where to save, then writes a diagnostics bundle there. Resolves to
+/// the path written, or `null` if the user cancels the dialog.
+#[tauri::command]
+pub async fn create_diagnostics_bundle(
+    app: AppHandle,
+    user_agent: String,
+) -> Result<Option<String>> {
+    let (picked, path) = oneshot::channel();
+    app.dialog()
+        .file()
+        .set_title("Save diagnostics")
+        .set_file_name(bundle::FILE_NAME)
+        .add_filter("Zip archive", &["zip"])
+        .save_file(move |path| {
+            let _ = picked.send(path);
+        });
+    let Some(path) = path.await.ok().flatten() else {
+        return Ok(None);
+    };
+    let path = path
+        .into_path()
+        .map_err(|e| Error::UnsupportedPath(e.to_string()))?;
+
+    let updater = bundle::updater_state(&app).await;
+    tauri::async_runtime::spawn_blocking(move || {
+        bundle::create(&app, &path, &user_agent, updater)?;
+        tracing::info!("diagnostics bundle written");
+        Ok(Some(path.display().to_string()))
+    })
+    .await?
+}
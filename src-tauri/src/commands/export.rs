@@ -0,0 +1,103 @@
+// Commands for exporting chat history
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_dialog::DialogExt;
+use tokio::sync::oneshot;
+
+use crate::e2e::E2e;
+use crate::error::{Error, Result};
+use crate::export;
+use crate::models::{ExportFormat, ExportSummary, IncomingMessage};
+use crate::rtdb::Database;
+use crate::session::Session;
+use crate::store::Store;
+
+/// Asks where to save, then exports one chat (or every chat when `chat_id`
+/// is omitted). Resolves to `null` if the user cancels the dialog.
+#[tauri::command]
+pub async fn export_chats(
+    app: AppHandle,
+    store: State<'_, Store>,
+    session: State<'_, Session>,
+    e2e: State<'_, E2e>,
+    chat_id: Option<String>,
+    format: ExportFormat,
+    utc_offset_minutes: i32,
+) -> Result<Option<ExportSummary>> {
+    let credentials = session.credentials();
+    let user_id = credentials.as_ref().map(|c| c.user_id.clone());
+    let mut chats = store.chats()?;
+    if let Some(chat_id) = &chat_id {
+        chats.retain(|chat| &chat.id == chat_id);
+        if chats.is_empty() {
+            return Err(Error::UnknownChat(chat_id.clone()));
+        }
+    }
+
+    let (picked, path) = oneshot::channel();
+    app.dialog()
+        .file()
+        .set_title("Export chat history")
+        .set_file_name(export::file_name(&chats, user_id.as_deref(), format))
+        .add_filter(format.description(), &[format.extension()])
+        .save_file(move |path| {
+            let _ = picked.send(path);
+        });
+    let Some(path) = path.await.ok().flatten() else {
+        return Ok(None);
+    };
+    let path = path
+        .into_path()
+        .map_err(|e| Error::UnsupportedPath(e.to_string()))?;
+
+    // Pick up messages that never reached this device's cache, e.g. in
+    // chats that were not opened since sign-in
+    if let Some(credentials) = &credentials {
+        let db = session.database(&reqwest::Client::new())?;
+        for chat in &chats {
+            if let Err(e) = refresh_history(&store, &e2e, &db, &credentials.user_id, &chat.id).await
+            {
+                tracing::warn!(chat_id = chat.id, "using cached history: {e}");
+            }
+        }
+    }
+
+    let summary = tauri::async_runtime::spawn_blocking(move || {
+        export::export(
+            &app.state::<Store>(),
+            user_id.as_deref(),
+            &chats,
+            format,
+            utc_offset_minutes,
+            &path,
+        )
+    })
+    .await??;
+    Ok(Some(summary))
+}
+
+/// Fetches a chat's messages from RTDB and caches them decrypted.
+async fn refresh_history(
+    store: &Store,
+    e2e: &E2e,
+    db: &Database,
+    user_id: &str,
+    chat_id: &str,
+) -> Result<()> {
+    let raw: Option<BTreeMap<String, Value>> = db.get(&format!("messages/{chat_id}")).await?;
+    let messages: Vec<IncomingMessage> = raw
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|(id, mut value)| {
+            value
+                .as_object_mut()?
+                .insert("id".into(), Value::String(id));
+            serde_json::from_value(value).ok()
+        })
+        .collect();
+    e2e.decrypt_messages(store, Some(user_id), chat_id, messages)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,48 @@
+// Commands for preparing images before they are attached
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::attachments::Attachments;
+use crate::error::Result;
+use crate::images;
+use crate::models::{ImageOptions, PreparedImage};
+use crate::profiles::Profile;
+
+/// Thumbnail size when none is given.
+const DEFAULT_THUMBNAIL_SIZE: u32 = 320;
+
+/// Strips the metadata from a chosen image and scales it down to the limits
+/// in `options`. The processed copy is written to the cache and comes back
+/// as a chosen file of its own; upload that instead of the original, whose
+/// token is used up.
+#[tauri::command]
+pub async fn prepare_image(
+    app: AppHandle,
+    attachments: State<'_, Attachments>,
+    token: String,
+    options: Option<ImageOptions>,
+) -> Result<PreparedImage> {
+    let path = attachments.take(&token)?;
+    let out_dir = app.state::<Profile>().cache_dir().join("images");
+    let options = options.unwrap_or_default();
+    let (path, image) =
+        tauri::async_runtime::spawn_blocking(move || images::prepare(&path, &out_dir, &options))
+            .await??;
+    Ok(PreparedImage {
+        file: attachments.choose(path),
+        image,
+    })
+}
+
+/// A thumbnail of a chosen image as a `data:` URL, for previews.
+#[tauri::command]
+pub async fn image_thumbnail(
+    attachments: State<'_, Attachments>,
+    token: String,
+    size: Option<u32>,
+) -> Result<String> {
+    let path: PathBuf = attachments.peek(&token)?;
+    let size = size.unwrap_or(DEFAULT_THUMBNAIL_SIZE);
+    tauri::async_runtime::spawn_blocking(move || images::thumbnail(&path, size)).await?
+}
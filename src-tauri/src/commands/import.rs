@@ -0,0 +1,78 @@
+// Commands for importing history from other messengers
+use std::collections::HashMap;
+
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_dialog::DialogExt;
+use tokio::sync::oneshot;
+
+use crate::error::{Error, Result};
+use crate::import::{self, ChosenExports};
+use crate::models::{ChosenFile, ImportReport, Message};
+use crate::store::Store;
+
+/// Asks for a WhatsApp or Telegram export. Resolves to `null` if the user
+/// cancels the dialog.
+#[tauri::command]
+pub async fn choose_import_file(
+    app: AppHandle,
+    exports: State<'_, ChosenExports>,
+) -> Result<Option<ChosenFile>> {
+    let (picked, path) = oneshot::channel();
+    app.dialog()
+        .file()
+        .set_title("Import chat history")
+        .add_filter("Chat export", &["txt", "zip", "json"])
+        .pick_file(move |path| {
+            let _ = picked.send(path);
+        });
+    let Some(path) = path.await.ok().flatten() else {
+        return Ok(None);
+    };
+    let path = path
+        .into_path()
+        .map_err(|e| Error::UnsupportedPath(e.to_string()))?;
+    Ok(Some(exports.choose(path)))
+}
+
+/// Imports the export chosen as `token` into a chat as archived messages, or
+/// with `dry_run` only reports what would be imported. The token stays valid
+/// until an import succeeds. See [`import::import`] for `mapping`.
+#[tauri::command]
+pub async fn import_history(
+    app: AppHandle,
+    store: State<'_, Store>,
+    exports: State<'_, ChosenExports>,
+    chat_id: String,
+    token: String,
+    mapping: Option<HashMap<String, String>>,
+    dry_run: bool,
+    utc_offset_minutes: i32,
+) -> Result<ImportReport> {
+    let chat = store
+        .chats()?
+        .into_iter()
+        .find(|chat| chat.id == chat_id)
+        .ok_or(Error::UnknownChat(chat_id))?;
+    let path = exports.peek(&token)?;
+
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let parsed = import::parse(&path, utc_offset_minutes)?;
+        import::import(
+            &app.state::<Store>(),
+            &chat,
+            parsed,
+            &mapping.unwrap_or_default(),
+            dry_run,
+        )
+    })
+    .await??;
+    if !dry_run {
+        exports.take(&token)?;
+    }
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn get_archived_messages(store: State<'_, Store>, chat_id: String) -> Result<Vec<Message>> {
+    store.archived_messages(&chat_id)
+}
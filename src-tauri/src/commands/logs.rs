@@ -0,0 +1,20 @@
+// Commands for the app log
+use serde_json::Value;
+use tauri::State;
+
+use crate::error::Result;
+use crate::logging::{self, Logs};
+use crate::models::{LogEntry, LogFilter, LogLevel};
+
+/// Adds an entry from the webview to the log.
+#[tauri::command]
+pub fn log_message(level: LogLevel, message: String, context: Option<Value>) {
+    logging::forward(level, &message, context);
+}
+
+/// The most recent log entries matching `filter`, oldest first.
+#[tauri::command]
+pub async fn get_logs(logs: State<'_, Logs>, filter: LogFilter) -> Result<Vec<LogEntry>> {
+    let dir = logs.dir().to_path_buf();
+    tauri::async_runtime::spawn_blocking(move || logging::read(&dir, &filter)).await?
+}
@@ -0,0 +1,19 @@
+// Commands for rendering message text
+use crate::error::Result;
+use crate::markdown;
+
+/// Renders each message text as sanitized HTML, in order.
+#[tauri::command]
+pub async fn render_markdown(texts: Vec<String>) -> Result<Vec<String>> {
+    let html = tauri::async_runtime::spawn_blocking(move || {
+        texts.iter().map(|text| markdown::render(text)).collect()
+    })
+    .await?;
+    Ok(html)
+}
+
+/// Stylesheet for highlighted code blocks in the light or dark theme.
+#[tauri::command]
+pub async fn get_highlight_css(dark: bool) -> Result<String> {
+    tauri::async_runtime::spawn_blocking(move || markdown::highlight_css(dark)).await?
+}
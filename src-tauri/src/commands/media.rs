@@ -0,0 +1,17 @@
+// Commands for the media cache
+use tauri::State;
+
+use crate::error::Result;
+use crate::media_cache::MediaCache;
+use crate::models::MediaCacheUsage;
+
+#[tauri::command]
+pub fn get_media_cache_usage(cache: State<'_, MediaCache>) -> MediaCacheUsage {
+    cache.usage()
+}
+
+/// Removes all cached media. Files are downloaded again when next shown.
+#[tauri::command]
+pub fn clear_media_cache(cache: State<'_, MediaCache>) -> Result<()> {
+    cache.clear()
+}
@@ -0,0 +1,21 @@
+// Tauri IPC commands, grouped by subsystem
+pub mod attachments;
+pub mod deep_link;
+pub mod diagnostics;
+pub mod export;
+pub mod images;
+pub mod import;
+pub mod logs;
+pub mod markdown;
+pub mod media;
+pub mod notifications;
+pub mod outbox;
+pub mod profiles;
+pub mod search;
+pub mod session;
+pub mod settings;
+pub mod store;
+pub mod subscriptions;
+pub mod tray;
+pub mod unread;
+pub mod windows;
@@ -0,0 +1,44 @@
+// Commands for showing notifications and muting chats
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::error::Result;
+use crate::models::{NotificationOutcome, PresenceNotification, Settings, Timestamp};
+use crate::notifications::{self, Notifier};
+
+/// Notifies about a contact coming online or going offline, if the rules
+/// allow. `utc_offset_minutes` places quiet hours in local time.
+#[tauri::command]
+pub async fn notify_presence(
+    app: AppHandle,
+    notification: PresenceNotification,
+    utc_offset_minutes: i32,
+) -> Result<NotificationOutcome> {
+    tauri::async_runtime::spawn_blocking(move || {
+        notifications::presence(&app, &notification, utc_offset_minutes)
+    })
+    .await?
+}
+
+/// Tells which chat the calling window shows, so it does not notify while
+/// the window has focus. Opening a chat removes its notification.
+#[tauri::command]
+pub fn set_open_chat(window: WebviewWindow, chat_id: Option<String>) {
+    if let Some(chat_id) = &chat_id {
+        notifications::dismiss(window.app_handle(), chat_id, None);
+    }
+    window
+        .state::<Notifier>()
+        .set_open_chat(window.label(), chat_id);
+}
+
+/// Mutes a chat until `until`, or until it is unmuted. Returns the new
+/// settings.
+#[tauri::command]
+pub fn mute_chat(app: AppHandle, chat_id: String, until: Option<Timestamp>) -> Result<Settings> {
+    notifications::mute_chat(&app, &chat_id, until)
+}
+
+#[tauri::command]
+pub fn unmute_chat(app: AppHandle, chat_id: String) -> Result<Settings> {
+    notifications::unmute_chat(&app, &chat_id)
+}
@@ -0,0 +1,35 @@
+// Commands for the offline outbox
+use tauri::AppHandle;
+
+use crate::error::Result;
+use crate::models::Attachment;
+use crate::outbox::{self, OutboxEntry};
+
+/// Queues a message for delivery. Returns immediately with the queued entry;
+/// progress is reported through `outbox-state` events.
+#[tauri::command]
+pub fn enqueue_message(
+    app: AppHandle,
+    chat_id: String,
+    sender_id: String,
+    sender_name: String,
+    text: String,
+    attachment: Option<Attachment>,
+) -> Result<OutboxEntry> {
+    outbox::enqueue(&app, chat_id, sender_id, sender_name, text, attachment)
+}
+
+#[tauri::command]
+pub fn list_outbox(app: AppHandle, chat_id: Option<String>) -> Result<Vec<OutboxEntry>> {
+    outbox::list(&app, chat_id.as_deref())
+}
+
+#[tauri::command]
+pub fn retry_outbox_message(app: AppHandle, id: String) -> Result<()> {
+    outbox::retry(&app, &id)
+}
+
+#[tauri::command]
+pub fn discard_outbox_message(app: AppHandle, id: String) -> Result<()> {
+    outbox::discard(&app, &id)
+}
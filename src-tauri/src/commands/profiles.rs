@@ -0,0 +1,17 @@
+// Commands for switching between profiles
+use tauri::AppHandle;
+
+use crate::error::Result;
+use crate::profiles;
+
+/// The names of all profiles, the default one first.
+#[tauri::command]
+pub fn list_profiles(app: AppHandle) -> Result<Vec<String>> {
+    profiles::list(&app)
+}
+
+/// Restarts the app with the profile `name`, creating it if needed.
+#[tauri::command]
+pub fn switch_profile(app: AppHandle, name: String) -> Result<()> {
+    profiles::switch(&app, &name)
+}
@@ -0,0 +1,22 @@
+// Commands for message search
+use tauri::State;
+
+use crate::error::Result;
+use crate::models::SearchHit;
+use crate::search::{self, SearchQuery};
+use crate::store::Store;
+
+const DEFAULT_RESULT_LIMIT: u32 = 50;
+
+/// Searches cached messages across all chats. `utcOffsetMinutes` is the
+/// caller's offset (minutes east of UTC) used for `before:`/`after:` dates.
+#[tauri::command]
+pub fn search_messages(
+    store: State<'_, Store>,
+    query: String,
+    limit: Option<u32>,
+    utc_offset_minutes: Option<i32>,
+) -> Result<Vec<SearchHit>> {
+    let query = SearchQuery::parse(&query, utc_offset_minutes.unwrap_or(0));
+    search::search(&store, &query, limit.unwrap_or(DEFAULT_RESULT_LIMIT))
+}
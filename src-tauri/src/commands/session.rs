@@ -0,0 +1,53 @@
+// Commands for handing the Firebase session to the Rust core
+use tauri::{AppHandle, State};
+
+use crate::e2e;
+use crate::error::Result;
+use crate::notifications;
+use crate::outbox;
+use crate::session::{Credentials, Session};
+use crate::subscriptions::{self, Subscriptions};
+use crate::{tray, unread};
+
+/// Called by the webview on sign-in and whenever the ID token rotates. Only
+/// a sign-in publishes the chosen status and starts following the user's
+/// chats.
+#[tauri::command]
+pub fn set_backend_session(
+    app: AppHandle,
+    session: State<'_, Session>,
+    database_url: String,
+    user_id: String,
+    id_token: String,
+    storage_bucket: Option<String>,
+) -> Result<()> {
+    let signed_in = session
+        .credentials()
+        .is_none_or(|current| current.user_id != user_id);
+    session.set(Some(Credentials {
+        database_url,
+        user_id,
+        id_token,
+        storage_bucket,
+    }));
+    e2e::spawn_publish(app.clone());
+    if signed_in {
+        tray::spawn_publish_status(app.clone());
+        subscriptions::follow_account(&app)?;
+    }
+    unread::publish(&app);
+    outbox::flush(&app)
+}
+
+#[tauri::command]
+pub fn clear_backend_session(
+    app: AppHandle,
+    session: State<'_, Session>,
+    subscriptions: State<'_, Subscriptions>,
+) {
+    session.set(None);
+    subscriptions.clear();
+    unread::publish(&app);
+    // Message previews should not outlast the session
+    notifications::dismiss_all(&app);
+}
@@ -0,0 +1,19 @@
+// Commands for user preferences
+use serde_json::Value;
+use tauri::{AppHandle, State};
+
+use crate::error::Result;
+use crate::models::Settings;
+use crate::settings::{self, SettingsStore};
+
+#[tauri::command]
+pub fn get_settings(settings: State<'_, SettingsStore>) -> Settings {
+    settings.get()
+}
+
+/// Changes the settings named in `changes` and returns all of them.
+/// Listeners of `settings-changed` are told if anything changed.
+#[tauri::command]
+pub fn update_settings(app: AppHandle, changes: Value) -> Result<Settings> {
+    settings::update(&app, changes)
+}
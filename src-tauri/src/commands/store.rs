@@ -0,0 +1,83 @@
+// Commands for the encrypted local store
+use tauri::{AppHandle, State};
+
+use crate::e2e::E2e;
+use crate::error::Result;
+use crate::models::{Chat, Message, User};
+use crate::session::Session;
+use crate::store::Store;
+use crate::unread;
+
+/// Page size used when the frontend does not ask for one.
+const DEFAULT_MESSAGE_LIMIT: u32 = 200;
+
+#[tauri::command]
+pub fn get_cached_chats(store: State<'_, Store>) -> Result<Vec<Chat>> {
+    store.chats()
+}
+
+#[tauri::command]
+pub fn cache_chats(store: State<'_, Store>, chats: Vec<Chat>) -> Result<()> {
+    store.upsert_chats(&chats)
+}
+
+#[tauri::command]
+pub fn remove_cached_chat(app: AppHandle, store: State<'_, Store>, chat_id: String) -> Result<()> {
+    store.remove_chat(&chat_id)?;
+    unread::publish(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_cached_messages(
+    store: State<'_, Store>,
+    chat_id: String,
+    before: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<Message>> {
+    store.messages(
+        &chat_id,
+        before.as_deref(),
+        limit.unwrap_or(DEFAULT_MESSAGE_LIMIT),
+    )
+}
+
+#[tauri::command]
+pub fn cache_messages(
+    store: State<'_, Store>,
+    chat_id: String,
+    messages: Vec<Message>,
+) -> Result<()> {
+    store.upsert_messages(&chat_id, &messages)
+}
+
+#[tauri::command]
+pub fn get_cached_user(store: State<'_, Store>, user_id: String) -> Result<Option<User>> {
+    store.user(&user_id)
+}
+
+#[tauri::command]
+pub fn cache_users(store: State<'_, Store>, users: Vec<User>) -> Result<()> {
+    store.upsert_users(&users)
+}
+
+/// Wipes the local store on sign-out, except for imported history. This
+/// device's public keys are withdrawn first (best effort) so nobody keeps
+/// encrypting for it.
+#[tauri::command]
+pub async fn clear_local_store(
+    app: AppHandle,
+    store: State<'_, Store>,
+    session: State<'_, Session>,
+    e2e: State<'_, E2e>,
+) -> Result<()> {
+    if let Some(credentials) = session.credentials() {
+        let db = session.database(&reqwest::Client::new())?;
+        if let Err(e) = e2e.unpublish_keys(&store, &db, &credentials.user_id).await {
+            tracing::warn!("failed to withdraw keys: {e}");
+        }
+    }
+    store.clear()?;
+    unread::publish(&app);
+    Ok(())
+}
@@ -0,0 +1,52 @@
+// Commands for live chat, message and presence subscriptions
+use tauri::ipc::Channel;
+use tauri::{State, Webview};
+
+use crate::error::Result;
+use crate::models::{Chat, LiveUpdate, Message, User};
+use crate::subscriptions::{self, Subscriptions};
+
+/// Streams the signed-in user's chats, newest first. Returns an ID for
+/// `unsubscribe`.
+#[tauri::command]
+pub fn subscribe_chats(
+    webview: Webview,
+    user_id: String,
+    on_update: Channel<LiveUpdate<Vec<Chat>>>,
+) -> Result<u32> {
+    subscriptions::chats(&webview, &user_id, on_update)
+}
+
+#[tauri::command]
+pub fn subscribe_chat(
+    webview: Webview,
+    chat_id: String,
+    on_update: Channel<LiveUpdate<Option<Chat>>>,
+) -> Result<u32> {
+    subscriptions::chat(&webview, &chat_id, on_update)
+}
+
+/// Streams a chat's messages, decrypted. Messages this device cannot decrypt
+/// come with placeholder text.
+#[tauri::command]
+pub fn subscribe_messages(
+    webview: Webview,
+    chat_id: String,
+    on_update: Channel<LiveUpdate<Vec<Message>>>,
+) -> Result<u32> {
+    subscriptions::messages(&webview, &chat_id, on_update)
+}
+
+#[tauri::command]
+pub fn subscribe_user(
+    webview: Webview,
+    user_id: String,
+    on_update: Channel<LiveUpdate<Option<User>>>,
+) -> Result<u32> {
+    subscriptions::user(&webview, &user_id, on_update)
+}
+
+#[tauri::command]
+pub fn unsubscribe(subscriptions: State<'_, Subscriptions>, id: u32) {
+    subscriptions.remove(id);
+}
@@ -0,0 +1,11 @@
+// Commands for state set from the tray menu
+use tauri::AppHandle;
+
+use crate::models::Timestamp;
+use crate::tray;
+
+/// When notifications muted from the tray resume, if they are muted.
+#[tauri::command]
+pub fn get_notifications_muted_until(app: AppHandle) -> Option<Timestamp> {
+    tray::muted_until(&app)
+}
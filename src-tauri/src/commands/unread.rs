@@ -0,0 +1,29 @@
+// Commands for unread chat tracking
+use tauri::{AppHandle, State};
+
+use crate::error::Result;
+use crate::models::{Timestamp, UnreadSummary};
+use crate::notifications;
+use crate::store::Store;
+use crate::unread;
+
+/// Called while the user has a chat open, with the chat's `updatedAt` as
+/// shown to them. Removes the chat's notification unless newer messages
+/// came in since.
+#[tauri::command]
+pub fn mark_chat_read(
+    app: AppHandle,
+    store: State<'_, Store>,
+    chat_id: String,
+    up_to: Timestamp,
+) -> Result<()> {
+    unread::mark_read(&store, &chat_id, up_to)?;
+    unread::publish(&app);
+    notifications::dismiss(&app, &chat_id, Some(up_to));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_unread_chats(app: AppHandle) -> Result<UnreadSummary> {
+    unread::current(&app)
+}
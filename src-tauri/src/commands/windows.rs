@@ -0,0 +1,11 @@
+// Commands for opening windows
+use tauri::AppHandle;
+
+use crate::error::Result;
+use crate::windows;
+
+/// Pops the chat `chat_id` out into a window of its own.
+#[tauri::command]
+pub fn open_chat_window(app: AppHandle, chat_id: String) -> Result<()> {
+    windows::open_chat(&app, &chat_id)
+}
@@ -0,0 +1,427 @@
+// Crash reports
+//
+// A panic hook writes a plain-text report to the app data directory: the
+// panic message and backtrace, the app and OS versions, the command the
+// panicking thread was running along with the last commands invoked, and the
+// last log entries. Panics inside async commands do not end the process, so
+// a report does not always mean the app went down.
+//
+// On the next launch the user is asked whether to save the report somewhere
+// to send it to support. Either way it moves out of `pending/`; the last few
+// stay behind and go into diagnostics bundles.
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, Write as _};
+use std::panic::{self, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+
+use tauri::ipc::Invoke;
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::error::{Error, Result};
+use crate::export::LocalTime;
+use crate::logging;
+use crate::models::{now, Timestamp};
+
+const PENDING_DIR: &str = "pending";
+const EXTENSION: &str = "txt";
+
+/// How many reports are kept once the user was asked about them.
+const KEEP_REPORTS: usize = 5;
+/// How many of the last invoked commands a report lists.
+const RECENT_COMMANDS: usize = 20;
+
+struct Context {
+    dir: PathBuf,
+    version: String,
+}
+
+static CONTEXT: OnceLock<Context> = OnceLock::new();
+static COMMANDS: Mutex<VecDeque<(Timestamp, String)>> = Mutex::new(VecDeque::new());
+
+thread_local! {
+    static RUNNING: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Installs the panic hook. Reports are only written once [`init`] has
+/// been called; until then panics are just printed.
+pub fn install_hook() {
+    let default = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Some(context) = CONTEXT.get() {
+            match write_report(context, info) {
+                Ok(path) => eprintln!("crash report written to {}", path.display()),
+                Err(e) => eprintln!("failed to write crash report: {e}"),
+            }
+        }
+        default(info);
+    }));
+}
+
+/// Sets where reports are written, under `dir`.
+pub fn init(dir: &Path, version: String) -> Result<()> {
+    fs::create_dir_all(dir.join(PENDING_DIR))?;
+    let _ = CONTEXT.set(Context {
+        dir: dir.to_path_buf(),
+        version,
+    });
+    Ok(())
+}
+
+/// Wraps the invoke handler so reports name the command that was running.
+pub fn track_commands<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let _running = running(invoke.message.command());
+        handler(invoke)
+    }
+}
+
+/// Marks `command` as running on this thread until the guard is dropped.
+/// Async commands only run here until their first `.await`.
+fn running(command: &str) -> Running {
+    if let Ok(mut commands) = COMMANDS.lock() {
+        if commands.len() == RECENT_COMMANDS {
+            commands.pop_front();
+        }
+        commands.push_back((now(), command.to_string()));
+    }
+    RUNNING.with(|running| *running.borrow_mut() = Some(command.to_string()));
+    Running
+}
+
+struct Running;
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        RUNNING.with(|running| running.borrow_mut().take());
+    }
+}
+
+/// Reports kept after the user was asked about them, newest first.
+pub fn reports(dir: &Path) -> Vec<PathBuf> {
+    let mut reports = list(dir);
+    reports.reverse();
+    reports
+}
+
+/// Asks about reports left by the last run, if any, offering to save the
+/// newest one.
+pub fn prompt_pending(app: &AppHandle, dir: &Path) {
+    let pending = list(&dir.join(PENDING_DIR));
+    let Some(newest) = pending.last().cloned() else {
+        return;
+    };
+    // Asked once; the reports stay available for diagnostics bundles
+    for report in &pending {
+        if let Some(name) = report.file_name() {
+            if let Err(e) = fs::rename(report, dir.join(name)) {
+                tracing::warn!("failed to move {}: {e}", report.display());
+            }
+        }
+    }
+    prune(dir);
+    let Some(name) = newest.file_name().map(|name| name.to_owned()) else {
+        return;
+    };
+    let report = dir.join(&name);
+
+    let handle = app.clone();
+    app.dialog()
+        .message(
+            "Chitchat closed unexpectedly or ran into an internal error the last time it ran. \
+            A crash report was saved; it contains technical details and recent log entries, \
+            but no messages.\n\nSave the report to send it to support?",
+        )
+        .title("Chitchat crashed")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Save report…".into(),
+            "Not now".into(),
+        ))
+        .show(move |save| {
+            if !save {
+                return;
+            }
+            handle
+                .dialog()
+                .file()
+                .set_title("Save crash report")
+                .set_file_name(name.to_string_lossy())
+                .add_filter("Text file", &[EXTENSION])
+                .save_file(move |target| {
+                    let Some(target) = target else {
+                        return;
+                    };
+                    let copied = target
+                        .into_path()
+                        .map_err(|e| Error::UnsupportedPath(e.to_string()))
+                        .and_then(|target| Ok(fs::copy(&report, target)?));
+                    if let Err(e) = copied {
+                        tracing::error!("failed to save crash report: {e}");
+                    }
+                });
+        });
+}
+
+/// The crash report directory for an app.
+pub fn dir(app: &AppHandle) -> Result<PathBuf> {
+    Ok(app.path().app_data_dir()?.join("crashes"))
+}
+
+/// What a report says about a panic.
+struct Panic {
+    message: String,
+    location: String,
+    thread: String,
+    /// The command the panicking thread was running, if any.
+    running: Option<String>,
+}
+
+impl Panic {
+    fn from_hook(info: &PanicHookInfo) -> Self {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("(no message)");
+        Panic {
+            message: message.to_string(),
+            location: info
+                .location()
+                .map(|location| location.to_string())
+                .unwrap_or_default(),
+            thread: thread::current().name().unwrap_or("unnamed").to_string(),
+            running: RUNNING
+                .try_with(|running| running.try_borrow().ok().and_then(|r| r.clone()))
+                .ok()
+                .flatten(),
+        }
+    }
+}
+
+fn write_report(context: &Context, info: &PanicHookInfo) -> Result<PathBuf> {
+    let timestamp = now();
+    let commands: Vec<(Timestamp, String)> = COMMANDS
+        .try_lock()
+        .map(|commands| commands.iter().cloned().collect())
+        .unwrap_or_default();
+    let report = format_report(
+        &context.version,
+        timestamp,
+        &Panic::from_hook(info),
+        &commands,
+        &Backtrace::force_capture().to_string(),
+        &logging::recent(),
+    );
+    save(&context.dir.join(PENDING_DIR), timestamp, &report)
+}
+
+fn format_report(
+    version: &str,
+    timestamp: Timestamp,
+    panic: &Panic,
+    commands: &[(Timestamp, String)],
+    backtrace: &str,
+    log: &[String],
+) -> String {
+    let mut report = String::new();
+    let _ = writeln!(report, "Chitchat {version} crash report");
+    let _ = writeln!(report, "Time: {}", LocalTime::new(timestamp, 0).iso());
+    let _ = writeln!(
+        report,
+        "OS: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let _ = writeln!(report, "Thread: {}", panic.thread);
+    let _ = writeln!(
+        report,
+        "Panic: {} at {}",
+        logging::redact(&panic.message),
+        panic.location
+    );
+    let _ = writeln!(
+        report,
+        "Running command: {}",
+        panic.running.as_deref().unwrap_or("none on this thread")
+    );
+    let _ = writeln!(report, "\nRecent commands:");
+    for (time, command) in commands {
+        let _ = writeln!(report, "  {} {command}", LocalTime::new(*time, 0).iso());
+    }
+
+    let _ = writeln!(report, "\nBacktrace:\n{backtrace}");
+    let _ = writeln!(report, "Recent log entries:");
+    for line in log {
+        let _ = writeln!(report, "  {line}");
+    }
+    report
+}
+
+/// Writes a report made at `timestamp` to `dir` and returns its path. The
+/// file is named after the timestamp; if another panic already took that
+/// name, the next free millisecond is used so neither report is lost.
+fn save(dir: &Path, timestamp: Timestamp, report: &str) -> Result<PathBuf> {
+    let mut timestamp = timestamp;
+    loop {
+        let path = dir.join(format!("crash-{timestamp}.{EXTENSION}"));
+        match File::create_new(&path) {
+            Ok(mut file) => {
+                file.write_all(report.as_bytes())?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => timestamp += 1,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Reports in `dir`, oldest first.
+fn list(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut reports: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == EXTENSION))
+        .collect();
+    // Named after their timestamp, which has the same number of digits until 2286
+    reports.sort();
+    reports
+}
+
+fn prune(dir: &Path) {
+    let reports = list(dir);
+    for report in &reports[..reports.len().saturating_sub(KEEP_REPORTS)] {
+        if let Err(e) = fs::remove_file(report) {
+            tracing::warn!("failed to remove {}: {e}", report.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    /// 2024-05-01 13:45 UTC
+    const CRASHED_AT: Timestamp = 1_714_571_100_000;
+
+    fn panic() -> Panic {
+        Panic {
+            message: "sync failed for ada@example.com".into(),
+            location: "src/sync.rs:42:9".into(),
+            thread: "tokio-runtime-worker".into(),
+            running: Some("send_message".into()),
+        }
+    }
+
+    #[test]
+    fn report_describes_the_panic() {
+        let time = LocalTime::new(CRASHED_AT, 0).iso();
+        let report = format_report(
+            "1.4.0",
+            CRASHED_AT,
+            &panic(),
+            &[
+                (CRASHED_AT - 60_000, "list_chats".into()),
+                (CRASHED_AT, "send_message".into()),
+            ],
+            "   0: chitchat::sync::run",
+            &["INFO chitchat: connected".into()],
+        );
+        let earlier = LocalTime::new(CRASHED_AT - 60_000, 0).iso();
+        assert_eq!(
+            report,
+            format!(
+                "Chitchat 1.4.0 crash report\n\
+                Time: {time}\n\
+                OS: {} {}\n\
+                Thread: tokio-runtime-worker\n\
+                Panic: sync failed for [email] at src/sync.rs:42:9\n\
+                Running command: send_message\n\
+                \n\
+                Recent commands:\n  \
+                {earlier} list_chats\n  \
+                {time} send_message\n\
+                \n\
+                Backtrace:\n   \
+                0: chitchat::sync::run\n\
+                Recent log entries:\n  \
+                INFO chitchat: connected\n",
+                std::env::consts::OS,
+                std::env::consts::ARCH
+            )
+        );
+    }
+
+    #[test]
+    fn report_without_a_running_command() {
+        let report = format_report(
+            "1.4.0",
+            CRASHED_AT,
+            &Panic {
+                running: None,
+                ..panic()
+            },
+            &[],
+            "",
+            &[],
+        );
+        assert!(report.contains("Running command: none on this thread\n"));
+        assert!(report.contains("Recent commands:\n\nBacktrace:"));
+    }
+
+    #[test]
+    fn reports_are_named_after_their_time() {
+        let dir = TempDir::new();
+        let path = save(&dir, CRASHED_AT, "first").unwrap();
+        assert_eq!(path, dir.join(format!("crash-{CRASHED_AT}.txt")));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+    }
+
+    #[test]
+    fn a_second_panic_keeps_the_first_report() {
+        let dir = TempDir::new();
+        let first = save(&dir, CRASHED_AT, "first").unwrap();
+        let second = save(&dir, CRASHED_AT, "second").unwrap();
+        let third = save(&dir, CRASHED_AT, "third").unwrap();
+        assert_eq!(list(&dir), [first.clone(), second.clone(), third.clone()]);
+        assert_eq!(fs::read_to_string(first).unwrap(), "first");
+        assert_eq!(fs::read_to_string(second).unwrap(), "second");
+        assert_eq!(fs::read_to_string(third).unwrap(), "third");
+    }
+
+    #[test]
+    fn pruning_keeps_the_newest_reports() {
+        let dir = TempDir::new();
+        for i in 0..KEEP_REPORTS as i64 + 2 {
+            save(&dir, CRASHED_AT + i * 1000, "report").unwrap();
+        }
+        fs::write(dir.join("notes.md"), "not a report").unwrap();
+        prune(&dir);
+        let kept = reports(&dir);
+        assert_eq!(kept.len(), KEEP_REPORTS);
+        assert_eq!(
+            kept[0],
+            dir.join(format!(
+                "crash-{}.txt",
+                CRASHED_AT + (KEEP_REPORTS as i64 + 1) * 1000
+            ))
+        );
+        assert_eq!(
+            kept[KEEP_REPORTS - 1],
+            dir.join(format!("crash-{}.txt", CRASHED_AT + 2000))
+        );
+        assert!(dir.join("notes.md").exists());
+    }
+}
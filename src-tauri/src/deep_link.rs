@@ -0,0 +1,357 @@
+// Deep links
+//
+// `chitchat://` links point into the app:
+//
+// - `chitchat://chat/<chat id>` opens a chat
+// - `chitchat://user/<email>` opens, or starts, a direct chat with a user
+// - `chitchat://invite/<token>` offers to join a group
+//
+// macOS hands links to the running app, which declares the scheme in its
+// `Info.plist`. Windows and Linux start the app with the link as an argument
+// instead, so release builds register the scheme for the current executable
+// on every start; while the app is running, the process started for a link
+// passes it on to the running one (see `instance`) and exits. Links go to the
+// default profile unless `--profile` is given as well.
+//
+// Links are parsed here, so the webview only sees well-formed ones, and are
+// held back until the webview has started listening for them.
+use std::sync::Mutex;
+
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
+use tauri::{AppHandle, Emitter, Manager, RunEvent};
+
+use crate::error::{Error, Result};
+use crate::models::DeepLink;
+use crate::tray;
+
+pub const SCHEME: &str = "chitchat";
+
+/// Event emitted with each link opened once the webview is listening.
+pub const OPEN_EVENT: &str = "deep-link";
+
+const MAX_KEY_LEN: usize = 128;
+const MAX_EMAIL_LEN: usize = 254;
+
+/// Links that arrived before the webview was listening, managed as state.
+#[derive(Default)]
+pub struct DeepLinks {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    listening: bool,
+    pending: Vec<DeepLink>,
+}
+
+impl DeepLinks {
+    /// Returns the links held back so far; later ones are emitted as
+    /// [`OPEN_EVENT`].
+    pub fn start_listening(&self) -> Vec<DeepLink> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.listening = true;
+        std::mem::take(&mut inner.pending)
+    }
+}
+
+/// The links among command line arguments.
+pub fn in_args(args: &[String]) -> Vec<String> {
+    args.iter()
+        .filter(|arg| arg.starts_with(&format!("{SCHEME}:")))
+        .cloned()
+        .collect()
+}
+
+/// Parses `links` and passes them to the webview, bringing the main window
+/// to the front. Invalid links are logged and dropped.
+pub fn open<S: AsRef<str>>(app: &AppHandle, links: impl IntoIterator<Item = S>) {
+    let links: Vec<DeepLink> = links
+        .into_iter()
+        .filter_map(|link| match parse(link.as_ref()) {
+            Ok(link) => Some(link),
+            Err(e) => {
+                tracing::warn!("ignoring link: {e}");
+                None
+            }
+        })
+        .collect();
+    if links.is_empty() {
+        return;
+    }
+    tray::show_main_window(app);
+
+    let state = app.state::<DeepLinks>();
+    let mut inner = state.inner.lock().unwrap_or_else(|e| e.into_inner());
+    if !inner.listening {
+        inner.pending.extend(links);
+        return;
+    }
+    for link in links {
+        if let Err(e) = app.emit(OPEN_EVENT, link) {
+            tracing::warn!("failed to emit link: {e}");
+        }
+    }
+}
+
+/// Opens links macOS hands to the running app.
+#[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+pub fn on_run_event(app: &AppHandle, event: RunEvent) {
+    #[cfg(target_os = "macos")]
+    if let RunEvent::Opened { urls } = event {
+        open(app, urls.iter().map(Url::as_str));
+    }
+}
+
+pub fn parse(link: &str) -> Result<DeepLink> {
+    let url = Url::parse(link).map_err(|e| Error::InvalidLink(e.to_string()))?;
+    if url.scheme() != SCHEME {
+        return Err(Error::InvalidLink(format!("not a {SCHEME}:// link")));
+    }
+    let segments: Vec<String> = url
+        .path_segments()
+        .into_iter()
+        .flatten()
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+        .collect();
+    let (Some(kind), [value]) = (url.host_str(), segments.as_slice()) else {
+        return Err(Error::InvalidLink(format!(
+            "expected {SCHEME}://<kind>/<value>"
+        )));
+    };
+    // Hosts are case-insensitive, even though the URL keeps this one as written
+    match kind.to_ascii_lowercase().as_str() {
+        "chat" if is_key(value) => Ok(DeepLink::Chat { id: value.clone() }),
+        "user" if is_email(value) => Ok(DeepLink::User {
+            email: value.to_lowercase(),
+        }),
+        "invite" if is_key(value) => Ok(DeepLink::Invite {
+            token: value.clone(),
+        }),
+        "chat" | "user" | "invite" => Err(Error::InvalidLink(format!("malformed {kind} link"))),
+        _ => Err(Error::InvalidLink(format!("unknown link kind {kind:?}"))),
+    }
+}
+
+/// Database keys, as used for chat IDs and invite tokens.
+fn is_key(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_KEY_LEN
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn is_email(value: &str) -> bool {
+    value.len() <= MAX_EMAIL_LEN
+        && value
+            .split_once('@')
+            .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.'))
+        && !value.chars().any(char::is_whitespace)
+}
+
+/// Points the scheme at this executable. Only release builds do this, so
+/// running a development build does not take links away from the installed
+/// app.
+pub fn register(app: &AppHandle) {
+    if cfg!(debug_assertions) {
+        return;
+    }
+    if let Err(e) = register_scheme(app) {
+        tracing::warn!("failed to register {SCHEME}:// links: {e}");
+    }
+}
+
+#[cfg(windows)]
+fn register_scheme(_app: &AppHandle) -> Result<()> {
+    use std::os::windows::process::CommandExt;
+    use std::process::Command;
+
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let key = format!(r"HKCU\Software\Classes\{SCHEME}");
+    let command_key = format!(r"{key}\shell\open\command");
+    let command = format!("\"{}\" \"%1\"", std::env::current_exe()?.display());
+    let entries: [&[&str]; 3] = [
+        &[&key, "/ve", "/d", "URL:Chitchat"],
+        &[&key, "/v", "URL Protocol", "/d", ""],
+        &[&command_key, "/ve", "/d", &command],
+    ];
+    for entry in entries {
+        let status = Command::new("reg")
+            .arg("add")
+            .args(entry)
+            .arg("/f")
+            .creation_flags(CREATE_NO_WINDOW)
+            .status()?;
+        if !status.success() {
+            return Err(std::io::Error::other(format!("reg add {} failed", entry[0])).into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn register_scheme(app: &AppHandle) -> Result<()> {
+    use std::fs;
+    use std::process::Command;
+
+    const DESKTOP_FILE: &str = "chitchat-url-handler.desktop";
+
+    // An AppImage runs from a temporary mount; register the image itself
+    let exe = match std::env::var_os("APPIMAGE") {
+        Some(image) => image.into(),
+        None => std::env::current_exe()?,
+    };
+    let exec = exe
+        .display()
+        .to_string()
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('`', "\\`")
+        .replace('$', "\\$");
+    let entry = format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Chitchat\n\
+         Exec=\"{exec}\" %u\n\
+         Terminal=false\n\
+         NoDisplay=true\n\
+         MimeType=x-scheme-handler/{SCHEME};\n"
+    );
+
+    let dir = app.path().data_dir()?.join("applications");
+    let path = dir.join(DESKTOP_FILE);
+    if fs::read_to_string(&path).is_ok_and(|existing| existing == entry) {
+        return Ok(());
+    }
+    fs::create_dir_all(&dir)?;
+    fs::write(&path, entry)?;
+    let status = Command::new("xdg-mime")
+        .args(["default", DESKTOP_FILE])
+        .arg(format!("x-scheme-handler/{SCHEME}"))
+        .status()?;
+    if !status.success() {
+        return Err(std::io::Error::other("xdg-mime failed").into());
+    }
+    Ok(())
+}
+
+/// macOS reads the scheme from the bundle's `Info.plist`.
+#[cfg(not(any(windows, target_os = "linux")))]
+fn register_scheme(_app: &AppHandle) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_each_kind_of_link() {
+        assert_eq!(
+            parse("chitchat://chat/-NxAbc_123").unwrap(),
+            DeepLink::Chat {
+                id: "-NxAbc_123".into()
+            }
+        );
+        assert_eq!(
+            parse("chitchat://user/alice@example.com").unwrap(),
+            DeepLink::User {
+                email: "alice@example.com".into()
+            }
+        );
+        assert_eq!(
+            parse("chitchat://invite/tok-en_1").unwrap(),
+            DeepLink::Invite {
+                token: "tok-en_1".into()
+            }
+        );
+    }
+
+    #[test]
+    fn hosts_ignore_case_and_emails_are_lowercased() {
+        assert_eq!(
+            parse("chitchat://CHAT/c1").unwrap(),
+            DeepLink::Chat { id: "c1".into() }
+        );
+        assert_eq!(
+            parse("chitchat://User/Alice@Example.COM").unwrap(),
+            DeepLink::User {
+                email: "alice@example.com".into()
+            }
+        );
+    }
+
+    #[test]
+    fn values_are_percent_decoded() {
+        assert_eq!(
+            parse("chitchat://user/alice%40example.com").unwrap(),
+            DeepLink::User {
+                email: "alice@example.com".into()
+            }
+        );
+        assert_eq!(
+            parse("chitchat://chat/c%31").unwrap(),
+            DeepLink::Chat { id: "c1".into() }
+        );
+        // Decoding must not let a value smuggle in a path or a space
+        assert!(parse("chitchat://chat/a%2Fb").is_err());
+        assert!(parse("chitchat://user/a%20b@example.com").is_err());
+    }
+
+    #[test]
+    fn trailing_slashes_are_allowed_but_not_extra_segments() {
+        assert_eq!(
+            parse("chitchat://chat/c1/").unwrap(),
+            DeepLink::Chat { id: "c1".into() }
+        );
+        for link in [
+            "chitchat://chat/c1/extra",
+            "chitchat://invite/a/b",
+            "chitchat://chat",
+            "chitchat://chat/",
+        ] {
+            assert!(parse(link).is_err(), "{link}");
+        }
+    }
+
+    #[test]
+    fn rejects_bad_keys_and_emails() {
+        let long_key = format!("chitchat://chat/{}", "a".repeat(MAX_KEY_LEN + 1));
+        let long_email = format!("chitchat://user/{}@example.com", "a".repeat(MAX_EMAIL_LEN));
+        for link in [
+            "chitchat://chat/a.b",
+            "chitchat://chat/a$b",
+            "chitchat://invite/tok%00en",
+            long_key.as_str(),
+            "chitchat://user/alice",
+            "chitchat://user/@example.com",
+            "chitchat://user/alice@localhost",
+            long_email.as_str(),
+        ] {
+            assert!(parse(link).is_err(), "{link}");
+        }
+        let max_key = format!("chitchat://chat/{}", "a".repeat(MAX_KEY_LEN));
+        assert!(parse(&max_key).is_ok());
+    }
+
+    #[test]
+    fn rejects_other_schemes_and_kinds() {
+        for link in [
+            "https://chat/c1",
+            "chitchat://group/c1",
+            "chitchat:chat/c1",
+            "not a link",
+        ] {
+            assert!(parse(link).is_err(), "{link}");
+        }
+    }
+
+    #[test]
+    fn finds_links_among_arguments() {
+        let args = ["chitchat", "--profile", "work", "chitchat://chat/c1"].map(String::from);
+        assert_eq!(in_args(&args), ["chitchat://chat/c1"]);
+    }
+}
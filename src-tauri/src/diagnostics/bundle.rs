@@ -0,0 +1,369 @@
+// Diagnostics bundles
+//
+// A zip to attach to a bug report, with what support needs to look into
+// something like "messages don't arrive" and nothing personal:
+//
+// - `summary.json`: app, OS and webview versions; session, outbox and media
+//   cache state; the user's settings
+// - `connection.json`: recent connection state changes
+// - `updater.json`: the update endpoints and the result of a fresh check
+// - `config.json`: the app configuration and `CHITCHAT_*` overrides
+// - `logs/`: the log files
+// - `crashes/`: the last crash reports
+//
+// Outbox entries go in without their text and the session without its token.
+// Everything is scrubbed on the way into the zip: values under keys that
+// look like secrets are removed, and every text value and every line of the
+// logs and crash reports is redacted like the log (see [`logging::redact`]),
+// which also catches what was written before a pattern was known.
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use reqwest::Url;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_updater::UpdaterExt;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::connection::ConnectionHistory;
+use crate::crash;
+use crate::error::Result;
+use crate::logging::{self, Logs};
+use crate::media_cache::MediaCache;
+use crate::models::now;
+use crate::outbox;
+use crate::profiles::Profile;
+use crate::session::Session;
+use crate::settings::SettingsStore;
+
+pub const FILE_NAME: &str = "chitchat-diagnostics.zip";
+
+const ENV_PREFIX: &str = "CHITCHAT_";
+
+/// How long to wait for the update server.
+const UPDATE_CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Keys whose values are removed, matched ignoring case, `_` and `-`.
+const SECRET_KEYS: [&str; 5] = ["token", "secret", "password", "apikey", "privatekey"];
+const REMOVED: &str = "[removed]";
+
+/// Writes a bundle to `path`. `user_agent` identifies the webview, and
+/// `updater` is the result of [`updater_state`].
+pub fn create(app: &AppHandle, path: &Path, user_agent: &str, updater: Value) -> Result<()> {
+    let documents = [
+        ("summary.json", summary(app, user_agent)),
+        (
+            "connection.json",
+            serde_json::to_value(app.state::<ConnectionHistory>().events())?,
+        ),
+        ("updater.json", updater),
+        ("config.json", config(app)?),
+    ];
+    let mut files = Vec::new();
+    if let Some(logs) = app.try_state::<Logs>() {
+        files.extend(
+            logging::files(logs.dir())?
+                .into_iter()
+                .map(|file| ("logs", file)),
+        );
+    }
+    files.extend(
+        crash::reports(&crash::dir(app)?)
+            .into_iter()
+            .map(|report| ("crashes", report)),
+    );
+    write(path, documents, &files)
+}
+
+/// Writes a zip of `documents` as JSON and of `files` in the folders they
+/// are listed with, all scrubbed.
+fn write(
+    path: &Path,
+    documents: impl IntoIterator<Item = (&'static str, Value)>,
+    files: &[(&str, PathBuf)],
+) -> Result<()> {
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(path)?));
+    for (name, mut document) in documents {
+        scrub(&mut document);
+        add_json(&mut zip, name, &document)?;
+    }
+    for (folder, file) in files {
+        let Some(name) = file.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        zip.start_file(format!("{folder}/{name}"), options())?;
+        for line in BufReader::new(File::open(file)?).lines() {
+            writeln!(zip, "{}", logging::redact(&line?))?;
+        }
+    }
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+/// The configured update endpoints and whether an update is available.
+pub async fn updater_state(app: &AppHandle) -> Value {
+    let check = match app.updater() {
+        Ok(updater) => tokio::time::timeout(UPDATE_CHECK_TIMEOUT, updater.check())
+            .await
+            .map_err(|_| "timed out".to_string())
+            .and_then(|result| result.map_err(|e| e.to_string())),
+        Err(e) => Err(e.to_string()),
+    };
+    let check = match check {
+        Ok(Some(update)) => json!({
+            "status": "available",
+            "version": update.version,
+            "date": update.date.map(|date| date.to_string()),
+        }),
+        Ok(None) => json!({ "status": "upToDate" }),
+        Err(e) => json!({ "status": "failed", "error": e }),
+    };
+    let endpoints = app
+        .config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|config| config.get("endpoints"))
+        .cloned();
+    json!({
+        "currentVersion": app.package_info().version.to_string(),
+        "endpoints": endpoints,
+        "checkedAt": now(),
+        "check": check,
+    })
+}
+
+fn options() -> SimpleFileOptions {
+    SimpleFileOptions::default().compression_method(CompressionMethod::Deflated)
+}
+
+fn add_json<W: Write + Seek>(
+    zip: &mut ZipWriter<W>,
+    name: &str,
+    value: &impl Serialize,
+) -> Result<()> {
+    zip.start_file(name, options())?;
+    serde_json::to_writer_pretty(&mut *zip, value)?;
+    Ok(())
+}
+
+fn summary(app: &AppHandle, user_agent: &str) -> Value {
+    let info = app.package_info();
+    let session = app.state::<Session>().credentials().map(|credentials| {
+        json!({
+            "userId": credentials.user_id,
+            "databaseHost": Url::parse(&credentials.database_url)
+                .ok()
+                .and_then(|url| url.host_str().map(String::from)),
+            "storageBucket": credentials.storage_bucket,
+        })
+    });
+    let outbox = match outbox::list(app, None) {
+        Ok(entries) => entries
+            .iter()
+            .map(|entry| {
+                json!({
+                    "id": entry.id,
+                    "chatId": entry.chat_id,
+                    "createdAt": entry.created_at,
+                    "attempts": entry.attempts,
+                    "state": entry.state,
+                    "lastError": entry.last_error,
+                    "hasAttachment": entry.attachment.is_some(),
+                })
+            })
+            .collect(),
+        Err(e) => json!({ "error": e.to_string() }),
+    };
+    json!({
+        "createdAt": now(),
+        "app": {
+            "name": info.name,
+            "version": info.version.to_string(),
+            "identifier": app.config().identifier,
+            "tauriVersion": tauri::VERSION,
+            "diagnosticsMode": super::enabled(),
+            "profile": app.state::<Profile>().name(),
+        },
+        "os": {
+            "os": std::env::consts::OS,
+            "family": std::env::consts::FAMILY,
+            "arch": std::env::consts::ARCH,
+            "version": os_version(),
+        },
+        "webview": user_agent,
+        "session": session,
+        "outbox": outbox,
+        "mediaCache": app.state::<MediaCache>().usage(),
+        "settings": app.state::<SettingsStore>().get(),
+    })
+}
+
+fn config(app: &AppHandle) -> Result<Value> {
+    let env: BTreeMap<String, String> = std::env::vars()
+        .filter(|(key, _)| key.starts_with(ENV_PREFIX))
+        .collect();
+    Ok(json!({
+        "app": serde_json::to_value(app.config())?,
+        "env": env,
+    }))
+}
+
+/// Removes values under secret-looking keys and redacts all text.
+fn scrub(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                let key = key.replace(['_', '-'], "").to_lowercase();
+                if SECRET_KEYS.iter().any(|secret| key.contains(secret)) {
+                    *value = Value::String(REMOVED.into());
+                } else {
+                    scrub(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(scrub),
+        Value::String(text) => {
+            if let Cow::Owned(redacted) = logging::redact(text) {
+                *text = redacted;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The OS release, where it can be read without running anything that
+/// would flash a window.
+fn os_version() -> Option<String> {
+    if cfg!(target_os = "linux") {
+        let release = fs::read_to_string("/etc/os-release").ok()?;
+        return release
+            .lines()
+            .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+            .map(|name| name.trim_matches('"').to_string());
+    }
+    if cfg!(target_os = "macos") {
+        let output = std::process::Command::new("sw_vers")
+            .arg("-productVersion")
+            .output()
+            .ok()?;
+        let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        return (!version.is_empty()).then(|| format!("macOS {version}"));
+    }
+    // Windows reports its version in the webview's user agent
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use zip::ZipArchive;
+
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    const LEAKED_URL: &str =
+        "https://db.example.com/messages/c1.json?auth=eyJhbGciOiJSUzI1NiJ9.eyJ1aWQiOiJ1MSJ9.c2ln";
+
+    fn contents(zip: &mut ZipArchive<File>, name: &str) -> String {
+        let mut text = String::new();
+        zip.by_name(name)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    #[test]
+    fn scrub_removes_secrets_and_redacts_text() {
+        let mut value = json!({
+            "app": {
+                "apiKey": "AIza123",
+                "plugins": [{ "private_key": "k", "Refresh-Token": "r" }],
+            },
+            "outbox": [{ "lastError": format!("failed: {LEAKED_URL}"), "attempts": 3 }],
+            "contact": "ada@example.com",
+            "keywords": ["release"],
+        });
+        scrub(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "app": {
+                    "apiKey": REMOVED,
+                    "plugins": [{ "private_key": REMOVED, "Refresh-Token": REMOVED }],
+                },
+                "outbox": [{
+                    "lastError": "failed: https://db.example.com/messages/c1.json?auth=[token]",
+                    "attempts": 3,
+                }],
+                "contact": "[email]",
+                "keywords": ["release"],
+            })
+        );
+    }
+
+    #[test]
+    fn bundles_hold_scrubbed_documents_logs_and_crash_reports() {
+        let dir = TempDir::new();
+        let log = dir.join("chitchat.2024-05-01.log");
+        fs::write(
+            &log,
+            format!(
+                "2024-05-01T10:00:00Z  WARN temp_chitchat_lib::rtdb::stream: listener failed: {LEAKED_URL}\n\
+                 2024-05-01T10:00:01Z  INFO temp_chitchat_lib::outbox: sent\n"
+            ),
+        )
+        .unwrap();
+        let report = dir.join("crash-1714557600000.txt");
+        fs::write(&report, "panicked for ada@example.com\n").unwrap();
+        let path = dir.join(FILE_NAME);
+
+        write(
+            &path,
+            [(
+                "summary.json",
+                json!({ "outbox": [{ "lastError": LEAKED_URL }] }),
+            )],
+            &[("logs", log), ("crashes", report)],
+        )
+        .unwrap();
+
+        let mut zip = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let mut names: Vec<String> = zip.file_names().map(String::from).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "crashes/crash-1714557600000.txt",
+                "logs/chitchat.2024-05-01.log",
+                "summary.json"
+            ]
+        );
+        let summary: Value = serde_json::from_str(&contents(&mut zip, "summary.json")).unwrap();
+        assert_eq!(
+            summary["outbox"][0]["lastError"],
+            "https://db.example.com/messages/c1.json?auth=[token]"
+        );
+        assert_eq!(
+            contents(&mut zip, "logs/chitchat.2024-05-01.log"),
+            "2024-05-01T10:00:00Z  WARN temp_chitchat_lib::rtdb::stream: listener failed: \
+             https://db.example.com/messages/c1.json?auth=[token]\n\
+             2024-05-01T10:00:01Z  INFO temp_chitchat_lib::outbox: sent\n"
+        );
+        assert_eq!(
+            contents(&mut zip, "crashes/crash-1714557600000.txt"),
+            "panicked for [email]\n"
+        );
+        for name in &names {
+            assert!(!contents(&mut zip, name).contains("eyJ"), "{name}");
+        }
+    }
+}
@@ -0,0 +1,53 @@
+// Connection state history
+//
+// The webview reports whenever its database connection or the network comes
+// or goes. The most recent changes are kept in memory for diagnostics
+// bundles, so a report like "messages don't arrive" can be matched against
+// the times the app was offline.
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::models::{now, ConnectionSource, Timestamp};
+
+const MAX_EVENTS: usize = 500;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionEvent {
+    pub timestamp: Timestamp,
+    pub source: ConnectionSource,
+    pub connected: bool,
+}
+
+#[derive(Default)]
+pub struct ConnectionHistory {
+    events: Mutex<VecDeque<ConnectionEvent>>,
+}
+
+impl ConnectionHistory {
+    /// Records a change; repeated reports of the same state are ignored.
+    pub fn record(&self, source: ConnectionSource, connected: bool) {
+        let mut events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+        let last = events.iter().rev().find(|event| event.source == source);
+        if last.is_some_and(|event| event.connected == connected) {
+            return;
+        }
+        tracing::info!(?source, connected, "connection state changed");
+        if events.len() == MAX_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(ConnectionEvent {
+            timestamp: now(),
+            source,
+            connected,
+        });
+    }
+
+    /// The recorded changes, oldest first.
+    pub fn events(&self) -> Vec<ConnectionEvent> {
+        let events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+        events.iter().cloned().collect()
+    }
+}
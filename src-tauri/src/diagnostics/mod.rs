@@ -0,0 +1,75 @@
+// Diagnostics mode
+//
+// The web inspector shows everything a signed-in session holds: ID tokens,
+// decrypted messages, the local store's contents. Release builds therefore
+// only have it with the `devtools` cargo feature, and even then it stays
+// off unless the app is started with `--diagnostics` and the user confirms
+// a warning. Either answer is logged, so a support session leaves a trace.
+//
+// Support also gets diagnostics bundles ([`bundle`]), which include the
+// connection state history kept by [`connection`].
+pub mod bundle;
+pub mod connection;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::error::Result;
+use crate::windows;
+
+pub const FLAG: &str = "--diagnostics";
+
+const WARNING: &str = "Chitchat was started in diagnostics mode. This enables the web \
+inspector, which shows your messages and sign-in details to anyone using this computer.\n\n\
+Only continue if you are working with Chitchat support.";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Whether the app was started with [`FLAG`].
+pub fn requested() -> bool {
+    std::env::args().skip(1).any(|arg| arg == FLAG)
+}
+
+/// Whether diagnostics mode was confirmed for this run.
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Opens the main window, first asking whether to enable diagnostics mode if
+/// it was requested.
+pub fn start(app: &AppHandle) -> Result<()> {
+    if !requested() {
+        windows::create_main(app, false)?;
+        return Ok(());
+    }
+    if !cfg!(any(debug_assertions, feature = "devtools")) {
+        tracing::warn!("{FLAG} ignored, this build has no web inspector");
+        windows::create_main(app, false)?;
+        return Ok(());
+    }
+
+    let handle = app.clone();
+    app.dialog()
+        .message(WARNING)
+        .title("Enable diagnostics mode?")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Enable diagnostics".into(),
+            "Start normally".into(),
+        ))
+        .show(move |confirmed| {
+            ENABLED.store(confirmed, Ordering::Relaxed);
+            if confirmed {
+                tracing::warn!("diagnostics mode enabled, web inspector is available");
+            } else {
+                tracing::info!("diagnostics mode declined");
+            }
+            if let Err(e) = windows::create_main(&handle, confirmed) {
+                tracing::error!("failed to open the main window: {e}");
+                handle.exit(1);
+            }
+        });
+    Ok(())
+}
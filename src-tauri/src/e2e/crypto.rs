@@ -0,0 +1,239 @@
+// Cryptographic primitives shared by X3DH, the double ratchet and sender keys
+//
+// X25519 for key agreement, Ed25519 for signatures, HKDF/HMAC-SHA256 for key
+// derivation and ChaCha20-Poly1305 for message encryption.
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use super::CryptoError;
+
+pub type Key = [u8; 32];
+
+pub fn random_key() -> Key {
+    let mut key = [0u8; 32];
+    rand::rng().fill_bytes(&mut key);
+    key
+}
+
+/// An X25519 key pair.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DhKeyPair {
+    secret: Key,
+    pub public: Key,
+}
+
+impl DhKeyPair {
+    pub fn generate() -> Self {
+        Self::from_secret(random_key())
+    }
+
+    pub fn from_secret(secret: Key) -> Self {
+        let public = PublicKey::from(&StaticSecret::from(secret)).to_bytes();
+        DhKeyPair { secret, public }
+    }
+
+    pub fn dh(&self, their_public: &Key) -> Key {
+        StaticSecret::from(self.secret)
+            .diffie_hellman(&PublicKey::from(*their_public))
+            .to_bytes()
+    }
+}
+
+/// An Ed25519 signing key pair, kept as its 32-byte seed.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SigningKeyPair {
+    seed: Key,
+    pub public: Key,
+}
+
+impl SigningKeyPair {
+    pub fn generate() -> Self {
+        Self::from_seed(random_key())
+    }
+
+    pub fn from_seed(seed: Key) -> Self {
+        let public = SigningKey::from_bytes(&seed).verifying_key().to_bytes();
+        SigningKeyPair { seed, public }
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        SigningKey::from_bytes(&self.seed)
+            .sign(message)
+            .to_bytes()
+            .to_vec()
+    }
+}
+
+pub fn verify(public: &Key, message: &[u8], signature: &[u8]) -> Result<(), CryptoError> {
+    let key = VerifyingKey::from_bytes(public).map_err(|_| CryptoError::BadSignature)?;
+    let signature = Signature::from_slice(signature).map_err(|_| CryptoError::BadSignature)?;
+    key.verify(message, &signature)
+        .map_err(|_| CryptoError::BadSignature)
+}
+
+pub fn hkdf(salt: &[u8], ikm: &[u8], info: &[u8], out: &mut [u8]) {
+    Hkdf::<Sha256>::new(Some(salt), ikm)
+        .expand(info, out)
+        .expect("HKDF output length is within bounds");
+}
+
+fn hmac(key: &Key, data: &[u8]) -> Key {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+/// Root-key KDF: mixes a DH output into the root key, yielding a new root key
+/// and a chain key.
+pub fn kdf_rk(root_key: &Key, dh_out: &Key) -> (Key, Key) {
+    let mut out = [0u8; 64];
+    hkdf(root_key, dh_out, b"Chitchat Ratchet", &mut out);
+    let (root, chain) = out.split_at(32);
+    (root.try_into().unwrap(), chain.try_into().unwrap())
+}
+
+/// Chain-key KDF: returns the next chain key and a message key.
+pub fn kdf_ck(chain_key: &Key) -> (Key, Key) {
+    (hmac(chain_key, &[0x02]), hmac(chain_key, &[0x01]))
+}
+
+fn message_cipher(message_key: &Key) -> (ChaCha20Poly1305, [u8; 12]) {
+    let mut out = [0u8; 44];
+    hkdf(&[0u8; 32], message_key, b"Chitchat Message Keys", &mut out);
+    let (key, nonce) = out.split_at(32);
+    let cipher = ChaCha20Poly1305::new_from_slice(key).expect("key is 32 bytes");
+    (cipher, nonce.try_into().unwrap())
+}
+
+/// Encrypts with a single-use message key, authenticating `ad` as well.
+pub fn seal(message_key: &Key, ad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    let (cipher, nonce) = message_cipher(message_key);
+    cipher
+        .encrypt(
+            &nonce.into(),
+            Payload {
+                msg: plaintext,
+                aad: ad,
+            },
+        )
+        .expect("ChaCha20-Poly1305 encryption cannot fail")
+}
+
+pub fn open(message_key: &Key, ad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let (cipher, nonce) = message_cipher(message_key);
+    cipher
+        .decrypt(
+            &nonce.into(),
+            Payload {
+                msg: ciphertext,
+                aad: ad,
+            },
+        )
+        .map_err(|_| CryptoError::Decrypt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn key(hex: &str) -> Key {
+        bytes(hex).try_into().unwrap()
+    }
+
+    #[test]
+    fn x25519_matches_rfc_7748() {
+        let alice = DhKeyPair::from_secret(key(
+            "77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a",
+        ));
+        let bob = DhKeyPair::from_secret(key(
+            "5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb",
+        ));
+        assert_eq!(
+            alice.public,
+            key("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a")
+        );
+        assert_eq!(
+            bob.public,
+            key("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f")
+        );
+        let shared = key("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742");
+        assert_eq!(alice.dh(&bob.public), shared);
+        assert_eq!(bob.dh(&alice.public), shared);
+    }
+
+    #[test]
+    fn ed25519_matches_rfc_8032() {
+        let signing = SigningKeyPair::from_seed(key(
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+        ));
+        assert_eq!(
+            signing.public,
+            key("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a")
+        );
+        let signature = signing.sign(b"");
+        assert_eq!(
+            signature,
+            bytes(
+                "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155\
+                 5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"
+            )
+        );
+        assert!(verify(&signing.public, b"", &signature).is_ok());
+        assert!(verify(&signing.public, b"x", &signature).is_err());
+    }
+
+    #[test]
+    fn key_derivation_known_answers() {
+        let (next, message_key) = kdf_ck(&[1; 32]);
+        assert_eq!(
+            next,
+            key("c31d79abaf8f2150ee1cfe3dc732eed02a56f79647909bad055a831cb762e9a2")
+        );
+        assert_eq!(
+            message_key,
+            key("cc6efb872c237f565ee82df42e4cab00098b13710395e3c6d29f2907d69e4f04")
+        );
+
+        let (root, chain) = kdf_rk(&[1; 32], &[2; 32]);
+        assert_eq!(
+            root,
+            key("fb4b4bf9daf5d81f6279b4404cc1c8140a6059ef16041c4eba49fb59aa36c3e9")
+        );
+        assert_eq!(
+            chain,
+            key("0c78458f55bd3f57d097ea30043ccff049d9224535280b8a8d9ce658bd84cfe8")
+        );
+    }
+
+    #[test]
+    fn seal_known_answer() {
+        let sealed = seal(&[3; 32], b"ad", b"hello");
+        assert_eq!(sealed, bytes("4b40e9c45583ba1a9e0a7270141fa9117170d45563"));
+        assert_eq!(open(&[3; 32], b"ad", &sealed).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn open_rejects_tampering() {
+        let sealed = seal(&[3; 32], b"ad", b"hello");
+        let mut flipped = sealed.clone();
+        flipped[0] ^= 1;
+        assert!(open(&[3; 32], b"ad", &flipped).is_err());
+        assert!(open(&[3; 32], b"other ad", &sealed).is_err());
+        assert!(open(&[4; 32], b"ad", &sealed).is_err());
+        assert!(open(&[3; 32], b"ad", &sealed[..sealed.len() - 1]).is_err());
+    }
+}
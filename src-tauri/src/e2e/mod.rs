@@ -0,0 +1,1120 @@
+// End-to-end encryption for chat messages
+//
+// Every device has its own identity key and publishes a prekey bundle at
+// `keys/{userId}/{deviceId}`. Direct chats are encrypted with X3DH and the
+// double ratchet towards each device of both participants; group chats are
+// encrypted once with a per-device sender key that is handed out over those
+// pairwise sessions. What a message says, its text and any attachment's
+// metadata and file key, only ever reaches RTDB inside the resulting
+// envelope, and all key material stays in the encrypted local store.
+mod crypto;
+mod ratchet;
+mod sender_key;
+mod state;
+mod wire;
+mod x3dh;
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+
+use self::crypto::{random_key, DhKeyPair, Key, SigningKeyPair};
+use self::ratchet::Ratchet;
+use self::sender_key::{Distribution, SenderKey};
+use self::state::{Identity, SessionRecord};
+use self::wire::{
+    B64Key, GroupMessage, PairwiseMessage, PreKeyBundle, SignedPreKey, X3dhHeader, ENVELOPE_VERSION,
+};
+use crate::error::{Error, Result};
+use crate::hex;
+use crate::models::{Attachment, IncomingMessage, Message};
+use crate::rtdb::Database;
+use crate::session::Session;
+use crate::store::Store;
+
+pub use self::wire::Envelope;
+
+/// Written to the chat summary instead of the message text.
+pub const ENCRYPTED_PREVIEW: &str = "🔒 Encrypted message";
+/// Shown in place of messages this device cannot decrypt.
+pub const UNDECRYPTABLE_TEXT: &str = "🔒 This message could not be decrypted";
+
+/// One-time prekeys kept published per device.
+const ONE_TIME_PRE_KEYS: usize = 20;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CryptoError {
+    #[error("message could not be decrypted")]
+    Decrypt,
+    #[error("invalid signature")]
+    BadSignature,
+    #[error("session cannot send yet")]
+    NoSendingChain,
+    #[error("too many skipped messages")]
+    TooManySkipped,
+    #[error("sender key belongs to another device")]
+    NotOwnSenderKey,
+    #[error("message was already decrypted")]
+    DuplicateMessage,
+    #[error("no session with {0}")]
+    NoSession(String),
+    #[error("unknown prekey {0}")]
+    UnknownPreKey(u32),
+    #[error("keys of {0} changed")]
+    IdentityChanged(String),
+    #[error("no sender key from {0}")]
+    MissingSenderKey(String),
+    #[error("{0} has not set up encryption yet")]
+    NoKeys(String),
+    #[error("message is not addressed to this device")]
+    NotForThisDevice,
+    #[error("message belongs to another chat")]
+    WrongChat,
+    #[error("sending device does not belong to the sender")]
+    WrongSender,
+    #[error("unsupported envelope version {0}")]
+    UnsupportedVersion(u32),
+    #[error("this device has no identity key")]
+    NoIdentity,
+}
+
+/// An encrypted outgoing message. The outbox keeps it until the message is
+/// stored, since encrypting again would use up another message key.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sealed {
+    pub envelope: Envelope,
+    /// Devices handed our sender key by this message, recorded by
+    /// [`E2e::confirm`] once the message is stored.
+    distributed: Option<(u32, Vec<String>)>,
+    /// Members that have not set up encryption and were left out.
+    pub without_keys: Vec<String>,
+}
+
+/// What a message says, as encrypted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Content {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<Attachment>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DirectPayload {
+    chat_id: String,
+    #[serde(flatten)]
+    content: Content,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DistributionPayload {
+    chat_id: String,
+    distribution: Distribution,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Members {
+    #[serde(default)]
+    participants: BTreeMap<String, bool>,
+    #[serde(default)]
+    is_group: Option<bool>,
+}
+
+/// Serializes access to sessions and sender keys, which encryption and
+/// decryption both advance.
+#[derive(Default)]
+pub struct E2e {
+    lock: Mutex<()>,
+}
+
+impl E2e {
+    /// Publishes this device's prekey bundle, creating its identity on first
+    /// use and topping up the one-time prekeys.
+    pub async fn publish_keys(&self, store: &Store, db: &Database, user_id: &str) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        publish(store, db, user_id).await.map(drop)
+    }
+
+    /// Removes this device's bundle so nobody encrypts for it any more.
+    pub async fn unpublish_keys(&self, store: &Store, db: &Database, user_id: &str) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        if let Some(identity) = state::identity(store, user_id)? {
+            db.remove(&bundle_path(&identity)).await?;
+        }
+        Ok(())
+    }
+
+    /// Encrypts `content` for every other device of the chat's members.
+    /// Members without published keys are left out and listed in the result.
+    pub async fn encrypt(
+        &self,
+        store: &Store,
+        db: &Database,
+        user_id: &str,
+        chat_id: &str,
+        content: &Content,
+    ) -> Result<Sealed> {
+        let _guard = self.lock.lock().await;
+        let identity = match state::identity(store, user_id)? {
+            Some(identity) => identity,
+            None => publish(store, db, user_id).await?,
+        };
+        let members: Members = db
+            .get(&format!("chats/{chat_id}"))
+            .await?
+            .ok_or_else(|| Error::UnknownChat(chat_id.into()))?;
+        let Recipients {
+            devices,
+            without_keys,
+        } = member_devices(db, &identity, &members.participants).await?;
+        if !without_keys.is_empty() {
+            tracing::warn!(
+                chat_id,
+                ?without_keys,
+                "members without keys will not get the message"
+            );
+        }
+
+        let mut sealed = if members.is_group == Some(true) {
+            encrypt_group(store, db, &identity, chat_id, &devices, content).await?
+        } else {
+            let payload = serde_json::to_vec(&DirectPayload {
+                chat_id: chat_id.into(),
+                content: content.clone(),
+            })?;
+            Sealed {
+                envelope: Envelope {
+                    v: ENVELOPE_VERSION,
+                    sender: identity.address(),
+                    dm: seal_for_devices(store, db, &identity, &devices, &payload).await?,
+                    group: None,
+                },
+                distributed: None,
+                without_keys: Vec::new(),
+            }
+        };
+        sealed.without_keys = without_keys;
+        Ok(sealed)
+    }
+
+    /// Records what an encrypted message handed out, once it is stored.
+    pub fn confirm(&self, store: &Store, chat_id: &str, sealed: &Sealed) -> Result<()> {
+        if let Some((key_id, addresses)) = &sealed.distributed {
+            state::add_sender_key_recipients(store, chat_id, *key_id, addresses)?;
+        }
+        Ok(())
+    }
+
+    /// Turns raw messages from RTDB into displayable ones and caches them.
+    /// Decrypted text is served from the cache afterwards, since every
+    /// message key is single-use and the same ciphertext cannot be opened
+    /// twice.
+    pub async fn decrypt_messages(
+        &self,
+        store: &Store,
+        user_id: Option<&str>,
+        chat_id: &str,
+        messages: Vec<IncomingMessage>,
+    ) -> Result<Vec<Message>> {
+        let _guard = self.lock.lock().await;
+        let identity = match user_id {
+            Some(user_id) => state::identity(store, user_id)?,
+            None => None,
+        };
+        let mut decrypted = Vec::with_capacity(messages.len());
+        let mut to_cache = Vec::new();
+
+        for incoming in messages {
+            let mut message = Message {
+                id: incoming.id,
+                sender_id: incoming.sender_id,
+                sender_name: incoming.sender_name,
+                text: incoming.text.unwrap_or_default(),
+                timestamp: incoming.timestamp,
+                archived: None,
+                attachment: None,
+            };
+            let Some(e2e) = incoming.e2e else {
+                to_cache.push(message.clone());
+                decrypted.push(message);
+                continue;
+            };
+
+            if let Some(cached) = store.message(chat_id, &message.id)? {
+                message.text = cached.text;
+                message.attachment = cached.attachment;
+                if cached.timestamp != message.timestamp {
+                    to_cache.push(message.clone());
+                }
+                decrypted.push(message);
+                continue;
+            }
+
+            let opened = match &identity {
+                Some(identity) => open_envelope(store, identity, chat_id, &message.sender_id, e2e),
+                None => Err(CryptoError::NoIdentity.into()),
+            };
+            match opened {
+                Ok(content) => {
+                    message.text = content.text;
+                    message.attachment = content.attachment;
+                    to_cache.push(message.clone());
+                }
+                Err(e) => {
+                    tracing::warn!(chat_id, message_id = message.id, "cannot decrypt: {e}");
+                    message.text = UNDECRYPTABLE_TEXT.into();
+                }
+            }
+            decrypted.push(message);
+        }
+
+        store.upsert_messages(chat_id, &to_cache)?;
+        Ok(decrypted)
+    }
+}
+
+/// Publishes this device's keys for the current session in the background.
+pub fn spawn_publish(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let session = app.state::<Session>();
+        let Some(credentials) = session.credentials() else {
+            return;
+        };
+        let store = app.state::<Store>();
+        let published = async {
+            let db = session.database(&reqwest::Client::new())?;
+            app.state::<E2e>()
+                .publish_keys(&store, &db, &credentials.user_id)
+                .await
+        };
+        if let Err(e) = published.await {
+            tracing::warn!("failed to publish keys: {e}");
+        }
+    });
+}
+
+async fn publish(store: &Store, db: &Database, user_id: &str) -> Result<Identity> {
+    let identity = match state::identity(store, user_id)? {
+        Some(identity) => identity,
+        None => {
+            let identity = new_identity(user_id);
+            state::save_identity(store, &identity)?;
+            identity
+        }
+    };
+
+    let held = state::pre_key_publics(store, user_id)?.len();
+    if held < ONE_TIME_PRE_KEYS {
+        state::add_pre_keys(store, user_id, ONE_TIME_PRE_KEYS - held)?;
+    }
+    db.set(&bundle_path(&identity), &own_bundle(store, &identity)?)
+        .await?;
+    Ok(identity)
+}
+
+/// The prekey bundle this device publishes.
+fn own_bundle(store: &Store, identity: &Identity) -> Result<PreKeyBundle> {
+    Ok(PreKeyBundle {
+        identity_key: identity.dh.public,
+        signing_key: identity.signing.public,
+        signed_pre_key: SignedPreKey {
+            id: identity.signed_pre_key_id,
+            key: identity.signed_pre_key.public,
+            signature: identity.signed_pre_key_signature.clone(),
+        },
+        one_time_pre_keys: state::pre_key_publics(store, &identity.user_id)?
+            .into_iter()
+            .map(|(id, key)| (id.to_string(), B64Key(key)))
+            .collect(),
+    })
+}
+
+fn new_identity(user_id: &str) -> Identity {
+    let signing = SigningKeyPair::generate();
+    let signed_pre_key = DhKeyPair::generate();
+    Identity {
+        user_id: user_id.into(),
+        device_id: hex::encode(&random_key()[..8]),
+        dh: DhKeyPair::generate(),
+        signed_pre_key_id: 1,
+        signed_pre_key_signature: signing.sign(&signed_pre_key.public),
+        signed_pre_key,
+        signing,
+    }
+}
+
+fn bundle_path(identity: &Identity) -> String {
+    format!("keys/{}/{}", identity.user_id, identity.device_id)
+}
+
+/// Who a message is encrypted for.
+struct Recipients {
+    /// Every device of the chat's members except this one, with its bundle.
+    devices: Vec<(String, PreKeyBundle)>,
+    /// Members that have not published any keys.
+    without_keys: Vec<String>,
+}
+
+/// Looks up the devices of the chat's members.
+async fn member_devices(
+    db: &Database,
+    identity: &Identity,
+    participants: &BTreeMap<String, bool>,
+) -> Result<Recipients> {
+    let mut published = BTreeMap::new();
+    for (user_id, _) in participants.iter().filter(|(_, member)| **member) {
+        let bundles: BTreeMap<String, serde_json::Value> = db
+            .get(&format!("keys/{user_id}"))
+            .await?
+            .unwrap_or_default();
+        published.insert(user_id.clone(), bundles);
+    }
+    pick_devices(identity, published)
+}
+
+/// Picks the devices to encrypt for out of each member's published bundles.
+/// Members without a usable bundle are skipped, unless that leaves nobody
+/// else to read the message.
+fn pick_devices(
+    identity: &Identity,
+    published: BTreeMap<String, BTreeMap<String, serde_json::Value>>,
+) -> Result<Recipients> {
+    let me = identity.address();
+    let mut devices = Vec::new();
+    let mut without_keys = Vec::new();
+    let mut others = 0;
+    for (user_id, bundles) in published {
+        let before = devices.len();
+        for (device_id, bundle) in bundles {
+            let address = format!("{user_id}:{device_id}");
+            if address == me {
+                continue;
+            }
+            match serde_json::from_value(bundle) {
+                Ok(bundle) => devices.push((address, bundle)),
+                Err(e) => tracing::warn!(address, "ignoring malformed bundle: {e}"),
+            }
+        }
+        if user_id == identity.user_id {
+            continue;
+        }
+        others += 1;
+        if devices.len() == before {
+            without_keys.push(user_id);
+        }
+    }
+    if others > 0 && without_keys.len() == others {
+        return Err(CryptoError::NoKeys(without_keys.join(", ")).into());
+    }
+    Ok(Recipients {
+        devices,
+        without_keys,
+    })
+}
+
+async fn encrypt_group(
+    store: &Store,
+    db: &Database,
+    identity: &Identity,
+    chat_id: &str,
+    devices: &[(String, PreKeyBundle)],
+    content: &Content,
+) -> Result<Sealed> {
+    let me = identity.address();
+    let (mut key, recipients) = match state::latest_sender_key(store, chat_id, &me)? {
+        Some(key) => {
+            let recipients = state::sender_key_recipients(store, chat_id, key.key_id)?;
+            if must_rotate(&recipients, devices) {
+                (SenderKey::generate(key.key_id + 1), Vec::new())
+            } else {
+                (key, recipients)
+            }
+        }
+        None => (SenderKey::generate(0), Vec::new()),
+    };
+
+    let newcomers: Vec<_> = devices
+        .iter()
+        .filter(|(address, _)| !recipients.contains(address))
+        .cloned()
+        .collect();
+    let payload = serde_json::to_vec(&DistributionPayload {
+        chat_id: chat_id.into(),
+        distribution: key.distribution(),
+    })?;
+    let dist = seal_for_devices(store, db, identity, &newcomers, &payload).await?;
+
+    let plaintext = serde_json::to_vec(content)?;
+    let (n, ct, sig) = key.encrypt(&group_ad(chat_id, &me, key.key_id), &plaintext)?;
+    state::save_sender_key(store, chat_id, &me, &key)?;
+
+    Ok(Sealed {
+        distributed: Some((key.key_id, dist.keys().cloned().collect())),
+        without_keys: Vec::new(),
+        envelope: Envelope {
+            v: ENVELOPE_VERSION,
+            sender: me,
+            dm: BTreeMap::new(),
+            group: Some(GroupMessage {
+                key_id: key.key_id,
+                n,
+                ct,
+                sig,
+                dist,
+            }),
+        },
+    })
+}
+
+/// Whether a sender key must be replaced: as soon as a device that knows it
+/// has left the chat.
+fn must_rotate(recipients: &[String], devices: &[(String, PreKeyBundle)]) -> bool {
+    !recipients
+        .iter()
+        .all(|r| devices.iter().any(|(address, _)| address == r))
+}
+
+/// Encrypts `plaintext` for each device. Devices whose keys fail
+/// verification are left out rather than blocking the message.
+async fn seal_for_devices(
+    store: &Store,
+    db: &Database,
+    identity: &Identity,
+    devices: &[(String, PreKeyBundle)],
+    plaintext: &[u8],
+) -> Result<BTreeMap<String, PairwiseMessage>> {
+    let mut sealed = BTreeMap::new();
+    for (address, bundle) in devices {
+        match encrypt_pairwise(store, db, identity, address, bundle, plaintext).await {
+            Ok(message) => {
+                sealed.insert(address.clone(), message);
+            }
+            Err(Error::E2e(e @ (CryptoError::BadSignature | CryptoError::IdentityChanged(_)))) => {
+                tracing::warn!(address, "skipping device: {e}");
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(sealed)
+}
+
+async fn encrypt_pairwise(
+    store: &Store,
+    db: &Database,
+    identity: &Identity,
+    address: &str,
+    bundle: &PreKeyBundle,
+    plaintext: &[u8],
+) -> Result<PairwiseMessage> {
+    let existing = state::sessions(store, address)?
+        .into_iter()
+        .find(|session| session.ratchet.can_send());
+    let mut session = match existing {
+        Some(session) => session,
+        None => start_session(store, db, identity, address, bundle).await?,
+    };
+    let (header, ct) = session.ratchet.encrypt(plaintext)?;
+    state::save_session(store, address, &session)?;
+    Ok(PairwiseMessage::new(session.pending_x3dh, header, ct))
+}
+
+async fn start_session(
+    store: &Store,
+    db: &Database,
+    identity: &Identity,
+    address: &str,
+    bundle: &PreKeyBundle,
+) -> Result<SessionRecord> {
+    let session = new_session(store, identity, address, bundle)?;
+
+    // Claim the one-time prekey so nobody else starts a session with it
+    if let Some(id) = session
+        .pending_x3dh
+        .as_ref()
+        .and_then(|x3dh| x3dh.one_time_pre_key_id)
+    {
+        let path = format!("keys/{}/oneTimePreKeys/{id}", address.replacen(':', "/", 1));
+        if let Err(e) = db.remove(&path).await {
+            tracing::warn!(address, "failed to claim prekey {id}: {e}");
+        }
+    }
+    Ok(session)
+}
+
+fn new_session(
+    store: &Store,
+    identity: &Identity,
+    address: &str,
+    bundle: &PreKeyBundle,
+) -> Result<SessionRecord> {
+    trust(store, address, &bundle.identity_key, &bundle.signing_key)?;
+    let initiated = x3dh::initiate(identity, bundle)?;
+    Ok(SessionRecord {
+        base_key: initiated.header.ephemeral_key,
+        ratchet: Ratchet::init_initiator(
+            initiated.shared_secret,
+            bundle.signed_pre_key.key,
+            initiated.associated_data,
+        ),
+        pending_x3dh: Some(initiated.header),
+    })
+}
+
+fn open_envelope(
+    store: &Store,
+    identity: &Identity,
+    chat_id: &str,
+    sender_id: &str,
+    raw: serde_json::Value,
+) -> Result<Content> {
+    let envelope: Envelope = serde_json::from_value(raw)?;
+    if envelope.v != ENVELOPE_VERSION {
+        return Err(CryptoError::UnsupportedVersion(envelope.v).into());
+    }
+    // The sending device must belong to the user the message claims to be from
+    if envelope.sender.split_once(':').map(|(user, _)| user) != Some(sender_id) {
+        return Err(CryptoError::WrongSender.into());
+    }
+    let me = identity.address();
+
+    let Some(group) = envelope.group else {
+        let message = envelope.dm.get(&me).ok_or(CryptoError::NotForThisDevice)?;
+        let payload: DirectPayload = serde_json::from_slice(&decrypt_pairwise(
+            store,
+            identity,
+            &envelope.sender,
+            message,
+        )?)?;
+        if payload.chat_id != chat_id {
+            return Err(CryptoError::WrongChat.into());
+        }
+        return Ok(payload.content);
+    };
+
+    let mut key = match state::sender_key(store, chat_id, &envelope.sender, group.key_id)? {
+        Some(key) => key,
+        None => {
+            let message = group
+                .dist
+                .get(&me)
+                .ok_or_else(|| CryptoError::MissingSenderKey(envelope.sender.clone()))?;
+            let payload: DistributionPayload = serde_json::from_slice(&decrypt_pairwise(
+                store,
+                identity,
+                &envelope.sender,
+                message,
+            )?)?;
+            if payload.chat_id != chat_id || payload.distribution.key_id != group.key_id {
+                return Err(CryptoError::WrongChat.into());
+            }
+            // Kept even if this message fails, as the distribution cannot be
+            // decrypted again
+            let key = SenderKey::from_distribution(&payload.distribution);
+            state::save_sender_key(store, chat_id, &envelope.sender, &key)?;
+            key
+        }
+    };
+    let plaintext = key.decrypt(
+        &group_ad(chat_id, &envelope.sender, group.key_id),
+        group.n,
+        &group.ct,
+        &group.sig,
+    )?;
+    state::save_sender_key(store, chat_id, &envelope.sender, &key)?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+fn decrypt_pairwise(
+    store: &Store,
+    identity: &Identity,
+    address: &str,
+    message: &PairwiseMessage,
+) -> Result<Vec<u8>> {
+    let sessions = state::sessions(store, address)?;
+    let base_key = message.x3dh.as_ref().map(|x3dh| x3dh.ephemeral_key);
+
+    // The first messages of a session carry the X3DH header
+    if let Some(x3dh) = &message.x3dh {
+        if !sessions.iter().any(|s| Some(s.base_key) == base_key) {
+            return accept_session(store, identity, address, message, x3dh);
+        }
+    }
+
+    let header = message.header();
+    for mut session in sessions
+        .into_iter()
+        .filter(|s| base_key.is_none_or(|key| s.base_key == key))
+    {
+        if let Ok(plaintext) = session.ratchet.decrypt(&header, &message.ct) {
+            // The peer has replied, so it knows the session now
+            session.pending_x3dh = None;
+            state::save_session(store, address, &session)?;
+            return Ok(plaintext);
+        }
+    }
+    Err(CryptoError::NoSession(address.into()).into())
+}
+
+fn accept_session(
+    store: &Store,
+    identity: &Identity,
+    address: &str,
+    message: &PairwiseMessage,
+    x3dh: &X3dhHeader,
+) -> Result<Vec<u8>> {
+    if x3dh.signed_pre_key_id != identity.signed_pre_key_id {
+        return Err(CryptoError::UnknownPreKey(x3dh.signed_pre_key_id).into());
+    }
+    let one_time = match x3dh.one_time_pre_key_id {
+        Some(id) => Some(
+            state::pre_key(store, &identity.user_id, id)?.ok_or(CryptoError::UnknownPreKey(id))?,
+        ),
+        None => None,
+    };
+
+    let (shared_secret, ad) =
+        x3dh::respond(identity, x3dh, &identity.signed_pre_key, one_time.as_ref());
+    let mut ratchet = Ratchet::init_responder(shared_secret, identity.signed_pre_key.clone(), ad);
+    let plaintext = ratchet.decrypt(&message.header(), &message.ct)?;
+
+    trust(store, address, &x3dh.identity_key, &x3dh.signing_key)?;
+    state::save_session(
+        store,
+        address,
+        &SessionRecord {
+            base_key: x3dh.ephemeral_key,
+            ratchet,
+            pending_x3dh: None,
+        },
+    )?;
+    if let Some(id) = x3dh.one_time_pre_key_id {
+        state::remove_pre_key(store, &identity.user_id, id)?;
+    }
+    Ok(plaintext)
+}
+
+/// Trust on first use: remembers a device's keys and refuses to talk to it
+/// if they ever change. Device IDs are random per install, so legitimate
+/// key changes show up as a new device instead.
+fn trust(store: &Store, address: &str, identity_key: &Key, signing_key: &Key) -> Result<()> {
+    match state::peer_identity(store, address)? {
+        Some(known) if known == (*identity_key, *signing_key) => Ok(()),
+        Some(_) => Err(CryptoError::IdentityChanged(address.into()).into()),
+        None => state::save_peer_identity(store, address, identity_key, signing_key),
+    }
+}
+
+fn group_ad(chat_id: &str, sender: &str, key_id: u32) -> Vec<u8> {
+    format!("{chat_id}/{sender}/{key_id}").into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ChatMute, MessageNotification, NotificationOutcome, NotificationSettings};
+    use crate::notifications::{self, rules};
+
+    /// A device with its own store, as after publishing its keys.
+    fn device(user_id: &str) -> (Store, Identity) {
+        let store = Store::open_in_memory();
+        let identity = new_identity(user_id);
+        state::save_identity(&store, &identity).unwrap();
+        state::add_pre_keys(&store, user_id, 2).unwrap();
+        (store, identity)
+    }
+
+    fn seal_direct(
+        store: &Store,
+        identity: &Identity,
+        to: &Identity,
+        bundle: &PreKeyBundle,
+        chat_id: &str,
+        text: &str,
+    ) -> Envelope {
+        let content = Content {
+            text: text.into(),
+            attachment: None,
+        };
+        seal_content(store, identity, to, bundle, chat_id, content)
+    }
+
+    /// Encrypts a direct message the way `E2e::encrypt` does, minus RTDB.
+    fn seal_content(
+        store: &Store,
+        identity: &Identity,
+        to: &Identity,
+        bundle: &PreKeyBundle,
+        chat_id: &str,
+        content: Content,
+    ) -> Envelope {
+        let address = to.address();
+        let existing = state::sessions(store, &address)
+            .unwrap()
+            .into_iter()
+            .find(|session| session.ratchet.can_send());
+        let mut session = match existing {
+            Some(session) => session,
+            None => new_session(store, identity, &address, bundle).unwrap(),
+        };
+        let payload = serde_json::to_vec(&DirectPayload {
+            chat_id: chat_id.into(),
+            content,
+        })
+        .unwrap();
+        let (header, ct) = session.ratchet.encrypt(&payload).unwrap();
+        state::save_session(store, &address, &session).unwrap();
+        Envelope {
+            v: ENVELOPE_VERSION,
+            sender: identity.address(),
+            dm: BTreeMap::from([(
+                address,
+                PairwiseMessage::new(session.pending_x3dh, header, ct),
+            )]),
+            group: None,
+        }
+    }
+
+    fn open(
+        store: &Store,
+        identity: &Identity,
+        sender: &str,
+        envelope: &Envelope,
+    ) -> Result<String> {
+        open_envelope(
+            store,
+            identity,
+            "chat",
+            sender,
+            serde_json::to_value(envelope).unwrap(),
+        )
+        .map(|content| content.text)
+    }
+
+    #[test]
+    fn direct_messages_round_trip() {
+        let (alice_store, alice) = device("alice");
+        let (bob_store, bob) = device("bob");
+        let bob_bundle = own_bundle(&bob_store, &bob).unwrap();
+        let alice_bundle = own_bundle(&alice_store, &alice).unwrap();
+
+        let first = seal_direct(&alice_store, &alice, &bob, &bob_bundle, "chat", "hi bob");
+        assert!(first.dm[&bob.address()].x3dh.is_some());
+        assert_eq!(open(&bob_store, &bob, "alice", &first).unwrap(), "hi bob");
+        // The one-time prekey is used up
+        assert_eq!(state::pre_key_publics(&bob_store, "bob").unwrap().len(), 1);
+
+        // Until Bob replies, Alice keeps sending the X3DH header
+        let second = seal_direct(
+            &alice_store,
+            &alice,
+            &bob,
+            &bob_bundle,
+            "chat",
+            "still there?",
+        );
+        assert!(second.dm[&bob.address()].x3dh.is_some());
+        assert_eq!(
+            open(&bob_store, &bob, "alice", &second).unwrap(),
+            "still there?"
+        );
+
+        let reply = seal_direct(&bob_store, &bob, &alice, &alice_bundle, "chat", "hi alice");
+        assert!(reply.dm[&alice.address()].x3dh.is_none());
+        assert_eq!(
+            open(&alice_store, &alice, "bob", &reply).unwrap(),
+            "hi alice"
+        );
+
+        let third = seal_direct(&alice_store, &alice, &bob, &bob_bundle, "chat", "great");
+        assert!(third.dm[&bob.address()].x3dh.is_none());
+        assert_eq!(open(&bob_store, &bob, "alice", &third).unwrap(), "great");
+    }
+
+    #[test]
+    fn attachments_travel_inside_the_envelope() {
+        let (alice_store, alice) = device("alice");
+        let (bob_store, bob) = device("bob");
+        let bundle = own_bundle(&bob_store, &bob).unwrap();
+        let attachment = Attachment {
+            name: "holiday.jpg".into(),
+            mime_type: "image/jpeg".into(),
+            size: 1234,
+            sha256: "ab".repeat(32),
+            storage_path: format!("attachments/chat/{}", "cd".repeat(32)),
+            key: "a2V5".into(),
+            image: None,
+        };
+        let content = Content {
+            text: "look".into(),
+            attachment: Some(attachment),
+        };
+
+        let envelope = seal_content(&alice_store, &alice, &bob, &bundle, "chat", content.clone());
+        let raw = serde_json::to_string(&envelope).unwrap();
+        assert!(!raw.contains("holiday") && !raw.contains("a2V5"));
+        let opened = open_envelope(
+            &bob_store,
+            &bob,
+            "chat",
+            "alice",
+            serde_json::to_value(&envelope).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(opened, content);
+    }
+
+    #[test]
+    fn first_messages_may_arrive_out_of_order() {
+        let (alice_store, alice) = device("alice");
+        let (bob_store, bob) = device("bob");
+        let bundle = own_bundle(&bob_store, &bob).unwrap();
+
+        let first = seal_direct(&alice_store, &alice, &bob, &bundle, "chat", "one");
+        let second = seal_direct(&alice_store, &alice, &bob, &bundle, "chat", "two");
+        assert_eq!(open(&bob_store, &bob, "alice", &second).unwrap(), "two");
+        assert_eq!(open(&bob_store, &bob, "alice", &first).unwrap(), "one");
+    }
+
+    #[test]
+    fn rejects_tampered_and_misaddressed_envelopes() {
+        let (alice_store, alice) = device("alice");
+        let (bob_store, bob) = device("bob");
+        let (carol_store, carol) = device("carol");
+        let bundle = own_bundle(&bob_store, &bob).unwrap();
+        let envelope = seal_direct(&alice_store, &alice, &bob, &bundle, "chat", "secret");
+
+        let mut tampered = envelope.clone();
+        tampered.dm.get_mut(&bob.address()).unwrap().ct[0] ^= 1;
+        assert!(open(&bob_store, &bob, "alice", &tampered).is_err());
+
+        // Claims to come from someone else
+        assert!(matches!(
+            open(&bob_store, &bob, "mallory", &envelope),
+            Err(Error::E2e(CryptoError::WrongSender))
+        ));
+        assert!(matches!(
+            open(&carol_store, &carol, "alice", &envelope),
+            Err(Error::E2e(CryptoError::NotForThisDevice))
+        ));
+        let newer = Envelope {
+            v: ENVELOPE_VERSION + 1,
+            ..envelope.clone()
+        };
+        assert!(matches!(
+            open(&bob_store, &bob, "alice", &newer),
+            Err(Error::E2e(CryptoError::UnsupportedVersion(_)))
+        ));
+
+        assert_eq!(
+            open(&bob_store, &bob, "alice", &envelope).unwrap(),
+            "secret"
+        );
+        // Each message opens once
+        assert!(open(&bob_store, &bob, "alice", &envelope).is_err());
+
+        // Replayed into another chat
+        let envelope = seal_direct(&alice_store, &alice, &bob, &bundle, "chat", "again");
+        assert!(matches!(
+            open_envelope(
+                &bob_store,
+                &bob,
+                "other",
+                "alice",
+                serde_json::to_value(&envelope).unwrap()
+            ),
+            Err(Error::E2e(CryptoError::WrongChat))
+        ));
+    }
+
+    #[test]
+    fn changed_identity_keys_are_refused() {
+        let (alice_store, alice) = device("alice");
+        let (bob_store, bob) = device("bob");
+        new_session(
+            &alice_store,
+            &alice,
+            &bob.address(),
+            &own_bundle(&bob_store, &bob).unwrap(),
+        )
+        .unwrap();
+
+        let impostor = Identity {
+            user_id: bob.user_id.clone(),
+            device_id: bob.device_id.clone(),
+            ..new_identity("bob")
+        };
+        let bundle = own_bundle(&bob_store, &impostor).unwrap();
+        assert!(matches!(
+            new_session(&alice_store, &alice, &bob.address(), &bundle),
+            Err(Error::E2e(CryptoError::IdentityChanged(_)))
+        ));
+    }
+
+    fn published(
+        store: &Store,
+        members: &[(&str, &[&Identity])],
+    ) -> BTreeMap<String, BTreeMap<String, serde_json::Value>> {
+        members
+            .iter()
+            .map(|(user_id, devices)| {
+                let bundles = devices
+                    .iter()
+                    .map(|identity| {
+                        let bundle = own_bundle(store, identity).unwrap();
+                        (
+                            identity.device_id.clone(),
+                            serde_json::to_value(bundle).unwrap(),
+                        )
+                    })
+                    .collect();
+                (user_id.to_string(), bundles)
+            })
+            .collect()
+    }
+
+    fn addresses(recipients: &Recipients) -> Vec<&str> {
+        recipients
+            .devices
+            .iter()
+            .map(|(address, _)| address.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn members_without_keys_are_skipped_and_reported() {
+        let (store, me) = device("alice");
+        let my_laptop = new_identity("alice");
+        let bob = new_identity("bob");
+        let members = published(
+            &store,
+            &[
+                ("alice", &[&me, &my_laptop]),
+                ("bob", &[&bob]),
+                ("carol", &[]),
+            ],
+        );
+
+        let recipients = pick_devices(&me, members).unwrap();
+        let mut expected = vec![my_laptop.address(), bob.address()];
+        expected.sort();
+        assert_eq!(addresses(&recipients), expected);
+        assert_eq!(recipients.without_keys, ["carol"]);
+    }
+
+    #[test]
+    fn malformed_bundles_count_as_no_keys() {
+        let (store, me) = device("alice");
+        let bob = new_identity("bob");
+        let mut members = published(
+            &store,
+            &[("alice", &[&me]), ("bob", &[&bob]), ("carol", &[])],
+        );
+        members
+            .get_mut("carol")
+            .unwrap()
+            .insert("laptop".into(), serde_json::json!({ "identityKey": 1 }));
+
+        let recipients = pick_devices(&me, members).unwrap();
+        assert_eq!(addresses(&recipients), [bob.address()]);
+        assert_eq!(recipients.without_keys, ["carol"]);
+    }
+
+    #[test]
+    fn no_member_with_keys_is_an_error() {
+        let (store, me) = device("alice");
+        let members = published(&store, &[("alice", &[&me]), ("bob", &[]), ("carol", &[])]);
+        assert!(matches!(
+            pick_devices(&me, members),
+            Err(Error::E2e(CryptoError::NoKeys(users))) if users == "bob, carol"
+        ));
+
+        // Talking only to our own devices is fine
+        let members = published(&store, &[("alice", &[&me])]);
+        let recipients = pick_devices(&me, members).unwrap();
+        assert!(recipients.devices.is_empty() && recipients.without_keys.is_empty());
+    }
+
+    #[test]
+    fn sender_keys_rotate_once_a_device_leaves() {
+        let (store, _) = device("alice");
+        let (bob, carol) = (new_identity("bob"), new_identity("carol"));
+        let devices: Vec<_> = [&bob, &carol]
+            .iter()
+            .map(|identity| (identity.address(), own_bundle(&store, identity).unwrap()))
+            .collect();
+
+        assert!(!must_rotate(&[], &devices));
+        assert!(!must_rotate(&[bob.address()], &devices));
+        assert!(!must_rotate(&[bob.address(), carol.address()], &devices));
+        assert!(must_rotate(&[bob.address()], &devices[1..]));
+        assert!(must_rotate(&["dave:1".into()], &devices));
+    }
+
+    #[test]
+    fn decrypted_mentions_reach_the_notification_rules() {
+        let (alice_store, alice) = device("alice");
+        let (bob_store, bob) = device("bob");
+        let bob_bundle = own_bundle(&bob_store, &bob).unwrap();
+        let text = "@bob can you check the build?";
+        let envelope = seal_direct(&alice_store, &alice, &bob, &bob_bundle, "chat", text);
+        let incoming = IncomingMessage {
+            id: "-m1".into(),
+            sender_id: "alice".into(),
+            sender_name: Some("Alice".into()),
+            text: None,
+            e2e: Some(serde_json::to_value(&envelope).unwrap()),
+            timestamp: 2_000,
+        };
+        let messages = tauri::async_runtime::block_on(E2e::default().decrypt_messages(
+            &bob_store,
+            Some("bob"),
+            "chat",
+            vec![incoming],
+        ))
+        .unwrap();
+
+        let unseen: Vec<MessageNotification> = notifications::unseen(&messages, "bob", 1_000)
+            .map(|message| notifications::for_message("chat", None, message))
+            .collect();
+        assert_eq!(unseen.len(), 1);
+        assert_eq!(unseen[0].sender_name, "Alice");
+        assert_eq!(unseen[0].text, text);
+        assert_eq!(notifications::unseen(&messages, "bob", 2_000).count(), 0);
+        assert_eq!(notifications::unseen(&messages, "alice", 1_000).count(), 0);
+
+        // The chat is muted, so only the mention gets through; the chat
+        // summary alone would not have
+        let settings = NotificationSettings {
+            muted_chats: vec![ChatMute {
+                chat_id: "chat".into(),
+                until: None,
+            }],
+            ..Default::default()
+        };
+        let names = ["bob".to_string()];
+        let context = rules::Context {
+            now: 3_000,
+            utc_offset_minutes: 0,
+            muted: false,
+            focused: false,
+            names: &names,
+        };
+        assert_eq!(
+            rules::message(&settings, &unseen[0], &context),
+            NotificationOutcome::Shown
+        );
+        let summary = MessageNotification {
+            text: ENCRYPTED_PREVIEW.into(),
+            ..unseen[0].clone()
+        };
+        assert_eq!(
+            rules::message(&settings, &summary, &context),
+            NotificationOutcome::ChatMuted
+        );
+    }
+}
@@ -0,0 +1,316 @@
+// Double ratchet for pairwise (device-to-device) sessions
+//
+// Follows the Signal specification: a DH ratchet step whenever the other side
+// sends a new ratchet key, symmetric-key ratchets for each message, and a
+// bounded store of skipped message keys for out-of-order delivery.
+use serde::{Deserialize, Serialize};
+
+use super::crypto::{kdf_ck, kdf_rk, open, seal, DhKeyPair, Key};
+use super::CryptoError;
+
+/// Most message keys a single chain may skip ahead.
+const MAX_SKIP: u32 = 1000;
+/// Most skipped keys retained per session.
+const MAX_STORED_SKIPPED: usize = 2000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Header {
+    /// The sender's current ratchet public key.
+    pub dh: Key,
+    /// Number of messages in the sender's previous sending chain.
+    pub pn: u32,
+    /// Message number in the current sending chain.
+    pub n: u32,
+}
+
+impl Header {
+    fn encode(&self) -> [u8; 40] {
+        let mut out = [0u8; 40];
+        out[..32].copy_from_slice(&self.dh);
+        out[32..36].copy_from_slice(&self.pn.to_be_bytes());
+        out[36..].copy_from_slice(&self.n.to_be_bytes());
+        out
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SkippedKey {
+    dh: Key,
+    n: u32,
+    key: Key,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Ratchet {
+    dhs: DhKeyPair,
+    dhr: Option<Key>,
+    rk: Key,
+    cks: Option<Key>,
+    ckr: Option<Key>,
+    ns: u32,
+    nr: u32,
+    pn: u32,
+    skipped: Vec<SkippedKey>,
+    /// Associated data from X3DH (both identity keys).
+    ad: Vec<u8>,
+}
+
+impl Ratchet {
+    /// Session initiator: knows the responder's signed prekey.
+    pub fn init_initiator(shared_secret: Key, their_ratchet_key: Key, ad: Vec<u8>) -> Self {
+        let dhs = DhKeyPair::generate();
+        let (rk, cks) = kdf_rk(&shared_secret, &dhs.dh(&their_ratchet_key));
+        Ratchet {
+            dhs,
+            dhr: Some(their_ratchet_key),
+            rk,
+            cks: Some(cks),
+            ckr: None,
+            ns: 0,
+            nr: 0,
+            pn: 0,
+            skipped: Vec::new(),
+            ad,
+        }
+    }
+
+    /// Session responder: its signed prekey is the first ratchet key.
+    pub fn init_responder(shared_secret: Key, signed_pre_key: DhKeyPair, ad: Vec<u8>) -> Self {
+        Ratchet {
+            dhs: signed_pre_key,
+            dhr: None,
+            rk: shared_secret,
+            cks: None,
+            ckr: None,
+            ns: 0,
+            nr: 0,
+            pn: 0,
+            skipped: Vec::new(),
+            ad,
+        }
+    }
+
+    pub fn can_send(&self) -> bool {
+        self.cks.is_some()
+    }
+
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<(Header, Vec<u8>), CryptoError> {
+        // A responder cannot send until it has received the first message
+        let cks = self.cks.ok_or(CryptoError::NoSendingChain)?;
+        let (next, message_key) = kdf_ck(&cks);
+        self.cks = Some(next);
+
+        let header = Header {
+            dh: self.dhs.public,
+            pn: self.pn,
+            n: self.ns,
+        };
+        self.ns += 1;
+
+        Ok((header, seal(&message_key, &self.ad_for(&header), plaintext)))
+    }
+
+    /// Decrypts a message. The state is only updated if decryption succeeds,
+    /// so a forged or corrupt message cannot desynchronize the session.
+    pub fn decrypt(&mut self, header: &Header, ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        if let Some(index) = self
+            .skipped
+            .iter()
+            .position(|s| s.dh == header.dh && s.n == header.n)
+        {
+            let plaintext = open(&self.skipped[index].key, &self.ad_for(header), ciphertext)?;
+            self.skipped.remove(index);
+            return Ok(plaintext);
+        }
+
+        let mut next = self.clone();
+        if next.dhr != Some(header.dh) {
+            next.skip_message_keys(header.pn)?;
+            next.dh_ratchet(header);
+        }
+        next.skip_message_keys(header.n)?;
+
+        let ckr = next.ckr.ok_or(CryptoError::Decrypt)?;
+        let (chain, message_key) = kdf_ck(&ckr);
+        let plaintext = open(&message_key, &next.ad_for(header), ciphertext)?;
+        next.ckr = Some(chain);
+        next.nr += 1;
+
+        *self = next;
+        Ok(plaintext)
+    }
+
+    fn ad_for(&self, header: &Header) -> Vec<u8> {
+        let mut ad = self.ad.clone();
+        ad.extend_from_slice(&header.encode());
+        ad
+    }
+
+    fn skip_message_keys(&mut self, until: u32) -> Result<(), CryptoError> {
+        let (Some(mut ckr), Some(dhr)) = (self.ckr, self.dhr) else {
+            return Ok(());
+        };
+        if until > self.nr.saturating_add(MAX_SKIP) {
+            return Err(CryptoError::TooManySkipped);
+        }
+        while self.nr < until {
+            let (next, key) = kdf_ck(&ckr);
+            self.skipped.push(SkippedKey {
+                dh: dhr,
+                n: self.nr,
+                key,
+            });
+            ckr = next;
+            self.nr += 1;
+        }
+        self.ckr = Some(ckr);
+
+        if self.skipped.len() > MAX_STORED_SKIPPED {
+            let excess = self.skipped.len() - MAX_STORED_SKIPPED;
+            self.skipped.drain(..excess);
+        }
+        Ok(())
+    }
+
+    fn dh_ratchet(&mut self, header: &Header) {
+        self.pn = self.ns;
+        self.ns = 0;
+        self.nr = 0;
+        self.dhr = Some(header.dh);
+
+        let (rk, ckr) = kdf_rk(&self.rk, &self.dhs.dh(&header.dh));
+        self.rk = rk;
+        self.ckr = Some(ckr);
+
+        self.dhs = DhKeyPair::generate();
+        let (rk, cks) = kdf_rk(&self.rk, &self.dhs.dh(&header.dh));
+        self.rk = rk;
+        self.cks = Some(cks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair() -> (Ratchet, Ratchet) {
+        let secret = [9; 32];
+        let bob_pre_key = DhKeyPair::generate();
+        let ad = b"alice-bob".to_vec();
+        (
+            Ratchet::init_initiator(secret, bob_pre_key.public, ad.clone()),
+            Ratchet::init_responder(secret, bob_pre_key, ad),
+        )
+    }
+
+    fn send(ratchet: &mut Ratchet, text: &str) -> (Header, Vec<u8>) {
+        ratchet.encrypt(text.as_bytes()).unwrap()
+    }
+
+    fn receive(ratchet: &mut Ratchet, (header, ct): &(Header, Vec<u8>)) -> String {
+        String::from_utf8(ratchet.decrypt(header, ct).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trip_in_both_directions() {
+        let (mut alice, mut bob) = pair();
+        assert!(!bob.can_send());
+        assert!(matches!(
+            bob.encrypt(b"too early"),
+            Err(CryptoError::NoSendingChain)
+        ));
+
+        for round in 0..3 {
+            let message = send(&mut alice, &format!("ping {round}"));
+            assert_eq!(receive(&mut bob, &message), format!("ping {round}"));
+            let reply = send(&mut bob, &format!("pong {round}"));
+            assert_eq!(receive(&mut alice, &reply), format!("pong {round}"));
+        }
+    }
+
+    #[test]
+    fn each_message_uses_a_new_key() {
+        let (mut alice, _) = pair();
+        let (first, second) = (send(&mut alice, "same"), send(&mut alice, "same"));
+        assert_ne!(first.1, second.1);
+        assert_eq!((first.0.n, second.0.n), (0, 1));
+    }
+
+    #[test]
+    fn out_of_order_messages() {
+        let (mut alice, mut bob) = pair();
+        let messages: Vec<_> = (0..4).map(|i| send(&mut alice, &format!("m{i}"))).collect();
+        assert_eq!(receive(&mut bob, &messages[2]), "m2");
+        assert_eq!(receive(&mut bob, &messages[0]), "m0");
+        assert_eq!(receive(&mut bob, &messages[3]), "m3");
+        assert_eq!(receive(&mut bob, &messages[1]), "m1");
+    }
+
+    #[test]
+    fn skipped_messages_from_an_earlier_chain() {
+        let (mut alice, mut bob) = pair();
+        let first = send(&mut alice, "first");
+        let lost = send(&mut alice, "lost");
+        assert_eq!(receive(&mut bob, &first), "first");
+
+        // Alice moves on to a new chain after Bob's reply
+        let reply = send(&mut bob, "reply");
+        assert_eq!(receive(&mut alice, &reply), "reply");
+        let next = send(&mut alice, "next");
+        assert_eq!(next.0.pn, 2);
+        assert_ne!(next.0.dh, lost.0.dh);
+
+        assert_eq!(receive(&mut bob, &next), "next");
+        assert_eq!(receive(&mut bob, &lost), "lost");
+    }
+
+    #[test]
+    fn messages_cannot_be_replayed() {
+        let (mut alice, mut bob) = pair();
+        let (first, second) = (send(&mut alice, "first"), send(&mut alice, "second"));
+        assert_eq!(receive(&mut bob, &second), "second");
+        assert_eq!(receive(&mut bob, &first), "first");
+        assert!(bob.decrypt(&first.0, &first.1).is_err());
+        assert!(bob.decrypt(&second.0, &second.1).is_err());
+    }
+
+    #[test]
+    fn tampered_messages_are_rejected_without_losing_the_session() {
+        let (mut alice, mut bob) = pair();
+        let (header, ct) = send(&mut alice, "hello");
+
+        let mut flipped = ct.clone();
+        flipped[0] ^= 1;
+        assert!(matches!(
+            bob.decrypt(&header, &flipped),
+            Err(CryptoError::Decrypt)
+        ));
+        // The header is authenticated as well
+        let moved = Header { n: 1, ..header };
+        assert!(bob.decrypt(&moved, &ct).is_err());
+        let forged = Header {
+            dh: DhKeyPair::generate().public,
+            ..header
+        };
+        assert!(bob.decrypt(&forged, &ct).is_err());
+
+        assert_eq!(receive(&mut bob, &(header, ct)), "hello");
+    }
+
+    #[test]
+    fn refuses_to_skip_too_far() {
+        let (mut alice, mut bob) = pair();
+        let first = send(&mut alice, "first");
+        assert_eq!(receive(&mut bob, &first), "first");
+        let (header, ct) = send(&mut alice, "second");
+        let far = Header {
+            n: MAX_SKIP + 2,
+            ..header
+        };
+        assert!(matches!(
+            bob.decrypt(&far, &ct),
+            Err(CryptoError::TooManySkipped)
+        ));
+        assert_eq!(receive(&mut bob, &(header, ct)), "second");
+    }
+}
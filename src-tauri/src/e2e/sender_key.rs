@@ -0,0 +1,246 @@
+// Sender keys for group chats
+//
+// Each device keeps one symmetric chain per group it sends to and hands the
+// chain key plus a signing key to every member device over their pairwise
+// sessions. Group messages are then encrypted once instead of per recipient.
+// Members who leave are cut off by rotating to a fresh key.
+use serde::{Deserialize, Serialize};
+
+use super::crypto::{kdf_ck, open, random_key, seal, verify, Key, SigningKeyPair};
+use super::CryptoError;
+
+/// How far ahead of the current iteration a message may be.
+const MAX_SKIP: u32 = 2000;
+/// Most skipped message keys retained per sender key.
+const MAX_STORED_SKIPPED: usize = 2000;
+
+/// What a member device needs to decrypt one sender's group messages.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Distribution {
+    pub key_id: u32,
+    pub iteration: u32,
+    pub chain_key: Key,
+    pub signing_key: Key,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SenderKey {
+    pub key_id: u32,
+    iteration: u32,
+    chain_key: Key,
+    signing_public: Key,
+    /// Only present for our own sender keys.
+    signing: Option<SigningKeyPair>,
+    skipped: Vec<(u32, Key)>,
+}
+
+impl SenderKey {
+    pub fn generate(key_id: u32) -> Self {
+        let signing = SigningKeyPair::generate();
+        SenderKey {
+            key_id,
+            iteration: 0,
+            chain_key: random_key(),
+            signing_public: signing.public,
+            signing: Some(signing),
+            skipped: Vec::new(),
+        }
+    }
+
+    pub fn from_distribution(distribution: &Distribution) -> Self {
+        SenderKey {
+            key_id: distribution.key_id,
+            iteration: distribution.iteration,
+            chain_key: distribution.chain_key,
+            signing_public: distribution.signing_key,
+            signing: None,
+            skipped: Vec::new(),
+        }
+    }
+
+    pub fn distribution(&self) -> Distribution {
+        Distribution {
+            key_id: self.key_id,
+            iteration: self.iteration,
+            chain_key: self.chain_key,
+            signing_key: self.signing_public,
+        }
+    }
+
+    /// Encrypts the next message. Returns its iteration, ciphertext and
+    /// signature.
+    pub fn encrypt(
+        &mut self,
+        ad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<(u32, Vec<u8>, Vec<u8>), CryptoError> {
+        let signing = self.signing.as_ref().ok_or(CryptoError::NotOwnSenderKey)?;
+        let (next, message_key) = kdf_ck(&self.chain_key);
+        let iteration = self.iteration;
+        let ciphertext = seal(&message_key, ad, plaintext);
+        let signature = signing.sign(&signed_bytes(ad, iteration, &ciphertext));
+
+        self.chain_key = next;
+        self.iteration += 1;
+        Ok((iteration, ciphertext, signature))
+    }
+
+    pub fn decrypt(
+        &mut self,
+        ad: &[u8],
+        iteration: u32,
+        ciphertext: &[u8],
+        signature: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        verify(
+            &self.signing_public,
+            &signed_bytes(ad, iteration, ciphertext),
+            signature,
+        )?;
+
+        if iteration < self.iteration {
+            let index = self
+                .skipped
+                .iter()
+                .position(|(n, _)| *n == iteration)
+                .ok_or(CryptoError::DuplicateMessage)?;
+            let plaintext = open(&self.skipped[index].1, ad, ciphertext)?;
+            self.skipped.remove(index);
+            return Ok(plaintext);
+        }
+        if iteration > self.iteration.saturating_add(MAX_SKIP) {
+            return Err(CryptoError::TooManySkipped);
+        }
+
+        let mut chain_key = self.chain_key;
+        let mut skipped = Vec::new();
+        for n in self.iteration..iteration {
+            let (next, key) = kdf_ck(&chain_key);
+            skipped.push((n, key));
+            chain_key = next;
+        }
+        let (next, message_key) = kdf_ck(&chain_key);
+        let plaintext = open(&message_key, ad, ciphertext)?;
+
+        self.skipped.extend(skipped);
+        if self.skipped.len() > MAX_STORED_SKIPPED {
+            let excess = self.skipped.len() - MAX_STORED_SKIPPED;
+            self.skipped.drain(..excess);
+        }
+        self.chain_key = next;
+        self.iteration = iteration + 1;
+        Ok(plaintext)
+    }
+}
+
+fn signed_bytes(ad: &[u8], iteration: u32, ciphertext: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(ad.len() + 4 + ciphertext.len());
+    bytes.extend_from_slice(ad);
+    bytes.extend_from_slice(&iteration.to_be_bytes());
+    bytes.extend_from_slice(ciphertext);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AD: &[u8] = b"chat/alice:1/0";
+
+    fn send(key: &mut SenderKey, text: &str) -> (u32, Vec<u8>, Vec<u8>) {
+        key.encrypt(AD, text.as_bytes()).unwrap()
+    }
+
+    fn receive(key: &mut SenderKey, (n, ct, sig): &(u32, Vec<u8>, Vec<u8>)) -> String {
+        String::from_utf8(key.decrypt(AD, *n, ct, sig).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trip_and_out_of_order() {
+        let mut own = SenderKey::generate(0);
+        let mut member = SenderKey::from_distribution(&own.distribution());
+        let messages: Vec<_> = (0..4).map(|i| send(&mut own, &format!("m{i}"))).collect();
+
+        assert_eq!(receive(&mut member, &messages[0]), "m0");
+        assert_eq!(receive(&mut member, &messages[3]), "m3");
+        assert_eq!(receive(&mut member, &messages[1]), "m1");
+        assert_eq!(receive(&mut member, &messages[2]), "m2");
+    }
+
+    #[test]
+    fn late_joiners_only_read_later_messages() {
+        let mut own = SenderKey::generate(0);
+        let early = send(&mut own, "before");
+        let mut member = SenderKey::from_distribution(&own.distribution());
+        let late = send(&mut own, "after");
+
+        assert_eq!(receive(&mut member, &late), "after");
+        let (n, ct, sig) = &early;
+        assert!(member.decrypt(AD, *n, ct, sig).is_err());
+    }
+
+    #[test]
+    fn duplicates_are_rejected() {
+        let mut own = SenderKey::generate(0);
+        let mut member = SenderKey::from_distribution(&own.distribution());
+        let message = send(&mut own, "once");
+        assert_eq!(receive(&mut member, &message), "once");
+        let (n, ct, sig) = &message;
+        assert!(matches!(
+            member.decrypt(AD, *n, ct, sig),
+            Err(CryptoError::DuplicateMessage)
+        ));
+    }
+
+    #[test]
+    fn tampered_messages_are_rejected() {
+        let mut own = SenderKey::generate(0);
+        let mut member = SenderKey::from_distribution(&own.distribution());
+        let (n, ct, sig) = send(&mut own, "hello");
+
+        let mut flipped = ct.clone();
+        flipped[0] ^= 1;
+        assert!(matches!(
+            member.decrypt(AD, n, &flipped, &sig),
+            Err(CryptoError::BadSignature)
+        ));
+        let mut forged = sig.clone();
+        forged[0] ^= 1;
+        assert!(member.decrypt(AD, n, &ct, &forged).is_err());
+        assert!(member.decrypt(AD, n + 1, &ct, &sig).is_err());
+        assert!(member.decrypt(b"other chat", n, &ct, &sig).is_err());
+
+        // Members cannot sign on the sender's behalf
+        assert!(matches!(
+            member.encrypt(AD, b"spoof"),
+            Err(CryptoError::NotOwnSenderKey)
+        ));
+        assert_eq!(receive(&mut member, &(n, ct, sig)), "hello");
+    }
+
+    #[test]
+    fn rotated_keys_lock_out_earlier_members() {
+        let mut old = SenderKey::generate(0);
+        let mut former_member = SenderKey::from_distribution(&old.distribution());
+        assert_eq!(receive(&mut former_member, &send(&mut old, "hi")), "hi");
+
+        let mut rotated = SenderKey::generate(old.key_id + 1);
+        let mut remaining = SenderKey::from_distribution(&rotated.distribution());
+        let message = send(&mut rotated, "after rotation");
+
+        assert_eq!(remaining.key_id, 1);
+        assert_eq!(receive(&mut remaining, &message), "after rotation");
+        let (n, ct, sig) = &message;
+        assert!(former_member.decrypt(AD, *n, ct, sig).is_err());
+    }
+
+    #[test]
+    fn distribution_survives_serialization() {
+        let mut own = SenderKey::generate(3);
+        send(&mut own, "advance");
+        let json = serde_json::to_string(&own.distribution()).unwrap();
+        let mut member = SenderKey::from_distribution(&serde_json::from_str(&json).unwrap());
+        assert_eq!(receive(&mut member, &send(&mut own, "next")), "next");
+    }
+}
@@ -0,0 +1,270 @@
+// Persistence for E2E key material (the `e2e_*` tables in the local store)
+//
+// Private keys and ratchet states never leave the encrypted store.
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use super::crypto::{DhKeyPair, Key, SigningKeyPair};
+use super::ratchet::Ratchet;
+use super::sender_key::SenderKey;
+use super::wire::X3dhHeader;
+use crate::error::Result;
+use crate::models::now;
+use crate::store::Store;
+
+/// Sessions kept per peer device; older ones are dropped.
+const MAX_SESSIONS_PER_PEER: usize = 5;
+
+/// This device's long-term keys for one signed-in user.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Identity {
+    pub user_id: String,
+    pub device_id: String,
+    pub dh: DhKeyPair,
+    pub signing: SigningKeyPair,
+    pub signed_pre_key_id: u32,
+    pub signed_pre_key: DhKeyPair,
+    pub signed_pre_key_signature: Vec<u8>,
+}
+
+impl Identity {
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.user_id, self.device_id)
+    }
+}
+
+/// A pairwise session with one peer device.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    /// The initiator's ephemeral key; identifies the session on both sides.
+    pub base_key: Key,
+    pub ratchet: Ratchet,
+    /// Our X3DH header, repeated on outgoing messages until the peer replies.
+    pub pending_x3dh: Option<X3dhHeader>,
+}
+
+pub fn identity(store: &Store, user_id: &str) -> Result<Option<Identity>> {
+    let raw: Option<String> = store
+        .conn()
+        .query_row(
+            "SELECT identity FROM e2e_identity WHERE user_id = ?1",
+            [user_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(raw.map(|raw| serde_json::from_str(&raw)).transpose()?)
+}
+
+pub fn save_identity(store: &Store, identity: &Identity) -> Result<()> {
+    store.conn().execute(
+        "INSERT OR REPLACE INTO e2e_identity (user_id, device_id, identity, created_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            identity.user_id,
+            identity.device_id,
+            serde_json::to_string(identity)?,
+            now()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Public halves of the one-time prekeys we still hold.
+pub fn pre_key_publics(store: &Store, user_id: &str) -> Result<Vec<(u32, Key)>> {
+    let conn = store.conn();
+    let mut stmt = conn.prepare_cached("SELECT id, key FROM e2e_pre_keys WHERE user_id = ?1")?;
+    let rows = stmt
+        .query_map([user_id], |row| {
+            Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    rows.into_iter()
+        .map(|(id, raw)| Ok((id, serde_json::from_str::<DhKeyPair>(&raw)?.public)))
+        .collect()
+}
+
+/// Generates `count` new one-time prekeys.
+pub fn add_pre_keys(store: &Store, user_id: &str, count: usize) -> Result<()> {
+    let mut conn = store.conn();
+    let tx = conn.transaction()?;
+    let next: u32 = tx.query_row(
+        "SELECT COALESCE(MAX(id), 0) + 1 FROM e2e_pre_keys WHERE user_id = ?1",
+        [user_id],
+        |row| row.get(0),
+    )?;
+    for id in next..next + count as u32 {
+        tx.execute(
+            "INSERT INTO e2e_pre_keys (user_id, id, key) VALUES (?1, ?2, ?3)",
+            params![user_id, id, serde_json::to_string(&DhKeyPair::generate())?],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn pre_key(store: &Store, user_id: &str, id: u32) -> Result<Option<DhKeyPair>> {
+    let raw: Option<String> = store
+        .conn()
+        .query_row(
+            "SELECT key FROM e2e_pre_keys WHERE user_id = ?1 AND id = ?2",
+            params![user_id, id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(raw.map(|raw| serde_json::from_str(&raw)).transpose()?)
+}
+
+pub fn remove_pre_key(store: &Store, user_id: &str, id: u32) -> Result<()> {
+    store.conn().execute(
+        "DELETE FROM e2e_pre_keys WHERE user_id = ?1 AND id = ?2",
+        params![user_id, id],
+    )?;
+    Ok(())
+}
+
+/// The identity and signing keys we first saw for a peer device.
+pub fn peer_identity(store: &Store, address: &str) -> Result<Option<(Key, Key)>> {
+    let keys = store
+        .conn()
+        .query_row(
+            "SELECT identity_key, signing_key FROM e2e_peers WHERE address = ?1",
+            [address],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    Ok(keys)
+}
+
+pub fn save_peer_identity(
+    store: &Store,
+    address: &str,
+    identity_key: &Key,
+    signing_key: &Key,
+) -> Result<()> {
+    store.conn().execute(
+        "INSERT INTO e2e_peers (address, identity_key, signing_key, first_seen)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (address) DO UPDATE SET
+             identity_key = excluded.identity_key,
+             signing_key = excluded.signing_key",
+        params![address, identity_key, signing_key, now()],
+    )?;
+    Ok(())
+}
+
+/// Sessions with a peer device, most recently used first.
+pub fn sessions(store: &Store, address: &str) -> Result<Vec<SessionRecord>> {
+    let conn = store.conn();
+    let mut stmt = conn.prepare_cached(
+        "SELECT state FROM e2e_sessions WHERE address = ?1 ORDER BY updated_at DESC",
+    )?;
+    let rows = stmt
+        .query_map([address], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    rows.iter()
+        .map(|raw| Ok(serde_json::from_str(raw)?))
+        .collect()
+}
+
+/// Saves a session and marks it as the most recently used one.
+pub fn save_session(store: &Store, address: &str, record: &SessionRecord) -> Result<()> {
+    let mut conn = store.conn();
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT OR REPLACE INTO e2e_sessions (address, base_key, state, updated_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            address,
+            record.base_key,
+            serde_json::to_string(record)?,
+            now()
+        ],
+    )?;
+    tx.execute(
+        "DELETE FROM e2e_sessions WHERE address = ?1 AND base_key NOT IN (
+             SELECT base_key FROM e2e_sessions WHERE address = ?1
+             ORDER BY updated_at DESC LIMIT ?2)",
+        params![address, MAX_SESSIONS_PER_PEER],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn sender_key(
+    store: &Store,
+    chat_id: &str,
+    sender: &str,
+    key_id: u32,
+) -> Result<Option<SenderKey>> {
+    let raw: Option<String> = store
+        .conn()
+        .query_row(
+            "SELECT state FROM e2e_sender_keys WHERE chat_id = ?1 AND sender = ?2 AND key_id = ?3",
+            params![chat_id, sender, key_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(raw.map(|raw| serde_json::from_str(&raw)).transpose()?)
+}
+
+/// The newest sender key `sender` uses in a chat.
+pub fn latest_sender_key(store: &Store, chat_id: &str, sender: &str) -> Result<Option<SenderKey>> {
+    let raw: Option<String> = store
+        .conn()
+        .query_row(
+            "SELECT state FROM e2e_sender_keys WHERE chat_id = ?1 AND sender = ?2
+             ORDER BY key_id DESC LIMIT 1",
+            params![chat_id, sender],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(raw.map(|raw| serde_json::from_str(&raw)).transpose()?)
+}
+
+pub fn save_sender_key(store: &Store, chat_id: &str, sender: &str, key: &SenderKey) -> Result<()> {
+    store.conn().execute(
+        "INSERT OR REPLACE INTO e2e_sender_keys (chat_id, sender, key_id, state, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            chat_id,
+            sender,
+            key.key_id,
+            serde_json::to_string(key)?,
+            now()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Devices that have received our sender key `key_id` for a chat.
+pub fn sender_key_recipients(store: &Store, chat_id: &str, key_id: u32) -> Result<Vec<String>> {
+    let conn = store.conn();
+    let mut stmt = conn.prepare_cached(
+        "SELECT address FROM e2e_sender_key_recipients WHERE chat_id = ?1 AND key_id = ?2",
+    )?;
+    let recipients = stmt
+        .query_map(params![chat_id, key_id], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(recipients)
+}
+
+pub fn add_sender_key_recipients(
+    store: &Store,
+    chat_id: &str,
+    key_id: u32,
+    addresses: &[String],
+) -> Result<()> {
+    let mut conn = store.conn();
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT OR IGNORE INTO e2e_sender_key_recipients (chat_id, key_id, address)
+             VALUES (?1, ?2, ?3)",
+        )?;
+        for address in addresses {
+            stmt.execute(params![chat_id, key_id, address])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
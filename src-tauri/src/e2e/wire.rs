@@ -0,0 +1,148 @@
+// JSON shapes written to and read from RTDB
+//
+// Binary values are base64 (standard alphabet). Device addresses are
+// `{userId}:{deviceId}`.
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::crypto::Key;
+use super::ratchet::Header;
+
+pub const ENVELOPE_VERSION: u32 = 1;
+
+/// Public keys a device publishes at `keys/{userId}/{deviceId}`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreKeyBundle {
+    #[serde(with = "b64_key")]
+    pub identity_key: Key,
+    #[serde(with = "b64_key")]
+    pub signing_key: Key,
+    pub signed_pre_key: SignedPreKey,
+    #[serde(default)]
+    pub one_time_pre_keys: BTreeMap<String, B64Key>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedPreKey {
+    pub id: u32,
+    #[serde(with = "b64_key")]
+    pub key: Key,
+    #[serde(with = "b64")]
+    pub signature: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct B64Key(#[serde(with = "b64_key")] pub Key);
+
+/// Sent with every message of a new session until the peer replies, so the
+/// recipient can derive the same shared secret.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct X3dhHeader {
+    #[serde(with = "b64_key")]
+    pub identity_key: Key,
+    #[serde(with = "b64_key")]
+    pub signing_key: Key,
+    #[serde(with = "b64_key")]
+    pub ephemeral_key: Key,
+    pub signed_pre_key_id: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub one_time_pre_key_id: Option<u32>,
+}
+
+/// A double-ratchet message for one recipient device.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PairwiseMessage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x3dh: Option<X3dhHeader>,
+    #[serde(with = "b64_key")]
+    pub dh: Key,
+    pub pn: u32,
+    pub n: u32,
+    #[serde(with = "b64")]
+    pub ct: Vec<u8>,
+}
+
+impl PairwiseMessage {
+    pub fn new(x3dh: Option<X3dhHeader>, header: Header, ct: Vec<u8>) -> Self {
+        PairwiseMessage {
+            x3dh,
+            dh: header.dh,
+            pn: header.pn,
+            n: header.n,
+            ct,
+        }
+    }
+
+    pub fn header(&self) -> Header {
+        Header {
+            dh: self.dh,
+            pn: self.pn,
+            n: self.n,
+        }
+    }
+}
+
+/// Group message encrypted with the sender's sender key.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMessage {
+    pub key_id: u32,
+    pub n: u32,
+    #[serde(with = "b64")]
+    pub ct: Vec<u8>,
+    #[serde(with = "b64")]
+    pub sig: Vec<u8>,
+    /// Sender key distributions for member devices that don't have it yet,
+    /// each encrypted over the pairwise session.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dist: BTreeMap<String, PairwiseMessage>,
+}
+
+/// The `e2e` field of an encrypted message in `messages/{chatId}/{id}`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    pub v: u32,
+    /// Address of the sending device.
+    pub sender: String,
+    /// Direct chats: one ratchet message per recipient device.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dm: BTreeMap<String, PairwiseMessage>,
+    /// Group chats.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<GroupMessage>,
+}
+
+mod b64 {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+mod b64_key {
+    use serde::{Deserializer, Serializer};
+
+    use super::Key;
+
+    pub fn serialize<S: Serializer>(key: &Key, serializer: S) -> Result<S::Ok, S::Error> {
+        super::b64::serialize(key, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Key, D::Error> {
+        super::b64::deserialize(deserializer)?
+            .try_into()
+            .map_err(|_| serde::de::Error::custom("expected a 32-byte key"))
+    }
+}
@@ -0,0 +1,184 @@
+// X3DH key agreement for setting up pairwise sessions
+//
+// The initiator combines its identity key and a fresh ephemeral key with the
+// responder's identity key, signed prekey and (if available) a one-time
+// prekey. Identity keys are X25519; the signed prekey is signed with the
+// device's separate Ed25519 key published alongside it.
+use super::crypto::{hkdf, verify, DhKeyPair, Key};
+use super::state::Identity;
+use super::wire::{PreKeyBundle, X3dhHeader};
+use super::CryptoError;
+
+/// Result of starting a session with someone's prekey bundle.
+pub struct Initiated {
+    pub shared_secret: Key,
+    pub associated_data: Vec<u8>,
+    pub header: X3dhHeader,
+}
+
+pub fn initiate(identity: &Identity, bundle: &PreKeyBundle) -> Result<Initiated, CryptoError> {
+    let spk = &bundle.signed_pre_key;
+    verify(&bundle.signing_key, &spk.key, &spk.signature)?;
+
+    let ephemeral = DhKeyPair::generate();
+    let one_time = bundle
+        .one_time_pre_keys
+        .iter()
+        .find_map(|(id, key)| Some((id.parse::<u32>().ok()?, key.0)));
+
+    let mut dh = Vec::with_capacity(4 * 32);
+    dh.extend_from_slice(&identity.dh.dh(&spk.key));
+    dh.extend_from_slice(&ephemeral.dh(&bundle.identity_key));
+    dh.extend_from_slice(&ephemeral.dh(&spk.key));
+    if let Some((_, key)) = &one_time {
+        dh.extend_from_slice(&ephemeral.dh(key));
+    }
+
+    Ok(Initiated {
+        shared_secret: derive(&dh),
+        associated_data: associated_data(&identity.dh.public, &bundle.identity_key),
+        header: X3dhHeader {
+            identity_key: identity.dh.public,
+            signing_key: identity.signing.public,
+            ephemeral_key: ephemeral.public,
+            signed_pre_key_id: spk.id,
+            one_time_pre_key_id: one_time.map(|(id, _)| id),
+        },
+    })
+}
+
+/// Responder side: derives the same secret from the initiator's header and
+/// our own prekeys. Returns the shared secret and associated data.
+pub fn respond(
+    identity: &Identity,
+    header: &X3dhHeader,
+    signed_pre_key: &DhKeyPair,
+    one_time_pre_key: Option<&DhKeyPair>,
+) -> (Key, Vec<u8>) {
+    let mut dh = Vec::with_capacity(4 * 32);
+    dh.extend_from_slice(&signed_pre_key.dh(&header.identity_key));
+    dh.extend_from_slice(&identity.dh.dh(&header.ephemeral_key));
+    dh.extend_from_slice(&signed_pre_key.dh(&header.ephemeral_key));
+    if let Some(key) = one_time_pre_key {
+        dh.extend_from_slice(&key.dh(&header.ephemeral_key));
+    }
+
+    (
+        derive(&dh),
+        associated_data(&header.identity_key, &identity.dh.public),
+    )
+}
+
+fn derive(dh: &[u8]) -> Key {
+    // 32 0xFF bytes prefix the input, as in the X3DH specification
+    let mut ikm = vec![0xFF; 32];
+    ikm.extend_from_slice(dh);
+    let mut key = [0u8; 32];
+    hkdf(&[0u8; 32], &ikm, b"Chitchat X3DH", &mut key);
+    key
+}
+
+fn associated_data(initiator: &Key, responder: &Key) -> Vec<u8> {
+    let mut ad = Vec::with_capacity(64);
+    ad.extend_from_slice(initiator);
+    ad.extend_from_slice(responder);
+    ad
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::super::new_identity;
+    use super::super::wire::{B64Key, SignedPreKey};
+    use super::*;
+
+    fn bundle(identity: &Identity, one_time: Option<(u32, &DhKeyPair)>) -> PreKeyBundle {
+        PreKeyBundle {
+            identity_key: identity.dh.public,
+            signing_key: identity.signing.public,
+            signed_pre_key: SignedPreKey {
+                id: identity.signed_pre_key_id,
+                key: identity.signed_pre_key.public,
+                signature: identity.signed_pre_key_signature.clone(),
+            },
+            one_time_pre_keys: one_time
+                .map(|(id, key)| (id.to_string(), B64Key(key.public)))
+                .into_iter()
+                .collect::<BTreeMap<_, _>>(),
+        }
+    }
+
+    #[test]
+    fn both_sides_agree() {
+        let (alice, bob) = (new_identity("alice"), new_identity("bob"));
+        let initiated = initiate(&alice, &bundle(&bob, None)).unwrap();
+        assert_eq!(initiated.header.one_time_pre_key_id, None);
+
+        let (secret, ad) = respond(&bob, &initiated.header, &bob.signed_pre_key, None);
+        assert_eq!(secret, initiated.shared_secret);
+        assert_eq!(ad, initiated.associated_data);
+        assert_eq!(&ad[..32], &alice.dh.public);
+        assert_eq!(&ad[32..], &bob.dh.public);
+    }
+
+    #[test]
+    fn both_sides_agree_with_a_one_time_prekey() {
+        let (alice, bob) = (new_identity("alice"), new_identity("bob"));
+        let one_time = DhKeyPair::generate();
+        let initiated = initiate(&alice, &bundle(&bob, Some((7, &one_time)))).unwrap();
+        assert_eq!(initiated.header.one_time_pre_key_id, Some(7));
+
+        let (secret, _) = respond(
+            &bob,
+            &initiated.header,
+            &bob.signed_pre_key,
+            Some(&one_time),
+        );
+        assert_eq!(secret, initiated.shared_secret);
+        // Without the one-time prekey the secret differs
+        let (secret, _) = respond(&bob, &initiated.header, &bob.signed_pre_key, None);
+        assert_ne!(secret, initiated.shared_secret);
+    }
+
+    #[test]
+    fn sessions_use_fresh_ephemeral_keys() {
+        let (alice, bob) = (new_identity("alice"), new_identity("bob"));
+        let first = initiate(&alice, &bundle(&bob, None)).unwrap();
+        let second = initiate(&alice, &bundle(&bob, None)).unwrap();
+        assert_ne!(first.header.ephemeral_key, second.header.ephemeral_key);
+        assert_ne!(first.shared_secret, second.shared_secret);
+    }
+
+    #[test]
+    fn rejects_a_forged_signed_prekey() {
+        let (alice, bob, mallory) = (
+            new_identity("alice"),
+            new_identity("bob"),
+            new_identity("mallory"),
+        );
+        let mut forged = bundle(&bob, None);
+        forged.signed_pre_key.key = mallory.signed_pre_key.public;
+        assert!(matches!(
+            initiate(&alice, &forged),
+            Err(CryptoError::BadSignature)
+        ));
+
+        let mut forged = bundle(&bob, None);
+        forged.signed_pre_key.signature[0] ^= 1;
+        assert!(matches!(
+            initiate(&alice, &forged),
+            Err(CryptoError::BadSignature)
+        ));
+    }
+
+    #[test]
+    fn derivation_known_answer() {
+        let expected: Key = [
+            0x8d, 0xc1, 0x7f, 0x23, 0x2e, 0x00, 0x07, 0x55, 0xe8, 0xfa, 0x40, 0xb5, 0x55, 0xf2,
+            0x07, 0x4c, 0x19, 0x21, 0x50, 0x6d, 0xbd, 0xd7, 0x80, 0x53, 0x18, 0xab, 0x32, 0xf9,
+            0x94, 0x49, 0x35, 0x53,
+        ];
+        assert_eq!(derive(&[4; 128]), expected);
+    }
+}
@@ -0,0 +1,126 @@
+use serde::{Serialize, Serializer};
+
+use crate::models::{CommandError, ErrorKind};
+
+/// Errors returned by the Rust core. Commands surface these to the webview
+/// as a [`CommandError`].
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("local store error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("keyring error: {0}")]
+    Keyring(#[from] keyring::Error),
+    #[error("cannot unlock the local store: {0}")]
+    StoreKey(String),
+    #[error("network error: {0}")]
+    Http(reqwest::Error),
+    #[error("invalid chat ID: {0:?}")]
+    InvalidChatId(String),
+    #[error("invalid user ID: {0:?}")]
+    InvalidUserId(String),
+    #[error("not a media cache key: {0:?}")]
+    InvalidMediaKey(String),
+    #[error("no outbox entry with id {0}")]
+    UnknownOutboxEntry(String),
+    #[error("chat {0} not found")]
+    UnknownChat(String),
+    #[error("message {0} not found")]
+    UnknownMessage(String),
+    #[error("not signed in")]
+    SignedOut,
+    #[error("cannot write to {0}")]
+    UnsupportedPath(String),
+    #[error("not a supported chat export: {0}")]
+    InvalidImport(String),
+    #[error("cannot attach {0}")]
+    InvalidAttachment(String),
+    #[error("cannot process image: {0}")]
+    InvalidImage(String),
+    #[error("cannot process image: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("invalid settings: {0}")]
+    InvalidSettings(String),
+    #[error("invalid profile: {0}")]
+    InvalidProfile(String),
+    #[error("invalid link: {0}")]
+    InvalidLink(String),
+    #[error("markdown error: {0}")]
+    Markdown(String),
+    #[error("logging error: {0}")]
+    Logging(String),
+    #[error("cannot show notification: {0}")]
+    Notification(String),
+    #[error("cannot write archive: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("attachment storage error: {0}")]
+    Storage(String),
+    #[error("{0} did not match its checksum; download it again")]
+    Integrity(String),
+    #[error("encryption error: {0}")]
+    E2e(#[from] crate::e2e::CryptoError),
+    #[error(transparent)]
+    Rtdb(#[from] crate::rtdb::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Sqlite(_) => ErrorKind::Storage,
+            Error::Keyring(_) | Error::StoreKey(_) => ErrorKind::Keyring,
+            Error::Http(_) | Error::Rtdb(_) | Error::Storage(_) | Error::SignedOut => {
+                ErrorKind::Network
+            }
+            Error::InvalidChatId(_)
+            | Error::InvalidUserId(_)
+            | Error::InvalidMediaKey(_)
+            | Error::UnsupportedPath(_)
+            | Error::InvalidImport(_)
+            | Error::InvalidAttachment(_)
+            | Error::InvalidImage(_)
+            | Error::Image(_)
+            | Error::InvalidSettings(_)
+            | Error::InvalidProfile(_)
+            | Error::InvalidLink(_) => ErrorKind::InvalidArgument,
+            Error::UnknownOutboxEntry(_) | Error::UnknownChat(_) | Error::UnknownMessage(_) => {
+                ErrorKind::NotFound
+            }
+            Error::E2e(_) => ErrorKind::Encryption,
+            Error::Integrity(_) => ErrorKind::Integrity,
+            Error::Markdown(_)
+            | Error::Logging(_)
+            | Error::Notification(_)
+            | Error::Zip(_)
+            | Error::Json(_)
+            | Error::Io(_)
+            | Error::Tauri(_) => ErrorKind::Internal,
+        }
+    }
+}
+
+// Request URLs may carry tokens, and these errors reach logs and the webview
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e.without_url())
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        CommandError {
+            kind: self.kind(),
+            message: self.to_string(),
+        }
+        .serialize(serializer)
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,133 @@
+// HTML export
+//
+// A single page with its stylesheet inlined and no scripts or external
+// resources, so it opens the same anywhere, offline, years from now. Uses
+// semantic markup (sections, ordered lists, `<time>`) so it also reads well
+// with assistive technology.
+use std::io::{self, Write};
+
+use super::{LocalTime, Transcript, Writer};
+use crate::models::Message;
+
+const STYLE: &str = "
+:root { color-scheme: light dark; --bg: #efeae2; --card: #ffffff; --own: #d9fdd3;
+  --text: #111b21; --muted: #667781; --accent: #008069; }
+@media (prefers-color-scheme: dark) {
+  :root { --bg: #0b141a; --card: #202c33; --own: #005c4b; --text: #e9edef;
+    --muted: #8696a0; --accent: #00a884; }
+}
+body { margin: 0; padding: 2rem 1rem; background: var(--bg); color: var(--text);
+  font: 15px/1.45 system-ui, -apple-system, 'Segoe UI', Roboto, sans-serif; }
+main { max-width: 48rem; margin: 0 auto; }
+header p, .participants { color: var(--muted); }
+h1 { font-size: 1.5rem; margin: 0 0 .25rem; }
+h2 { font-size: 1.2rem; margin: 2.5rem 0 .25rem; color: var(--accent); }
+ol { list-style: none; margin: 1rem 0; padding: 0; display: flex; flex-direction: column;
+  gap: .4rem; }
+li { background: var(--card); border-radius: .5rem; padding: .4rem .7rem; max-width: 80%;
+  align-self: flex-start; box-shadow: 0 1px .5px rgb(0 0 0 / .13); }
+li.own { background: var(--own); align-self: flex-end; }
+.meta { display: flex; gap: .75rem; justify-content: space-between; font-size: .8rem; }
+.sender { font-weight: 600; color: var(--accent); }
+time { color: var(--muted); }
+.text { margin: .15rem 0 0; white-space: pre-wrap; overflow-wrap: anywhere; }
+.attachment { margin: .15rem 0 0; color: var(--muted); overflow-wrap: anywhere; }
+";
+
+pub struct HtmlWriter<'w> {
+    out: &'w mut dyn Write,
+    in_chat: bool,
+}
+
+impl<'w> HtmlWriter<'w> {
+    pub fn new(out: &'w mut dyn Write) -> Self {
+        HtmlWriter {
+            out,
+            in_chat: false,
+        }
+    }
+
+    fn end_chat(&mut self) -> io::Result<()> {
+        if self.in_chat {
+            writeln!(self.out, "</ol>\n</section>")?;
+        }
+        Ok(())
+    }
+}
+
+impl Writer for HtmlWriter<'_> {
+    fn begin(&mut self, exported_at: &LocalTime) -> io::Result<()> {
+        write!(
+            self.out,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+             <title>Chitchat export</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<main>\n\
+             <header>\n<h1>Chitchat export</h1>\n\
+             <p>Exported <time datetime=\"{}\">{}</time> ({})</p>\n</header>\n",
+            exported_at.iso(),
+            exported_at.short(),
+            exported_at.zone()
+        )
+    }
+
+    fn chat(&mut self, transcript: &Transcript) -> io::Result<()> {
+        self.end_chat()?;
+        self.in_chat = true;
+        write!(
+            self.out,
+            "<section>\n<h2>{}</h2>\n<p class=\"participants\">Participants: {}</p>\n<ol>\n",
+            escape(&transcript.title),
+            escape(&transcript.participants.join(", "))
+        )
+    }
+
+    fn message(
+        &mut self,
+        message: &Message,
+        sender: &str,
+        own: bool,
+        at: &LocalTime,
+    ) -> io::Result<()> {
+        write!(
+            self.out,
+            "<li{}><div class=\"meta\"><span class=\"sender\">{}</span>\
+             <time datetime=\"{}\">{}</time></div>",
+            if own { " class=\"own\"" } else { "" },
+            escape(sender),
+            at.iso(),
+            at.short(),
+        )?;
+        let text = message.text.trim_end();
+        if !text.is_empty() {
+            write!(self.out, "<p class=\"text\">{}</p>", escape(text))?;
+        }
+        if let Some(attachment) = &message.attachment {
+            write!(
+                self.out,
+                "<p class=\"attachment\">Attachment: {}</p>",
+                escape(&attachment.name)
+            )?;
+        }
+        writeln!(self.out, "</li>")
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.end_chat()?;
+        writeln!(self.out, "</main>\n</body>\n</html>")
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
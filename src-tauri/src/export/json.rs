@@ -0,0 +1,65 @@
+// JSON export: `{ "exportedAt", "chats": [{ "chat", "messages": [...] }] }`
+// with chats and messages in the same shape as the IPC models
+use std::io::{self, Write};
+
+use super::{LocalTime, Transcript, Writer};
+use crate::models::Message;
+
+pub struct JsonWriter<'w> {
+    out: &'w mut dyn Write,
+    chats: usize,
+    messages: usize,
+}
+
+impl<'w> JsonWriter<'w> {
+    pub fn new(out: &'w mut dyn Write) -> Self {
+        JsonWriter {
+            out,
+            chats: 0,
+            messages: 0,
+        }
+    }
+
+    fn end_chat(&mut self) -> io::Result<()> {
+        if self.chats > 0 {
+            let newline = if self.messages > 0 { "\n    " } else { "" };
+            write!(self.out, "{newline}]\n  }}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Writer for JsonWriter<'_> {
+    fn begin(&mut self, exported_at: &LocalTime) -> io::Result<()> {
+        write!(
+            self.out,
+            "{{\n  \"exportedAt\": {},\n  \"chats\": [",
+            exported_at.timestamp
+        )
+    }
+
+    fn chat(&mut self, transcript: &Transcript) -> io::Result<()> {
+        self.end_chat()?;
+        let separator = if self.chats > 0 { "," } else { "" };
+        write!(self.out, "{separator}\n  {{\n    \"chat\": ")?;
+        serde_json::to_writer(&mut self.out, transcript.chat)?;
+        write!(self.out, ",\n    \"messages\": [")?;
+        self.chats += 1;
+        self.messages = 0;
+        Ok(())
+    }
+
+    fn message(&mut self, message: &Message, _: &str, _: bool, _: &LocalTime) -> io::Result<()> {
+        let separator = if self.messages > 0 { "," } else { "" };
+        write!(self.out, "{separator}\n      ")?;
+        serde_json::to_writer(&mut self.out, message)?;
+        self.messages += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.end_chat()?;
+        let newline = if self.chats > 0 { "\n  " } else { "" };
+        writeln!(self.out, "{newline}]\n}}")
+    }
+}
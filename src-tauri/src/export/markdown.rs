@@ -0,0 +1,87 @@
+// Markdown export
+//
+// Message text is escaped so it comes out exactly as written instead of being
+// rendered as markup.
+use std::io::{self, Write};
+
+use super::{LocalTime, Transcript, Writer};
+use crate::models::Message;
+
+pub struct MarkdownWriter<'w> {
+    out: &'w mut dyn Write,
+}
+
+impl<'w> MarkdownWriter<'w> {
+    pub fn new(out: &'w mut dyn Write) -> Self {
+        MarkdownWriter { out }
+    }
+}
+
+impl Writer for MarkdownWriter<'_> {
+    fn begin(&mut self, exported_at: &LocalTime) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "# Chitchat export\n\nExported {} ({}).",
+            exported_at.short(),
+            exported_at.zone()
+        )
+    }
+
+    fn chat(&mut self, transcript: &Transcript) -> io::Result<()> {
+        writeln!(self.out, "\n## {}\n", escape(&transcript.title))?;
+        writeln!(
+            self.out,
+            "Participants: {}\n",
+            escape(&transcript.participants.join(", "))
+        )
+    }
+
+    fn message(
+        &mut self,
+        message: &Message,
+        sender: &str,
+        _own: bool,
+        at: &LocalTime,
+    ) -> io::Result<()> {
+        writeln!(self.out, "**{}** · {}  ", escape(sender), at.short())?;
+        let mut lines: Vec<String> = message.text.trim_end().lines().map(escape).collect();
+        if let Some(attachment) = &message.attachment {
+            lines.push(format!("*Attachment: {}*", escape(&attachment.name)));
+        }
+        // Trailing backslashes force the line breaks of the original
+        writeln!(self.out, "{}\n", lines.join("\\\n"))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Escapes a single line so Markdown shows it literally.
+fn escape(line: &str) -> String {
+    let mut escaped = String::with_capacity(line.len());
+    let trimmed = line.trim_start();
+    escaped.push_str(&line[..line.len() - trimmed.len()]);
+
+    // Block syntax only matters at the start of a line
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    let mut chars = trimmed.char_indices().peekable();
+    if digits > 0 && matches!(trimmed[digits..].chars().next(), Some('.' | ')')) {
+        escaped.push_str(&trimmed[..digits]);
+        escaped.push('\\');
+        while chars.next_if(|(i, _)| *i < digits).is_some() {}
+    } else if trimmed.starts_with(['#', '>', '-', '+', '=']) {
+        escaped.push('\\');
+    }
+
+    for (_, c) in chars {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '~' | '!' | '&'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,527 @@
+// Chat history export
+//
+// Writes one chat or all of them to a single file in one of the
+// `ExportFormat`s. Messages are read from the local store a page at a time
+// and written as they come, so exporting a long history never holds it all in
+// memory. Names shown for participants come from the chat's
+// `participantNames`, falling back to the name stored with each message.
+// Attachments are listed by name; the files themselves are not exported.
+mod html;
+mod json;
+mod markdown;
+mod text;
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::civil::civil_from_days;
+use crate::error::Result;
+use crate::models::{now, Chat, ExportFormat, ExportSummary, Message, Timestamp};
+use crate::store::Store;
+
+/// Messages read from the store per query.
+const PAGE_SIZE: u32 = 500;
+
+/// A chat as it appears in an export.
+pub struct Transcript<'a> {
+    pub chat: &'a Chat,
+    pub title: String,
+    /// Display names of everyone in the chat, the exporting user included.
+    pub participants: Vec<String>,
+}
+
+/// One output format. Called in order: `begin`, then `chat` followed by that
+/// chat's messages for every chat, then `finish`.
+trait Writer {
+    fn begin(&mut self, exported_at: &LocalTime) -> io::Result<()>;
+    fn chat(&mut self, transcript: &Transcript) -> io::Result<()>;
+    fn message(
+        &mut self,
+        message: &Message,
+        sender: &str,
+        own: bool,
+        at: &LocalTime,
+    ) -> io::Result<()>;
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Html => "html",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Text => "txt",
+        }
+    }
+
+    /// Name of the format in the save dialog's file type filter.
+    pub fn description(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "JSON",
+            ExportFormat::Html => "Web page",
+            ExportFormat::Markdown => "Markdown",
+            ExportFormat::Text => "Plain text",
+        }
+    }
+
+    fn writer<'w>(self, out: &'w mut dyn Write) -> Box<dyn Writer + 'w> {
+        match self {
+            ExportFormat::Json => Box::new(json::JsonWriter::new(out)),
+            ExportFormat::Html => Box::new(html::HtmlWriter::new(out)),
+            ExportFormat::Markdown => Box::new(markdown::MarkdownWriter::new(out)),
+            ExportFormat::Text => Box::new(text::TextWriter::new(out)),
+        }
+    }
+}
+
+/// Writes `chats` to `path`. Times are shown at `utc_offset_minutes` from
+/// UTC; `user_id` (if known) marks which messages are the user's own. A
+/// partially written file is removed if the export fails.
+pub fn export(
+    store: &Store,
+    user_id: Option<&str>,
+    chats: &[Chat],
+    format: ExportFormat,
+    utc_offset_minutes: i32,
+    path: &Path,
+) -> Result<ExportSummary> {
+    let result = write_file(store, user_id, chats, format, utc_offset_minutes, path);
+    if result.is_err() {
+        let _ = fs::remove_file(path);
+    }
+    let messages = result?;
+    Ok(ExportSummary {
+        path: path.display().to_string(),
+        chats: chats.len() as u32,
+        messages,
+    })
+}
+
+fn write_file(
+    store: &Store,
+    user_id: Option<&str>,
+    chats: &[Chat],
+    format: ExportFormat,
+    utc_offset_minutes: i32,
+    path: &Path,
+) -> Result<u32> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let mut count = 0;
+    {
+        let mut writer = format.writer(&mut out);
+        writer.begin(&LocalTime::new(now(), utc_offset_minutes))?;
+        for chat in chats {
+            writer.chat(&transcript(chat, user_id))?;
+
+            let mut after: Option<(Timestamp, String)> = None;
+            loop {
+                let cursor = after.as_ref().map(|(at, id)| (*at, id.as_str()));
+                let page = store.messages_after(&chat.id, cursor, PAGE_SIZE)?;
+                for message in &page {
+                    let own = user_id == Some(message.sender_id.as_str());
+                    let at = LocalTime::new(message.timestamp, utc_offset_minutes);
+                    writer.message(message, &sender_name(chat, message), own, &at)?;
+                    count += 1;
+                }
+                match page.last() {
+                    Some(last) if page.len() == PAGE_SIZE as usize => {
+                        after = Some((last.timestamp, last.id.clone()));
+                    }
+                    _ => break,
+                }
+            }
+        }
+        writer.finish()?;
+    }
+    out.into_inner().map_err(io::Error::from)?.sync_all()?;
+    Ok(count)
+}
+
+/// Name shown for the sender of `message`.
+pub fn sender_name(chat: &Chat, message: &Message) -> String {
+    chat.participant_names
+        .as_ref()
+        .and_then(|names| names.get(&message.sender_id))
+        .or(message.sender_name.as_ref())
+        .filter(|name| !name.is_empty())
+        .cloned()
+        .unwrap_or_else(|| "Unknown".into())
+}
+
+fn transcript<'a>(chat: &'a Chat, user_id: Option<&str>) -> Transcript<'a> {
+    let name_of = |id: &str| {
+        chat.participant_names
+            .as_ref()
+            .and_then(|names| names.get(id))
+            .cloned()
+            .unwrap_or_else(|| "Unknown".into())
+    };
+    let participants: Vec<String> = chat.participants.keys().map(|id| name_of(id)).collect();
+
+    let title = if chat.is_group == Some(true) {
+        chat.group_name
+            .clone()
+            .unwrap_or_else(|| "Group chat".into())
+    } else {
+        let others: Vec<String> = chat
+            .participants
+            .keys()
+            .filter(|id| Some(id.as_str()) != user_id)
+            .map(|id| name_of(id))
+            .collect();
+        format!("Chat with {}", others.join(", "))
+    };
+    Transcript {
+        chat,
+        title,
+        participants,
+    }
+}
+
+/// Suggested file name for exporting `chats`.
+pub fn file_name(chats: &[Chat], user_id: Option<&str>, format: ExportFormat) -> String {
+    let stem = match chats {
+        [chat] => {
+            let slug: String = transcript(chat, user_id)
+                .title
+                .chars()
+                .map(|c| {
+                    if c.is_alphanumeric() {
+                        c.to_ascii_lowercase()
+                    } else {
+                        '-'
+                    }
+                })
+                .collect();
+            let slug = slug
+                .split('-')
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("-");
+            format!("chitchat-{slug}")
+        }
+        _ => "chitchat-export".into(),
+    };
+    format!("{stem}.{}", format.extension())
+}
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// A timestamp broken down into calendar fields at a fixed UTC offset.
+pub struct LocalTime {
+    pub timestamp: Timestamp,
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    offset_minutes: i32,
+}
+
+impl LocalTime {
+    pub fn new(timestamp: Timestamp, offset_minutes: i32) -> Self {
+        let seconds = timestamp.div_euclid(1000) + i64::from(offset_minutes) * 60;
+        let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+        let of_day = seconds.rem_euclid(86_400);
+        LocalTime {
+            timestamp,
+            year,
+            month,
+            day,
+            hour: (of_day / 3600) as u32,
+            minute: (of_day % 3600 / 60) as u32,
+            offset_minutes,
+        }
+    }
+
+    fn offset(&self) -> String {
+        let sign = if self.offset_minutes < 0 { '-' } else { '+' };
+        let minutes = self.offset_minutes.unsigned_abs();
+        format!("{sign}{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+
+    /// `2024-05-01 13:45`
+    pub fn short(&self) -> String {
+        format!(
+            "{}-{:02}-{:02} {:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute
+        )
+    }
+
+    /// `1 May 2024 at 13:45`, which screen readers announce naturally.
+    pub fn spoken(&self) -> String {
+        format!(
+            "{} {} {} at {:02}:{:02}",
+            self.day,
+            MONTHS[self.month as usize - 1],
+            self.year,
+            self.hour,
+            self.minute
+        )
+    }
+
+    /// `2024-05-01T13:45+02:00`, for machine-readable markup.
+    pub fn iso(&self) -> String {
+        format!(
+            "{}-{:02}-{:02}T{:02}:{:02}{}",
+            self.year,
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.offset()
+        )
+    }
+
+    /// `UTC+02:00`
+    pub fn zone(&self) -> String {
+        format!("UTC{}", self.offset())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::models::Attachment;
+    use crate::temp_dir::TempDir;
+
+    /// 2024-05-01 13:45 UTC.
+    const SENT_AT: Timestamp = 1_714_571_100_000;
+    const MINUTE: Timestamp = 60_000;
+
+    fn message(id: &str, sender_id: &str, text: &str, timestamp: Timestamp) -> Message {
+        Message {
+            id: id.into(),
+            sender_id: sender_id.into(),
+            sender_name: None,
+            text: text.into(),
+            timestamp,
+            archived: None,
+            attachment: None,
+        }
+    }
+
+    /// A chat between Alice and Bob with formatting, markup, an attachment,
+    /// an edited message and imported history.
+    fn chat(store: &Store) -> Chat {
+        let chat: Chat = serde_json::from_value(json!({
+            "id": "c1",
+            "participants": { "alice": true, "bob": true },
+            "participantNames": { "alice": "Alice", "bob": "Bob" },
+        }))
+        .unwrap();
+        store.upsert_chats(std::slice::from_ref(&chat)).unwrap();
+        let photo = Message {
+            attachment: Some(Attachment {
+                name: "beach_*day*.jpg".into(),
+                mime_type: "image/jpeg".into(),
+                size: 2048,
+                sha256: "ab".repeat(32),
+                storage_path: "attachments/c1/p1".into(),
+                key: "a2V5".into(),
+                image: None,
+            }),
+            ..message("m3", "alice", "", SENT_AT + 2 * MINUTE)
+        };
+        store
+            .upsert_messages(
+                "c1",
+                &[
+                    message("m1", "alice", "Hi **Bob**\n# not a heading", SENT_AT),
+                    message(
+                        "m2",
+                        "bob",
+                        "<script>alert(\"hi\")</script> & 'bye'",
+                        SENT_AT + MINUTE,
+                    ),
+                    photo,
+                    message("m4", "bob", "first draft", SENT_AT + 3 * MINUTE),
+                ],
+            )
+            .unwrap();
+        // Edited: stored again with the new text
+        store
+            .upsert_messages(
+                "c1",
+                &[message("m4", "bob", "final text", SENT_AT + 3 * MINUTE)],
+            )
+            .unwrap();
+        store
+            .insert_archived_messages(
+                "c1",
+                &[Message {
+                    sender_name: Some("Ana".into()),
+                    ..message(
+                        "a1",
+                        "imported:ana",
+                        "From before",
+                        SENT_AT - 24 * 60 * MINUTE,
+                    )
+                }],
+            )
+            .unwrap();
+        chat
+    }
+
+    /// Exports the chat as Alice, two hours east of UTC.
+    fn exported(format: ExportFormat) -> String {
+        let store = Store::open_in_memory();
+        let chat = chat(&store);
+        let dir = TempDir::new();
+        let path = dir.join(format!("export.{}", format.extension()));
+        let summary = export(&store, Some("alice"), &[chat], format, 120, &path).unwrap();
+        assert_eq!((summary.chats, summary.messages), (1, 5));
+        fs::read_to_string(&path).unwrap()
+    }
+
+    /// What follows the export's header, which holds the current time.
+    fn body<'a>(export: &'a str, chat_start: &str) -> &'a str {
+        &export[export.find(chat_start).unwrap()..]
+    }
+
+    #[test]
+    fn json_holds_the_stored_chat_and_messages() {
+        let store = Store::open_in_memory();
+        let chat = chat(&store);
+        let export: Value = serde_json::from_str(&exported(ExportFormat::Json)).unwrap();
+        assert_eq!(export["chats"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            export["chats"][0]["chat"],
+            serde_json::to_value(&chat).unwrap()
+        );
+        assert_eq!(
+            export["chats"][0]["messages"],
+            serde_json::to_value(store.messages_after("c1", None, 10).unwrap()).unwrap()
+        );
+        let messages = export["chats"][0]["messages"].as_array().unwrap();
+        let ids: Vec<&str> = messages.iter().map(|m| m["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, ["a1", "m1", "m2", "m3", "m4"]);
+        assert_eq!(messages[0]["archived"], true);
+        assert_eq!(messages[3]["attachment"]["name"], "beach_*day*.jpg");
+        assert_eq!(messages[4]["text"], "final text");
+    }
+
+    #[test]
+    fn json_stays_valid_with_empty_chats() {
+        let store = Store::open_in_memory();
+        let full = chat(&store);
+        let empty: Chat = serde_json::from_value(json!({ "id": "c2" })).unwrap();
+        let dir = TempDir::new();
+        let cases = [
+            (vec![], vec![]),
+            (vec![empty.clone()], vec![0]),
+            (vec![empty.clone(), full, empty], vec![0, 5, 0]),
+        ];
+        for (chats, expected) in cases {
+            let path = dir.join("export.json");
+            export(&store, None, &chats, ExportFormat::Json, 0, &path).unwrap();
+            let export: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+            let counts: Vec<usize> = export["chats"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|chat| chat["messages"].as_array().unwrap().len())
+                .collect();
+            assert_eq!(counts, expected);
+        }
+    }
+
+    #[test]
+    fn html_escapes_text_and_lists_attachments() {
+        let export = exported(ExportFormat::Html);
+        assert!(export.starts_with("<!DOCTYPE html>"));
+        assert!(export.ends_with("</main>\n</body>\n</html>\n"));
+        assert!(!export.contains("<script"));
+        assert!(!export.contains("first draft"));
+        assert_eq!(
+            body(&export, "<section>"),
+            "<section>\n<h2>Chat with Bob</h2>\n\
+             <p class=\"participants\">Participants: Alice, Bob</p>\n<ol>\n\
+             <li><div class=\"meta\"><span class=\"sender\">Ana</span>\
+             <time datetime=\"2024-04-30T15:45+02:00\">2024-04-30 15:45</time></div>\
+             <p class=\"text\">From before</p></li>\n\
+             <li class=\"own\"><div class=\"meta\"><span class=\"sender\">Alice</span>\
+             <time datetime=\"2024-05-01T15:45+02:00\">2024-05-01 15:45</time></div>\
+             <p class=\"text\">Hi **Bob**\n# not a heading</p></li>\n\
+             <li><div class=\"meta\"><span class=\"sender\">Bob</span>\
+             <time datetime=\"2024-05-01T15:46+02:00\">2024-05-01 15:46</time></div>\
+             <p class=\"text\">&lt;script&gt;alert(&quot;hi&quot;)&lt;/script&gt; &amp; &#39;bye&#39;</p></li>\n\
+             <li class=\"own\"><div class=\"meta\"><span class=\"sender\">Alice</span>\
+             <time datetime=\"2024-05-01T15:47+02:00\">2024-05-01 15:47</time></div>\
+             <p class=\"attachment\">Attachment: beach_*day*.jpg</p></li>\n\
+             <li><div class=\"meta\"><span class=\"sender\">Bob</span>\
+             <time datetime=\"2024-05-01T15:48+02:00\">2024-05-01 15:48</time></div>\
+             <p class=\"text\">final text</p></li>\n\
+             </ol>\n</section>\n</main>\n</body>\n</html>\n"
+        );
+    }
+
+    #[test]
+    fn markdown_shows_text_as_written() {
+        let export = exported(ExportFormat::Markdown);
+        assert!(export.starts_with("# Chitchat export\n\nExported "));
+        assert_eq!(
+            body(&export, "\n## "),
+            "\n## Chat with Bob\n\n\
+             Participants: Alice, Bob\n\n\
+             **Ana** · 2024-04-30 15:45  \nFrom before\n\n\
+             **Alice** · 2024-05-01 15:45  \nHi \\*\\*Bob\\*\\*\\\n\\# not a heading\n\n\
+             **Bob** · 2024-05-01 15:46  \n\
+             \\<script\\>alert(\"hi\")\\</script\\> \\& 'bye'\n\n\
+             **Alice** · 2024-05-01 15:47  \n*Attachment: beach\\_\\*day\\*.jpg*\n\n\
+             **Bob** · 2024-05-01 15:48  \nfinal text\n\n"
+        );
+    }
+
+    #[test]
+    fn text_reads_like_a_transcript() {
+        let export = exported(ExportFormat::Text);
+        assert!(export.starts_with("Chitchat export, created "));
+        assert_eq!(
+            body(&export, "\nChat with Bob."),
+            "\nChat with Bob.\n\
+             Participants: Alice, Bob.\n\n\
+             Ana, 30 April 2024 at 15:45:\nFrom before\n\n\
+             Alice, 1 May 2024 at 15:45:\nHi **Bob**\n# not a heading\n\n\
+             Bob, 1 May 2024 at 15:46:\n<script>alert(\"hi\")</script> & 'bye'\n\n\
+             Alice, 1 May 2024 at 15:47:\nAttachment: beach_*day*.jpg.\n\n\
+             Bob, 1 May 2024 at 15:48:\nfinal text\n\n\
+             End of export.\n"
+        );
+    }
+
+    #[test]
+    fn file_names_follow_the_chat_title() {
+        let store = Store::open_in_memory();
+        let chat = chat(&store);
+        assert_eq!(
+            file_name(
+                std::slice::from_ref(&chat),
+                Some("alice"),
+                ExportFormat::Markdown
+            ),
+            "chitchat-chat-with-bob.md"
+        );
+        assert_eq!(
+            file_name(&[chat.clone(), chat], None, ExportFormat::Json),
+            "chitchat-export.json"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+// Plain-text transcript
+//
+// Written to be read aloud: no decorative characters or tables, dates spelled
+// out, one message per paragraph introduced by who wrote it and when.
+use std::io::{self, Write};
+
+use super::{LocalTime, Transcript, Writer};
+use crate::models::Message;
+
+pub struct TextWriter<'w> {
+    out: &'w mut dyn Write,
+}
+
+impl<'w> TextWriter<'w> {
+    pub fn new(out: &'w mut dyn Write) -> Self {
+        TextWriter { out }
+    }
+}
+
+impl Writer for TextWriter<'_> {
+    fn begin(&mut self, exported_at: &LocalTime) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "Chitchat export, created {} ({}).\n",
+            exported_at.spoken(),
+            exported_at.zone()
+        )
+    }
+
+    fn chat(&mut self, transcript: &Transcript) -> io::Result<()> {
+        writeln!(self.out, "\n{}.", transcript.title)?;
+        writeln!(
+            self.out,
+            "Participants: {}.\n",
+            transcript.participants.join(", ")
+        )
+    }
+
+    fn message(
+        &mut self,
+        message: &Message,
+        sender: &str,
+        _own: bool,
+        at: &LocalTime,
+    ) -> io::Result<()> {
+        writeln!(self.out, "{sender}, {}:", at.spoken())?;
+        let text = message.text.trim_end();
+        if !text.is_empty() {
+            writeln!(self.out, "{text}")?;
+        }
+        if let Some(attachment) = &message.attachment {
+            writeln!(self.out, "Attachment: {}.", attachment.name)?;
+        }
+        writeln!(self.out)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        writeln!(self.out, "End of export.")
+    }
+}
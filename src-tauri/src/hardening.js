@@ -0,0 +1,30 @@
+// Runs before any page script, on every page the webview loads. Keeps the
+// webview from remembering what is typed into the app: nothing is kept in
+// session storage, and form history, autofill and spell checking are off on
+// every input, including ones added later.
+(() => {
+  try {
+    sessionStorage.clear();
+  } catch (e) {}
+
+  const harden = (node) => {
+    if (!(node instanceof Element)) return;
+    for (const element of [node, ...node.querySelectorAll('input, form')]) {
+      if (element.matches('form')) {
+        element.setAttribute('autocomplete', 'off');
+      } else if (element.matches('input')) {
+        element.setAttribute('autocomplete', 'off');
+        element.setAttribute('autocorrect', 'off');
+        element.setAttribute('autocapitalize', 'off');
+        element.setAttribute('spellcheck', 'false');
+      }
+    }
+  };
+
+  // The document is still empty at this point, so watch it from the start
+  new MutationObserver((mutations) => {
+    for (const mutation of mutations) {
+      mutation.addedNodes.forEach(harden);
+    }
+  }).observe(document, { childList: true, subtree: true });
+})();
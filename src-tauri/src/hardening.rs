@@ -0,0 +1,65 @@
+// Webview hardening
+//
+// The Content Security Policy lives in `tauri.conf.json`; this adds the
+// script that keeps typed text out of the webview's form history and
+// storage. It is registered as an initialization script, so the webview runs
+// it before the page's own scripts on every load, reloads included, and in
+// every window the app opens.
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::Runtime;
+
+const PLUGIN_NAME: &str = "hardening";
+
+const SCRIPT: &str = include_str!("hardening.js");
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::new(PLUGIN_NAME).js_init_script(SCRIPT).build()
+}
+
+#[cfg(test)]
+mod tests {
+    use tauri::plugin::Plugin;
+
+    use super::*;
+
+    #[test]
+    fn script_runs_before_page_content() {
+        let plugin = init::<tauri::Wry>();
+        let script = plugin
+            .initialization_script_2()
+            .expect("no initialization script");
+        assert_eq!(script.script, SCRIPT);
+        assert!(script.for_main_frame_only);
+    }
+
+    #[test]
+    fn csp_only_allows_firebase_origins() {
+        let config: serde_json::Value =
+            serde_json::from_str(include_str!("../tauri.conf.json")).unwrap();
+        let security = &config["app"]["security"];
+        for key in ["csp", "devCsp"] {
+            let csp = security[key].as_object().expect("CSP is not set");
+            assert_eq!(csp["default-src"], "'self'");
+            assert_eq!(csp["script-src"], "'self'");
+            for (directive, sources) in csp {
+                for source in sources.as_str().unwrap().split_whitespace() {
+                    assert!(
+                        !source.contains("unsafe-eval") && source != "*",
+                        "{key} {directive} allows {source}"
+                    );
+                    if let Some((_, host)) = source.split_once("://") {
+                        let host = host.split(':').next().unwrap();
+                        assert!(
+                            ["localhost", "ipc.localhost", "chitchat-media.localhost"]
+                                .contains(&host)
+                                || host.ends_with(".googleapis.com")
+                                || host.ends_with(".firebaseio.com")
+                                || host.ends_with(".firebasedatabase.app"),
+                            "{key} {directive} allows {source}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,389 @@
+// Image processing for outgoing attachments
+//
+// Images are decoded and encoded again before they are uploaded, which drops
+// everything but the pixels: EXIF (including GPS coordinates), XMP and ICC
+// data never reach the backend. The EXIF orientation is applied to the pixels
+// first so photos stay upright. Large images are downscaled, then the quality
+// and if need be the dimensions are lowered until the file fits the size
+// limit. Opaque images become JPEG, PNGs and images with transparency stay
+// PNG unless they are opaque and too large.
+//
+// JPEG, PNG, WebP and GIF are decoded; GIFs are flattened to their first
+// frame, so the webview sends animated ones as they are.
+//
+// Every prepared image also gets a small thumbnail, which travels with the
+// attachment inside the end-to-end encrypted message so chats can show it
+// before the image is downloaded.
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType as PngFilter, PngEncoder};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits, RgbImage};
+
+use crate::error::{Error, Result};
+use crate::models::{ImageInfo, ImageOptions};
+use crate::push_id;
+
+/// Largest image accepted for decoding, per side.
+const MAX_INPUT_DIMENSION: u32 = 20_000;
+
+/// Prepared images older than this are removed from the cache.
+const KEEP_PREPARED: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Lowest JPEG quality tried before shrinking the image instead.
+const MIN_QUALITY: u8 = 50;
+
+/// Images are not shrunk below this to meet the size limit.
+const MIN_DIMENSION: u32 = 320;
+
+const THUMBNAIL_QUALITY: u8 = 70;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Jpeg(u8),
+    Png,
+}
+
+/// Processes the image at `path` and writes the result to its own folder
+/// under `out_dir`, keeping the original file name with the new extension.
+pub fn prepare(
+    path: &Path,
+    out_dir: &Path,
+    options: &ImageOptions,
+) -> Result<(PathBuf, ImageInfo)> {
+    let (mut image, format) = decode(path)?;
+    image = fit(image, options.max_dimension);
+    let opaque = is_opaque(&image);
+
+    let mut output = if format == ImageFormat::Png || image.color().has_alpha() {
+        Output::Png
+    } else {
+        Output::Jpeg(options.quality.clamp(1, 100))
+    };
+    let bytes = loop {
+        let bytes = encode(&image, output)?;
+        if bytes.len() as u64 <= options.max_bytes {
+            break bytes;
+        }
+        output = match output {
+            Output::Jpeg(quality) if quality > MIN_QUALITY => {
+                Output::Jpeg(quality.saturating_sub(10).max(MIN_QUALITY))
+            }
+            Output::Png if opaque => Output::Jpeg(options.quality.clamp(1, 100)),
+            _ if image.width().max(image.height()) > MIN_DIMENSION => {
+                let longest = image.width().max(image.height());
+                image = fit(image, (longest * 3 / 4).max(MIN_DIMENSION));
+                output
+            }
+            // As small as it gets; send it anyway
+            _ => break bytes,
+        };
+    };
+
+    clean_up(out_dir);
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("image");
+    let extension = match output {
+        Output::Jpeg(_) => "jpg",
+        Output::Png => "png",
+    };
+    let dir = out_dir.join(push_id::generate());
+    fs::create_dir_all(&dir)?;
+    let target = dir.join(format!("{stem}.{extension}"));
+    let mut file = BufWriter::new(File::create(&target)?);
+    file.write_all(&bytes)?;
+    file.flush()?;
+
+    let info = ImageInfo {
+        width: image.width(),
+        height: image.height(),
+        thumbnail: thumbnail_of(&image, options.thumbnail_size)?,
+    };
+    Ok((target, info))
+}
+
+/// A thumbnail of the image at `path` as a `data:` URL, at most `size`
+/// pixels on its longest side.
+pub fn thumbnail(path: &Path, size: u32) -> Result<String> {
+    let (image, _) = decode(path)?;
+    thumbnail_of(&image, size)
+}
+
+fn thumbnail_of(image: &DynamicImage, size: u32) -> Result<String> {
+    let small = image.thumbnail(size.max(1), size.max(1));
+    // Transparency needs PNG; everything else is far smaller as JPEG
+    let (mime_type, bytes) = if small.color().has_alpha() {
+        ("image/png", encode(&small, Output::Png)?)
+    } else {
+        (
+            "image/jpeg",
+            encode(&small, Output::Jpeg(THUMBNAIL_QUALITY))?,
+        )
+    };
+    Ok(format!("data:{mime_type};base64,{}", BASE64.encode(bytes)))
+}
+
+/// Decodes an image, with its EXIF orientation applied.
+fn decode(path: &Path) -> Result<(DynamicImage, ImageFormat)> {
+    let mut reader = ImageReader::new(BufReader::new(File::open(path)?)).with_guessed_format()?;
+    let format = reader
+        .format()
+        .ok_or_else(|| Error::InvalidImage(format!("{} is not an image", path.display())))?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_INPUT_DIMENSION);
+    limits.max_image_height = Some(MAX_INPUT_DIMENSION);
+    reader.limits(limits);
+
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok((image, format))
+}
+
+/// Scales the image down so its longest side is at most `max` pixels.
+fn fit(image: DynamicImage, max: u32) -> DynamicImage {
+    if image.width().max(image.height()) <= max {
+        return image;
+    }
+    image.resize(max, max, FilterType::Lanczos3)
+}
+
+fn encode(image: &DynamicImage, output: Output) -> Result<Vec<u8>> {
+    let mut bytes = Cursor::new(Vec::new());
+    match output {
+        Output::Jpeg(quality) => {
+            flatten(image).write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, quality))?
+        }
+        Output::Png => image.write_with_encoder(PngEncoder::new_with_quality(
+            &mut bytes,
+            CompressionType::Best,
+            PngFilter::Adaptive,
+        ))?,
+    }
+    Ok(bytes.into_inner())
+}
+
+/// Whether no pixel is even partly transparent. Screenshots and many other
+/// PNGs have an alpha channel they do not use.
+fn is_opaque(image: &DynamicImage) -> bool {
+    !image.color().has_alpha() || image.to_rgba8().pixels().all(|pixel| pixel.0[3] == u8::MAX)
+}
+
+/// The image as 8-bit RGB, with any transparency blended onto white.
+fn flatten(image: &DynamicImage) -> RgbImage {
+    if !image.color().has_alpha() {
+        return image.to_rgb8();
+    }
+    let rgba = image.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend =
+            |c: u8| ((u16::from(c) * u16::from(a) + 255 * (255 - u16::from(a))) / 255) as u8;
+        image::Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+/// Removes images prepared by earlier runs that were never sent (or were
+/// sent long ago).
+fn clean_up(out_dir: &Path) {
+    let Ok(entries) = fs::read_dir(out_dir) else {
+        return;
+    };
+    let cutoff = SystemTime::now() - KEEP_PREPARED;
+    for entry in entries.flatten() {
+        let stale = entry
+            .metadata()
+            .and_then(|meta| meta.modified())
+            .is_ok_and(|modified| modified < cutoff);
+        if stale {
+            if let Err(e) = fs::remove_dir_all(entry.path()) {
+                tracing::warn!("failed to remove {}: {e}", entry.path().display());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ImageEncoder, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    /// Pixels that compress badly, the same on every run.
+    fn noise(width: u32, height: u32, alpha: u8) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let n = (x.wrapping_mul(7919) ^ y.wrapping_mul(104_729)).wrapping_mul(2_654_435_761);
+            let [r, g, b, _] = n.to_be_bytes();
+            Rgba([r, g, b, alpha])
+        })
+    }
+
+    /// An EXIF block saying the image is rotated a quarter turn clockwise,
+    /// with a description and a GPS position.
+    fn exif() -> Vec<u8> {
+        let mut tiff = b"MM\0\x2a\0\0\0\x08".to_vec();
+        tiff.extend([0, 3]);
+        // ImageDescription, ASCII, at offset 50
+        tiff.extend([0x01, 0x0e, 0, 2, 0, 0, 0, 13, 0, 0, 0, 50]);
+        // Orientation, SHORT: rotate 90° clockwise
+        tiff.extend([0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0]);
+        // GPS IFD, at offset 64
+        tiff.extend([0x88, 0x25, 0, 4, 0, 0, 0, 1, 0, 0, 0, 64]);
+        tiff.extend([0, 0, 0, 0]);
+        tiff.extend(b"SECRET-PLACE\0\0");
+        // GPSLatitudeRef "N"
+        tiff.extend([
+            0, 1, 0x00, 0x01, 0, 2, 0, 0, 0, 2, b'N', 0, 0, 0, 0, 0, 0, 0,
+        ]);
+        let mut segment = vec![0xff, 0xe1];
+        segment.extend(u16::try_from(2 + 6 + tiff.len()).unwrap().to_be_bytes());
+        segment.extend(b"Exif\0\0");
+        segment.extend(tiff);
+        segment
+    }
+
+    fn write_jpeg(path: &Path, image: &RgbaImage, with_exif: bool) {
+        let mut jpeg = Vec::new();
+        DynamicImage::ImageRgba8(image.clone())
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, 90))
+            .unwrap();
+        if with_exif {
+            jpeg.splice(2..2, exif());
+        }
+        fs::write(path, jpeg).unwrap();
+    }
+
+    fn write_png(path: &Path, image: &RgbaImage) {
+        let file = BufWriter::new(File::create(path).unwrap());
+        PngEncoder::new(file)
+            .write_image(
+                image.as_raw(),
+                image.width(),
+                image.height(),
+                image::ExtendedColorType::Rgba8,
+            )
+            .unwrap();
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+    }
+
+    #[test]
+    fn metadata_is_stripped_after_applying_the_orientation() {
+        let dir = TempDir::new();
+        let path = dir.join("photo.jpeg");
+        write_jpeg(&path, &noise(40, 20, 255), true);
+        let original = fs::read(&path).unwrap();
+        assert!(contains(&original, b"SECRET-PLACE"));
+        assert_eq!(decode(&path).unwrap().0.width(), 20);
+
+        let (target, info) = prepare(&path, &dir.join("out"), &ImageOptions::default()).unwrap();
+        assert_eq!(target.file_name().unwrap(), "photo.jpg");
+        assert_eq!((info.width, info.height), (20, 40));
+        assert!(info.thumbnail.starts_with("data:image/jpeg;base64,"));
+
+        let prepared = fs::read(&target).unwrap();
+        for needle in [&b"Exif"[..], b"SECRET-PLACE", b"MM\0\x2a", b"\xff\xe1"] {
+            assert!(!contains(&prepared, needle), "{needle:?}");
+        }
+        // Upright without an orientation to apply
+        let mut decoder = ImageReader::open(&target).unwrap().into_decoder().unwrap();
+        assert_eq!(
+            decoder.orientation().unwrap(),
+            image::metadata::Orientation::NoTransforms
+        );
+        assert_eq!(decoder.dimensions(), (20, 40));
+    }
+
+    #[test]
+    fn large_images_are_downscaled() {
+        let dir = TempDir::new();
+        let path = dir.join("wide.png");
+        write_png(&path, &noise(800, 400, 255));
+        let options = ImageOptions {
+            max_dimension: 400,
+            max_bytes: u64::MAX,
+            ..ImageOptions::default()
+        };
+
+        let (target, info) = prepare(&path, &dir.join("out"), &options).unwrap();
+        assert_eq!((info.width, info.height), (400, 200));
+        assert_eq!(target.extension().unwrap(), "png");
+        assert_eq!(decode(&target).unwrap().0.width(), 400);
+    }
+
+    #[test]
+    fn quality_and_then_size_are_lowered_to_fit_the_limit() {
+        let dir = TempDir::new();
+        let path = dir.join("noise.png");
+        write_png(&path, &noise(480, 480, 255));
+        let options = ImageOptions {
+            max_bytes: 100_000,
+            ..ImageOptions::default()
+        };
+
+        // Opaque PNGs that are too large become JPEG, and shrink if need be
+        let (target, info) = prepare(&path, &dir.join("out"), &options).unwrap();
+        assert_eq!(target.extension().unwrap(), "jpg");
+        assert!(fs::metadata(&target).unwrap().len() <= options.max_bytes);
+        assert!(info.width < 480 && info.width >= MIN_DIMENSION);
+        assert_eq!(info.width, info.height);
+    }
+
+    #[test]
+    fn transparent_images_stay_png_while_shrinking() {
+        let dir = TempDir::new();
+        let path = dir.join("sticker.png");
+        write_png(&path, &noise(500, 500, 128));
+        let options = ImageOptions {
+            max_bytes: 450_000,
+            ..ImageOptions::default()
+        };
+
+        let (target, info) = prepare(&path, &dir.join("out"), &options).unwrap();
+        assert_eq!(target.extension().unwrap(), "png");
+        assert!(fs::metadata(&target).unwrap().len() <= options.max_bytes);
+        assert!(info.width < 500);
+        assert!(info.thumbnail.starts_with("data:image/png;base64,"));
+    }
+
+    #[test]
+    fn images_that_cannot_fit_are_sent_at_the_smallest_size() {
+        let dir = TempDir::new();
+        let path = dir.join("noise.jpg");
+        write_jpeg(&path, &noise(480, 240, 255), false);
+        let options = ImageOptions {
+            max_bytes: 1,
+            ..ImageOptions::default()
+        };
+
+        let (target, info) = prepare(&path, &dir.join("out"), &options).unwrap();
+        assert!(target.exists());
+        assert_eq!(info.width, MIN_DIMENSION);
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_images() {
+        let dir = TempDir::new();
+        let path = dir.join("notes.txt");
+        fs::write(&path, "not an image").unwrap();
+        assert!(matches!(
+            prepare(&path, &dir.join("out"), &ImageOptions::default()),
+            Err(Error::InvalidImage(_))
+        ));
+    }
+}
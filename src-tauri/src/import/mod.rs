@@ -0,0 +1,293 @@
+// Chat history import
+//
+// Parses chat exports of other messengers (see `ImportSource`) and stores
+// them in a chat as archived messages. Senders are mapped to the chat's
+// participants by display name or email; anyone who can't be matched keeps
+// the name from the export. Imported messages get IDs derived from their
+// content, so importing the same export twice adds nothing the second time.
+//
+// Archived messages only live in the local store: writing them to RTDB would
+// publish years of history without end-to-end encryption.
+//
+// Like attachments, the export is picked in a dialog the core opens, and the
+// webview only gets a token for it (see [`ChosenExports`]), never a path.
+mod telegram;
+mod whatsapp;
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use sha2::{Digest, Sha256};
+
+use crate::civil::{days_from_civil, days_in_month};
+use crate::error::{Error, Result};
+use crate::hex;
+use crate::models::{
+    Chat, ChosenFile, ImportReport, ImportSender, ImportSource, Message, Timestamp,
+};
+use crate::store::Store;
+
+/// Prefix of `senderId` for senders not mapped to a participant.
+const UNMAPPED_SENDER: &str = "imported:";
+
+/// Exports the user picked, by token, managed as state.
+#[derive(Default)]
+pub struct ChosenExports(Mutex<HashMap<String, PathBuf>>);
+
+impl ChosenExports {
+    /// Records an export the user picked, returning the token the webview
+    /// may import it by.
+    pub fn choose(&self, path: PathBuf) -> ChosenFile {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let token = hex::encode(&rand::random::<[u8; 16]>());
+        self.lock().insert(token.clone(), path);
+        ChosenFile { token, name }
+    }
+
+    /// The export recorded under `token`, which stays valid.
+    pub fn peek(&self, token: &str) -> Result<PathBuf> {
+        self.lock().get(token).cloned().ok_or_else(not_chosen)
+    }
+
+    /// The export recorded under `token`. The token cannot be used again.
+    pub fn take(&self, token: &str) -> Result<PathBuf> {
+        self.lock().remove(token).ok_or_else(not_chosen)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, PathBuf>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn not_chosen() -> Error {
+    Error::InvalidImport("no such file was chosen".into())
+}
+
+/// An export read into memory.
+pub struct Parsed {
+    pub source: ImportSource,
+    pub title: Option<String>,
+    pub messages: Vec<ParsedMessage>,
+    pub skipped: u32,
+}
+
+pub struct ParsedMessage {
+    pub sender: String,
+    pub text: String,
+    pub timestamp: Timestamp,
+}
+
+/// Reads the export at `path`, telling the format from its extension.
+/// Exports without time zone information are read at `utc_offset_minutes`.
+pub fn parse(path: &Path, utc_offset_minutes: i32) -> Result<Parsed> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("txt") => whatsapp::parse_file(path, utc_offset_minutes),
+        Some("zip") => whatsapp::parse_zip(path, utc_offset_minutes),
+        Some("json") => telegram::parse_file(path, utc_offset_minutes),
+        _ => Err(Error::InvalidImport(
+            "expected a WhatsApp .txt or .zip, or a Telegram result.json".into(),
+        )),
+    }
+}
+
+/// Imports `parsed` into `chat`, or only reports what would be imported if
+/// `dry_run` is set. `mapping` overrides how senders are matched: keys are
+/// names from the export, values a participant's user ID or email, or empty
+/// to keep the sender unmapped.
+pub fn import(
+    store: &Store,
+    chat: &Chat,
+    parsed: Parsed,
+    mapping: &HashMap<String, String>,
+    dry_run: bool,
+) -> Result<ImportReport> {
+    let participants = Participants::new(store, chat)?;
+    let mut senders: BTreeMap<String, ImportSender> = BTreeMap::new();
+    let mut occurrences: HashMap<String, u32> = HashMap::new();
+    let mut messages = Vec::with_capacity(parsed.messages.len());
+
+    for parsed_message in parsed.messages {
+        let sender = match senders.get_mut(&parsed_message.sender) {
+            Some(sender) => sender,
+            None => {
+                let user_id = match mapping.get(&parsed_message.sender) {
+                    Some(choice) if choice.is_empty() => None,
+                    Some(choice) => Some(participants.find(choice).ok_or_else(|| {
+                        Error::InvalidImport(format!("{choice} is not in this chat"))
+                    })?),
+                    None => participants.find(&parsed_message.sender),
+                };
+                senders
+                    .entry(parsed_message.sender.clone())
+                    .or_insert(ImportSender {
+                        name: parsed_message.sender.clone(),
+                        messages: 0,
+                        user_id,
+                    })
+            }
+        };
+        sender.messages += 1;
+
+        let key = message_key(parsed.source, &parsed_message);
+        let occurrence = occurrences.entry(key.clone()).or_default();
+        *occurrence += 1;
+        messages.push(Message {
+            id: message_id(&key, *occurrence),
+            sender_id: sender
+                .user_id
+                .clone()
+                .unwrap_or_else(|| format!("{UNMAPPED_SENDER}{}", sender.name)),
+            sender_name: Some(parsed_message.sender),
+            text: parsed_message.text,
+            timestamp: parsed_message.timestamp,
+            archived: Some(true),
+            attachment: None,
+        });
+    }
+
+    let already_imported = if dry_run {
+        let ids: Vec<&str> = messages.iter().map(|m| m.id.as_str()).collect();
+        store.existing_message_ids(&chat.id, &ids)?.len() as u32
+    } else {
+        messages.len() as u32 - store.insert_archived_messages(&chat.id, &messages)?
+    };
+
+    let mut senders: Vec<ImportSender> = senders.into_values().collect();
+    senders.sort_by(|a, b| {
+        b.messages
+            .cmp(&a.messages)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(ImportReport {
+        source: parsed.source,
+        title: parsed.title,
+        dry_run,
+        messages: messages.len() as u32,
+        already_imported,
+        skipped: parsed.skipped,
+        first_timestamp: messages.iter().map(|m| m.timestamp).min(),
+        last_timestamp: messages.iter().map(|m| m.timestamp).max(),
+        senders,
+    })
+}
+
+/// Everything a participant of a chat can be recognised by.
+struct Participants<'a> {
+    chat: &'a Chat,
+    /// Normalised display names and emails; `None` where several
+    /// participants share one.
+    by_name: HashMap<String, Option<String>>,
+}
+
+impl<'a> Participants<'a> {
+    fn new(store: &Store, chat: &'a Chat) -> Result<Self> {
+        let mut by_name: HashMap<String, Option<String>> = HashMap::new();
+        for id in chat.participants.keys() {
+            let user = store.user(id)?;
+            let names = [
+                chat.participant_names
+                    .as_ref()
+                    .and_then(|names| names.get(id))
+                    .cloned(),
+                user.as_ref().map(|u| u.display_name.clone()),
+                user.map(|u| u.email),
+            ];
+            for name in names.into_iter().flatten() {
+                let name = normalize(&name);
+                if name.is_empty() {
+                    continue;
+                }
+                by_name
+                    .entry(name)
+                    .and_modify(|existing| {
+                        if existing.as_deref() != Some(id.as_str()) {
+                            *existing = None;
+                        }
+                    })
+                    .or_insert_with(|| Some(id.clone()));
+            }
+        }
+        Ok(Participants { chat, by_name })
+    }
+
+    /// The participant with user ID, display name or email `name`.
+    fn find(&self, name: &str) -> Option<String> {
+        if self.chat.participants.contains_key(name) {
+            return Some(name.to_string());
+        }
+        self.by_name.get(&normalize(name)).cloned().flatten()
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn message_key(source: ImportSource, message: &ParsedMessage) -> String {
+    let source = match source {
+        ImportSource::Whatsapp => "whatsapp",
+        ImportSource::Telegram => "telegram",
+    };
+    format!(
+        "{source}\0{}\0{}\0{}",
+        message.timestamp, message.sender, message.text
+    )
+}
+
+/// Stable ID of the `occurrence`th message with `key`, so identical
+/// messages sent within the same minute are all kept.
+fn message_id(key: &str, occurrence: u32) -> String {
+    let digest = Sha256::new()
+        .chain_update(key)
+        .chain_update(occurrence.to_be_bytes())
+        .finalize();
+    format!("import-{}", hex::encode(&digest[..12]))
+}
+
+/// The timestamp of a wall-clock time at `utc_offset_minutes` from UTC, or
+/// `None` if the date does not exist.
+fn local_timestamp(
+    (year, month, day): (i64, u32, u32),
+    (hour, minute, second): (u32, u32, u32),
+    utc_offset_minutes: i32,
+) -> Option<Timestamp> {
+    if day == 0 || day > days_in_month(year, month)? || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let seconds = days_from_civil(year, month, day) * 86_400
+        + i64::from(hour * 3600 + minute * 60 + second)
+        - i64::from(utc_offset_minutes) * 60;
+    Some(seconds * 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chosen_exports_are_peeked_until_taken() {
+        let exports = ChosenExports::default();
+        let chosen = exports.choose(PathBuf::from("exports/WhatsApp Chat with Alice.zip"));
+        assert_eq!(chosen.name, "WhatsApp Chat with Alice.zip");
+
+        let path = PathBuf::from("exports/WhatsApp Chat with Alice.zip");
+        assert_eq!(exports.peek(&chosen.token).unwrap(), path);
+        assert_eq!(exports.take(&chosen.token).unwrap(), path);
+        assert!(matches!(
+            exports.take(&chosen.token),
+            Err(Error::InvalidImport(_))
+        ));
+        assert!(exports.peek("not-a-token").is_err());
+    }
+}
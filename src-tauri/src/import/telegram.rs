@@ -0,0 +1,338 @@
+// Telegram Desktop export parser
+//
+// Reads the `result.json` written by "Export chat history" in the
+// machine-readable JSON format. Message text is either a string or a list of
+// plain strings and formatted entities, which are flattened to plain text.
+// Service entries ("Alice joined the group") are skipped, and media without
+// a caption is imported as a short placeholder such as "[photo]".
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::{local_timestamp, Parsed, ParsedMessage};
+use crate::error::{Error, Result};
+use crate::models::{ImportSource, Timestamp};
+
+/// Shown as the sender of messages from deleted accounts.
+const DELETED_ACCOUNT: &str = "Deleted Account";
+
+/// A single-chat export, or a full account export listing chats.
+#[derive(Deserialize)]
+struct Export {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    messages: Option<Vec<Entry>>,
+    #[serde(default)]
+    chats: Option<ChatList>,
+}
+
+#[derive(Deserialize)]
+struct ChatList {
+    list: Vec<Export>,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    #[serde(rename = "type")]
+    kind: String,
+    /// Local time of the exporting machine, e.g. `2024-05-01T13:45:00`.
+    date: String,
+    /// Seconds since the epoch; missing from older exports.
+    #[serde(default)]
+    date_unixtime: Option<String>,
+    #[serde(default)]
+    from: Option<String>,
+    #[serde(default)]
+    text: Option<Text>,
+    #[serde(default)]
+    media_type: Option<String>,
+    #[serde(default)]
+    photo: Option<String>,
+    #[serde(default)]
+    file_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Text {
+    Plain(String),
+    Parts(Vec<Part>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Part {
+    Plain(String),
+    Entity { text: String },
+}
+
+pub fn parse_file(path: &Path, utc_offset_minutes: i32) -> Result<Parsed> {
+    let export: Export = serde_json::from_reader(BufReader::new(File::open(path)?))
+        .map_err(|e| Error::InvalidImport(format!("unreadable Telegram export: {e}")))?;
+    let export = match export {
+        Export {
+            messages: Some(_), ..
+        } => export,
+        Export {
+            chats: Some(ChatList { list }),
+            ..
+        } => {
+            let count = list.len();
+            let mut list = list.into_iter();
+            match (list.next(), list.next()) {
+                (Some(chat), None) => chat,
+                _ => {
+                    return Err(Error::InvalidImport(format!(
+                        "this export contains {count} chats; export a single chat instead"
+                    )))
+                }
+            }
+        }
+        _ => return Err(Error::InvalidImport("no Telegram messages found".into())),
+    };
+
+    let mut messages = Vec::new();
+    let mut skipped = 0;
+    for entry in export.messages.unwrap_or_default() {
+        let text = entry.text();
+        match (
+            entry.kind.as_str(),
+            entry.timestamp(utc_offset_minutes),
+            text,
+        ) {
+            ("message", Some(timestamp), Some(text)) => messages.push(ParsedMessage {
+                sender: entry
+                    .from
+                    .filter(|from| !from.trim().is_empty())
+                    .unwrap_or_else(|| DELETED_ACCOUNT.into()),
+                text,
+                timestamp,
+            }),
+            _ => skipped += 1,
+        }
+    }
+    Ok(Parsed {
+        source: ImportSource::Telegram,
+        title: export.name,
+        messages,
+        skipped,
+    })
+}
+
+impl Entry {
+    fn timestamp(&self, utc_offset_minutes: i32) -> Option<Timestamp> {
+        if let Some(seconds) = self
+            .date_unixtime
+            .as_deref()
+            .and_then(|s| s.parse::<i64>().ok())
+        {
+            return Some(seconds * 1000);
+        }
+        let (date, time) = self.date.split_once('T')?;
+        let mut date = date.split('-').map(|part| part.parse::<u32>().ok());
+        let mut time = time.split(':').map(|part| part.parse::<u32>().ok());
+        local_timestamp(
+            (i64::from(date.next()??), date.next()??, date.next()??),
+            (time.next()??, time.next()??, time.next()??),
+            utc_offset_minutes,
+        )
+    }
+
+    /// The message text, or a placeholder for media sent without a caption.
+    fn text(&self) -> Option<String> {
+        let text = match &self.text {
+            Some(Text::Plain(text)) => text.clone(),
+            Some(Text::Parts(parts)) => parts
+                .iter()
+                .map(|part| match part {
+                    Part::Plain(text) | Part::Entity { text } => text.as_str(),
+                })
+                .collect(),
+            None => String::new(),
+        };
+        if !text.trim().is_empty() {
+            return Some(text.trim_end().to_string());
+        }
+        if let Some(media_type) = &self.media_type {
+            Some(format!("[{}]", media_type.replace('_', " ")))
+        } else if self.photo.is_some() {
+            Some("[photo]".into())
+        } else {
+            self.file_name
+                .as_ref()
+                .map(|name| format!("[file: {name}]"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    fn parse(export: Value, utc_offset_minutes: i32) -> Result<Parsed> {
+        let dir = TempDir::new();
+        let path = dir.join("result.json");
+        std::fs::write(&path, export.to_string()).unwrap();
+        parse_file(&path, utc_offset_minutes)
+    }
+
+    fn chat(messages: Value) -> Value {
+        json!({ "name": "Book club", "type": "private_group", "id": 1, "messages": messages })
+    }
+
+    fn texts(parsed: &Parsed) -> Vec<(&str, &str)> {
+        parsed
+            .messages
+            .iter()
+            .map(|message| (message.sender.as_str(), message.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn flattens_text_entities() {
+        let parsed = parse(
+            chat(json!([{
+                "id": 1,
+                "type": "message",
+                "date": "2024-05-01T13:45:00",
+                "from": "Alice",
+                "text": [
+                    "Read ",
+                    { "type": "bold", "text": "this" },
+                    ": ",
+                    { "type": "link", "text": "https://example.com" },
+                    "\n",
+                ],
+            }])),
+            0,
+        )
+        .unwrap();
+        assert_eq!(parsed.title.as_deref(), Some("Book club"));
+        assert_eq!(
+            texts(&parsed),
+            [("Alice", "Read this: https://example.com")]
+        );
+    }
+
+    #[test]
+    fn uncaptioned_media_gets_a_placeholder() {
+        let entry = |media: Value| {
+            let mut entry = json!({
+                "type": "message",
+                "date": "2024-05-01T13:45:00",
+                "from": "Alice",
+                "text": "",
+            });
+            entry
+                .as_object_mut()
+                .unwrap()
+                .extend(media.as_object().unwrap().clone());
+            entry
+        };
+        let parsed = parse(
+            chat(json!([
+                entry(json!({ "media_type": "voice_message", "file": "voice/1.ogg" })),
+                entry(json!({ "photo": "photos/1.jpg", "width": 800, "height": 600 })),
+                entry(json!({ "file": "files/report.pdf", "file_name": "report.pdf" })),
+                entry(json!({ "photo": "photos/2.jpg", "text": "Look at this" })),
+            ])),
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            texts(&parsed),
+            [
+                ("Alice", "[voice message]"),
+                ("Alice", "[photo]"),
+                ("Alice", "[file: report.pdf]"),
+                ("Alice", "Look at this"),
+            ]
+        );
+    }
+
+    #[test]
+    fn prefers_date_unixtime_over_the_local_date() {
+        let parsed = parse(
+            chat(json!([
+                {
+                    "type": "message",
+                    "date": "2024-05-01T13:45:00",
+                    "date_unixtime": "1714571100",
+                    "from": "Alice",
+                    "text": "Exact",
+                },
+                {
+                    "type": "message",
+                    "date": "2024-05-01T13:45:00",
+                    "from": "Alice",
+                    "text": "Local",
+                },
+            ])),
+            120,
+        )
+        .unwrap();
+        let timestamps: Vec<i64> = parsed.messages.iter().map(|m| m.timestamp).collect();
+        assert_eq!(timestamps, [1_714_571_100_000, 1_714_563_900_000]);
+    }
+
+    #[test]
+    fn skips_service_entries_and_names_deleted_accounts() {
+        let parsed = parse(
+            chat(json!([
+                {
+                    "type": "service",
+                    "date": "2024-05-01T13:45:00",
+                    "actor": "Alice",
+                    "action": "invite_members",
+                    "text": "",
+                },
+                {
+                    "type": "message",
+                    "date": "2024-05-01T13:46:00",
+                    "from": null,
+                    "text": "Gone",
+                },
+            ])),
+            0,
+        )
+        .unwrap();
+        assert_eq!(texts(&parsed), [(DELETED_ACCOUNT, "Gone")]);
+        assert_eq!(parsed.skipped, 1);
+    }
+
+    #[test]
+    fn reads_a_single_chat_from_an_account_export() {
+        let message = json!([{
+            "type": "message",
+            "date": "2024-05-01T13:45:00",
+            "from": "Alice",
+            "text": "Hi",
+        }]);
+        let parsed = parse(json!({ "chats": { "list": [chat(message.clone())] } }), 0).unwrap();
+        assert_eq!(texts(&parsed), [("Alice", "Hi")]);
+
+        let result = parse(
+            json!({ "chats": { "list": [chat(message.clone()), chat(message)] } }),
+            0,
+        );
+        assert!(matches!(result, Err(Error::InvalidImport(_))));
+    }
+
+    #[test]
+    fn rejects_other_json() {
+        assert!(matches!(
+            parse(json!({ "hello": "world" }), 0),
+            Err(Error::InvalidImport(_))
+        ));
+        assert!(matches!(
+            parse(json!([1, 2]), 0),
+            Err(Error::InvalidImport(_))
+        ));
+    }
+}
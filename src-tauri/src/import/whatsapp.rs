@@ -0,0 +1,369 @@
+// WhatsApp "Export chat" parser
+//
+// The export is a plain-text log with one message per line, continued on the
+// following lines if it spans several, in one of two layouts:
+//
+//   31/12/2023, 23:59 - Alice: Happy new year!        (Android)
+//   [31/12/2023, 23:59:59] Alice: Happy new year!     (iOS)
+//
+// Dates follow the phone's locale, so the order of day and month is worked
+// out from the whole file, and times may be 12-hour. Timestamps carry no time
+// zone and are read at the given UTC offset. Lines without a sender, such as
+// "Alice added Bob", are system messages and are skipped.
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use zip::ZipArchive;
+
+use super::{local_timestamp, Parsed, ParsedMessage};
+use crate::error::{Error, Result};
+use crate::models::ImportSource;
+
+/// Largest chat log read, on its own or from an archive; attachments are not
+/// imported.
+const MAX_LOG_SIZE: u64 = 512 * 1024 * 1024;
+
+/// How the three numbers of a date are ordered.
+#[derive(Clone, Copy)]
+enum DateOrder {
+    DayMonthYear,
+    MonthDayYear,
+    YearMonthDay,
+}
+
+/// A line that starts a new message or system notice.
+struct Entry {
+    date: [u32; 3],
+    /// Whether `date[0]` was written with four digits.
+    year_first: bool,
+    time: (u32, u32, u32),
+    rest: String,
+}
+
+pub fn parse_file(path: &Path, utc_offset_minutes: i32) -> Result<Parsed> {
+    let mut text = String::new();
+    File::open(path)?
+        .take(MAX_LOG_SIZE)
+        .read_to_string(&mut text)?;
+    parse(&text, title_from(path.to_str()), utc_offset_minutes)
+}
+
+/// Reads the chat log out of the archive WhatsApp shares when exporting
+/// "with media".
+pub fn parse_zip(path: &Path, utc_offset_minutes: i32) -> Result<Parsed> {
+    let invalid = |e: zip::result::ZipError| Error::InvalidImport(e.to_string());
+    let mut archive = ZipArchive::new(File::open(path)?).map_err(invalid)?;
+    let logs: Vec<String> = archive
+        .file_names()
+        .filter(|name| name.to_ascii_lowercase().ends_with(".txt"))
+        .map(str::to_string)
+        .collect();
+    let Some(log) = logs
+        .iter()
+        .find(|name| name.ends_with("_chat.txt"))
+        .or_else(|| logs.first())
+    else {
+        return Err(Error::InvalidImport(
+            "the archive does not contain a WhatsApp chat".into(),
+        ));
+    };
+
+    let mut text = String::new();
+    archive
+        .by_name(log)
+        .map_err(invalid)?
+        .take(MAX_LOG_SIZE)
+        .read_to_string(&mut text)?;
+    let title = title_from(Some(log.as_str())).or_else(|| title_from(path.to_str()));
+    parse(&text, title, utc_offset_minutes)
+}
+
+fn parse(text: &str, title: Option<String>, utc_offset_minutes: i32) -> Result<Parsed> {
+    let mut entries: Vec<Entry> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_start_matches(['\u{feff}', '\u{200e}']);
+        match entry(line) {
+            Some(entry) => entries.push(entry),
+            None => {
+                if let Some(last) = entries.last_mut() {
+                    last.rest.push('\n');
+                    last.rest.push_str(line);
+                }
+            }
+        }
+    }
+    if entries.is_empty() {
+        return Err(Error::InvalidImport("no WhatsApp messages found".into()));
+    }
+
+    let order = date_order(&entries);
+    let mut messages = Vec::with_capacity(entries.len());
+    let mut skipped = 0;
+    for entry in entries {
+        let timestamp = local_timestamp(entry.ymd(order), entry.time, utc_offset_minutes);
+        match (timestamp, entry.rest.split_once(": ")) {
+            (Some(timestamp), Some((sender, text))) if !sender.trim().is_empty() => {
+                messages.push(ParsedMessage {
+                    sender: sender.trim().to_string(),
+                    text: text.trim_end().to_string(),
+                    timestamp,
+                });
+            }
+            _ => skipped += 1,
+        }
+    }
+    Ok(Parsed {
+        source: ImportSource::Whatsapp,
+        title,
+        messages,
+        skipped,
+    })
+}
+
+impl Entry {
+    fn ymd(&self, order: DateOrder) -> (i64, u32, u32) {
+        let [a, b, c] = self.date;
+        let (year, month, day) = match order {
+            DateOrder::DayMonthYear => (c, b, a),
+            DateOrder::MonthDayYear => (c, a, b),
+            DateOrder::YearMonthDay => (a, b, c),
+        };
+        // Two-digit years are this century's
+        let year = if year < 100 { 2000 + year } else { year };
+        (i64::from(year), month, day)
+    }
+}
+
+/// Parses the date and time a message line starts with.
+fn entry(line: &str) -> Option<Entry> {
+    let (stamp, rest) = match line.strip_prefix('[') {
+        Some(inner) => {
+            let (stamp, rest) = inner.split_once(']')?;
+            (stamp, rest.strip_prefix(' ').unwrap_or(rest))
+        }
+        None => line.split_once(" - ")?,
+    };
+    let (date, time) = stamp.split_once(',')?;
+
+    let parts: Vec<&str> = date.trim().split(['/', '.', '-']).map(str::trim).collect();
+    let [a, b, c] = parts.as_slice() else {
+        return None;
+    };
+    Some(Entry {
+        date: [a.parse().ok()?, b.parse().ok()?, c.parse().ok()?],
+        year_first: a.len() == 4,
+        time: parse_time(time)?,
+        rest: rest.to_string(),
+    })
+}
+
+/// `23:59`, `23:59:59`, `11:59 PM` or `11:59 p.m.`
+fn parse_time(time: &str) -> Option<(u32, u32, u32)> {
+    let time = time.trim();
+    let end = time
+        .find(|c: char| !(c.is_ascii_digit() || c == ':'))
+        .unwrap_or(time.len());
+    let (clock, suffix) = time.split_at(end);
+    let suffix: String = suffix
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .collect::<String>()
+        .to_lowercase();
+
+    let mut fields = clock.split(':').map(|field| field.parse::<u32>().ok());
+    let hour = fields.next()??;
+    let minute = fields.next()??;
+    let second = fields.next().map_or(Some(0), |field| field)?;
+    let hour = match suffix.as_str() {
+        "" => hour,
+        "am" if (1..=12).contains(&hour) => hour % 12,
+        "pm" if (1..=12).contains(&hour) => hour % 12 + 12,
+        _ => return None,
+    };
+    Some((hour, minute, second))
+}
+
+/// Day-first unless a first number over 12 is never seen but a second one
+/// is. Files where neither is seen are ambiguous; most locales put the day
+/// first.
+fn date_order(entries: &[Entry]) -> DateOrder {
+    if entries.iter().any(|entry| entry.year_first) {
+        return DateOrder::YearMonthDay;
+    }
+    let first_over_12 = entries.iter().any(|entry| entry.date[0] > 12);
+    let second_over_12 = entries.iter().any(|entry| entry.date[1] > 12);
+    if second_over_12 && !first_over_12 {
+        DateOrder::MonthDayYear
+    } else {
+        DateOrder::DayMonthYear
+    }
+}
+
+/// The contact or group name in "WhatsApp Chat with Alice.txt".
+fn title_from(path: Option<&str>) -> Option<String> {
+    let stem = Path::new(path?).file_stem()?.to_str()?;
+    ["WhatsApp Chat with ", "WhatsApp Chat - "]
+        .iter()
+        .find_map(|prefix| stem.strip_prefix(prefix))
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    fn messages(parsed: &Parsed) -> Vec<(&str, &str, i64)> {
+        parsed
+            .messages
+            .iter()
+            .map(|message| {
+                (
+                    message.sender.as_str(),
+                    message.text.as_str(),
+                    message.timestamp,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reads_day_first_dates_and_24_hour_times() {
+        let parsed = parse(
+            "31/12/2023, 23:59 - Alice: Happy new year!\n\
+             02/01/2024, 09:05 - Bob: Back at work",
+            None,
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            messages(&parsed),
+            [
+                ("Alice", "Happy new year!", 1_704_067_140_000),
+                ("Bob", "Back at work", 1_704_186_300_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_month_first_dates_and_12_hour_times() {
+        let parsed = parse(
+            "1/2/24, 9:05 PM - Alice: Dinner?\n\
+             1/13/24, 12:30 AM - Bob: Sorry, only saw this now",
+            None,
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            messages(&parsed),
+            [
+                ("Alice", "Dinner?", 1_704_229_500_000),
+                ("Bob", "Sorry, only saw this now", 1_705_105_800_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_the_ios_layout_with_seconds() {
+        let parsed = parse(
+            "[2024-01-02, 09:05:07] Alice: Morning\n\
+             [2024-01-02, 9:05:00 p.m.] Bob: Evening",
+            None,
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            messages(&parsed),
+            [
+                ("Alice", "Morning", 1_704_186_307_000),
+                ("Bob", "Evening", 1_704_229_500_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn ambiguous_dates_are_day_first() {
+        let parsed = parse("02.01.24, 21:05 - Alice: Hi", None, 0).unwrap();
+        assert_eq!(messages(&parsed), [("Alice", "Hi", 1_704_229_500_000)]);
+    }
+
+    #[test]
+    fn joins_continuation_lines_and_skips_system_messages() {
+        let parsed = parse(
+            "\u{feff}31/12/2023, 23:59 - Alice added Bob\n\
+             31/12/2023, 23:59 - Alice: Shopping list:\n\
+             - milk\n\
+             \n\
+             - eggs: a dozen\n\
+             31/12/2023, 23:59 - Bob: On it",
+            None,
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            messages(&parsed),
+            [
+                (
+                    "Alice",
+                    "Shopping list:\n- milk\n\n- eggs: a dozen",
+                    1_704_067_140_000
+                ),
+                ("Bob", "On it", 1_704_067_140_000),
+            ]
+        );
+        assert_eq!(parsed.skipped, 1);
+    }
+
+    #[test]
+    fn skips_impossible_dates() {
+        let parsed = parse(
+            "31/02/2024, 10:00 - Alice: Never sent\n\
+             01/03/2024, 25:00 - Alice: Nor this\n\
+             01/03/2024, 10:00 - Alice: Sent",
+            None,
+            0,
+        )
+        .unwrap();
+        assert_eq!(parsed.messages.len(), 1);
+        assert_eq!(parsed.skipped, 2);
+    }
+
+    #[test]
+    fn reads_times_at_the_utc_offset() {
+        let parsed = parse("31/12/2023, 23:59 - Alice: Hi", None, 60).unwrap();
+        assert_eq!(parsed.messages[0].timestamp, 1_704_067_140_000 - 3_600_000);
+    }
+
+    #[test]
+    fn rejects_text_without_messages() {
+        assert!(matches!(
+            parse("Just some notes\nwithout dates", None, 0),
+            Err(Error::InvalidImport(_))
+        ));
+    }
+
+    #[test]
+    fn titles_come_from_the_file_name() {
+        assert_eq!(
+            title_from(Some("exports/WhatsApp Chat with Alice.txt")).as_deref(),
+            Some("Alice")
+        );
+        assert_eq!(
+            title_from(Some("WhatsApp Chat - Book club.zip")).as_deref(),
+            Some("Book club")
+        );
+        assert_eq!(title_from(Some("_chat.txt")), None);
+    }
+
+    #[test]
+    fn parse_file_reads_the_title_from_the_path() {
+        let dir = TempDir::new();
+        let path = dir.join("WhatsApp Chat with Alice.txt");
+        std::fs::write(&path, "31/12/2023, 23:59 - Alice: Hi").unwrap();
+        let parsed = parse_file(&path, 0).unwrap();
+        assert_eq!(parsed.title.as_deref(), Some("Alice"));
+        assert_eq!(parsed.messages.len(), 1);
+    }
+}
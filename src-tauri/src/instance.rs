@@ -0,0 +1,334 @@
+// Single instance
+//
+// One process runs per profile. The first one claims the profile and listens
+// on a local socket: a Unix domain socket guarded by a lock file in the
+// user's runtime directory, or a named pipe on Windows, whose first instance
+// is the lock. Both are named after the app, the profile and the user's home
+// directory. A later process started for the same profile hands its
+// arguments over through the socket and exits, and the running one brings
+// its window to the front and opens any links among them. Without this, both
+// would sign in, keep presence up and play every sound.
+//
+// Each connection carries one request, a line of JSON, and the answer `ok`
+// once it has been handled.
+use std::io::{self, BufRead, BufReader, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::AppHandle;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt};
+
+use crate::deep_link;
+use crate::hex;
+use crate::tray;
+
+/// How long a later process keeps trying to reach the running one.
+const TIMEOUT: Duration = Duration::from_secs(5);
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+const ACK: &str = "ok";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Request {
+    args: Vec<String>,
+}
+
+impl Request {
+    fn from_line(line: &str) -> io::Result<Self> {
+        Ok(serde_json::from_str(line)?)
+    }
+
+    fn to_line(&self) -> io::Result<String> {
+        let mut line = serde_json::to_string(self)?;
+        line.push('\n');
+        Ok(line)
+    }
+
+    /// The links the running process opens. Anything else, such as the
+    /// profile it was started for, is already how that process runs.
+    fn links(&self) -> Vec<String> {
+        deep_link::in_args(&self.args)
+    }
+}
+
+pub enum Claim {
+    /// This process runs the profile; pass the instance to [`listen`].
+    Claimed(Instance),
+    /// Another process runs it and took over the arguments.
+    Forwarded,
+    /// Neither worked out, so this process runs without taking requests.
+    Unclaimed,
+}
+
+/// The claim on a profile, held until the process exits.
+pub struct Instance {
+    #[cfg(unix)]
+    _lock: std::fs::File,
+    #[cfg(unix)]
+    listener: std::os::unix::net::UnixListener,
+    #[cfg(windows)]
+    name: String,
+    #[cfg(windows)]
+    server: tokio::net::windows::named_pipe::NamedPipeServer,
+}
+
+/// Claims `profile` for this process, or hands `args` to the process that
+/// has it. One that is still starting up or shutting down is waited for;
+/// one that does not answer at all is run alongside, rather than leaving the
+/// user without a window.
+pub fn claim(identifier: &str, profile: &str, args: &[String]) -> Claim {
+    let name = name(identifier, profile);
+    let request = Request {
+        args: args.to_vec(),
+    };
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        match take(&name) {
+            Ok(Some(instance)) => return Claim::Claimed(instance),
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("could not claim the profile: {e}");
+                return Claim::Unclaimed;
+            }
+        }
+        match send(&name, &request) {
+            Ok(()) => return Claim::Forwarded,
+            Err(e) if Instant::now() >= deadline => {
+                eprintln!("the running instance did not answer: {e}");
+                return Claim::Unclaimed;
+            }
+            Err(_) => thread::sleep(RETRY_INTERVAL),
+        }
+    }
+}
+
+/// Starts taking requests from later processes.
+pub fn listen(app: &AppHandle, instance: Instance) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = serve(&app, instance).await {
+            tracing::warn!("not taking requests from other instances: {e}");
+        }
+    });
+}
+
+/// A name unique to the app, the profile and the user.
+fn name(identifier: &str, profile: &str) -> String {
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .unwrap_or_default();
+    let mut hash = Sha256::new();
+    hash.update(identifier.as_bytes());
+    hash.update([0]);
+    hash.update(profile.as_bytes());
+    hash.update([0]);
+    hash.update(home.as_encoded_bytes());
+    let hash = hash.finalize();
+    format!("chitchat-{}", hex::encode(&hash[..8]))
+}
+
+fn send(name: &str, request: &Request) -> io::Result<()> {
+    let stream = connect(name)?;
+    (&stream).write_all(request.to_line()?.as_bytes())?;
+    let mut answer = String::new();
+    BufReader::new(&stream).read_line(&mut answer)?;
+    if answer.trim_end() != ACK {
+        return Err(io::Error::other(format!("unexpected answer {answer:?}")));
+    }
+    Ok(())
+}
+
+async fn handle<S: AsyncRead + AsyncWrite>(app: &AppHandle, stream: S) -> io::Result<()> {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut line = String::new();
+    tokio::io::BufReader::new(reader)
+        .read_line(&mut line)
+        .await?;
+    let request = Request::from_line(&line)?;
+    // The arguments may hold links with email addresses, so only count them
+    tracing::info!(
+        args = request.args.len(),
+        "started again, showing the window"
+    );
+    tray::show_main_window(app);
+    deep_link::open(app, request.links());
+    writer.write_all(format!("{ACK}\n").as_bytes()).await?;
+    writer.flush().await
+}
+
+#[cfg(unix)]
+fn path(name: &str, extension: &str) -> std::path::PathBuf {
+    // The runtime directory is private to the user; the temporary directory
+    // is too on macOS
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join(format!("{name}.{extension}"))
+}
+
+#[cfg(unix)]
+fn take(name: &str) -> io::Result<Option<Instance>> {
+    use std::fs::{self, File, TryLockError};
+    use std::os::unix::net::UnixListener;
+
+    let lock = File::options()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(path(name, "lock"))?;
+    match lock.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => return Ok(None),
+        Err(TryLockError::Error(e)) => return Err(e),
+    }
+    // Left behind by a process that did not exit cleanly, as the lock was free
+    let path = path(name, "sock");
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    let listener = UnixListener::bind(&path)?;
+    listener.set_nonblocking(true)?;
+    Ok(Some(Instance {
+        _lock: lock,
+        listener,
+    }))
+}
+
+#[cfg(unix)]
+fn connect(name: &str) -> io::Result<std::os::unix::net::UnixStream> {
+    let stream = std::os::unix::net::UnixStream::connect(path(name, "sock"))?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    Ok(stream)
+}
+
+#[cfg(unix)]
+async fn serve(app: &AppHandle, instance: Instance) -> io::Result<()> {
+    let Instance { _lock, listener } = instance;
+    let listener = tokio::net::UnixListener::from_std(listener)?;
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = handle(&app, stream).await {
+                tracing::warn!("bad request from another instance: {e}");
+            }
+        });
+    }
+}
+
+#[cfg(windows)]
+fn pipe_name(name: &str) -> String {
+    format!(r"\\.\pipe\{name}")
+}
+
+#[cfg(windows)]
+fn take(name: &str) -> io::Result<Option<Instance>> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    // Pipes are registered with the runtime that later serves them
+    let server = tauri::async_runtime::block_on(async {
+        ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(pipe_name(name))
+    });
+    match server {
+        Ok(server) => Ok(Some(Instance {
+            name: name.to_string(),
+            server,
+        })),
+        // Another process created the first instance
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(windows)]
+fn connect(name: &str) -> io::Result<std::fs::File> {
+    // Pipes are opened like files; reads wait for the answer. Opening fails
+    // while the running process sets up the next pipe instance, and is retried.
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(pipe_name(name))
+}
+
+#[cfg(windows)]
+async fn serve(app: &AppHandle, instance: Instance) -> io::Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let name = pipe_name(&instance.name);
+    let mut server = instance.server;
+    loop {
+        server.connect().await?;
+        // Have the next instance of the pipe ready before handling this one
+        let stream = std::mem::replace(&mut server, ServerOptions::new().create(&name)?);
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = handle(&app, stream).await {
+                tracing::warn!("bad request from another instance: {e}");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forwarded(args: &[&str]) -> Request {
+        let request = Request {
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        };
+        let line = request.to_line().unwrap();
+        assert!(line.ends_with('\n') && !line.trim_end().contains('\n'));
+        Request::from_line(&line).unwrap()
+    }
+
+    #[test]
+    fn forwards_links_along_with_the_profile() {
+        let request = forwarded(&["--profile", "work", "chitchat://chat/c1?message=m1"]);
+        assert_eq!(
+            request.args,
+            ["--profile", "work", "chitchat://chat/c1?message=m1"]
+        );
+        assert_eq!(request.links(), ["chitchat://chat/c1?message=m1"]);
+
+        let request = forwarded(&[
+            "chitchat://chat/c1",
+            "--profile=work",
+            "chitchat://invite/abc",
+        ]);
+        assert_eq!(
+            request.links(),
+            ["chitchat://chat/c1", "chitchat://invite/abc"]
+        );
+    }
+
+    #[test]
+    fn a_plain_start_opens_no_links() {
+        assert!(forwarded(&[]).links().is_empty());
+        assert!(forwarded(&["--profile", "work"]).links().is_empty());
+        assert!(forwarded(&["https://example.com", "--diagnostics"])
+            .links()
+            .is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_requests() {
+        for line in ["", "ok\n", "{\"args\": \"chitchat://chat/c1\"}\n", "[]\n"] {
+            assert!(Request::from_line(line).is_err(), "{line:?}");
+        }
+    }
+
+    #[test]
+    fn names_differ_per_app_and_profile() {
+        let default = name("com.chitchat.desktop", "default");
+        assert_eq!(default, name("com.chitchat.desktop", "default"));
+        assert_ne!(default, name("com.chitchat.desktop", "work"));
+        assert_ne!(default, name("com.chitchat.dev", "default"));
+        assert!(default.starts_with("chitchat-") && default.len() == "chitchat-".len() + 16);
+    }
+}
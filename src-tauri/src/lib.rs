@@ -1,59 +1,221 @@
-use tauri::Manager;
+mod attachments;
+mod civil;
+mod commands;
+mod crash;
+mod deep_link;
+mod diagnostics;
+mod e2e;
+mod error;
+mod export;
+mod hardening;
+mod hex;
+mod images;
+mod import;
+mod instance;
+mod logging;
+mod markdown;
+mod media_cache;
+mod models;
+mod notifications;
+mod outbox;
+mod profiles;
+mod push_id;
+mod rtdb;
+mod search;
+mod session;
+mod settings;
+mod store;
+mod subscriptions;
+#[cfg(test)]
+mod temp_dir;
+mod tray;
+mod unread;
+mod windows;
 
-// Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
-#[tauri::command]
-fn greet(name: &str) -> String {
-    format!("Hello, {}! You've been greeted from Rust!", name)
-}
+use tauri::{Manager, RunEvent, WindowEvent};
+
+use crate::attachments::Attachments;
+use crate::deep_link::DeepLinks;
+use crate::diagnostics::connection::ConnectionHistory;
+use crate::e2e::E2e;
+use crate::import::ChosenExports;
+use crate::instance::Claim;
+use crate::media_cache::MediaCache;
+use crate::notifications::shown::Shown;
+use crate::notifications::Notifier;
+use crate::outbox::Outbox;
+use crate::profiles::Profile;
+use crate::session::Session;
+use crate::settings::SettingsStore;
+use crate::store::Store;
+use crate::subscriptions::Subscriptions;
+use crate::windows::state::WindowStates;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    crash::install_hook();
+    let context = tauri::generate_context!();
+
+    // One process per profile; later ones hand their arguments, such as links,
+    // to it and exit
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let instance = match instance::claim(
+        &context.config().identifier,
+        &profiles::requested_name(),
+        &args,
+    ) {
+        Claim::Claimed(instance) => Some(instance),
+        Claim::Forwarded => return,
+        Claim::Unclaimed => None,
+    };
+    let links = deep_link::in_args(&args);
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_notification::init())
-        .invoke_handler(tauri::generate_handler![greet])
-        .setup(|app| {
-            // Disable WebView data persistence (form history, autofill, etc.)
-            if let Some(webview) = app.get_webview_window("main") {
-                // Clear any cached form data and disable autofill
-                webview.eval(r#"
-                    // Clear stored form data
-                    try {
-                        sessionStorage.clear();
-                    } catch(e) {}
-                    
-                    // Disable form autofill on all inputs
-                    const disableAutofill = () => {
-                        document.querySelectorAll('input').forEach(input => {
-                            input.setAttribute('autocomplete', 'off');
-                            input.setAttribute('autocorrect', 'off');
-                            input.setAttribute('autocapitalize', 'off');
-                            input.setAttribute('spellcheck', 'false');
-                        });
-                        document.querySelectorAll('form').forEach(form => {
-                            form.setAttribute('autocomplete', 'off');
-                        });
-                    };
-                    
-                    // Run on load and observe for new elements
-                    if (document.readyState === 'loading') {
-                        document.addEventListener('DOMContentLoaded', disableAutofill);
-                    } else {
-                        disableAutofill();
-                    }
-                    
-                    // Observer for dynamically added inputs
-                    const observer = new MutationObserver(disableAutofill);
-                    observer.observe(document.body || document.documentElement, {
-                        childList: true,
-                        subtree: true
-                    });
-                "#).ok();
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(hardening::init())
+        // Cached media for the webview, e.g. chitchat-media://localhost/<sha256>
+        .register_asynchronous_uri_scheme_protocol(
+            media_cache::SCHEME,
+            |ctx, request, responder| {
+                let app = ctx.app_handle().clone();
+                tauri::async_runtime::spawn_blocking(move || {
+                    responder.respond(media_cache::respond(&app, &request))
+                });
+            },
+        )
+        .invoke_handler(crash::track_commands(tauri::generate_handler![
+            commands::store::get_cached_chats,
+            commands::store::cache_chats,
+            commands::store::remove_cached_chat,
+            commands::store::get_cached_messages,
+            commands::store::cache_messages,
+            commands::store::get_cached_user,
+            commands::store::cache_users,
+            commands::store::clear_local_store,
+            commands::session::set_backend_session,
+            commands::session::clear_backend_session,
+            commands::subscriptions::subscribe_chats,
+            commands::subscriptions::subscribe_chat,
+            commands::subscriptions::subscribe_messages,
+            commands::subscriptions::subscribe_user,
+            commands::subscriptions::unsubscribe,
+            commands::outbox::enqueue_message,
+            commands::outbox::list_outbox,
+            commands::outbox::retry_outbox_message,
+            commands::outbox::discard_outbox_message,
+            commands::search::search_messages,
+            commands::export::export_chats,
+            commands::import::choose_import_file,
+            commands::import::import_history,
+            commands::import::get_archived_messages,
+            commands::attachments::choose_attachment,
+            commands::attachments::upload_attachment,
+            commands::attachments::save_attachment,
+            commands::attachments::cache_attachment,
+            commands::images::prepare_image,
+            commands::images::image_thumbnail,
+            commands::media::get_media_cache_usage,
+            commands::media::clear_media_cache,
+            commands::markdown::render_markdown,
+            commands::markdown::get_highlight_css,
+            commands::logs::log_message,
+            commands::logs::get_logs,
+            commands::diagnostics::record_connection_state,
+            commands::diagnostics::create_diagnostics_bundle,
+            commands::unread::mark_chat_read,
+            commands::unread::get_unread_chats,
+            commands::tray::get_notifications_muted_until,
+            commands::settings::get_settings,
+            commands::settings::update_settings,
+            commands::profiles::list_profiles,
+            commands::profiles::switch_profile,
+            commands::deep_link::take_deep_links,
+            commands::windows::open_chat_window,
+            commands::notifications::notify_presence,
+            commands::notifications::set_open_chat,
+            commands::notifications::mute_chat,
+            commands::notifications::unmute_chat,
+        ]))
+        .setup(move |app| {
+            // Log to files from here on; a failure leaves only stderr
+            match logging::init(&app.path().app_log_dir()?) {
+                Ok(logs) => {
+                    app.manage(logs);
+                }
+                Err(e) => eprintln!("logging: {e}"),
+            }
+            let crash_dir = crash::dir(app.handle())?;
+            crash::init(&crash_dir, app.package_info().version.to_string())?;
+
+            // Everything below is kept apart per profile
+            let profile = Profile::from_args(app.handle())?;
+            tracing::info!(profile = profile.name(), "starting");
+            app.manage(SettingsStore::open(profile.config_dir())?);
+            app.manage(WindowStates::open(profile.config_dir()));
+
+            // Open the encrypted local cache before the webview starts invoking commands
+            let store = Store::open(profile.data_dir(), profile.name())?;
+            app.manage(store);
+            app.manage(Session::default());
+            app.manage(Subscriptions::default());
+            app.manage(ConnectionHistory::default());
+            app.manage(E2e::default());
+            app.manage(Attachments::default());
+            app.manage(ChosenExports::default());
+            let media_dir = profile.cache_dir().join("media");
+            app.manage(MediaCache::open(&media_dir)?);
+            app.manage(DeepLinks::default());
+            app.manage(Notifier::default());
+            app.manage(Shown::open(profile.data_dir()));
+            if let Some(instance) = instance {
+                instance::listen(app.handle(), instance);
+            }
+            app.manage(profile);
+
+            // Deliver queued messages in the background, including ones left over from
+            // the last run
+            app.manage(Outbox::default());
+            outbox::spawn_worker(app.handle().clone());
+
+            // Ask before enabling the web inspector, then open the main window
+            diagnostics::start(app.handle())?;
+            crash::prompt_pending(app.handle(), &crash_dir);
+
+            // Keep running in the tray when the window is closed
+            if let Err(e) = tray::init(app.handle()) {
+                tracing::error!("failed to create the tray icon: {e}");
             }
+
+            // Handle chitchat:// links, including the one the app was started for
+            deep_link::register(app.handle());
+            deep_link::open(app.handle(), links);
+
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .on_page_load(subscriptions::on_page_load)
+        .on_window_event(|window, event| {
+            windows::on_event(window, event);
+            attachments::on_window_event(window, event);
+            subscriptions::on_window_event(window, event);
+            if let WindowEvent::CloseRequested { api, .. } = event {
+                if window.label() == windows::MAIN && tray::hides_on_close(window.app_handle()) {
+                    api.prevent_close();
+                    let _ = window.hide();
+                }
+            }
+        })
+        .build(context)
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                if let Some(Err(e)) = app.try_state::<WindowStates>().map(|states| states.save()) {
+                    tracing::warn!("failed to save window placement: {e}");
+                }
+            }
+            deep_link::on_run_event(app, event);
+        });
 }
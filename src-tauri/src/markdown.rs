@@ -0,0 +1,360 @@
+// Markdown rendering for message text
+//
+// Messages are parsed as CommonMark (plus strikethrough), with a few chat
+// conventions: single line breaks are kept, bare http(s) URLs become links,
+// raw HTML is shown as typed and images are shown as links to them. Fenced
+// code blocks with a known language are highlighted into `hl-` prefixed
+// classes, styled by the CSS from [`highlight_css`].
+//
+// The generated HTML is always passed through an allowlist sanitizer before
+// it reaches the webview: only formatting tags survive, links are limited to
+// http(s) and mailto, and the only classes kept are the highlighter's.
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use pulldown_cmark::{
+    CodeBlockKind, CowStr, Event, LinkType, Options, Parser, Tag, TagEnd, TextMergeStream,
+};
+use syntect::highlighting::ThemeSet;
+use syntect::html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+use crate::error::{Error, Result};
+
+/// Prefix of the highlighter's classes.
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+const LIGHT_THEME: &str = "InspiredGitHub";
+const DARK_THEME: &str = "base16-ocean.dark";
+
+/// Longer code blocks are shown without highlighting, which gets slow.
+const MAX_HIGHLIGHTED_LEN: usize = 20_000;
+
+const URL_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+static THEMES: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
+
+static SANITIZER: LazyLock<ammonia::Builder<'static>> = LazyLock::new(|| {
+    let mut builder = ammonia::Builder::empty();
+    builder
+        .tags(HashSet::from([
+            "p",
+            "br",
+            "strong",
+            "em",
+            "del",
+            "code",
+            "pre",
+            "span",
+            "a",
+            "ul",
+            "ol",
+            "li",
+            "blockquote",
+        ]))
+        .add_tag_attributes("a", ["href"])
+        .add_tag_attributes("ol", ["start"])
+        .add_tag_attributes("pre", ["class"])
+        .add_tag_attributes("span", ["class"])
+        .clean_content_tags(HashSet::from(["script", "style"]))
+        .url_schemes(HashSet::from(URL_SCHEMES))
+        .url_relative(ammonia::UrlRelative::Deny)
+        .link_rel(Some("noopener noreferrer nofollow"))
+        .attribute_filter(|element, attribute, value| match (element, attribute) {
+            ("pre" | "span", "class") => {
+                let classes: Vec<&str> = value
+                    .split_whitespace()
+                    .filter(|class| class.starts_with("hl-"))
+                    .collect();
+                Some(Cow::Owned(classes.join(" ")))
+            }
+            _ => Some(Cow::Borrowed(value)),
+        });
+    builder
+});
+
+/// Renders message text as sanitized HTML.
+pub fn render(text: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+
+    let mut events = Vec::new();
+    let mut code: Option<(String, String)> = None;
+    let mut link_depth = 0;
+    // Merged, so URLs are not split where emphasis could have started
+    for event in TextMergeStream::new(Parser::new_ext(text, options)) {
+        if let Some((language, body)) = &mut code {
+            match event {
+                Event::Text(text) => body.push_str(&text),
+                Event::End(TagEnd::CodeBlock) => {
+                    events.push(Event::Html(code_block(language, body).into()));
+                    code = None;
+                }
+                _ => {}
+            }
+            continue;
+        }
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().unwrap_or("").to_string()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code = Some((language, String::new()));
+            }
+            // Shown as typed rather than interpreted
+            Event::Html(html) | Event::InlineHtml(html) => events.push(Event::Text(html)),
+            Event::SoftBreak => events.push(Event::HardBreak),
+            // A message has no document structure; headings are just bold
+            Event::Start(Tag::Heading { .. }) => {
+                events.push(Event::Start(Tag::Paragraph));
+                events.push(Event::Start(Tag::Strong));
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                events.push(Event::End(TagEnd::Strong));
+                events.push(Event::End(TagEnd::Paragraph));
+            }
+            // Remote images would tell their host who read the message
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => {
+                link_depth += 1;
+                events.push(Event::Start(Tag::Link {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                }));
+            }
+            Event::End(TagEnd::Image) => {
+                link_depth -= 1;
+                events.push(Event::End(TagEnd::Link));
+            }
+            Event::Start(Tag::Link { .. }) => {
+                link_depth += 1;
+                events.push(event);
+            }
+            Event::End(TagEnd::Link) => {
+                link_depth -= 1;
+                events.push(event);
+            }
+            Event::Text(text) if link_depth == 0 => linkify(text, &mut events),
+            event => events.push(event),
+        }
+    }
+
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events.into_iter());
+    SANITIZER.clean(&html).to_string()
+}
+
+/// Stylesheet for highlighted code blocks, for the light or dark theme.
+pub fn highlight_css(dark: bool) -> Result<String> {
+    let name = if dark { DARK_THEME } else { LIGHT_THEME };
+    let theme = THEMES
+        .themes
+        .get(name)
+        .ok_or_else(|| Error::Markdown(format!("missing theme {name}")))?;
+    css_for_theme_with_class_style(theme, CLASS_STYLE).map_err(|e| Error::Markdown(e.to_string()))
+}
+
+/// A `<pre>` block, highlighted if the language is known.
+fn code_block(language: &str, body: &str) -> String {
+    let syntax = (!language.is_empty() && body.len() <= MAX_HIGHLIGHTED_LEN)
+        .then(|| SYNTAXES.find_syntax_by_token(language))
+        .flatten();
+    let highlighted = syntax.and_then(|syntax| {
+        let mut generator =
+            ClassedHTMLGenerator::new_with_class_style(syntax, &SYNTAXES, CLASS_STYLE);
+        for line in LinesWithEndings::from(body) {
+            generator
+                .parse_html_for_line_which_includes_newline(line)
+                .ok()?;
+        }
+        Some(generator.finalize())
+    });
+    let code = highlighted.unwrap_or_else(|| {
+        let mut escaped = String::new();
+        pulldown_cmark::html::push_html(&mut escaped, [Event::Text(body.into())].into_iter());
+        escaped
+    });
+    format!("<pre class=\"hl-code\"><code>{code}</code></pre>\n")
+}
+
+/// Turns bare http(s) URLs in `text` into links.
+fn linkify<'a>(text: CowStr<'a>, events: &mut Vec<Event<'a>>) {
+    let mut rest: &str = &text;
+    let mut found = false;
+    while let Some(start) = [rest.find("http://"), rest.find("https://")]
+        .into_iter()
+        .flatten()
+        .min()
+    {
+        let len = rest[start..]
+            .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"'))
+            .unwrap_or(rest.len() - start);
+        let url = trim_url(&rest[start..start + len]);
+        if url.len() <= "https://".len() {
+            break;
+        }
+        found = true;
+        if start > 0 {
+            events.push(Event::Text(rest[..start].to_string().into()));
+        }
+        let dest_url: CowStr = url.to_string().into();
+        events.push(Event::Start(Tag::Link {
+            link_type: LinkType::Autolink,
+            dest_url: dest_url.clone(),
+            title: "".into(),
+            id: "".into(),
+        }));
+        events.push(Event::Text(dest_url));
+        events.push(Event::End(TagEnd::Link));
+        rest = &rest[start + url.len()..];
+    }
+    if !found {
+        events.push(Event::Text(text));
+    } else if !rest.is_empty() {
+        events.push(Event::Text(rest.to_string().into()));
+    }
+}
+
+/// Drops punctuation that more likely ends the sentence than the URL, e.g.
+/// in "see https://example.com/a_(b)."
+fn trim_url(url: &str) -> &str {
+    let mut url = url;
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ':', ';', '!', '?', '\'', '*', '_']);
+        let trimmed = if trimmed.ends_with(')')
+            && trimmed.matches('(').count() < trimmed.matches(')').count()
+        {
+            &trimmed[..trimmed.len() - 1]
+        } else {
+            trimmed
+        };
+        if trimmed.len() == url.len() {
+            return url;
+        }
+        url = trimmed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REL: &str = "rel=\"noopener noreferrer nofollow\"";
+
+    #[test]
+    fn drops_links_to_scripts_and_data() {
+        assert_eq!(
+            render("[x](javascript:alert(1)) [y](data:text/html,hi) [z](https://example.com)"),
+            format!(
+                "<p><a {REL}>x</a> <a {REL}>y</a> <a href=\"https://example.com\" {REL}>z</a></p>\n"
+            )
+        );
+        assert_eq!(
+            render("[mail](mailto:ada@example.com) [file](/etc/passwd)"),
+            format!("<p><a href=\"mailto:ada@example.com\" {REL}>mail</a> <a {REL}>file</a></p>\n")
+        );
+    }
+
+    #[test]
+    fn escapes_raw_html() {
+        assert_eq!(
+            render("<script>alert(1)</script> <b>bold</b>"),
+            "&lt;script&gt;alert(1)&lt;/script&gt; &lt;b&gt;bold&lt;/b&gt;"
+        );
+        assert_eq!(
+            render("a <img src=x onerror=alert(1)> b"),
+            "<p>a &lt;img src=x onerror=alert(1)&gt; b</p>\n"
+        );
+    }
+
+    #[test]
+    fn keeps_only_highlighter_classes() {
+        assert_eq!(
+            SANITIZER
+                .clean("<pre class=\"evil hl-code\"><span class=\"hl-x big\" style=\"color:red\">x</span></pre>")
+                .to_string(),
+            "<pre class=\"hl-code\"><span class=\"hl-x\">x</span></pre>"
+        );
+        let html = render("```rust\nfn main() {}\n```");
+        assert!(html.starts_with("<pre class=\"hl-code\"><code><span class=\"hl-"));
+        for class in html.split("class=\"").skip(1) {
+            let class = &class[..class.find('"').unwrap()];
+            assert!(class.split(' ').all(|c| c.starts_with("hl-")), "{class}");
+        }
+    }
+
+    #[test]
+    fn unknown_languages_are_escaped_without_highlighting() {
+        assert_eq!(
+            render("```nope\n<b>x</b>\n```"),
+            "<pre class=\"hl-code\"><code>&lt;b&gt;x&lt;/b&gt;\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn images_become_links() {
+        assert_eq!(
+            render("![cat](https://img.example.com/cat.png)"),
+            format!("<p><a href=\"https://img.example.com/cat.png\" {REL}>cat</a></p>\n")
+        );
+    }
+
+    #[test]
+    fn keeps_line_breaks_and_flattens_headings() {
+        assert_eq!(render("one\ntwo"), "<p>one<br>\ntwo</p>\n");
+        assert_eq!(render("# Title"), "<p><strong>Title</strong></p>\n");
+    }
+
+    #[test]
+    fn links_bare_urls() {
+        assert_eq!(
+            render("see https://example.com/a_(b). and http://example.org/path, ok"),
+            format!(
+                "<p>see <a href=\"https://example.com/a_(b)\" {REL}>https://example.com/a_(b)</a>. \
+                 and <a href=\"http://example.org/path\" {REL}>http://example.org/path</a>, ok</p>\n"
+            )
+        );
+        assert_eq!(render("https://"), "<p>https://</p>\n");
+        // URLs inside links are left alone
+        assert_eq!(
+            render("[https://a.example](https://b.example)"),
+            format!("<p><a href=\"https://b.example\" {REL}>https://a.example</a></p>\n")
+        );
+    }
+
+    #[test]
+    fn trim_url_drops_trailing_punctuation() {
+        assert_eq!(trim_url("https://example.com."), "https://example.com");
+        assert_eq!(
+            trim_url("https://example.com/?q=1!)."),
+            "https://example.com/?q=1"
+        );
+        assert_eq!(
+            trim_url("https://en.wikipedia.org/wiki/Rust_(language)"),
+            "https://en.wikipedia.org/wiki/Rust_(language)"
+        );
+        assert_eq!(
+            trim_url("https://example.com/a_(b))_"),
+            "https://example.com/a_(b)"
+        );
+    }
+
+    #[test]
+    fn highlight_css_uses_the_prefix() {
+        for dark in [false, true] {
+            assert!(highlight_css(dark).unwrap().contains(".hl-"));
+        }
+    }
+}
@@ -0,0 +1,587 @@
+// Content-addressed media cache
+//
+// Downloaded media is kept on disk under its SHA-256, at
+// `<cache>/media/<first two hex digits>/<sha256>`, so a file is fetched once
+// no matter how often (or in how many chats) it is shown. Files are hashed
+// when they are added, whenever they are handed out by path, and before they
+// are first served to the webview in each run; a file that no longer matches
+// its name is deleted and treated as missing. The cache is capped in size and
+// evicts the least recently used files first, tracked through file
+// modification times so the order survives restarts.
+//
+// The webview loads cached files through the `chitchat-media` URI scheme,
+// e.g. `chitchat-media://localhost/<sha256>`. `Range` requests are answered
+// with just the part asked for, so audio and video can seek without loading
+// the whole file. Responses are limited to content types recognised from the
+// file itself, so a cached file can never be served as HTML or script.
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use sha2::{Digest, Sha256};
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager};
+
+use crate::error::{Error, Result};
+use crate::hex;
+use crate::models::MediaCacheUsage;
+
+/// URI scheme the webview loads cached media from.
+pub const SCHEME: &str = "chitchat-media";
+
+/// Size cap when `CHITCHAT_MEDIA_CACHE_MB` is not set.
+pub const DEFAULT_MAX_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Overrides the size cap, in MiB.
+const MAX_SIZE_ENV: &str = "CHITCHAT_MEDIA_CACHE_MB";
+
+/// Most bytes sent for one `Range` request; players ask for more as they go.
+const MAX_RANGE_BYTES: u64 = 1024 * 1024;
+
+/// Where downloads collect their data before they are added.
+const PARTIAL_DIR: &str = "partial";
+
+pub struct MediaCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    index: Mutex<Index>,
+}
+
+#[derive(Default)]
+struct Index {
+    entries: HashMap<String, Entry>,
+    total: u64,
+}
+
+struct Entry {
+    size: u64,
+    last_used: SystemTime,
+    /// Whether the file was hashed, or added, during this run.
+    verified: bool,
+}
+
+impl MediaCache {
+    /// Opens the cache in `dir`, picking up files from earlier runs. The size
+    /// cap comes from `CHITCHAT_MEDIA_CACHE_MB`, or [`DEFAULT_MAX_BYTES`].
+    pub fn open(dir: &Path) -> Result<Self> {
+        let max_bytes = std::env::var(MAX_SIZE_ENV)
+            .ok()
+            .and_then(|mb| mb.parse::<u64>().ok())
+            .map_or(DEFAULT_MAX_BYTES, |mb| mb * 1024 * 1024);
+        Self::open_with_cap(dir, max_bytes)
+    }
+
+    fn open_with_cap(dir: &Path, max_bytes: u64) -> Result<Self> {
+        fs::create_dir_all(dir.join(PARTIAL_DIR))?;
+        let cache = MediaCache {
+            dir: dir.to_path_buf(),
+            max_bytes,
+            index: Mutex::default(),
+        };
+        {
+            let mut index = cache.lock();
+            for shard in fs::read_dir(dir)?.flatten() {
+                if shard.file_name() == PARTIAL_DIR || !shard.path().is_dir() {
+                    continue;
+                }
+                for file in fs::read_dir(shard.path())?.flatten() {
+                    let Some(sha256) = file.file_name().to_str().map(String::from) else {
+                        continue;
+                    };
+                    let Ok(meta) = file.metadata() else {
+                        continue;
+                    };
+                    if !is_sha256(&sha256) || !meta.is_file() {
+                        continue;
+                    }
+                    index.total += meta.len();
+                    index.entries.insert(
+                        sha256,
+                        Entry {
+                            size: meta.len(),
+                            last_used: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                            verified: false,
+                        },
+                    );
+                }
+            }
+        }
+        cache.evict(None);
+        Ok(cache)
+    }
+
+    /// Where a download of `sha256` should collect its data before
+    /// [`insert_file`](Self::insert_file). Kept between runs, so downloads can
+    /// resume.
+    pub fn partial_path(&self, sha256: &str) -> Result<PathBuf> {
+        check_key(sha256)?;
+        Ok(self.dir.join(PARTIAL_DIR).join(sha256))
+    }
+
+    /// Moves a finished file into the cache. Fails with
+    /// [`Error::Integrity`] and deletes the file if its contents do not hash
+    /// to `sha256`.
+    pub fn insert_file(&self, sha256: &str, source: &Path) -> Result<PathBuf> {
+        check_key(sha256)?;
+        if hash_file(source)? != sha256 {
+            let _ = fs::remove_file(source);
+            return Err(Error::Integrity(sha256.into()));
+        }
+        let target = self.path(sha256);
+        if let Some(shard) = target.parent() {
+            fs::create_dir_all(shard)?;
+        }
+        fs::rename(source, &target)?;
+        let size = fs::metadata(&target)?.len();
+        {
+            let mut index = self.lock();
+            let previous = index.entries.insert(
+                sha256.into(),
+                Entry {
+                    size,
+                    last_used: SystemTime::now(),
+                    verified: true,
+                },
+            );
+            index.total = index.total - previous.map_or(0, |entry| entry.size) + size;
+        }
+        self.evict(Some(sha256));
+        Ok(target)
+    }
+
+    /// The cached file for `sha256`, after checking its contents. Counts as a
+    /// use for eviction.
+    pub fn get(&self, sha256: &str) -> Result<Option<PathBuf>> {
+        check_key(sha256)?;
+        if !self.lock().entries.contains_key(sha256) {
+            return Ok(None);
+        }
+        let path = self.path(sha256);
+        if !self.verify(sha256, &path)? {
+            return Ok(None);
+        }
+        self.touch(sha256, &path);
+        Ok(Some(path))
+    }
+
+    /// The cached file for `sha256`, opened for reading. Its contents are
+    /// checked the first time it is read in a run only, as the webview reads
+    /// media piece by piece. Counts as a use for eviction.
+    pub fn open_file(&self, sha256: &str) -> Result<Option<File>> {
+        check_key(sha256)?;
+        let verified = self.lock().entries.get(sha256).map(|entry| entry.verified);
+        let Some(verified) = verified else {
+            return Ok(None);
+        };
+        let path = self.path(sha256);
+        if !verified && !self.verify(sha256, &path)? {
+            return Ok(None);
+        }
+        match File::open(&path) {
+            Ok(file) => {
+                self.touch(sha256, &path);
+                Ok(Some(file))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                self.remove(sha256);
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn usage(&self) -> MediaCacheUsage {
+        let index = self.lock();
+        MediaCacheUsage {
+            files: index.entries.len() as u32,
+            bytes: index.total,
+            max_bytes: self.max_bytes,
+        }
+    }
+
+    /// Removes every cached file, including unfinished downloads.
+    pub fn clear(&self) -> Result<()> {
+        let mut index = self.lock();
+        for sha256 in index.entries.keys() {
+            if let Err(e) = fs::remove_file(self.path(sha256)) {
+                if e.kind() != io::ErrorKind::NotFound {
+                    return Err(e.into());
+                }
+            }
+        }
+        *index = Index::default();
+        let partial = self.dir.join(PARTIAL_DIR);
+        fs::remove_dir_all(&partial)?;
+        fs::create_dir_all(&partial)?;
+        Ok(())
+    }
+
+    fn path(&self, sha256: &str) -> PathBuf {
+        self.dir.join(&sha256[..2]).join(sha256)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Index> {
+        self.index.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn touch(&self, sha256: &str, path: &Path) {
+        let now = SystemTime::now();
+        if let Some(entry) = self.lock().entries.get_mut(sha256) {
+            entry.last_used = now;
+        }
+        // Keeps the order across restarts; the index has it for this run
+        if let Err(e) = File::options()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_modified(now))
+        {
+            tracing::warn!("failed to update {sha256}: {e}");
+        }
+    }
+
+    /// Hashes the file of `sha256`, deleting it if it no longer matches its
+    /// name. Returns whether it does.
+    fn verify(&self, sha256: &str, path: &Path) -> Result<bool> {
+        match hash_file(path) {
+            Ok(actual) if actual == sha256 => {
+                if let Some(entry) = self.lock().entries.get_mut(sha256) {
+                    entry.verified = true;
+                }
+                Ok(true)
+            }
+            Ok(_) => {
+                tracing::warn!("{sha256} is corrupt, removing it");
+                self.remove(sha256);
+                Ok(false)
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                self.remove(sha256);
+                Ok(false)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn remove(&self, sha256: &str) {
+        let mut index = self.lock();
+        if let Some(entry) = index.entries.remove(sha256) {
+            index.total -= entry.size;
+        }
+        let _ = fs::remove_file(self.path(sha256));
+    }
+
+    /// Deletes the least recently used files until the cache fits its cap.
+    /// `keep` is never evicted, even if it alone exceeds the cap.
+    fn evict(&self, keep: Option<&str>) {
+        let mut index = self.lock();
+        if index.total <= self.max_bytes {
+            return;
+        }
+        let mut by_age: Vec<(SystemTime, String)> = index
+            .entries
+            .iter()
+            .filter(|(sha256, _)| Some(sha256.as_str()) != keep)
+            .map(|(sha256, entry)| (entry.last_used, sha256.clone()))
+            .collect();
+        by_age.sort();
+        for (_, sha256) in by_age {
+            if index.total <= self.max_bytes {
+                break;
+            }
+            if let Err(e) = fs::remove_file(self.path(&sha256)) {
+                if e.kind() != io::ErrorKind::NotFound {
+                    tracing::warn!("failed to evict {sha256}: {e}");
+                    continue;
+                }
+            }
+            if let Some(entry) = index.entries.remove(&sha256) {
+                index.total -= entry.size;
+            }
+        }
+    }
+}
+
+/// Part of a file asked for by a `Range` header.
+#[derive(Debug, PartialEq, Eq)]
+enum Range {
+    /// No usable header; the whole file is sent.
+    Whole,
+    /// First and last byte, inclusive.
+    Part(u64, u64),
+    Unsatisfiable,
+}
+
+/// Answers a `chitchat-media://localhost/<sha256>` request from the webview.
+pub fn respond(app: &AppHandle, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let sha256 = request.uri().path().trim_start_matches('/');
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok());
+    let result = match app.try_state::<MediaCache>() {
+        Some(cache) if is_sha256(sha256) => cache.open_file(sha256),
+        _ => Ok(None),
+    };
+    let result = result.and_then(|file| file.map(|file| serve(file, range)).transpose());
+    let status = match result {
+        Ok(Some(response)) => return response,
+        Ok(None) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            tracing::warn!("failed to read {sha256}: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+    Response::builder()
+        .status(status)
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .body(Vec::new())
+        .unwrap_or_default()
+}
+
+/// The whole file, or the part of it `range` asks for, capped at
+/// [`MAX_RANGE_BYTES`].
+fn serve(mut file: File, range: Option<&str>) -> Result<Response<Vec<u8>>> {
+    let size = file.metadata()?.len();
+    let mut head = Vec::new();
+    file.by_ref().take(16).read_to_end(&mut head)?;
+    let response = Response::builder()
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .header(header::CONTENT_TYPE, content_type(&head))
+        .header(header::ACCEPT_RANGES, "bytes")
+        // The URL names the contents, so they can never change
+        .header(
+            header::CACHE_CONTROL,
+            "private, max-age=31536000, immutable",
+        );
+
+    let (response, start, len) = match parse_range(range, size) {
+        Range::Whole => (response.status(StatusCode::OK), 0, size),
+        Range::Part(start, end) => {
+            let end = end.min(start + MAX_RANGE_BYTES - 1);
+            let response = response
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_RANGE, format!("bytes {start}-{end}/{size}"));
+            (response, start, end - start + 1)
+        }
+        Range::Unsatisfiable => {
+            let response = response
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{size}"));
+            return Ok(response.body(Vec::new()).unwrap_or_default());
+        }
+    };
+    file.seek(SeekFrom::Start(start))?;
+    let mut body = Vec::with_capacity(len as usize);
+    file.take(len).read_to_end(&mut body)?;
+    Ok(response.body(body).unwrap_or_default())
+}
+
+/// Reads a single `bytes=` range for a file of `size` bytes. Malformed
+/// headers and multiple ranges are ignored, as HTTP allows.
+fn parse_range(header: Option<&str>, size: u64) -> Range {
+    let Some(spec) = header.and_then(|header| header.trim().strip_prefix("bytes=")) else {
+        return Range::Whole;
+    };
+    if spec.contains(',') {
+        return Range::Whole;
+    }
+    let Some((first, last)) = spec.split_once('-') else {
+        return Range::Whole;
+    };
+    let (first, last) = (first.trim(), last.trim());
+    if first.is_empty() {
+        // The last `n` bytes
+        return match last.parse::<u64>() {
+            Ok(0) => Range::Unsatisfiable,
+            Ok(_) if size == 0 => Range::Unsatisfiable,
+            Ok(n) => Range::Part(size.saturating_sub(n), size - 1),
+            Err(_) => Range::Whole,
+        };
+    }
+    let Ok(start) = first.parse::<u64>() else {
+        return Range::Whole;
+    };
+    let end = if last.is_empty() {
+        u64::MAX
+    } else {
+        match last.parse::<u64>() {
+            Ok(end) if end >= start => end,
+            _ => return Range::Whole,
+        }
+    };
+    if start >= size {
+        Range::Unsatisfiable
+    } else {
+        Range::Part(start, end.min(size - 1))
+    }
+}
+
+/// Content type of a cached file, recognised from its first bytes. Anything
+/// else is served as opaque data.
+fn content_type(bytes: &[u8]) -> &'static str {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => "image/png",
+        [0xff, 0xd8, 0xff, ..] => "image/jpeg",
+        [b'G', b'I', b'F', b'8', ..] => "image/gif",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => "audio/wav",
+        [b'%', b'P', b'D', b'F', ..] => "application/pdf",
+        [b'O', b'g', b'g', b'S', ..] => "audio/ogg",
+        [b'I', b'D', b'3', ..] => "audio/mpeg",
+        [0x1a, 0x45, 0xdf, 0xa3, ..] => "video/webm",
+        [_, _, _, _, b'f', b't', b'y', b'p', b'M', b'4', b'A', ..] => "audio/mp4",
+        [_, _, _, _, b'f', b't', b'y', b'p', b'q', b't', ..] => "video/quicktime",
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => "video/mp4",
+        _ => "application/octet-stream",
+    }
+}
+
+fn check_key(sha256: &str) -> Result<()> {
+    if is_sha256(sha256) {
+        Ok(())
+    } else {
+        Err(Error::InvalidMediaKey(sha256.into()))
+    }
+}
+
+/// Whether `s` is a hex SHA-256 as used for cache keys.
+pub fn is_sha256(s: &str) -> bool {
+    s.len() == 64 && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Hex SHA-256 of a file's contents.
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(&hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    /// Adds `contents` to the cache the way downloads do, returning its key.
+    fn add(cache: &MediaCache, contents: &[u8]) -> String {
+        let sha256 = hex::encode(&Sha256::digest(contents));
+        let partial = cache.partial_path(&sha256).unwrap();
+        fs::write(&partial, contents).unwrap();
+        cache.insert_file(&sha256, &partial).unwrap();
+        sha256
+    }
+
+    #[test]
+    fn least_recently_used_files_are_evicted() {
+        let dir = TempDir::new();
+        let cache = MediaCache::open_with_cap(&dir, 10).unwrap();
+        let first = add(&cache, b"aaaa");
+        let second = add(&cache, b"bbbb");
+        assert!(cache.get(&first).unwrap().is_some());
+
+        // Crossing the cap evicts `second`, used longer ago than `first`
+        let third = add(&cache, b"cccc");
+        assert!(cache.get(&second).unwrap().is_none());
+        assert!(!cache.path(&second).exists());
+        assert!(cache.get(&first).unwrap().is_some());
+        assert!(cache.get(&third).unwrap().is_some());
+        assert_eq!((cache.usage().files, cache.usage().bytes), (2, 8));
+    }
+
+    #[test]
+    fn inserts_that_do_not_match_their_hash_are_rejected() {
+        let dir = TempDir::new();
+        let cache = MediaCache::open_with_cap(&dir, DEFAULT_MAX_BYTES).unwrap();
+        let sha256 = "00".repeat(32);
+        let partial = cache.partial_path(&sha256).unwrap();
+        fs::write(&partial, b"not what was promised").unwrap();
+
+        let result = cache.insert_file(&sha256, &partial);
+        assert!(matches!(result, Err(Error::Integrity(_))));
+        assert!(!partial.exists());
+        assert!(!cache.path(&sha256).exists());
+        assert_eq!(cache.usage().files, 0);
+    }
+
+    #[test]
+    fn corrupted_files_are_removed_on_read() {
+        let dir = TempDir::new();
+        let sha256 = add(
+            &MediaCache::open_with_cap(&dir, DEFAULT_MAX_BYTES).unwrap(),
+            b"original",
+        );
+        let path = dir.join(&sha256[..2]).join(&sha256);
+        fs::write(&path, b"tampered").unwrap();
+
+        // A new run has not checked the file yet
+        let cache = MediaCache::open_with_cap(&dir, DEFAULT_MAX_BYTES).unwrap();
+        assert_eq!(cache.usage().files, 1);
+        assert!(cache.open_file(&sha256).unwrap().is_none());
+        assert!(!path.exists());
+        assert_eq!(cache.usage().files, 0);
+    }
+
+    #[test]
+    fn ranges_are_read_and_clamped() {
+        assert_eq!(parse_range(None, 100), Range::Whole);
+        assert_eq!(parse_range(Some("bytes=0-"), 100), Range::Part(0, 99));
+        assert_eq!(parse_range(Some("bytes=10-19"), 100), Range::Part(10, 19));
+        assert_eq!(parse_range(Some("bytes=90-200"), 100), Range::Part(90, 99));
+        assert_eq!(parse_range(Some("bytes=-10"), 100), Range::Part(90, 99));
+        assert_eq!(parse_range(Some("bytes=-500"), 100), Range::Part(0, 99));
+    }
+
+    #[test]
+    fn ranges_past_the_end_are_unsatisfiable() {
+        assert_eq!(parse_range(Some("bytes=100-"), 100), Range::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=-0"), 100), Range::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=-5"), 0), Range::Unsatisfiable);
+    }
+
+    #[test]
+    fn unusable_ranges_get_the_whole_file() {
+        for header in [
+            "items=0-1",
+            "bytes=5-1",
+            "bytes=a-b",
+            "bytes=0-1,5-6",
+            "bytes=5",
+        ] {
+            assert_eq!(parse_range(Some(header), 100), Range::Whole, "{header}");
+        }
+    }
+
+    #[test]
+    fn parts_of_a_file_are_served() {
+        let dir = TempDir::new();
+        let path = dir.join("clip");
+        let contents: Vec<u8> = (0..=255u8)
+            .cycle()
+            .take(3 * MAX_RANGE_BYTES as usize)
+            .collect();
+        fs::write(&path, &contents).unwrap();
+
+        let response = serve(File::open(&path).unwrap(), Some("bytes=4-7")).unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers()[header::CONTENT_RANGE],
+            format!("bytes 4-7/{}", contents.len())
+        );
+        assert_eq!(response.body(), &contents[4..8]);
+
+        // Open-ended ranges are sent a piece at a time
+        let response = serve(File::open(&path).unwrap(), Some("bytes=1-")).unwrap();
+        assert_eq!(response.body().len() as u64, MAX_RANGE_BYTES);
+
+        let response = serve(File::open(&path).unwrap(), None).unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ACCEPT_RANGES], "bytes");
+        assert_eq!(response.body(), &contents);
+
+        let response = serve(File::open(&path).unwrap(), Some("bytes=-0")).unwrap();
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert!(response.body().is_empty());
+    }
+}
@@ -0,0 +1,700 @@
+// Data models shared with the frontend
+//
+// Every type crossing the IPC boundary lives here. `build.rs` includes this
+// file and exports each of them to `src/bindings/` as TypeScript, which
+// `src/types/index.ts` builds on, so the two sides cannot drift apart. Keep it
+// free of dependencies beyond serde and ts-rs.
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// Milliseconds since the Unix epoch, as written by RTDB `serverTimestamp()`.
+pub type Timestamp = i64;
+
+/// The current time as a [`Timestamp`].
+pub fn now() -> Timestamp {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as Timestamp)
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(optional_fields)]
+pub struct User {
+    pub id: String,
+    pub email: String,
+    pub display_name: String,
+    #[serde(default)]
+    #[ts(type = "Date | number")]
+    pub created_at: Timestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_online: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(type = "Date | number")]
+    pub last_seen: Option<Timestamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<UserStatus>,
+}
+
+/// Availability a user shows to their contacts, chosen from the tray menu.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "lowercase")]
+pub enum UserStatus {
+    #[default]
+    Available,
+    Away,
+    Busy,
+}
+
+impl UserStatus {
+    pub const ALL: [UserStatus; 3] = [UserStatus::Available, UserStatus::Away, UserStatus::Busy];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UserStatus::Available => "available",
+            UserStatus::Away => "away",
+            UserStatus::Busy => "busy",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(optional_fields)]
+pub struct Chat {
+    pub id: String,
+    #[serde(default)]
+    #[ts(type = "Record<string, boolean>")]
+    pub participants: BTreeMap<String, bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(type = "Record<string, string>")]
+    pub participant_names: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_group: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner_id: Option<String>,
+    #[serde(default)]
+    pub last_message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_message_sender_id: Option<String>,
+    #[serde(default)]
+    #[ts(type = "Date | number")]
+    pub updated_at: Timestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(type = "Record<string, boolean>")]
+    pub typing: Option<BTreeMap<String, bool>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(optional_fields)]
+pub struct Message {
+    pub id: String,
+    pub sender_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender_name: Option<String>,
+    pub text: String,
+    #[serde(default)]
+    #[ts(type = "Date | number")]
+    pub timestamp: Timestamp,
+    /// Set on history imported from another messenger. Archived messages
+    /// only exist in the local store and are never edited or sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<Attachment>,
+}
+
+/// A file sent with a message, as carried inside the encrypted message. The
+/// file itself is kept, encrypted, by the attachment backend under
+/// `storage_path`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub name: String,
+    pub mime_type: String,
+    /// Size in bytes.
+    #[ts(type = "number")]
+    pub size: u64,
+    /// Hex SHA-256 of the contents, checked after every download.
+    pub sha256: String,
+    pub storage_path: String,
+    /// Base64 key the stored file is encrypted with.
+    pub key: String,
+    /// Set for images, so chats can show a preview without downloading them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub image: Option<ImageInfo>,
+}
+
+/// Dimensions and inline preview of an image attachment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageInfo {
+    pub width: u32,
+    pub height: u32,
+    /// Small JPEG or PNG as a `data:` URL.
+    pub thumbnail: String,
+}
+
+/// Limits for images prepared for sending. Omitted fields keep their
+/// defaults.
+#[derive(Debug, Clone, Copy, Deserialize, TS)]
+#[serde(rename_all = "camelCase", default)]
+#[ts(optional_fields)]
+pub struct ImageOptions {
+    /// Longest side of the sent image, in pixels.
+    pub max_dimension: u32,
+    /// Size the encoded image should fit in; quality and then dimensions are
+    /// reduced until it does.
+    #[ts(type = "number")]
+    pub max_bytes: u64,
+    /// JPEG quality to start from, 1-100.
+    pub quality: u8,
+    /// Longest side of the thumbnail, in pixels.
+    pub thumbnail_size: u32,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        ImageOptions {
+            max_dimension: 2560,
+            max_bytes: 2 * 1024 * 1024,
+            quality: 85,
+            thumbnail_size: 320,
+        }
+    }
+}
+
+/// A file the user picked or dropped. The webview refers to it by a one-time
+/// token, never by its path.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ChosenFile {
+    pub token: String,
+    /// File name, for display.
+    pub name: String,
+}
+
+/// An image re-encoded for sending, without its metadata.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct PreparedImage {
+    /// The processed copy, ready for `upload_attachment`.
+    pub file: ChosenFile,
+    pub image: ImageInfo,
+}
+
+/// How much of the media cache is in use.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaCacheUsage {
+    pub files: u32,
+    #[ts(type = "number")]
+    pub bytes: u64,
+    /// Size cap; least recently used files are evicted beyond it.
+    #[ts(type = "number")]
+    pub max_bytes: u64,
+}
+
+/// Which way an attachment is being transferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum TransferDirection {
+    Upload,
+    Download,
+}
+
+/// Payload of the `attachment-progress` event, emitted after every chunk.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentProgress {
+    pub sha256: String,
+    pub direction: TransferDirection,
+    #[ts(type = "number")]
+    pub transferred: u64,
+    #[ts(type = "number")]
+    pub total: u64,
+}
+
+/// A message as stored in RTDB, before decryption. Encrypted messages carry
+/// an `e2e` envelope instead of `text`.
+#[derive(Debug, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(optional_fields)]
+pub struct IncomingMessage {
+    pub id: String,
+    pub sender_id: String,
+    #[serde(default)]
+    pub sender_name: Option<String>,
+    /// Plaintext of messages sent before encryption was introduced.
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    #[ts(type = "unknown", optional)]
+    pub e2e: Option<serde_json::Value>,
+    #[serde(default)]
+    #[ts(type = "number")]
+    pub timestamp: Timestamp,
+}
+
+/// Delivery state of an outgoing message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryState {
+    Queued,
+    Sent,
+    Failed,
+    /// Dropped by the user without being sent. Only ever reported in events;
+    /// the entry is gone from the outbox.
+    Discarded,
+}
+
+impl DeliveryState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeliveryState::Queued => "queued",
+            DeliveryState::Sent => "sent",
+            DeliveryState::Failed => "failed",
+            DeliveryState::Discarded => "discarded",
+        }
+    }
+}
+
+/// A message waiting in (or failed out of) the outbox.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(optional_fields)]
+pub struct OutboxEntry {
+    pub id: String,
+    pub chat_id: String,
+    pub sender_id: String,
+    pub sender_name: String,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<Attachment>,
+    #[ts(type = "number")]
+    pub created_at: Timestamp,
+    pub attempts: u32,
+    pub state: DeliveryState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// What a live subscription streams to the webview.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(tag = "kind", content = "value", rename_all = "camelCase")]
+pub enum LiveUpdate<T> {
+    /// The current value, sent first and after every change.
+    Value(T),
+    /// The server ended the subscription, e.g. because the user may no
+    /// longer read the data. Nothing follows.
+    Cancelled,
+}
+
+/// Payload of the `outbox-state` event.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(optional_fields)]
+pub struct OutboxStateEvent {
+    pub id: String,
+    pub chat_id: String,
+    pub state: DeliveryState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Members a sent message was not encrypted for, as they have not set
+    /// up encryption.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub without_keys: Option<Vec<String>>,
+}
+
+/// A message found by `search_messages`.
+#[derive(Debug, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(optional_fields)]
+pub struct SearchHit {
+    pub chat_id: String,
+    pub message_id: String,
+    pub sender_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sender_name: Option<String>,
+    /// Group name, for hits in group chats.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat_name: Option<String>,
+    #[ts(type = "number")]
+    pub timestamp: Timestamp,
+    /// The matching part of the message, split into plain and highlighted
+    /// runs so the UI never has to render HTML from message text.
+    pub snippet: Vec<SnippetPart>,
+}
+
+/// A run of snippet text; `highlight` marks the parts matching the query.
+#[derive(Debug, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct SnippetPart {
+    pub text: String,
+    pub highlight: bool,
+}
+
+/// Chats whose last message came from someone else after the user last read
+/// them, most recent first. Payload of the `unread-changed` event.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct UnreadSummary {
+    pub chat_ids: Vec<String>,
+}
+
+/// File formats chats can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    /// Machine-readable; each chat as a [`Chat`] with its [`Message`]s.
+    Json,
+    /// A single self-contained, styled page.
+    Html,
+    Markdown,
+    /// A plain transcript, easy to follow with a screen reader.
+    Text,
+}
+
+/// Result of a finished export.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    pub path: String,
+    pub chats: u32,
+    pub messages: u32,
+}
+
+/// Messengers whose chat exports can be imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportSource {
+    /// A "Export chat" `.txt` file, or the `.zip` it is shared as.
+    Whatsapp,
+    /// The `result.json` of a Telegram Desktop chat export.
+    Telegram,
+}
+
+/// Someone who wrote messages in an imported export.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(optional_fields)]
+pub struct ImportSender {
+    /// Name as it appears in the export.
+    pub name: String,
+    pub messages: u32,
+    /// The chat participant the sender was mapped to, if any. Messages of
+    /// unmapped senders keep the name from the export.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+}
+
+/// What an import did, or would do on a dry run.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(optional_fields)]
+pub struct ImportReport {
+    pub source: ImportSource,
+    /// Name of the chat in the other messenger, when the export has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub dry_run: bool,
+    /// Messages found in the export.
+    pub messages: u32,
+    /// Of those, messages imported before and left alone.
+    pub already_imported: u32,
+    /// Lines or entries that are not messages, such as "X joined".
+    pub skipped: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(type = "number", optional)]
+    pub first_timestamp: Option<Timestamp>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(type = "number", optional)]
+    pub last_timestamp: Option<Timestamp>,
+    /// Most active first.
+    pub senders: Vec<ImportSender>,
+}
+
+/// Severity of a log entry, least severe first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// An entry of the app log.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    /// RFC 3339, in UTC.
+    pub timestamp: String,
+    pub level: LogLevel,
+    /// The Rust module that logged the entry, or `webview` for entries
+    /// forwarded by the frontend.
+    pub target: String,
+    /// The message followed by its fields, with personal data redacted.
+    pub message: String,
+}
+
+/// Which log entries to return; all given conditions must match.
+#[derive(Debug, Clone, Default, Deserialize, TS)]
+#[serde(rename_all = "camelCase", default)]
+#[ts(optional_fields)]
+pub struct LogFilter {
+    /// Least severe level to include.
+    pub level: Option<LogLevel>,
+    /// Text the target or message must contain, ignoring case.
+    pub contains: Option<String>,
+    /// How many of the most recent matching entries to return.
+    pub limit: Option<u32>,
+}
+
+/// What a connection state change was observed on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum ConnectionSource {
+    /// The webview's Realtime Database connection (`.info/connected`).
+    Database,
+    /// The network, as far as the OS reports it to the webview.
+    Network,
+}
+
+/// Where a `chitchat://` link points. Payload of the `deep-link` event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, TS)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum DeepLink {
+    /// `chitchat://chat/<id>`
+    Chat { id: String },
+    /// `chitchat://user/<email>`: a direct chat with the user.
+    User { email: String },
+    /// `chitchat://invite/<token>`: an invitation to a group.
+    Invite { token: String },
+}
+
+/// A Firebase project, as given to `initializeApp`.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(optional_fields)]
+pub struct BackendConfig {
+    pub api_key: String,
+    pub auth_domain: String,
+    pub project_id: String,
+    pub storage_bucket: String,
+    #[serde(rename = "databaseURL")]
+    pub database_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub messaging_sender_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>,
+}
+
+/// The profile the webview runs in, set as `window.__CHITCHAT_PROFILE__`.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(optional_fields)]
+pub struct ProfileInfo {
+    pub name: String,
+    /// The profile's own Firebase project; the build's project is used
+    /// without one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<BackendConfig>,
+}
+
+/// User preferences, stored in `settings.json` in the config directory.
+/// Fields missing from the file take their defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub theme: Theme,
+    pub sounds: SoundSettings,
+    pub notifications: NotificationSettings,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum Theme {
+    Light,
+    Dark,
+    /// Follow the OS.
+    #[default]
+    System,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SoundSettings {
+    /// Play sounds when messages are sent and received.
+    pub enabled: bool,
+    /// From 0 (silent) to 1.
+    pub volume: f32,
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        SoundSettings {
+            enabled: true,
+            volume: 0.5,
+        }
+    }
+}
+
+/// Which desktop notifications are shown. Mentions and keywords get through
+/// muted chats and quiet hours.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NotificationSettings {
+    /// New messages.
+    pub messages: bool,
+    /// Contacts coming online or going offline.
+    pub presence: bool,
+    pub muted_chats: Vec<ChatMute>,
+    /// When notifications are held back, e.g. nights and weekends.
+    pub quiet_hours: Vec<QuietHours>,
+    /// Notify for messages that mention the user with `@` and their name.
+    pub mentions: bool,
+    /// Notify for messages containing any of these words, ignoring case.
+    pub keywords: Vec<String>,
+    /// When notifications muted from the tray resume.
+    #[ts(type = "number | null")]
+    pub muted_until: Option<Timestamp>,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings {
+            messages: true,
+            presence: true,
+            muted_chats: Vec::new(),
+            quiet_hours: Vec::new(),
+            mentions: true,
+            keywords: Vec::new(),
+            muted_until: None,
+        }
+    }
+}
+
+/// A chat whose messages do not notify.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(optional_fields)]
+pub struct ChatMute {
+    pub chat_id: String,
+    /// When the chat notifies again; muted until unmuted without one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(type = "number")]
+    pub until: Option<Timestamp>,
+}
+
+/// A weekly period without notifications, in local time. One that ends
+/// before it starts runs past midnight into the next day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietHours {
+    /// The days it starts on.
+    pub days: Vec<Weekday>,
+    /// Minutes after midnight.
+    pub start: u16,
+    /// Minutes after midnight; the same as `start` for the whole day.
+    pub end: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    pub const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+}
+
+/// A new message to notify about, if the rules allow.
+#[derive(Debug, Clone)]
+pub struct MessageNotification {
+    pub chat_id: String,
+    pub sender_name: String,
+    /// The decrypted text.
+    pub text: String,
+    pub sent_at: Timestamp,
+}
+
+/// A contact coming online or going offline.
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct PresenceNotification {
+    pub user_id: String,
+    pub user_name: String,
+    pub online: bool,
+}
+
+/// What became of a notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum NotificationOutcome {
+    Shown,
+    /// Added to the chat's notification, which was shown moments ago.
+    Coalesced,
+    /// Turned off in the settings.
+    Disabled,
+    /// All notifications are muted from the tray.
+    Muted,
+    ChatMuted,
+    QuietHours,
+    /// The chat is open in a focused window.
+    Focused,
+    /// Too many about the same contact, or about contacts at all, lately.
+    RateLimited,
+}
+
+/// What every failed command rejects with.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandError {
+    pub kind: ErrorKind,
+    /// Human-readable description, not meant for matching on.
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorKind {
+    /// The local store could not be read or written.
+    Storage,
+    /// The OS keyring refused access to the store key.
+    Keyring,
+    /// The backend could not be reached or rejected the request.
+    Network,
+    /// An argument was malformed (e.g. not a valid database key).
+    InvalidArgument,
+    /// The referenced outbox entry or chat does not exist.
+    NotFound,
+    /// A message could not be encrypted or decrypted.
+    Encryption,
+    /// Downloaded data did not match its checksum.
+    Integrity,
+    Internal,
+}
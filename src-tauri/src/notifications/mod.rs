@@ -0,0 +1,402 @@
+// Notifications
+//
+// Every desktop notification goes through here. New messages come from the
+// session's own subscription to the user's chats, decrypted (see
+// [`crate::subscriptions`]); the webview reports contacts coming online or
+// going offline. [`rules`] decide from the settings, the tray mute and the
+// chats open in each window whether they are shown, and those that are go to
+// [`toast`]. The outcome of a message is sent to the main window, which plays
+// a sound only for messages that popped up a notification or that the user
+// is looking at.
+//
+// A chat's messages share one notification until the chat is opened or read
+// (see [`shown`]). It pops up for the first message, and again for a later
+// one only if no message came in for a while before it; in between it is
+// updated in place where the platform allows, and left as it is elsewhere
+// (see [`toast`]).
+pub mod rules;
+pub mod shown;
+mod toast;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde_json::json;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::{Error, Result};
+use crate::models::{
+    now, Chat, ChatMute, Message, MessageNotification, NotificationOutcome, PresenceNotification,
+    Settings, Timestamp,
+};
+use crate::push_id;
+use crate::session::Session;
+use crate::settings::{self, SettingsStore};
+use crate::store::Store;
+use crate::tray;
+use crate::windows;
+
+use self::rules::{Context, PresenceHistory};
+use self::shown::{ChatNotification, Shown};
+
+/// Event emitted to the main window with the [`NotificationOutcome`] of each
+/// new message.
+pub const NOTIFIED_EVENT: &str = "message-notified";
+
+/// Message previews are cut off after this many characters.
+const MAX_BODY_LEN: usize = 100;
+
+/// A chat's notification pops up again for a new message that came in this
+/// long after the one before.
+const REPEAT_AFTER_MS: i64 = 60 * 1000;
+
+/// Which chat each window shows, the presence notifications shown lately and
+/// the names the user goes by, managed as state.
+#[derive(Default)]
+pub struct Notifier {
+    open_chats: Mutex<HashMap<String, String>>,
+    presence: Mutex<PresenceHistory>,
+    own_names: Mutex<OwnNames>,
+}
+
+/// The names a user goes by in each chat, worked out once per session.
+#[derive(Default)]
+struct OwnNames {
+    user_id: String,
+    chats: HashMap<String, Vec<String>>,
+}
+
+impl Notifier {
+    /// Records that the window `label` shows `chat_id`, or no chat.
+    pub fn set_open_chat(&self, label: &str, chat_id: Option<String>) {
+        let mut open_chats = self.open_chats.lock().unwrap_or_else(|e| e.into_inner());
+        match chat_id {
+            Some(chat_id) => open_chats.insert(label.to_string(), chat_id),
+            None => open_chats.remove(label),
+        };
+    }
+}
+
+/// The messages of `messages` to notify `user_id` about: those others sent
+/// after `after`.
+pub fn unseen<'a>(
+    messages: &'a [Message],
+    user_id: &'a str,
+    after: Timestamp,
+) -> impl Iterator<Item = &'a Message> {
+    messages
+        .iter()
+        .filter(move |message| message.timestamp > after && message.sender_id != user_id)
+}
+
+/// A notification for `message` in `chat_id`, named after the sender as
+/// the message or else `chat` knows them.
+pub fn for_message(chat_id: &str, chat: Option<&Chat>, message: &Message) -> MessageNotification {
+    let sender_name = message
+        .sender_name
+        .clone()
+        .or_else(|| {
+            chat.and_then(|chat| chat.participant_names.as_ref())
+                .and_then(|names| names.get(&message.sender_id).cloned())
+        })
+        .unwrap_or_else(|| "Someone".to_string());
+    MessageNotification {
+        chat_id: chat_id.to_string(),
+        sender_name,
+        text: message.text.clone(),
+        sent_at: message.timestamp,
+    }
+}
+
+/// Notifies about new messages in the background, in order, and tells the
+/// main window what became of each.
+pub fn spawn_messages(app: AppHandle, notifications: Vec<MessageNotification>) {
+    if notifications.is_empty() {
+        return;
+    }
+    // Talks to the notification server; keep it off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        for notification in notifications {
+            match message(&app, &notification) {
+                Ok(outcome) => {
+                    if let Err(e) = app.emit_to(windows::MAIN, NOTIFIED_EVENT, outcome) {
+                        tracing::warn!("failed to emit notification outcome: {e}");
+                    }
+                }
+                Err(e) => tracing::warn!(
+                    chat_id = notification.chat_id,
+                    "failed to notify about a message: {e}"
+                ),
+            }
+        }
+    });
+}
+
+/// Shows a notification for a new message unless the rules hold it back.
+pub fn message(app: &AppHandle, notification: &MessageNotification) -> Result<NotificationOutcome> {
+    let settings = app.state::<SettingsStore>().get().notifications;
+    let names = own_names(app, &notification.chat_id);
+    let context = Context {
+        now: now(),
+        utc_offset_minutes: utc_offset_minutes(),
+        muted: is_muted(app),
+        focused: is_focused(app, &notification.chat_id),
+        names: &names,
+    };
+    let outcome = rules::message(&settings, notification, &context);
+    tracing::debug!(
+        chat_id = notification.chat_id,
+        ?outcome,
+        "message notification"
+    );
+    if outcome != NotificationOutcome::Shown {
+        return Ok(outcome);
+    }
+
+    let body: String = if notification.text.chars().count() > MAX_BODY_LEN {
+        let cut: String = notification.text.chars().take(MAX_BODY_LEN - 3).collect();
+        format!("{cut}...")
+    } else {
+        notification.text.clone()
+    };
+    let shown = app.state::<Shown>();
+    let now = now();
+    let (chat, previous) = shown.add(
+        &notification.chat_id,
+        &notification.sender_name,
+        notification.sent_at,
+        now,
+    );
+    let repeat = now - previous >= REPEAT_AFTER_MS;
+    if !repeat && !toast::CAN_REPLACE {
+        return Ok(NotificationOutcome::Coalesced);
+    }
+    let id = toast::show(app, chat.id, &title(&chat), &body, !repeat)?;
+    shown.set_shown(&notification.chat_id, id)?;
+    Ok(if repeat {
+        NotificationOutcome::Shown
+    } else {
+        NotificationOutcome::Coalesced
+    })
+}
+
+/// Shows a notification for a contact coming online or going offline unless
+/// the rules hold it back.
+pub fn presence(
+    app: &AppHandle,
+    notification: &PresenceNotification,
+    utc_offset_minutes: i32,
+) -> Result<NotificationOutcome> {
+    let settings = app.state::<SettingsStore>().get().notifications;
+    let context = Context {
+        now: now(),
+        utc_offset_minutes,
+        muted: is_muted(app),
+        focused: false,
+        names: &[],
+    };
+    let notifier = app.state::<Notifier>();
+    let mut history = notifier.presence.lock().unwrap_or_else(|e| e.into_inner());
+    let outcome = rules::presence(&settings, notification, &history, &context);
+    tracing::debug!(
+        online = notification.online,
+        ?outcome,
+        "presence notification"
+    );
+    if outcome == NotificationOutcome::Shown {
+        history.record(&notification.user_id, context.now);
+        let (title, state) = if notification.online {
+            ("Contact Online", "online")
+        } else {
+            ("Contact Offline", "offline")
+        };
+        toast::show(
+            app,
+            None,
+            title,
+            &format!("{} is now {state}", notification.user_name),
+            false,
+        )?;
+    }
+    Ok(outcome)
+}
+
+/// Forgets `chat_id`'s notification, unless it holds messages sent after
+/// `up_to`, and closes it where the platform allows (see [`toast`]).
+pub fn dismiss(app: &AppHandle, chat_id: &str, up_to: Option<Timestamp>) {
+    let Some(shown) = app.try_state::<Shown>() else {
+        return;
+    };
+    if let Some(chat) = shown.remove(chat_id, up_to) {
+        tracing::debug!(chat_id, "dismissing notification");
+        close(vec![chat]);
+    }
+}
+
+/// Forgets every chat's notification, e.g. on sign-out, closing them where
+/// the platform allows.
+pub fn dismiss_all(app: &AppHandle) {
+    if let Some(shown) = app.try_state::<Shown>() {
+        close(shown.clear());
+    }
+    if let Some(notifier) = app.try_state::<Notifier>() {
+        *notifier.own_names.lock().unwrap_or_else(|e| e.into_inner()) = OwnNames::default();
+    }
+}
+
+fn close(chats: Vec<ChatNotification>) {
+    let ids: Vec<u32> = chats.into_iter().filter_map(|chat| chat.id).collect();
+    if ids.is_empty() {
+        return;
+    }
+    // Talks to the notification server; keep it off the calling thread
+    tauri::async_runtime::spawn_blocking(move || ids.into_iter().for_each(toast::close));
+}
+
+/// Mutes `chat_id` until `until`, or until it is unmuted.
+pub fn mute_chat(app: &AppHandle, chat_id: &str, until: Option<Timestamp>) -> Result<Settings> {
+    if !push_id::is_valid_key(chat_id) {
+        return Err(Error::InvalidChatId(chat_id.into()));
+    }
+    let mut muted_chats = remaining_mutes(app, chat_id);
+    muted_chats.push(ChatMute {
+        chat_id: chat_id.to_string(),
+        until,
+    });
+    settings::update(
+        app,
+        json!({ "notifications": { "mutedChats": muted_chats } }),
+    )
+}
+
+pub fn unmute_chat(app: &AppHandle, chat_id: &str) -> Result<Settings> {
+    let muted_chats = remaining_mutes(app, chat_id);
+    settings::update(
+        app,
+        json!({ "notifications": { "mutedChats": muted_chats } }),
+    )
+}
+
+/// The chat mutes other than the one for `chat_id`, leaving out expired ones.
+fn remaining_mutes(app: &AppHandle, chat_id: &str) -> Vec<ChatMute> {
+    let now = now();
+    app.state::<SettingsStore>()
+        .get()
+        .notifications
+        .muted_chats
+        .into_iter()
+        .filter(|mute| mute.chat_id != chat_id && mute.until.is_none_or(|until| now < until))
+        .collect()
+}
+
+/// Minutes local time is ahead of UTC, for quiet hours.
+fn utc_offset_minutes() -> i32 {
+    chrono::Local::now().offset().local_minus_utc() / 60
+}
+
+fn is_muted(app: &AppHandle) -> bool {
+    tray::muted_until(app).is_some()
+}
+
+/// Whether `chat_id` is open in a window the user is looking at.
+fn is_focused(app: &AppHandle, chat_id: &str) -> bool {
+    let notifier = app.state::<Notifier>();
+    let mut open_chats = notifier
+        .open_chats
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    // Forget windows that were closed
+    open_chats.retain(|label, _| app.get_webview_window(label).is_some());
+    open_chats.iter().any(|(label, open)| {
+        open == chat_id
+            && app.get_webview_window(label).is_some_and(|window| {
+                window.is_focused().unwrap_or(false)
+                    && window.is_visible().unwrap_or(false)
+                    && !window.is_minimized().unwrap_or(true)
+            })
+    })
+}
+
+/// The names the user goes by in `chat_id`, for mentions: their display
+/// name, the name the chat knows them by and their email address without
+/// the domain. Looked up once per chat and session.
+fn own_names(app: &AppHandle, chat_id: &str) -> Vec<String> {
+    let Some(credentials) = app.state::<Session>().credentials() else {
+        return Vec::new();
+    };
+    let notifier = app.state::<Notifier>();
+    let mut cache = notifier.own_names.lock().unwrap_or_else(|e| e.into_inner());
+    if cache.user_id != credentials.user_id {
+        *cache = OwnNames {
+            user_id: credentials.user_id.clone(),
+            chats: HashMap::new(),
+        };
+    }
+    if let Some(names) = cache.chats.get(chat_id) {
+        return names.clone();
+    }
+    let (names, found) = look_up_own_names(app, &credentials.user_id, chat_id);
+    // A user or chat not cached yet may still turn up later
+    if found {
+        cache.chats.insert(chat_id.to_string(), names.clone());
+    }
+    names
+}
+
+/// See [`own_names`]; also says whether both the user and `chat_id` were
+/// found.
+fn look_up_own_names(app: &AppHandle, user_id: &str, chat_id: &str) -> (Vec<String>, bool) {
+    let Some(store) = app.try_state::<Store>() else {
+        return (Vec::new(), false);
+    };
+    let mut names = Vec::new();
+    let user_known = match store.user(user_id) {
+        Ok(Some(user)) => {
+            names.push(user.display_name);
+            if let Some((local, _)) = user.email.split_once('@') {
+                names.push(local.to_string());
+            }
+            true
+        }
+        Ok(None) => false,
+        Err(e) => {
+            tracing::warn!("failed to look up the user: {e}");
+            false
+        }
+    };
+    let chat_known = match store.chats() {
+        Ok(chats) => match chats.into_iter().find(|chat| chat.id == chat_id) {
+            Some(chat) => {
+                names.extend(
+                    chat.participant_names
+                        .and_then(|mut names| names.remove(user_id)),
+                );
+                true
+            }
+            None => false,
+        },
+        Err(e) => {
+            tracing::warn!("failed to look up the chat: {e}");
+            false
+        }
+    };
+    names.sort();
+    names.dedup();
+    (names, user_known && chat_known)
+}
+
+/// "New message from Alice", or "5 new messages from Alice and Bob".
+fn title(chat: &ChatNotification) -> String {
+    let senders = match chat.senders.as_slice() {
+        [] => "Someone".to_string(),
+        [one] => one.clone(),
+        [one, two] => format!("{one} and {two}"),
+        [one, two, three] => format!("{one}, {two} and {three}"),
+        [one, two, rest @ ..] => format!("{one}, {two} and {} others", rest.len()),
+    };
+    if chat.count == 1 {
+        format!("New message from {senders}")
+    } else {
+        format!("{} new messages from {senders}", chat.count)
+    }
+}
@@ -0,0 +1,347 @@
+// Notification rules
+//
+// Whether a notification is shown, checked in this order:
+//
+// 1. the kind of notification is turned off in the settings
+// 2. for messages: the chat is open in a focused window
+// 3. all notifications are muted from the tray
+// 4. for messages: an `@` mention of the user or one of their keywords,
+//    which is shown whatever follows
+// 5. for messages: the chat is muted
+// 6. quiet hours
+// 7. for presence: the contact was notified about in the last few minutes,
+//    or several contacts were in the last minute
+//
+// Quiet hours are in local time, given as an offset from UTC with every
+// notification (the system's for messages, the webview's for presence), so
+// they follow daylight saving time.
+use std::collections::{HashMap, VecDeque};
+
+use crate::models::{
+    MessageNotification, NotificationOutcome, NotificationSettings, PresenceNotification,
+    QuietHours, Timestamp, Weekday,
+};
+
+const MINUTES_PER_DAY: i64 = 24 * 60;
+
+/// A contact flapping between online and offline notifies once in this long.
+const PRESENCE_PER_CONTACT_MS: i64 = 5 * 60 * 1000;
+/// At most this many presence notifications a minute, across contacts.
+const PRESENCE_PER_MINUTE: usize = 3;
+const MINUTE_MS: i64 = 60 * 1000;
+
+/// Everything the rules look at besides the settings.
+pub struct Context<'a> {
+    pub now: Timestamp,
+    pub utc_offset_minutes: i32,
+    /// Whether the tray mute is on.
+    pub muted: bool,
+    /// Whether the message's chat is open in a focused window.
+    pub focused: bool,
+    /// Names the user is mentioned by.
+    pub names: &'a [String],
+}
+
+pub fn message(
+    settings: &NotificationSettings,
+    notification: &MessageNotification,
+    context: &Context,
+) -> NotificationOutcome {
+    if !settings.messages {
+        return NotificationOutcome::Disabled;
+    }
+    if context.focused {
+        return NotificationOutcome::Focused;
+    }
+    if context.muted {
+        return NotificationOutcome::Muted;
+    }
+    if alerts(settings, &notification.text, context.names) {
+        return NotificationOutcome::Shown;
+    }
+    if is_chat_muted(settings, &notification.chat_id, context.now) {
+        return NotificationOutcome::ChatMuted;
+    }
+    if in_quiet_hours(
+        &settings.quiet_hours,
+        context.now,
+        context.utc_offset_minutes,
+    ) {
+        return NotificationOutcome::QuietHours;
+    }
+    NotificationOutcome::Shown
+}
+
+/// Presence notifications shown lately, for rate limiting.
+#[derive(Default)]
+pub struct PresenceHistory {
+    by_contact: HashMap<String, Timestamp>,
+    /// Oldest first.
+    recent: VecDeque<Timestamp>,
+}
+
+impl PresenceHistory {
+    pub fn record(&mut self, user_id: &str, now: Timestamp) {
+        self.by_contact
+            .retain(|_, at| now - *at < PRESENCE_PER_CONTACT_MS);
+        self.by_contact.insert(user_id.to_string(), now);
+        while self.recent.front().is_some_and(|at| now - at >= MINUTE_MS) {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(now);
+    }
+
+    fn is_limited(&self, user_id: &str, now: Timestamp) -> bool {
+        self.by_contact
+            .get(user_id)
+            .is_some_and(|at| now - at < PRESENCE_PER_CONTACT_MS)
+            || self
+                .recent
+                .iter()
+                .filter(|at| now - *at < MINUTE_MS)
+                .count()
+                >= PRESENCE_PER_MINUTE
+    }
+}
+
+pub fn presence(
+    settings: &NotificationSettings,
+    notification: &PresenceNotification,
+    history: &PresenceHistory,
+    context: &Context,
+) -> NotificationOutcome {
+    if !settings.presence {
+        return NotificationOutcome::Disabled;
+    }
+    if context.muted {
+        return NotificationOutcome::Muted;
+    }
+    if in_quiet_hours(
+        &settings.quiet_hours,
+        context.now,
+        context.utc_offset_minutes,
+    ) {
+        return NotificationOutcome::QuietHours;
+    }
+    if history.is_limited(&notification.user_id, context.now) {
+        return NotificationOutcome::RateLimited;
+    }
+    NotificationOutcome::Shown
+}
+
+/// Whether `chat_id` is muted at `now`.
+pub fn is_chat_muted(settings: &NotificationSettings, chat_id: &str, now: Timestamp) -> bool {
+    settings
+        .muted_chats
+        .iter()
+        .any(|mute| mute.chat_id == chat_id && mute.until.is_none_or(|until| now < until))
+}
+
+pub fn in_quiet_hours(quiet_hours: &[QuietHours], now: Timestamp, utc_offset_minutes: i32) -> bool {
+    let minutes = now.div_euclid(60_000) + i64::from(utc_offset_minutes);
+    let day = minutes.div_euclid(MINUTES_PER_DAY);
+    let minute = minutes.rem_euclid(MINUTES_PER_DAY);
+    let today = weekday(day);
+    let yesterday = weekday(day - 1);
+
+    quiet_hours.iter().any(|period| {
+        let (start, end) = (i64::from(period.start), i64::from(period.end));
+        if start == end {
+            period.days.contains(&today)
+        } else if start < end {
+            period.days.contains(&today) && (start..end).contains(&minute)
+        } else {
+            // Runs past midnight: the evening of a listed day, or the
+            // morning after one
+            (period.days.contains(&today) && minute >= start)
+                || (period.days.contains(&yesterday) && minute < end)
+        }
+    })
+}
+
+/// The day of the week of a day counted from 1970-01-01, a Thursday.
+fn weekday(day: i64) -> Weekday {
+    Weekday::ALL[(day + 3).rem_euclid(7) as usize]
+}
+
+/// Whether `text` mentions the user or contains one of their keywords.
+fn alerts(settings: &NotificationSettings, text: &str, names: &[String]) -> bool {
+    let text = text.to_lowercase();
+    let mentioned = settings.mentions
+        && names
+            .iter()
+            .filter(|name| !name.trim().is_empty())
+            .any(|name| contains_word(&text, &format!("@{}", name.trim().to_lowercase())));
+    mentioned
+        || settings
+            .keywords
+            .iter()
+            .filter(|keyword| !keyword.trim().is_empty())
+            .any(|keyword| contains_word(&text, &keyword.trim().to_lowercase()))
+}
+
+/// Whether `word` occurs in `text` without letters or digits right before
+/// or after it.
+fn contains_word(text: &str, word: &str) -> bool {
+    text.match_indices(word).any(|(at, _)| {
+        let before = text[..at].chars().next_back();
+        let after = text[at + word.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::civil::days_from_civil;
+    use crate::models::ChatMute;
+
+    /// A UTC time in October 2026; the 16th is a Friday.
+    fn at(day: u32, hour: i64, minute: i64) -> Timestamp {
+        ((days_from_civil(2026, 10, day) * 24 + hour) * 60 + minute) * MINUTE_MS
+    }
+
+    fn quiet(days: &[Weekday], start: u16, end: u16) -> Vec<QuietHours> {
+        vec![QuietHours {
+            days: days.to_vec(),
+            start,
+            end,
+        }]
+    }
+
+    #[test]
+    fn weekdays_count_from_a_thursday() {
+        assert_eq!(weekday(0), Weekday::Thursday);
+        assert_eq!(weekday(-1), Weekday::Wednesday);
+        assert_eq!(weekday(days_from_civil(2026, 10, 16)), Weekday::Friday);
+        assert_eq!(weekday(days_from_civil(2026, 10, 18)), Weekday::Sunday);
+    }
+
+    #[test]
+    fn quiet_hours_within_a_day() {
+        let hours = quiet(&[Weekday::Friday], 9 * 60, 17 * 60);
+        assert!(in_quiet_hours(&hours, at(16, 9, 0), 0));
+        assert!(in_quiet_hours(&hours, at(16, 16, 59), 0));
+        assert!(!in_quiet_hours(&hours, at(16, 17, 0), 0));
+        assert!(!in_quiet_hours(&hours, at(16, 8, 59), 0));
+        assert!(!in_quiet_hours(&hours, at(17, 12, 0), 0));
+    }
+
+    #[test]
+    fn quiet_hours_past_midnight_belong_to_the_day_they_start() {
+        let hours = quiet(&[Weekday::Friday], 22 * 60, 7 * 60);
+        assert!(in_quiet_hours(&hours, at(16, 23, 0), 0));
+        assert!(in_quiet_hours(&hours, at(17, 6, 59), 0));
+        assert!(!in_quiet_hours(&hours, at(17, 7, 0), 0));
+        assert!(!in_quiet_hours(&hours, at(16, 21, 59), 0));
+        // Friday morning follows Thursday night, which is not listed
+        assert!(!in_quiet_hours(&hours, at(16, 6, 0), 0));
+        assert!(!in_quiet_hours(&hours, at(15, 23, 0), 0));
+    }
+
+    #[test]
+    fn quiet_hours_starting_and_ending_together_last_all_day() {
+        let hours = quiet(&[Weekday::Sunday], 0, 0);
+        assert!(in_quiet_hours(&hours, at(18, 0, 0), 0));
+        assert!(in_quiet_hours(&hours, at(18, 23, 59), 0));
+        assert!(!in_quiet_hours(&hours, at(19, 0, 0), 0));
+    }
+
+    #[test]
+    fn quiet_hours_are_in_local_time() {
+        let hours = quiet(&[Weekday::Friday], 22 * 60, 23 * 60);
+        assert!(in_quiet_hours(&hours, at(16, 21, 30), 60));
+        assert!(!in_quiet_hours(&hours, at(16, 21, 30), -60));
+        // Saturday in UTC, still Friday evening locally
+        assert!(in_quiet_hours(&hours, at(17, 0, 30), -120));
+        assert!(!in_quiet_hours(&hours, at(17, 0, 30), -90));
+    }
+
+    #[test]
+    fn words_need_boundaries() {
+        assert!(contains_word("hi @alice!", "@alice"));
+        assert!(contains_word("@alice", "@alice"));
+        assert!(!contains_word("hi @alicexyz", "@alice"));
+        assert!(!contains_word("bob@alice", "@alice"));
+        assert!(contains_word("releases, then the release", "release"));
+        assert!(contains_word("café-release", "release"));
+        assert!(!contains_word("ärelease", "release"));
+        assert!(!contains_word("", "release"));
+    }
+
+    #[test]
+    fn chat_mutes_expire() {
+        let settings = NotificationSettings {
+            muted_chats: vec![
+                ChatMute {
+                    chat_id: "forever".into(),
+                    until: None,
+                },
+                ChatMute {
+                    chat_id: "later".into(),
+                    until: Some(1_000),
+                },
+            ],
+            ..Default::default()
+        };
+        assert!(is_chat_muted(&settings, "forever", i64::MAX));
+        assert!(is_chat_muted(&settings, "later", 999));
+        assert!(!is_chat_muted(&settings, "later", 1_000));
+        assert!(!is_chat_muted(&settings, "other", 0));
+    }
+
+    #[test]
+    fn presence_is_limited_per_contact_and_per_minute() {
+        let mut history = PresenceHistory::default();
+        history.record("u1", 0);
+        assert!(history.is_limited("u1", 1_000));
+        assert!(!history.is_limited("u1", PRESENCE_PER_CONTACT_MS));
+        assert!(!history.is_limited("u2", 1_000));
+
+        history.record("u2", 1_000);
+        history.record("u3", 2_000);
+        assert!(history.is_limited("u4", 3_000));
+        assert!(!history.is_limited("u4", MINUTE_MS + 1_000));
+    }
+
+    #[test]
+    fn keywords_get_through_muted_chats_and_quiet_hours() {
+        let settings = NotificationSettings {
+            keywords: vec!["release".into()],
+            muted_chats: vec![ChatMute {
+                chat_id: "c1".into(),
+                until: None,
+            }],
+            quiet_hours: quiet(&Weekday::ALL, 0, 0),
+            ..Default::default()
+        };
+        let names = ["Alice".to_string()];
+        let context = Context {
+            now: at(16, 12, 0),
+            utc_offset_minutes: 0,
+            muted: false,
+            focused: false,
+            names: &names,
+        };
+        let notification = |text: &str| MessageNotification {
+            chat_id: "c1".into(),
+            sender_name: "Bob".into(),
+            text: text.into(),
+            sent_at: 0,
+        };
+
+        let outcome = |text| message(&settings, &notification(text), &context);
+        assert_eq!(outcome("the Release is out"), NotificationOutcome::Shown);
+        assert_eq!(outcome("thanks @alice"), NotificationOutcome::Shown);
+        assert_eq!(outcome("hello"), NotificationOutcome::ChatMuted);
+
+        let focused = Context {
+            focused: true,
+            ..context
+        };
+        assert_eq!(
+            message(&settings, &notification("release"), &focused),
+            NotificationOutcome::Focused
+        );
+    }
+}
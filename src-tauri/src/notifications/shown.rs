@@ -0,0 +1,184 @@
+// Notifications on screen
+//
+// Each chat has at most one notification, which counts the messages that
+// came in since the chat was last read ("5 new messages from Alice") and is
+// forgotten once it is. What each chat's notification holds, and the ID it
+// was shown with where the platform has one, is kept in `notifications.json`
+// with the profile's data and saved on every change, so notifications a
+// previous run left on screen are still counted, updated and closed.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::models::Timestamp;
+
+const FILE_NAME: &str = "notifications.json";
+
+/// Notifications by chat ID, managed as state.
+pub struct Shown {
+    path: PathBuf,
+    chats: Mutex<BTreeMap<String, ChatNotification>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatNotification {
+    /// The ID it is shown with, where the platform has one.
+    pub id: Option<u32>,
+    pub count: u32,
+    /// Who sent the messages, in the order they first wrote.
+    pub senders: Vec<String>,
+    /// When the latest message was sent.
+    pub latest: Timestamp,
+    /// When the latest message came in, by this device's clock.
+    #[serde(default)]
+    pub received_at: Timestamp,
+}
+
+impl Shown {
+    /// Loads the notifications saved in `dir`. An unreadable file is logged
+    /// and treated as empty.
+    pub fn open(dir: &Path) -> Self {
+        let path = dir.join(FILE_NAME);
+        let chats = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                tracing::warn!("ignoring {}: {e}", path.display());
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Shown {
+            path,
+            chats: Mutex::new(chats),
+        }
+    }
+
+    /// Counts a message from `sender` that came in at `received_at` into
+    /// `chat_id`'s notification. Returns what the notification now holds, and
+    /// when the message before came in (0 for the first).
+    pub fn add(
+        &self,
+        chat_id: &str,
+        sender: &str,
+        sent_at: Timestamp,
+        received_at: Timestamp,
+    ) -> (ChatNotification, Timestamp) {
+        let mut chats = self.chats.lock().unwrap_or_else(|e| e.into_inner());
+        let chat = chats.entry(chat_id.to_string()).or_default();
+        chat.count += 1;
+        if !chat.senders.iter().any(|name| name == sender) {
+            chat.senders.push(sender.to_string());
+        }
+        chat.latest = chat.latest.max(sent_at);
+        let previous = std::mem::replace(&mut chat.received_at, received_at);
+        let chat = chat.clone();
+        if let Err(e) = self.save(&chats) {
+            tracing::warn!("failed to save notifications: {e}");
+        }
+        (chat, previous)
+    }
+
+    /// Records that `chat_id`'s notification is shown with `id`.
+    pub fn set_shown(&self, chat_id: &str, id: Option<u32>) -> Result<()> {
+        let mut chats = self.chats.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(chat) = chats.get_mut(chat_id) {
+            chat.id = id;
+        }
+        self.save(&chats)
+    }
+
+    /// Forgets `chat_id`'s notification if it holds no message sent after
+    /// `up_to`, or in any case without one, and returns it.
+    pub fn remove(&self, chat_id: &str, up_to: Option<Timestamp>) -> Option<ChatNotification> {
+        let mut chats = self.chats.lock().unwrap_or_else(|e| e.into_inner());
+        if up_to.is_some_and(|up_to| chats.get(chat_id).is_some_and(|chat| chat.latest > up_to)) {
+            return None;
+        }
+        let removed = chats.remove(chat_id)?;
+        if let Err(e) = self.save(&chats) {
+            tracing::warn!("failed to save notifications: {e}");
+        }
+        Some(removed)
+    }
+
+    /// Forgets all notifications and returns them.
+    pub fn clear(&self) -> Vec<ChatNotification> {
+        let mut chats = self.chats.lock().unwrap_or_else(|e| e.into_inner());
+        let removed = std::mem::take(&mut *chats).into_values().collect();
+        if let Err(e) = self.save(&chats) {
+            tracing::warn!("failed to save notifications: {e}");
+        }
+        removed
+    }
+
+    fn save(&self, chats: &BTreeMap<String, ChatNotification>) -> Result<()> {
+        // Write a copy and swap it in, so a crash never leaves half a file
+        let partial = self.path.with_extension("json.partial");
+        fs::write(&partial, serde_json::to_vec_pretty(chats)?)?;
+        fs::rename(&partial, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn messages_of_a_chat_share_one_notification() {
+        let dir = TempDir::new();
+        let shown = Shown::open(&dir);
+        shown.add("c1", "Alice", 10, 11);
+        shown.add("c1", "Bob", 30, 31);
+        let (chat, previous) = shown.add("c1", "Alice", 20, 32);
+        assert_eq!(chat.count, 3);
+        assert_eq!(chat.senders, ["Alice", "Bob"]);
+        assert_eq!(chat.latest, 30);
+        assert_eq!(previous, 31);
+        let (chat, previous) = shown.add("c2", "Carol", 5, 6);
+        assert_eq!((chat.count, previous), (1, 0));
+    }
+
+    #[test]
+    fn reading_up_to_an_older_message_keeps_the_notification() {
+        let dir = TempDir::new();
+        let shown = Shown::open(&dir);
+        shown.add("c1", "Alice", 10, 10);
+        shown.add("c1", "Alice", 20, 20);
+        assert!(shown.remove("c1", Some(19)).is_none());
+        assert_eq!(shown.remove("c1", Some(20)).unwrap().count, 2);
+        assert!(shown.remove("c1", None).is_none());
+
+        shown.add("c2", "Bob", 50, 50);
+        assert_eq!(shown.remove("c2", None).unwrap().latest, 50);
+    }
+
+    #[test]
+    fn counted_messages_survive_a_restart() {
+        let dir = TempDir::new();
+        let shown = Shown::open(&dir);
+        shown.add("c1", "Alice", 10, 11);
+        shown.add("c1", "Bob", 20, 21);
+
+        let (chat, previous) = Shown::open(&dir).add("c1", "Alice", 30, 31);
+        assert_eq!((chat.count, chat.senders.len(), previous), (3, 2, 21));
+    }
+
+    #[test]
+    fn shown_notifications_survive_a_restart() {
+        let dir = TempDir::new();
+        let shown = Shown::open(&dir);
+        shown.add("c1", "Alice", 10, 11);
+        shown.set_shown("c1", Some(7)).unwrap();
+
+        let reopened = Shown::open(&dir);
+        let chat = reopened.remove("c1", None).unwrap();
+        assert_eq!((chat.id, chat.count, chat.received_at), (Some(7), 1, 11));
+        assert!(Shown::open(&dir).clear().is_empty());
+    }
+}
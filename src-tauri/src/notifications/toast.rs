@@ -0,0 +1,116 @@
+// Desktop notifications
+//
+// The notification plugin can only add notifications on the desktop: it
+// ignores their IDs and cannot remove them. Where notifications go through a
+// freedesktop.org notification server (Linux and the BSDs), they are sent to
+// it directly instead, so that a chat's notification is replaced as messages
+// come in and closed once the chat is read. Elsewhere (macOS and Windows)
+// they go through the plugin, and can be neither: a chat's notification pops
+// up once for each burst of messages, without the messages after the first,
+// and stays until the user dismisses it.
+pub use self::imp::{close, show, CAN_REPLACE};
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd"
+))]
+mod imp {
+    use notify_rust::{Hint, Notification};
+    use tauri::AppHandle;
+
+    use crate::error::{Error, Result};
+
+    const BUS_NAME: &str = "org.freedesktop.Notifications";
+    const OBJECT_PATH: &str = "/org/freedesktop/Notifications";
+
+    /// Whether shown notifications can be replaced and closed.
+    pub const CAN_REPLACE: bool = true;
+
+    /// Shows a notification, in place of the one with ID `replaces` if that
+    /// is still shown, and returns its ID. A `quiet` one plays no sound.
+    pub fn show(
+        app: &AppHandle,
+        replaces: Option<u32>,
+        title: &str,
+        body: &str,
+        quiet: bool,
+    ) -> Result<Option<u32>> {
+        let mut notification = Notification::new();
+        notification
+            .appname(&app.package_info().name)
+            .summary(title)
+            .body(body)
+            .auto_icon();
+        if quiet {
+            notification.hint(Hint::SuppressSound(true));
+        } else {
+            notification.sound_name("Default");
+        }
+        if let Some(id) = replaces {
+            notification.id(id);
+        }
+        let handle = notification
+            .show()
+            .map_err(|e| Error::Notification(e.to_string()))?;
+        Ok(Some(handle.id()))
+    }
+
+    /// Closes the notification with ID `id`, if it is still shown.
+    pub fn close(id: u32) {
+        let closed = zbus::blocking::Connection::session().and_then(|connection| {
+            connection.call_method(
+                Some(BUS_NAME),
+                OBJECT_PATH,
+                Some(BUS_NAME),
+                "CloseNotification",
+                &(id,),
+            )
+        });
+        if let Err(e) = closed {
+            tracing::debug!("failed to close notification {id}: {e}");
+        }
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd"
+)))]
+mod imp {
+    use tauri::AppHandle;
+    use tauri_plugin_notification::NotificationExt;
+
+    use crate::error::{Error, Result};
+
+    /// Whether shown notifications can be replaced and closed.
+    pub const CAN_REPLACE: bool = false;
+
+    /// Shows a notification. It has no ID to replace or close it by, so
+    /// `replaces` is ignored and `None` returned.
+    pub fn show(
+        app: &AppHandle,
+        _replaces: Option<u32>,
+        title: &str,
+        body: &str,
+        quiet: bool,
+    ) -> Result<Option<u32>> {
+        let mut builder = app.notification().builder().title(title).body(body);
+        if !quiet {
+            builder = builder.sound("Default");
+        }
+        builder
+            .show()
+            .map_err(|e| Error::Notification(e.to_string()))?;
+        Ok(None)
+    }
+
+    /// Does nothing. Notifications are shown without an ID here, so there is
+    /// never one to close; the plugin has no way to remove them.
+    pub fn close(_id: u32) {}
+}
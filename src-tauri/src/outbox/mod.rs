@@ -0,0 +1,366 @@
+// Persistent outbox for outgoing messages
+//
+// Messages are written to the local store first and delivered by a background
+// worker, so nothing is lost when the network is down. Each message gets its
+// push ID up front; delivery is idempotent on that ID, which makes retries
+// safe. The frontend follows progress through `outbox-state` events.
+//
+// A chat's messages are delivered in the order they were written: while one
+// waits for a retry, or has failed until it is retried or discarded, the ones
+// after it in the same chat wait too.
+mod queue;
+mod transport;
+
+use std::time::Duration;
+
+use rand::Rng;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Notify;
+
+use crate::e2e::E2e;
+use crate::error::{Error, Result};
+use crate::models::{now, Attachment, Message, OutboxStateEvent, Timestamp};
+use crate::push_id;
+use crate::session::Session;
+use crate::store::Store;
+
+use self::transport::DeliveryError;
+
+pub use crate::models::{DeliveryState, OutboxEntry};
+
+/// Event emitted whenever an entry changes state.
+pub const STATE_EVENT: &str = "outbox-state";
+
+/// Attempts before an entry is marked failed and left for the user to retry.
+const MAX_ATTEMPTS: u32 = 12;
+const BASE_BACKOFF_MS: i64 = 2_000;
+const MAX_BACKOFF_MS: i64 = 5 * 60 * 1_000;
+/// How long the worker sleeps when nothing is scheduled.
+const IDLE_POLL: Duration = Duration::from_secs(60);
+
+/// Handle used to wake the delivery worker.
+#[derive(Default)]
+pub struct Outbox {
+    wake: Notify,
+}
+
+impl Outbox {
+    /// Asks the worker to look at the queue now instead of waiting for the
+    /// next scheduled attempt.
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+}
+
+/// Queues a message for delivery and returns its entry (with the new ID).
+pub fn enqueue(
+    app: &AppHandle,
+    chat_id: String,
+    sender_id: String,
+    sender_name: String,
+    text: String,
+    attachment: Option<Attachment>,
+) -> Result<OutboxEntry> {
+    if !push_id::is_valid_key(&chat_id) {
+        return Err(Error::InvalidChatId(chat_id));
+    }
+    if !push_id::is_valid_key(&sender_id) {
+        return Err(Error::InvalidUserId(sender_id));
+    }
+
+    let entry = OutboxEntry {
+        id: push_id::generate(),
+        chat_id,
+        sender_id,
+        sender_name,
+        text,
+        attachment,
+        created_at: now(),
+        attempts: 0,
+        state: DeliveryState::Queued,
+        last_error: None,
+    };
+    queue::insert(&app.state::<Store>(), &entry)?;
+    emit_state(app, &entry.id, &entry.chat_id, DeliveryState::Queued, None);
+    app.state::<Outbox>().wake();
+    Ok(entry)
+}
+
+pub fn list(app: &AppHandle, chat_id: Option<&str>) -> Result<Vec<OutboxEntry>> {
+    queue::list(&app.state::<Store>(), chat_id)
+}
+
+/// Moves a failed entry back into the queue.
+pub fn retry(app: &AppHandle, id: &str) -> Result<()> {
+    let store = app.state::<Store>();
+    let entry = queue::get(&store, id)?.ok_or_else(|| Error::UnknownOutboxEntry(id.into()))?;
+    if !queue::requeue(&store, id, now())? {
+        return Err(Error::UnknownOutboxEntry(id.into()));
+    }
+    emit_state(app, id, &entry.chat_id, DeliveryState::Queued, None);
+    app.state::<Outbox>().wake();
+    Ok(())
+}
+
+/// Drops an entry without sending it.
+pub fn discard(app: &AppHandle, id: &str) -> Result<()> {
+    let store = app.state::<Store>();
+    let entry = queue::get(&store, id)?.ok_or_else(|| Error::UnknownOutboxEntry(id.into()))?;
+    if !queue::remove(&store, id)? {
+        return Err(Error::UnknownOutboxEntry(id.into()));
+    }
+    emit_state(app, id, &entry.chat_id, DeliveryState::Discarded, None);
+    Ok(())
+}
+
+/// Schedules every queued entry for an immediate attempt, e.g. once a
+/// session becomes available.
+pub fn flush(app: &AppHandle) -> Result<()> {
+    queue::wake_all(&app.state::<Store>(), now())?;
+    app.state::<Outbox>().wake();
+    Ok(())
+}
+
+/// Starts the background delivery worker.
+pub fn spawn_worker(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let client = reqwest::Client::new();
+        loop {
+            let outbox = app.state::<Outbox>();
+            let wait = match deliver_due(&app, &client).await {
+                Ok(wait) => wait,
+                Err(e) => {
+                    tracing::warn!("delivery failed: {e}");
+                    IDLE_POLL
+                }
+            };
+            // Either a new message/session wakes us, or the next retry is due
+            let _ = tokio::time::timeout(wait, outbox.wake.notified()).await;
+        }
+    });
+}
+
+/// Attempts every due entry once and returns how long to wait before the
+/// next pass.
+async fn deliver_due(app: &AppHandle, client: &reqwest::Client) -> Result<Duration> {
+    let store = app.state::<Store>();
+    let e2e = app.state::<E2e>();
+    let session = app.state::<Session>();
+    let Some(credentials) = session.credentials() else {
+        return Ok(IDLE_POLL);
+    };
+    let db = session.database(client)?;
+
+    // Once one message of a chat is held back in this pass, later ones wait
+    // so the conversation never arrives out of order; `due` leaves out those
+    // held back by earlier passes
+    let mut blocked_chats: Vec<String> = Vec::new();
+
+    for entry in queue::due(&store, now())? {
+        // Never send on behalf of a different account than the signed-in one
+        if entry.sender_id != credentials.user_id || blocked_chats.contains(&entry.chat_id) {
+            continue;
+        }
+
+        match transport::deliver(&db, &credentials.user_id, &store, &e2e, &entry).await {
+            Ok(without_keys) => {
+                queue::remove(&store, &entry.id)?;
+                // Cache it right away; the live listener fills in the server timestamp
+                store.upsert_messages(
+                    &entry.chat_id,
+                    &[Message {
+                        id: entry.id.clone(),
+                        sender_id: entry.sender_id.clone(),
+                        sender_name: Some(entry.sender_name.clone()),
+                        text: entry.text.clone(),
+                        timestamp: entry.created_at,
+                        archived: None,
+                        attachment: entry.attachment.clone(),
+                    }],
+                )?;
+                emit(
+                    app,
+                    OutboxStateEvent {
+                        without_keys: (!without_keys.is_empty()).then_some(without_keys),
+                        ..state_event(&entry.id, &entry.chat_id, DeliveryState::Sent, None)
+                    },
+                );
+            }
+            Err(error) => {
+                if let Some(error) = record_failure(&store, &entry, error, now())? {
+                    emit_state(
+                        app,
+                        &entry.id,
+                        &entry.chat_id,
+                        DeliveryState::Failed,
+                        Some(&error),
+                    );
+                }
+                blocked_chats.push(entry.chat_id);
+            }
+        }
+    }
+
+    let wait = match queue::next_due_at(&store)? {
+        Some(at) => {
+            Duration::from_millis((at - now()).clamp(0, IDLE_POLL.as_millis() as i64) as u64)
+        }
+        None => IDLE_POLL,
+    };
+    Ok(wait)
+}
+
+/// Schedules another attempt after a transient error, or marks the entry
+/// failed once it is out of attempts or was rejected. Returns the error if
+/// it failed.
+fn record_failure(
+    store: &Store,
+    entry: &OutboxEntry,
+    error: DeliveryError,
+    now: Timestamp,
+) -> Result<Option<String>> {
+    let attempts = entry.attempts + 1;
+    match error {
+        DeliveryError::Transient(error) if attempts < MAX_ATTEMPTS => {
+            queue::schedule_retry(store, &entry.id, attempts, now + backoff(attempts), &error)?;
+            Ok(None)
+        }
+        DeliveryError::Transient(error) | DeliveryError::Rejected(error) => {
+            queue::mark_failed(store, &entry.id, attempts, &error)?;
+            Ok(Some(error))
+        }
+    }
+}
+
+/// Exponential backoff with up to 20% jitter, capped at five minutes.
+fn backoff(attempts: u32) -> i64 {
+    let delay = BASE_BACKOFF_MS
+        .saturating_mul(1 << attempts.min(16))
+        .min(MAX_BACKOFF_MS);
+    delay + rand::rng().random_range(0..=delay / 5)
+}
+
+fn emit_state(app: &AppHandle, id: &str, chat_id: &str, state: DeliveryState, error: Option<&str>) {
+    emit(app, state_event(id, chat_id, state, error));
+}
+
+fn state_event(
+    id: &str,
+    chat_id: &str,
+    state: DeliveryState,
+    error: Option<&str>,
+) -> OutboxStateEvent {
+    OutboxStateEvent {
+        id: id.into(),
+        chat_id: chat_id.into(),
+        state,
+        error: error.map(String::from),
+        without_keys: None,
+    }
+}
+
+fn emit(app: &AppHandle, event: OutboxStateEvent) {
+    if let Err(e) = app.emit(STATE_EVENT, event) {
+        tracing::warn!("failed to emit state event: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(store: &Store) -> OutboxEntry {
+        let entry = OutboxEntry {
+            id: "m1".into(),
+            chat_id: "c1".into(),
+            sender_id: "alice".into(),
+            sender_name: "Alice".into(),
+            text: "hi".into(),
+            attachment: None,
+            created_at: 1,
+            attempts: 0,
+            state: DeliveryState::Queued,
+            last_error: None,
+        };
+        queue::insert(store, &entry).unwrap();
+        entry
+    }
+
+    /// The delay before attempt `attempts + 1`, without jitter.
+    fn base_delay(attempts: u32) -> i64 {
+        (BASE_BACKOFF_MS << attempts.min(16)).min(MAX_BACKOFF_MS)
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap_with_jitter() {
+        for attempts in 1..40 {
+            let delay = backoff(attempts);
+            let base = base_delay(attempts);
+            assert!(
+                (base..=base + base / 5).contains(&delay),
+                "{attempts}: {delay}"
+            );
+        }
+        assert_eq!(base_delay(1), 4_000);
+        assert_eq!(base_delay(2), 8_000);
+        assert_eq!(base_delay(7), 256_000);
+        assert_eq!(base_delay(8), MAX_BACKOFF_MS);
+    }
+
+    #[test]
+    fn transient_errors_are_retried_until_out_of_attempts() {
+        let store = Store::open_in_memory();
+        let mut entry = queued(&store);
+        let now = 1_000;
+
+        for attempts in 1..MAX_ATTEMPTS {
+            let failed = record_failure(
+                &store,
+                &entry,
+                DeliveryError::Transient("offline".into()),
+                now,
+            )
+            .unwrap();
+            assert_eq!(failed, None);
+
+            entry = queue::get(&store, "m1").unwrap().unwrap();
+            assert_eq!(entry.state, DeliveryState::Queued);
+            assert_eq!(entry.attempts, attempts);
+            assert_eq!(entry.last_error.as_deref(), Some("offline"));
+            let wait = queue::next_due_at(&store).unwrap().unwrap() - now;
+            let base = base_delay(attempts);
+            assert!((base..=base + base / 5).contains(&wait), "{wait}");
+            assert!(queue::due(&store, now).unwrap().is_empty());
+        }
+
+        let failed = record_failure(
+            &store,
+            &entry,
+            DeliveryError::Transient("offline".into()),
+            now,
+        )
+        .unwrap();
+        assert_eq!(failed.as_deref(), Some("offline"));
+        let entry = queue::get(&store, "m1").unwrap().unwrap();
+        assert_eq!(entry.state, DeliveryState::Failed);
+        assert_eq!(entry.attempts, MAX_ATTEMPTS);
+        assert_eq!(queue::next_due_at(&store).unwrap(), None);
+    }
+
+    #[test]
+    fn rejected_entries_fail_at_once() {
+        let store = Store::open_in_memory();
+        let entry = queued(&store);
+        let failed = record_failure(
+            &store,
+            &entry,
+            DeliveryError::Rejected("permission denied".into()),
+            1_000,
+        )
+        .unwrap();
+        assert_eq!(failed.as_deref(), Some("permission denied"));
+        let entry = queue::get(&store, "m1").unwrap().unwrap();
+        assert_eq!(entry.state, DeliveryState::Failed);
+        assert_eq!(entry.attempts, 1);
+    }
+}
@@ -0,0 +1,287 @@
+// Persistence for outbox entries (the `outbox` table in the local store)
+use rusqlite::{params, OptionalExtension, Row};
+
+use super::{DeliveryState, OutboxEntry};
+use crate::e2e::Sealed;
+use crate::error::Result;
+use crate::models::Timestamp;
+use crate::store::{parse_json, Store};
+
+const COLUMNS: &str =
+    "id, chat_id, sender_id, sender_name, text, created_at, attempts, state, last_error, attachment";
+
+pub fn insert(store: &Store, entry: &OutboxEntry) -> Result<()> {
+    store.conn().execute(
+        "INSERT INTO outbox (id, chat_id, sender_id, sender_name, text, created_at,
+                             attempts, next_attempt_at, state, last_error, attachment)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0, ?6, ?7, NULL, ?8)",
+        params![
+            entry.id,
+            entry.chat_id,
+            entry.sender_id,
+            entry.sender_name,
+            entry.text,
+            entry.created_at,
+            entry.state.as_str(),
+            entry
+                .attachment
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn get(store: &Store, id: &str) -> Result<Option<OutboxEntry>> {
+    let entry = store
+        .conn()
+        .query_row(
+            &format!("SELECT {COLUMNS} FROM outbox WHERE id = ?1"),
+            [id],
+            entry_from_row,
+        )
+        .optional()?;
+    Ok(entry)
+}
+
+/// All pending and failed entries, optionally limited to one chat, oldest first.
+pub fn list(store: &Store, chat_id: Option<&str>) -> Result<Vec<OutboxEntry>> {
+    let conn = store.conn();
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {COLUMNS} FROM outbox
+         WHERE ?1 IS NULL OR chat_id = ?1
+         ORDER BY created_at, id"
+    ))?;
+    let entries = stmt
+        .query_map([chat_id], entry_from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(entries)
+}
+
+/// Queued entries whose next attempt is due, oldest first. Entries queued
+/// after one of their chat that failed or is waiting for a retry are left
+/// out, so a chat's messages never arrive out of order.
+pub fn due(store: &Store, now: Timestamp) -> Result<Vec<OutboxEntry>> {
+    let conn = store.conn();
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {COLUMNS} FROM outbox o
+         WHERE state = 'queued' AND next_attempt_at <= ?1
+           AND NOT EXISTS (
+               SELECT 1 FROM outbox held
+               WHERE held.chat_id = o.chat_id
+                 AND (held.state = 'failed' OR held.next_attempt_at > ?1)
+                 AND (held.created_at, held.id) < (o.created_at, o.id)
+           )
+         ORDER BY created_at, id"
+    ))?;
+    let entries = stmt
+        .query_map([now], entry_from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(entries)
+}
+
+/// When the earliest queued entry becomes due, if any.
+pub fn next_due_at(store: &Store) -> Result<Option<Timestamp>> {
+    let next = store.conn().query_row(
+        "SELECT MIN(next_attempt_at) FROM outbox WHERE state = 'queued'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(next)
+}
+
+/// The message as encrypted by an earlier attempt, if any.
+pub fn sealed(store: &Store, id: &str) -> Result<Option<Sealed>> {
+    let raw: Option<String> = store
+        .conn()
+        .query_row("SELECT sealed FROM outbox WHERE id = ?1", [id], |row| {
+            row.get(0)
+        })
+        .optional()?
+        .flatten();
+    Ok(raw.map(|raw| serde_json::from_str(&raw)).transpose()?)
+}
+
+pub fn set_sealed(store: &Store, id: &str, sealed: &Sealed) -> Result<()> {
+    store.conn().execute(
+        "UPDATE outbox SET sealed = ?2 WHERE id = ?1",
+        params![id, serde_json::to_string(sealed)?],
+    )?;
+    Ok(())
+}
+
+pub fn schedule_retry(
+    store: &Store,
+    id: &str,
+    attempts: u32,
+    next_attempt_at: Timestamp,
+    error: &str,
+) -> Result<()> {
+    store.conn().execute(
+        "UPDATE outbox SET attempts = ?2, next_attempt_at = ?3, last_error = ?4 WHERE id = ?1",
+        params![id, attempts, next_attempt_at, error],
+    )?;
+    Ok(())
+}
+
+pub fn mark_failed(store: &Store, id: &str, attempts: u32, error: &str) -> Result<()> {
+    store.conn().execute(
+        "UPDATE outbox SET state = 'failed', attempts = ?2, last_error = ?3 WHERE id = ?1",
+        params![id, attempts, error],
+    )?;
+    Ok(())
+}
+
+/// Puts an entry back in the queue for an immediate attempt. Returns false if
+/// it no longer exists.
+pub fn requeue(store: &Store, id: &str, now: Timestamp) -> Result<bool> {
+    let updated = store.conn().execute(
+        "UPDATE outbox SET state = 'queued', attempts = 0, next_attempt_at = ?2, last_error = NULL
+         WHERE id = ?1",
+        params![id, now],
+    )?;
+    Ok(updated > 0)
+}
+
+/// Requeues everything for an immediate attempt, e.g. after sign-in.
+pub fn wake_all(store: &Store, now: Timestamp) -> Result<()> {
+    store.conn().execute(
+        "UPDATE outbox SET next_attempt_at = ?1 WHERE state = 'queued' AND next_attempt_at > ?1",
+        [now],
+    )?;
+    Ok(())
+}
+
+pub fn remove(store: &Store, id: &str) -> Result<bool> {
+    let removed = store
+        .conn()
+        .execute("DELETE FROM outbox WHERE id = ?1", [id])?;
+    Ok(removed > 0)
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<OutboxEntry> {
+    let state: String = row.get(7)?;
+    Ok(OutboxEntry {
+        id: row.get(0)?,
+        chat_id: row.get(1)?,
+        sender_id: row.get(2)?,
+        sender_name: row.get(3)?,
+        text: row.get(4)?,
+        attachment: row
+            .get::<_, Option<String>>(9)?
+            .map(|raw| parse_json(9, &raw))
+            .transpose()?,
+        created_at: row.get(5)?,
+        attempts: row.get(6)?,
+        state: if state == "failed" {
+            DeliveryState::Failed
+        } else {
+            DeliveryState::Queued
+        },
+        last_error: row.get(8)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn entry(id: &str, chat_id: &str, created_at: Timestamp) -> OutboxEntry {
+        OutboxEntry {
+            id: id.into(),
+            chat_id: chat_id.into(),
+            sender_id: "alice".into(),
+            sender_name: "Alice".into(),
+            text: format!("message {id}"),
+            attachment: None,
+            created_at,
+            attempts: 0,
+            state: DeliveryState::Queued,
+            last_error: None,
+        }
+    }
+
+    fn due_ids(store: &Store, now: Timestamp) -> Vec<String> {
+        due(store, now)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect()
+    }
+
+    #[test]
+    fn chats_wait_for_held_back_entries() {
+        let store = Store::open_in_memory();
+        for entry in [
+            entry("a1", "a", 1),
+            entry("a2", "a", 2),
+            entry("b1", "b", 3),
+        ] {
+            insert(&store, &entry).unwrap();
+        }
+        assert_eq!(due_ids(&store, 10), ["a1", "a2", "b1"]);
+
+        // Waiting for a retry holds back the rest of its chat only
+        schedule_retry(&store, "a1", 1, 100, "offline").unwrap();
+        assert_eq!(due_ids(&store, 10), ["b1"]);
+        assert_eq!(due_ids(&store, 100), ["a1", "a2", "b1"]);
+
+        // So does failing, until it is retried or discarded
+        mark_failed(&store, "a1", 12, "offline").unwrap();
+        assert_eq!(due_ids(&store, 100), ["b1"]);
+        assert!(requeue(&store, "a1", 100).unwrap());
+        assert_eq!(due_ids(&store, 100), ["a1", "a2", "b1"]);
+        mark_failed(&store, "a1", 12, "offline").unwrap();
+        assert!(remove(&store, "a1").unwrap());
+        assert_eq!(due_ids(&store, 100), ["a2", "b1"]);
+    }
+
+    #[test]
+    fn requeue_resets_attempts() {
+        let store = Store::open_in_memory();
+        insert(&store, &entry("a1", "a", 1)).unwrap();
+        mark_failed(&store, "a1", 12, "rejected").unwrap();
+        let failed = get(&store, "a1").unwrap().unwrap();
+        assert_eq!(failed.state, DeliveryState::Failed);
+        assert_eq!(failed.last_error.as_deref(), Some("rejected"));
+
+        assert!(requeue(&store, "a1", 50).unwrap());
+        let queued = get(&store, "a1").unwrap().unwrap();
+        assert_eq!(queued.state, DeliveryState::Queued);
+        assert_eq!(queued.attempts, 0);
+        assert_eq!(queued.last_error, None);
+        assert_eq!(next_due_at(&store).unwrap(), Some(50));
+    }
+
+    #[test]
+    fn missing_entries_cannot_be_requeued_or_removed() {
+        let store = Store::open_in_memory();
+        assert!(!requeue(&store, "gone", 1).unwrap());
+        assert!(!remove(&store, "gone").unwrap());
+        assert!(get(&store, "gone").unwrap().is_none());
+        assert_eq!(next_due_at(&store).unwrap(), None);
+    }
+
+    #[test]
+    fn sealed_messages_are_kept_with_the_entry() {
+        let store = Store::open_in_memory();
+        insert(&store, &entry("a1", "a", 1)).unwrap();
+        assert!(sealed(&store, "a1").unwrap().is_none());
+
+        let value = json!({
+            "envelope": { "v": 1, "sender": "alice:d1" },
+            "distributed": [3, ["bob:d1"]],
+            "withoutKeys": ["carol"],
+        });
+        let encrypted: Sealed = serde_json::from_value(value.clone()).unwrap();
+        set_sealed(&store, "a1", &encrypted).unwrap();
+        let kept = sealed(&store, "a1").unwrap().unwrap();
+        assert_eq!(serde_json::to_value(&kept).unwrap(), value);
+
+        remove(&store, "a1").unwrap();
+        assert!(sealed(&store, "a1").unwrap().is_none());
+    }
+}
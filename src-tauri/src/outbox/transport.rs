@@ -0,0 +1,91 @@
+// Delivery of outbox entries over the RTDB REST API
+//
+// The text and any attachment's metadata are end-to-end encrypted on the
+// first attempt and the result is kept with the entry, so every attempt sends
+// the same ciphertext. Only the envelope is written to
+// `messages/{chatId}/{id}`, with a create-only conditional PUT, so a retry
+// after a lost response is answered with `412 Precondition Failed` instead of
+// writing a duplicate. The chat summary
+// update afterwards is a plain PATCH and safe to repeat.
+use serde_json::json;
+
+use super::{queue, OutboxEntry};
+use crate::e2e::{Content, CryptoError, E2e, ENCRYPTED_PREVIEW};
+use crate::error::Error;
+use crate::rtdb::{Database, ServerTimestamp};
+use crate::store::Store;
+
+/// Why a delivery attempt did not go through.
+pub enum DeliveryError {
+    /// Worth trying again later (offline, server error, expired token).
+    Transient(String),
+    /// The server rejected the write; retrying will not help.
+    Rejected(String),
+}
+
+impl From<Error> for DeliveryError {
+    fn from(error: Error) -> Self {
+        let transient = match &error {
+            Error::Rtdb(e) => e.is_transient(),
+            // Nobody else has set up encryption yet; they may on a newer version
+            Error::E2e(CryptoError::NoKeys(_)) => true,
+            Error::Http(_) => true,
+            _ => false,
+        };
+        if transient {
+            DeliveryError::Transient(error.to_string())
+        } else {
+            DeliveryError::Rejected(error.to_string())
+        }
+    }
+}
+
+pub async fn deliver(
+    db: &Database,
+    user_id: &str,
+    store: &Store,
+    e2e: &E2e,
+    entry: &OutboxEntry,
+) -> Result<Vec<String>, DeliveryError> {
+    // A retry sends what the first attempt encrypted, which may already be
+    // stored, so the key distribution confirmed below is the one it carries
+    let sealed = match queue::sealed(store, &entry.id)? {
+        Some(sealed) => sealed,
+        None => {
+            let content = Content {
+                text: entry.text.clone(),
+                attachment: entry.attachment.clone(),
+            };
+            let sealed = e2e
+                .encrypt(store, db, user_id, &entry.chat_id, &content)
+                .await?;
+            queue::set_sealed(store, &entry.id, &sealed)?;
+            sealed
+        }
+    };
+    let message = json!({
+        "senderId": entry.sender_id,
+        "senderName": entry.sender_name,
+        "e2e": sealed.envelope,
+        "timestamp": ServerTimestamp,
+    });
+    // `false` means the message is already there from an earlier attempt
+    db.create(
+        &format!("messages/{}/{}", entry.chat_id, entry.id),
+        &message,
+    )
+    .await
+    .map_err(Error::from)?;
+    e2e.confirm(store, &entry.chat_id, &sealed)?;
+
+    let summary = json!({
+        "lastMessage": ENCRYPTED_PREVIEW,
+        "lastMessageSenderId": entry.sender_id,
+        "updatedAt": ServerTimestamp,
+        format!("typing/{}", entry.sender_id): false,
+    });
+    db.update(&format!("chats/{}", entry.chat_id), &summary)
+        .await
+        .map_err(Error::from)?;
+    Ok(sealed.without_keys)
+}
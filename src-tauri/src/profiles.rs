@@ -0,0 +1,264 @@
+// Profiles
+//
+// A profile is a separate copy of everything the app keeps locally: the
+// encrypted store and its key, the media cache, settings, and the webview's
+// storage, where Firebase keeps the signed-in user. A profile can also use
+// its own Firebase project, configured in `backend.json` in its config
+// directory; without one it uses the project the app was built for.
+//
+// The default profile uses the app's directories as they are, so installs
+// from before profiles carry on unchanged; the others live under
+// `profiles/<name>/` in each of them. `--profile <name>` picks the profile for
+// a run, and switching profiles starts the app again with the other one, so
+// nothing from one profile stays behind in memory for the next.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+
+use crate::error::{Error, Result};
+use crate::models::{BackendConfig, ProfileInfo};
+
+pub const FLAG: &str = "--profile";
+pub const DEFAULT: &str = "default";
+
+const PROFILES_DIR: &str = "profiles";
+const BACKEND_FILE: &str = "backend.json";
+const WEBVIEW_DIR: &str = "webview";
+const MAX_NAME_LEN: usize = 32;
+
+/// The profile of this run, managed as state.
+pub struct Profile {
+    name: String,
+    data_dir: PathBuf,
+    cache_dir: PathBuf,
+    config_dir: PathBuf,
+}
+
+impl Profile {
+    /// The profile named with [`FLAG`], or the default one.
+    pub fn from_args(app: &AppHandle) -> Result<Self> {
+        let name = requested_name();
+        let path = app.path();
+        let dir = |base: PathBuf| {
+            if name == DEFAULT {
+                base
+            } else {
+                base.join(PROFILES_DIR).join(&name)
+            }
+        };
+        Ok(Profile {
+            data_dir: dir(path.app_data_dir()?),
+            cache_dir: dir(path.app_cache_dir()?),
+            config_dir: dir(path.app_config_dir()?),
+            name,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.name == DEFAULT
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    pub fn config_dir(&self) -> &Path {
+        &self.config_dir
+    }
+
+    /// Where the webview keeps its storage, unless it is the default
+    /// profile's, which stays where the webview puts it.
+    pub fn webview_dir(&self) -> Option<PathBuf> {
+        (!self.is_default()).then(|| self.data_dir.join(WEBVIEW_DIR))
+    }
+
+    /// Names the webview's storage on macOS, which has no directory for it.
+    pub fn webview_store_id(&self) -> [u8; 16] {
+        let hash = Sha256::digest(self.name.as_bytes());
+        let mut id = [0u8; 16];
+        id.copy_from_slice(&hash[..16]);
+        id
+    }
+
+    /// The Firebase project set up for this profile, if any.
+    pub fn backend(&self) -> Result<Option<BackendConfig>> {
+        let path = self.config_dir.join(BACKEND_FILE);
+        match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(|e| Error::InvalidProfile(format!("{}: {e}", path.display()))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Script that tells the webview which profile it runs in, before any of
+    /// the page's own scripts.
+    pub fn script(&self) -> Result<String> {
+        let info = ProfileInfo {
+            name: self.name.clone(),
+            backend: self.backend()?,
+        };
+        Ok(format!(
+            "window.__CHITCHAT_PROFILE__ = {};",
+            serde_json::to_string(&info)?
+        ))
+    }
+}
+
+/// The names of all profiles, the default one first.
+pub fn list(app: &AppHandle) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    match fs::read_dir(app.path().app_data_dir()?.join(PROFILES_DIR)) {
+        Ok(entries) => {
+            for entry in entries.flatten() {
+                let Ok(name) = entry.file_name().into_string() else {
+                    continue;
+                };
+                if entry.path().is_dir() && validate(&name).is_ok() {
+                    names.push(name);
+                }
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    names.sort();
+    names.insert(0, DEFAULT.into());
+    Ok(names)
+}
+
+/// Starts the app again with the profile `name`, which is created if it does
+/// not exist yet.
+pub fn switch(app: &AppHandle, name: &str) -> Result<()> {
+    validate(name)?;
+    if app.state::<Profile>().name() == name {
+        return Ok(());
+    }
+    if name != DEFAULT {
+        fs::create_dir_all(app.path().app_data_dir()?.join(PROFILES_DIR).join(name))?;
+    }
+    tracing::info!(profile = name, "switching profile");
+    Command::new(std::env::current_exe()?)
+        .arg(FLAG)
+        .arg(name)
+        .spawn()?;
+    app.exit(0);
+    Ok(())
+}
+
+/// The name of the profile picked on the command line, or of the default
+/// one if none or an invalid one was.
+pub fn requested_name() -> String {
+    match requested(std::env::args().skip(1)) {
+        Some(name) => match validate(&name) {
+            Ok(()) => name,
+            Err(e) => {
+                tracing::warn!("{e}, using the default profile");
+                DEFAULT.into()
+            }
+        },
+        None => DEFAULT.into(),
+    }
+}
+
+/// The profile named in `args`, as `--profile name` or `--profile=name`.
+fn requested(args: impl IntoIterator<Item = String>) -> Option<String> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == FLAG {
+            return args.next();
+        }
+        if let Some(name) = arg
+            .strip_prefix(FLAG)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(name.to_string());
+        }
+    }
+    None
+}
+
+/// Profile names become directory names, so they are kept to letters,
+/// digits, `-` and `_`.
+fn validate(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidProfile(format!(
+            "{name:?} is not a valid profile name; use up to {MAX_NAME_LEN} letters, digits, - and _"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requested_in(args: &[&str]) -> Option<String> {
+        requested(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn reads_the_profile_from_the_arguments() {
+        assert_eq!(
+            requested_in(&["--profile", "work"]).as_deref(),
+            Some("work")
+        );
+        assert_eq!(requested_in(&["--profile=work"]).as_deref(), Some("work"));
+        assert_eq!(
+            requested_in(&["chitchat://chat/c1", "--profile", "work"]).as_deref(),
+            Some("work")
+        );
+        assert_eq!(requested_in(&["--profile=", "x"]).as_deref(), Some(""));
+        assert_eq!(requested_in(&["--profile"]), None);
+        assert_eq!(requested_in(&["--profiles=work", "work"]), None);
+        assert_eq!(requested_in(&[]), None);
+    }
+
+    #[test]
+    fn accepts_letters_digits_dashes_and_underscores() {
+        for name in ["work", "Work-2", "test_account", &"a".repeat(MAX_NAME_LEN)] {
+            assert!(validate(name).is_ok(), "{name}");
+        }
+    }
+
+    #[test]
+    fn rejects_names_that_are_not_plain_directory_names() {
+        let too_long = "a".repeat(MAX_NAME_LEN + 1);
+        for name in [
+            "",
+            "..",
+            ".",
+            "../work",
+            "work/other",
+            "work\\other",
+            "C:",
+            "work profile",
+            "wörk",
+            &too_long,
+        ] {
+            assert!(
+                matches!(validate(name), Err(Error::InvalidProfile(_))),
+                "{name}"
+            );
+        }
+    }
+}
@@ -0,0 +1,59 @@
+// Client-side generation of RTDB push IDs
+//
+// Same scheme as the Firebase SDKs' `push()`: 8 characters of timestamp
+// followed by 12 random characters, so IDs sort chronologically and never
+// collide across clients. IDs generated within the same millisecond increment
+// the random part to keep them strictly ordered.
+use std::sync::Mutex;
+
+use rand::Rng;
+
+use crate::models::{now, Timestamp};
+
+const PUSH_CHARS: &[u8; 64] = b"-0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ_abcdefghijklmnopqrstuvwxyz";
+
+static LAST: Mutex<(Timestamp, [u8; 12])> = Mutex::new((0, [0; 12]));
+
+/// Generates a new push ID for the current time.
+pub fn generate() -> String {
+    let time = now();
+    let mut last = LAST.lock().unwrap_or_else(|e| e.into_inner());
+
+    if time == last.0 {
+        // Increment the random suffix, carrying over as needed
+        for digit in last.1.iter_mut().rev() {
+            if *digit == 63 {
+                *digit = 0;
+            } else {
+                *digit += 1;
+                break;
+            }
+        }
+    } else {
+        last.0 = time;
+        let mut rng = rand::rng();
+        for digit in last.1.iter_mut() {
+            *digit = rng.random_range(0..64);
+        }
+    }
+
+    let mut id = Vec::with_capacity(20);
+    let mut remaining = time;
+    for _ in 0..8 {
+        id.push(PUSH_CHARS[(remaining % 64) as usize]);
+        remaining /= 64;
+    }
+    id.reverse();
+    id.extend(last.1.iter().map(|&digit| PUSH_CHARS[digit as usize]));
+
+    String::from_utf8(id).expect("push ID alphabet is ASCII")
+}
+
+/// Whether `key` can be used as an RTDB path segment.
+pub fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= 768
+        && !key
+            .chars()
+            .any(|c| matches!(c, '.' | '#' | '$' | '[' | ']' | '/') || c.is_control())
+}
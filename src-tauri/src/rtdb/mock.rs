@@ -0,0 +1,149 @@
+// Mock RTDB server for tests
+//
+// Answers each HTTP/1.1 request from a handler and records it. Streaming
+// responses send their events and then close the connection, which listeners
+// treat like a dropped connection.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    /// Path with the query string, e.g. `/chats/c1.json?ns=test`.
+    pub target: String,
+    /// Header names are lower-case.
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or_default()
+    }
+
+    pub fn query(&self, name: &str) -> Option<String> {
+        let url = reqwest::Url::parse(&format!("http://mock{}", self.target)).ok()?;
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    }
+}
+
+pub enum Response {
+    Json(u16, String),
+    /// `text/event-stream` of `(event, data)` pairs.
+    Events(Vec<(&'static str, String)>),
+}
+
+pub struct Server {
+    /// Base URL of the database, with a `?ns=` parameter like the emulator's.
+    pub url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl Server {
+    /// The requests received so far, oldest first.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Serves on a random local port until the test process ends.
+pub fn serve<F>(handler: F) -> Server
+where
+    F: Fn(&Request) -> Response + Send + Sync + 'static,
+{
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let url = format!("http://{}/?ns=test", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let (handler, recorded) = (Arc::new(handler), requests.clone());
+    tauri::async_runtime::spawn(async move {
+        let listener = TcpListener::from_std(listener).unwrap();
+        while let Ok((stream, _)) = listener.accept().await {
+            let (handler, recorded) = (handler.clone(), recorded.clone());
+            tauri::async_runtime::spawn(async move {
+                let mut stream = stream;
+                let Some(request) = read_request(&mut stream).await else {
+                    return;
+                };
+                let response = handler(&request);
+                recorded.lock().unwrap().push(request);
+                let _ = write_response(&mut stream, response).await;
+            });
+        }
+    });
+    Server { url, requests }
+}
+
+async fn read_request(stream: &mut TcpStream) -> Option<Request> {
+    let mut buffer = Vec::new();
+    let head_end = loop {
+        if let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end;
+        }
+        fill(stream, &mut buffer).await?;
+    };
+    let head = String::from_utf8_lossy(&buffer[..head_end]).into_owned();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split(' ');
+    let (method, target) = (
+        request_line.next()?.to_string(),
+        request_line.next()?.to_string(),
+    );
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let length: usize = headers
+        .get("content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    let body_start = head_end + 4;
+    while buffer.len() < body_start + length {
+        fill(stream, &mut buffer).await?;
+    }
+    let body = String::from_utf8_lossy(&buffer[body_start..body_start + length]).into_owned();
+    Some(Request {
+        method,
+        target,
+        headers,
+        body,
+    })
+}
+
+async fn fill(stream: &mut TcpStream, buffer: &mut Vec<u8>) -> Option<()> {
+    let mut chunk = [0; 4096];
+    let read = stream.read(&mut chunk).await.ok()?;
+    buffer.extend_from_slice(&chunk[..read]);
+    (read > 0).then_some(())
+}
+
+async fn write_response(stream: &mut TcpStream, response: Response) -> std::io::Result<()> {
+    match response {
+        Response::Json(status, body) => {
+            let head = format!(
+                "HTTP/1.1 {status} Mock\r\ncontent-type: application/json\r\n\
+                 content-length: {}\r\nconnection: close\r\n\r\n",
+                body.len()
+            );
+            stream.write_all(head.as_bytes()).await?;
+            stream.write_all(body.as_bytes()).await?;
+        }
+        Response::Events(events) => {
+            let head = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\
+                        connection: close\r\n\r\n";
+            stream.write_all(head.as_bytes()).await?;
+            for (name, data) in events {
+                let event = format!("event: {name}\ndata: {data}\n\n");
+                stream.write_all(event.as_bytes()).await?;
+            }
+        }
+    }
+    stream.shutdown().await
+}
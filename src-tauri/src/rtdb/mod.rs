@@ -0,0 +1,459 @@
+// Firebase Realtime Database client
+//
+// Talks to RTDB over its REST API for reads and writes and over the
+// server-sent events protocol for live listeners. Listeners run on the async
+// runtime rather than in the webview, so they keep going while the window is
+// hidden.
+//
+// The base URL may point at a production database
+// (`https://<db>.firebaseio.com`), the local emulator
+// (`http://127.0.0.1:9000/?ns=<namespace>`) or any mock HTTP server; query
+// parameters on it are kept on every request. Paths are relative to the
+// database root, without the `.json` suffix.
+#[cfg(test)]
+pub(crate) mod mock;
+mod stream;
+mod tree;
+
+use std::sync::Arc;
+
+use reqwest::{Client, Method, RequestBuilder, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::{Serialize, Serializer};
+
+pub use self::stream::{Event, Listener};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("network error: {0}")]
+    Network(reqwest::Error),
+    #[error("server responded {0}")]
+    Status(StatusCode),
+    #[error("invalid database URL {0:?}")]
+    InvalidUrl(String),
+}
+
+// The URL carries the ID token, so it is left out of errors, which end up
+// in logs and the outbox
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Network(e.without_url())
+    }
+}
+
+impl Error {
+    /// Whether the same request may succeed later (offline, server error,
+    /// expired token).
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Network(_) => true,
+            Error::Status(status) => {
+                status.is_server_error()
+                    || *status == StatusCode::UNAUTHORIZED
+                    || *status == StatusCode::REQUEST_TIMEOUT
+                    || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            Error::InvalidUrl(_) => false,
+        }
+    }
+}
+
+/// Supplies the Firebase ID token sent with each request. Asked again on
+/// every request and reconnect, so it can hand out refreshed tokens.
+pub trait TokenSource: Send + Sync + 'static {
+    fn id_token(&self) -> Option<String>;
+}
+
+/// Written in place of a value to have the server fill in its current time
+/// (milliseconds since the epoch), like `serverTimestamp()` in the JS SDK.
+#[derive(Debug, Clone, Copy)]
+pub struct ServerTimestamp;
+
+impl Serialize for ServerTimestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(".sv", "timestamp")?;
+        map.end()
+    }
+}
+
+/// Handle to one database. Cheap to clone; clones share the HTTP client and
+/// token source.
+#[derive(Clone)]
+pub struct Database {
+    http: Client,
+    url: Url,
+    tokens: Option<Arc<dyn TokenSource>>,
+}
+
+impl Database {
+    /// An unauthenticated client for the database at `url`.
+    pub fn new(url: &str) -> Result<Self, Error> {
+        let url = Url::parse(url).map_err(|_| Error::InvalidUrl(url.into()))?;
+        if url.cannot_be_a_base() {
+            return Err(Error::InvalidUrl(url.into()));
+        }
+        Ok(Database {
+            http: Client::new(),
+            url,
+            tokens: None,
+        })
+    }
+
+    /// Shares an existing HTTP client (and its connection pool).
+    pub fn with_client(mut self, http: Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Authenticates every request with whatever token `source` currently
+    /// provides.
+    pub fn with_token_source(mut self, source: impl TokenSource) -> Self {
+        self.tokens = Some(Arc::new(source));
+        self
+    }
+
+    fn url(&self, path: &str) -> Url {
+        let mut url = self.url.clone();
+        {
+            let mut segments = url.path_segments_mut().expect("checked in Database::new");
+            segments.pop_if_empty();
+            let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+            match parts.split_last() {
+                Some((last, parents)) => {
+                    segments.extend(parents);
+                    segments.push(&format!("{last}.json"));
+                }
+                None => {
+                    segments.push(".json");
+                }
+            }
+        }
+        if let Some(token) = self.tokens.as_ref().and_then(|t| t.id_token()) {
+            url.query_pairs_mut().append_pair("auth", &token);
+        }
+        url
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.http.request(method, self.url(path))
+    }
+
+    /// Reads the value at `path`; `None` if nothing is stored there.
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<Option<T>, Error> {
+        let response = self.request(Method::GET, path).send().await?;
+        check(response.status())?;
+        Ok(response.json::<Option<T>>().await?)
+    }
+
+    /// Writes `value` at `path`, replacing what was there.
+    pub async fn set<T: Serialize + ?Sized>(&self, path: &str, value: &T) -> Result<(), Error> {
+        let response = self.request(Method::PUT, path).json(value).send().await?;
+        check(response.status())
+    }
+
+    /// Writes `value` at `path` only if nothing is stored there yet. Returns
+    /// false if the location already had a value.
+    pub async fn create<T: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        value: &T,
+    ) -> Result<bool, Error> {
+        let response = self
+            .request(Method::PUT, path)
+            .header("if-match", "null_etag")
+            .json(value)
+            .send()
+            .await?;
+        if response.status() == StatusCode::PRECONDITION_FAILED {
+            return Ok(false);
+        }
+        check(response.status())?;
+        Ok(true)
+    }
+
+    /// Updates the children of `path` listed in `value`. Keys may be
+    /// slash-separated paths for multi-location updates.
+    pub async fn update<T: Serialize + ?Sized>(&self, path: &str, value: &T) -> Result<(), Error> {
+        let response = self.request(Method::PATCH, path).json(value).send().await?;
+        check(response.status())
+    }
+
+    pub async fn remove(&self, path: &str) -> Result<(), Error> {
+        let response = self.request(Method::DELETE, path).send().await?;
+        check(response.status())
+    }
+
+    /// Calls `callback` with the value at `path` now and after every change,
+    /// like `onValue` in the JS SDK. Reconnects on its own after network
+    /// errors; stops when the returned [`Listener`] is dropped or the server
+    /// cancels the subscription.
+    pub fn listen<F>(&self, path: &str, callback: F) -> Listener
+    where
+        F: FnMut(Event<'_>) + Send + 'static,
+    {
+        stream::spawn(self.clone(), path.to_string(), callback)
+    }
+}
+
+fn check(status: StatusCode) -> Result<(), Error> {
+    if status.is_success() {
+        Ok(())
+    } else {
+        Err(Error::Status(status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use serde_json::{json, Value};
+
+    use super::mock::{self, Response};
+    use super::*;
+
+    /// Hands out `token-1`, `token-2`, ... like a session whose token keeps
+    /// rotating.
+    #[derive(Default)]
+    struct Rotating(AtomicUsize);
+
+    impl TokenSource for Rotating {
+        fn id_token(&self) -> Option<String> {
+            Some(format!(
+                "token-{}",
+                self.0.fetch_add(1, Ordering::Relaxed) + 1
+            ))
+        }
+    }
+
+    struct Fixed;
+
+    impl TokenSource for Fixed {
+        fn id_token(&self) -> Option<String> {
+            Some("secret".into())
+        }
+    }
+
+    fn put(path: &str, data: Value) -> (&'static str, String) {
+        ("put", json!({ "path": path, "data": data }).to_string())
+    }
+
+    fn patch(path: &str, data: Value) -> (&'static str, String) {
+        ("patch", json!({ "path": path, "data": data }).to_string())
+    }
+
+    /// Collects what a listener reports; `None` stands for `Cancelled`.
+    fn collect(db: &Database, path: &str) -> (Listener, mpsc::Receiver<Option<Value>>) {
+        let (tx, rx) = mpsc::channel();
+        let listener = db.listen(path, move |event| {
+            let _ = tx.send(match event {
+                Event::Value(value) => Some(value.clone()),
+                Event::Cancelled => None,
+            });
+        });
+        (listener, rx)
+    }
+
+    fn next(rx: &mpsc::Receiver<Option<Value>>) -> Option<Value> {
+        rx.recv_timeout(Duration::from_secs(10)).expect("no event")
+    }
+
+    #[test]
+    fn rest_requests_keep_the_namespace_and_send_the_token() {
+        let server = mock::serve(|request| match request.method.as_str() {
+            "GET" if request.path() == "/users/u1.json" => {
+                Response::Json(200, r#"{"displayName":"Ada"}"#.into())
+            }
+            _ => Response::Json(200, "null".into()),
+        });
+        let db = Database::new(&server.url).unwrap().with_token_source(Fixed);
+
+        tauri::async_runtime::block_on(async {
+            let user: Option<Value> = db.get("users/u1").await.unwrap();
+            assert_eq!(user, Some(json!({ "displayName": "Ada" })));
+            assert_eq!(db.get::<Value>("/users/u2/").await.unwrap(), None);
+            db.set("users/u1/status", "away").await.unwrap();
+            db.update(
+                "",
+                &json!({ "users/u1/isOnline": false, "chats/c1/updatedAt": ServerTimestamp }),
+            )
+            .await
+            .unwrap();
+            db.remove("users/u1/typing").await.unwrap();
+        });
+
+        let requests = server.requests();
+        let summary: Vec<(&str, &str)> = requests
+            .iter()
+            .map(|r| (r.method.as_str(), r.path()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("GET", "/users/u1.json"),
+                ("GET", "/users/u2.json"),
+                ("PUT", "/users/u1/status.json"),
+                ("PATCH", "/.json"),
+                ("DELETE", "/users/u1/typing.json"),
+            ]
+        );
+        for request in &requests {
+            assert_eq!(request.query("ns").as_deref(), Some("test"));
+            assert_eq!(request.query("auth").as_deref(), Some("secret"));
+        }
+        assert_eq!(requests[2].body, r#""away""#);
+        let update: Value = serde_json::from_str(&requests[3].body).unwrap();
+        assert_eq!(
+            update,
+            json!({ "users/u1/isOnline": false, "chats/c1/updatedAt": { ".sv": "timestamp" } })
+        );
+    }
+
+    #[test]
+    fn create_only_writes_empty_locations() {
+        let server = mock::serve(|request| match request.path() {
+            "/taken.json" => Response::Json(412, "null".into()),
+            _ => Response::Json(200, "1".into()),
+        });
+        let db = Database::new(&server.url).unwrap();
+
+        tauri::async_runtime::block_on(async {
+            assert!(db.create("free", &1).await.unwrap());
+            assert!(!db.create("taken", &1).await.unwrap());
+        });
+        for request in server.requests() {
+            assert_eq!(request.method, "PUT");
+            assert_eq!(
+                request.headers.get("if-match").map(String::as_str),
+                Some("null_etag")
+            );
+            assert_eq!(request.query("auth"), None);
+        }
+    }
+
+    #[test]
+    fn error_statuses_tell_whether_to_retry() {
+        let server = mock::serve(|request| match request.path() {
+            "/expired.json" => Response::Json(401, "{}".into()),
+            "/busy.json" => Response::Json(503, "{}".into()),
+            _ => Response::Json(403, "{}".into()),
+        });
+        let db = Database::new(&server.url).unwrap();
+
+        tauri::async_runtime::block_on(async {
+            for (path, transient) in [("expired", true), ("busy", true), ("denied", false)] {
+                let error = db.get::<Value>(path).await.unwrap_err();
+                assert!(matches!(error, Error::Status(_)), "{path}: {error}");
+                assert_eq!(error.is_transient(), transient, "{path}");
+            }
+        });
+        assert!(matches!(
+            Database::new("not a url"),
+            Err(Error::InvalidUrl(_))
+        ));
+        assert!(matches!(
+            Database::new("mailto:a@b"),
+            Err(Error::InvalidUrl(_))
+        ));
+    }
+
+    #[test]
+    fn network_errors_leave_out_the_token() {
+        // Nothing listens on a port that was just released
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let db = Database::new(&format!("http://127.0.0.1:{port}/"))
+            .unwrap()
+            .with_token_source(Fixed);
+
+        let error = tauri::async_runtime::block_on(db.get::<Value>("users/u1")).unwrap_err();
+        assert!(matches!(error, Error::Network(_)), "{error}");
+        assert!(!error.to_string().contains("secret"), "{error}");
+        assert!(!format!("{error:?}").contains("secret"), "{error:?}");
+    }
+
+    #[test]
+    fn listener_applies_puts_and_patches_until_cancelled() {
+        let server = mock::serve(|_| {
+            Response::Events(vec![
+                put("/", json!({ "a": 1, "b": { "c": 2 } })),
+                ("keep-alive", "null".into()),
+                patch("/b", json!({ "d": 3 })),
+                put("/a", Value::Null),
+                ("cancel", "null".into()),
+            ])
+        });
+        let db = Database::new(&server.url).unwrap();
+        let (_listener, rx) = collect(&db, "chats/c1");
+
+        assert_eq!(next(&rx), Some(json!({ "a": 1, "b": { "c": 2 } })));
+        assert_eq!(next(&rx), Some(json!({ "a": 1, "b": { "c": 2, "d": 3 } })));
+        assert_eq!(next(&rx), Some(json!({ "b": { "c": 2, "d": 3 } })));
+        assert_eq!(next(&rx), None);
+        // The listener is done and has dropped the callback
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(10)),
+            Err(mpsc::RecvTimeoutError::Disconnected)
+        );
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path(), "/chats/c1.json");
+        assert_eq!(
+            requests[0].headers.get("accept").map(String::as_str),
+            Some("text/event-stream")
+        );
+    }
+
+    #[test]
+    fn listener_reconnects_with_a_fresh_token_after_auth_revoked() {
+        let server = mock::serve(|request| {
+            if request.query("auth").as_deref() == Some("token-1") {
+                Response::Events(vec![
+                    put("/", json!({ "n": 1 })),
+                    ("auth_revoked", "\"credential is no longer valid\"".into()),
+                ])
+            } else {
+                Response::Events(vec![put("/", json!({ "n": 2 })), ("cancel", "null".into())])
+            }
+        });
+        let db = Database::new(&server.url)
+            .unwrap()
+            .with_token_source(Rotating::default());
+        let (_listener, rx) = collect(&db, "users/u1");
+
+        assert_eq!(next(&rx), Some(json!({ "n": 1 })));
+        // The new connection starts from scratch with the whole value
+        assert_eq!(next(&rx), Some(json!({ "n": 2 })));
+        assert_eq!(next(&rx), None);
+
+        let tokens: Vec<Option<String>> =
+            server.requests().iter().map(|r| r.query("auth")).collect();
+        assert_eq!(tokens, [Some("token-1".into()), Some("token-2".into())]);
+    }
+
+    #[test]
+    fn dropping_the_listener_stops_it() {
+        let server = mock::serve(|_| Response::Events(vec![put("/", json!(1))]));
+        let db = Database::new(&server.url).unwrap();
+        let (listener, rx) = collect(&db, "x");
+
+        assert_eq!(next(&rx), Some(json!(1)));
+        drop(listener);
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(10)),
+            Err(mpsc::RecvTimeoutError::Disconnected)
+        );
+        // The connection closed after the put; a live listener would have
+        // been back within a second
+        std::thread::sleep(Duration::from_millis(1500));
+        assert_eq!(server.requests().len(), 1);
+    }
+}
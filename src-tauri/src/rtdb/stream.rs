@@ -0,0 +1,215 @@
+// Live listeners over the RTDB streaming protocol (server-sent events)
+//
+// A GET with `Accept: text/event-stream` is answered with a `put` of the whole
+// subtree, followed by `put`/`patch` events for each change and a
+// `keep-alive` every 30 seconds. `auth_revoked` means the ID token expired
+// and `cancel` that the rules no longer allow reading the path.
+use std::mem;
+use std::time::Duration;
+
+use reqwest::header::ACCEPT;
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::Value;
+use tauri::async_runtime::JoinHandle;
+
+use super::{check, tree, Database, Error};
+
+/// Reconnect if not even a keep-alive arrived for this long.
+const STALL_TIMEOUT: Duration = Duration::from_secs(90);
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// What a listener reports to its callback.
+pub enum Event<'a> {
+    /// The current value at the path (`Null` when nothing is stored there).
+    Value(&'a Value),
+    /// The server ended the subscription, e.g. because the security rules
+    /// no longer allow reading the path. No further events follow.
+    Cancelled,
+}
+
+/// A running listener. Dropping it stops the subscription.
+pub struct Listener {
+    task: JoinHandle<()>,
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+enum Outcome {
+    Cancelled,
+    Reconnect,
+}
+
+#[derive(Deserialize)]
+struct Change {
+    path: String,
+    data: Value,
+}
+
+pub(super) fn spawn<F>(db: Database, path: String, mut callback: F) -> Listener
+where
+    F: FnMut(Event<'_>) + Send + 'static,
+{
+    let task = tauri::async_runtime::spawn(async move {
+        let mut delay = MIN_RECONNECT_DELAY;
+        loop {
+            match follow(&db, &path, &mut callback, &mut delay).await {
+                Ok(Outcome::Cancelled) => return,
+                Ok(Outcome::Reconnect) => {}
+                Err(e) => tracing::warn!(path, "listener failed: {e}"),
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    });
+    Listener { task }
+}
+
+/// Follows one connection until it ends.
+async fn follow<F>(
+    db: &Database,
+    path: &str,
+    callback: &mut F,
+    delay: &mut Duration,
+) -> Result<Outcome, Error>
+where
+    F: FnMut(Event<'_>),
+{
+    let mut response = db
+        .request(Method::GET, path)
+        .header(ACCEPT, "text/event-stream")
+        .send()
+        .await?;
+    check(response.status())?;
+
+    let mut parser = Parser::default();
+    let mut value = Value::Null;
+    loop {
+        let Ok(chunk) = tokio::time::timeout(STALL_TIMEOUT, response.chunk()).await else {
+            return Ok(Outcome::Reconnect);
+        };
+        let Some(chunk) = chunk? else {
+            return Ok(Outcome::Reconnect);
+        };
+
+        for event in parser.feed(&chunk) {
+            match event.name.as_str() {
+                "put" | "patch" => {
+                    let change: Change = match serde_json::from_str(&event.data) {
+                        Ok(change) => change,
+                        Err(e) => {
+                            tracing::warn!(path, event = event.name, "malformed event: {e}");
+                            continue;
+                        }
+                    };
+                    match change.data {
+                        Value::Object(children) if event.name == "patch" => {
+                            tree::patch(&mut value, &change.path, children)
+                        }
+                        data => tree::put(&mut value, &change.path, data),
+                    }
+                    callback(Event::Value(&value));
+                    // Only back off again once the server rejects us anew
+                    *delay = MIN_RECONNECT_DELAY;
+                }
+                "cancel" => {
+                    callback(Event::Cancelled);
+                    return Ok(Outcome::Cancelled);
+                }
+                // The token expired; reconnect, which asks the token source
+                // for a fresh one
+                "auth_revoked" => return Ok(Outcome::Reconnect),
+                _ => {}
+            }
+        }
+    }
+}
+
+struct ServerEvent {
+    name: String,
+    data: String,
+}
+
+/// Incremental `text/event-stream` parser.
+#[derive(Default)]
+struct Parser {
+    buffer: Vec<u8>,
+    name: String,
+    data: String,
+}
+
+impl Parser {
+    fn feed(&mut self, chunk: &[u8]) -> Vec<ServerEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                if !self.name.is_empty() || !self.data.is_empty() {
+                    events.push(ServerEvent {
+                        name: mem::take(&mut self.name),
+                        data: mem::take(&mut self.data),
+                    });
+                }
+            } else if let Some(name) = line.strip_prefix("event:") {
+                self.name = name.trim_start().to_string();
+            } else if let Some(data) = line.strip_prefix("data:") {
+                if !self.data.is_empty() {
+                    self.data.push('\n');
+                }
+                self.data.push_str(data.strip_prefix(' ').unwrap_or(data));
+            }
+            // Comments (`:`) and other fields carry nothing we need
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(parser: &mut Parser, chunk: &str) -> Vec<(String, String)> {
+        parser
+            .feed(chunk.as_bytes())
+            .into_iter()
+            .map(|event| (event.name, event.data))
+            .collect()
+    }
+
+    #[test]
+    fn parser_joins_events_split_across_chunks() {
+        let mut parser = Parser::default();
+        assert!(events(&mut parser, "event: put\ndata: {\"pa").is_empty());
+        assert_eq!(
+            events(
+                &mut parser,
+                "th\":\"/\"}\n\nevent: keep-alive\r\ndata: null\r\n\r\n"
+            ),
+            [
+                ("put".into(), r#"{"path":"/"}"#.into()),
+                ("keep-alive".into(), "null".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn parser_joins_data_lines_and_skips_comments() {
+        let mut parser = Parser::default();
+        assert_eq!(
+            events(
+                &mut parser,
+                ": hello\n\nevent:patch\ndata:a\ndata: b\nid: 7\n\n"
+            ),
+            [("patch".into(), "a\nb".into())]
+        );
+    }
+}
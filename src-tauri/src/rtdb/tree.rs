@@ -0,0 +1,78 @@
+// Local copy of a listened-to subtree, kept current from `put`/`patch` events
+use serde_json::{Map, Value};
+
+/// Replaces the value at `path` (relative to `root`). `null` deletes it, and
+/// objects left empty disappear, as they do on the server.
+pub fn put(root: &mut Value, path: &str, data: Value) {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    set(root, &segments, data);
+}
+
+/// Replaces each child listed in `data` below `path`.
+pub fn patch(root: &mut Value, path: &str, data: Map<String, Value>) {
+    for (child, value) in data {
+        put(root, &format!("{path}/{child}"), value);
+    }
+}
+
+fn set(node: &mut Value, segments: &[&str], data: Value) {
+    let Some((first, rest)) = segments.split_first() else {
+        *node = data;
+        return;
+    };
+    if !node.is_object() {
+        if data.is_null() {
+            return;
+        }
+        *node = Value::Object(Map::new());
+    }
+    let Value::Object(children) = node else {
+        unreachable!()
+    };
+
+    let child = children.entry(*first).or_insert(Value::Null);
+    set(child, rest, data);
+    if child.is_null() {
+        children.remove(*first);
+    }
+    if children.is_empty() {
+        *node = Value::Null;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn put_replaces_and_creates_nested_values() {
+        let mut root = Value::Null;
+        put(&mut root, "/", json!({ "a": 1 }));
+        put(&mut root, "/b/c", json!(2));
+        put(&mut root, "a", json!({ "x": true }));
+        assert_eq!(root, json!({ "a": { "x": true }, "b": { "c": 2 } }));
+    }
+
+    #[test]
+    fn null_removes_values_and_empty_parents() {
+        let mut root = json!({ "a": { "b": { "c": 1 } }, "d": 2 });
+        put(&mut root, "/a/b/c", Value::Null);
+        assert_eq!(root, json!({ "d": 2 }));
+        put(&mut root, "/missing/child", Value::Null);
+        assert_eq!(root, json!({ "d": 2 }));
+        put(&mut root, "/d", Value::Null);
+        assert_eq!(root, Value::Null);
+    }
+
+    #[test]
+    fn patch_only_touches_listed_children() {
+        let mut root = json!({ "chat": { "lastMessage": "hi", "typing": { "u1": true } } });
+        let Value::Object(changes) = json!({ "lastMessage": "bye", "typing/u1": null }) else {
+            unreachable!()
+        };
+        patch(&mut root, "/chat", changes);
+        assert_eq!(root, json!({ "chat": { "lastMessage": "bye" } }));
+    }
+}
@@ -0,0 +1,289 @@
+// Full-text search over locally cached messages
+//
+// Uses an FTS5 index (`messages_fts`) that triggers keep in sync with the
+// `messages` table, so anything the store has cached is searchable.
+mod query;
+
+use rusqlite::types::Value;
+
+use crate::error::Result;
+use crate::models::{SearchHit, SnippetPart};
+use crate::store::Store;
+
+pub use self::query::SearchQuery;
+
+/// Markers wrapped around matches by `snippet()`. Control characters never
+/// occur in message text, so they are safe to split on.
+const MATCH_START: char = '\u{2}';
+const MATCH_END: char = '\u{3}';
+/// Approximate number of words in a snippet.
+const SNIPPET_TOKENS: u32 = 16;
+
+/// Runs `query` and returns up to `limit` hits, best match first. Queries
+/// with only filters return the newest matching messages.
+pub fn search(store: &Store, query: &SearchQuery, limit: u32) -> Result<Vec<SearchHit>> {
+    let mut params: Vec<Value> = Vec::new();
+    let mut conditions: Vec<String> = Vec::new();
+
+    let fts = query.fts_expression();
+    let (source, snippet, order) = match &fts {
+        Some(expression) => {
+            params.push(Value::Text(expression.clone()));
+            conditions.push(format!("messages_fts MATCH ?{}", params.len()));
+            (
+                "messages_fts JOIN messages m ON m.seq = messages_fts.rowid",
+                format!(
+                    "snippet(messages_fts, 0, '{MATCH_START}', '{MATCH_END}', '…', {SNIPPET_TOKENS})"
+                ),
+                // bm25 is lower-is-better; recency breaks ties
+                "bm25(messages_fts), m.timestamp DESC",
+            )
+        }
+        None => ("messages m", "m.text".to_string(), "m.timestamp DESC"),
+    };
+
+    if let Some(from) = &query.from {
+        params.push(Value::Text(from.clone()));
+        let exact = params.len();
+        params.push(Value::Text(like_pattern(from)));
+        let pattern = params.len();
+        conditions.push(format!(
+            "(m.sender_id = ?{exact}
+              OR m.sender_name LIKE ?{pattern} ESCAPE '\\'
+              OR m.sender_id IN (SELECT id FROM users
+                                 WHERE email LIKE ?{pattern} ESCAPE '\\'
+                                    OR display_name LIKE ?{pattern} ESCAPE '\\'))"
+        ));
+    }
+    if let Some(chat) = &query.chat {
+        params.push(Value::Text(chat.clone()));
+        let exact = params.len();
+        params.push(Value::Text(like_pattern(chat)));
+        let pattern = params.len();
+        conditions.push(format!(
+            "(m.chat_id = ?{exact}
+              OR c.group_name LIKE ?{pattern} ESCAPE '\\'
+              OR EXISTS (SELECT 1 FROM json_each(c.participant_names)
+                         WHERE json_each.value LIKE ?{pattern} ESCAPE '\\'))"
+        ));
+    }
+    if let Some(before) = query.before {
+        params.push(Value::Integer(before));
+        conditions.push(format!("m.timestamp < ?{}", params.len()));
+    }
+    if let Some(after) = query.after {
+        params.push(Value::Integer(after));
+        conditions.push(format!("m.timestamp >= ?{}", params.len()));
+    }
+
+    if conditions.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    params.push(Value::Integer(i64::from(limit)));
+    let sql = format!(
+        "SELECT m.chat_id, m.id, m.sender_id, m.sender_name, c.group_name, m.timestamp, {snippet}
+         FROM {source}
+         LEFT JOIN chats c ON c.id = m.chat_id
+         WHERE {}
+         ORDER BY {order}
+         LIMIT ?{}",
+        conditions.join(" AND "),
+        params.len(),
+    );
+
+    let conn = store.conn();
+    let mut stmt = conn.prepare(&sql)?;
+    let hits = stmt
+        .query_map(rusqlite::params_from_iter(params), |row| {
+            let snippet: String = row.get(6)?;
+            Ok(SearchHit {
+                chat_id: row.get(0)?,
+                message_id: row.get(1)?,
+                sender_id: row.get(2)?,
+                sender_name: row.get(3)?,
+                chat_name: row.get(4)?,
+                timestamp: row.get(5)?,
+                snippet: if fts.is_some() {
+                    split_snippet(&snippet)
+                } else {
+                    plain_snippet(&snippet)
+                },
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(hits)
+}
+
+/// A `LIKE` pattern matching `value` anywhere, with wildcards escaped.
+fn like_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+/// Splits a marked-up `snippet()` result into highlighted and plain runs.
+fn split_snippet(snippet: &str) -> Vec<SnippetPart> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut highlight = false;
+
+    for c in snippet.chars() {
+        if c == MATCH_START || c == MATCH_END {
+            if !current.is_empty() {
+                parts.push(SnippetPart {
+                    text: std::mem::take(&mut current),
+                    highlight,
+                });
+            }
+            highlight = c == MATCH_START;
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        parts.push(SnippetPart {
+            text: current,
+            highlight,
+        });
+    }
+
+    parts
+}
+
+/// The opening words of a message, for filter-only searches.
+fn plain_snippet(text: &str) -> Vec<SnippetPart> {
+    let mut words = text.split_whitespace();
+    let mut preview = words
+        .by_ref()
+        .take(SNIPPET_TOKENS as usize)
+        .collect::<Vec<_>>()
+        .join(" ");
+    if words.next().is_some() {
+        preview.push('…');
+    }
+    vec![SnippetPart {
+        text: preview,
+        highlight: false,
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::models::{Chat, Message, User};
+
+    fn message(id: &str, sender_id: &str, text: &str, timestamp: i64) -> Message {
+        Message {
+            id: id.into(),
+            sender_id: sender_id.into(),
+            sender_name: None,
+            text: text.into(),
+            timestamp,
+            archived: None,
+            attachment: None,
+        }
+    }
+
+    /// A store with a direct chat and a group chat.
+    fn store() -> Store {
+        let store = Store::open_in_memory();
+        let user: User = serde_json::from_value(json!({
+            "id": "alice",
+            "email": "ada@example.com",
+            "displayName": "Ada Lovelace",
+        }))
+        .unwrap();
+        store.upsert_users(&[user]).unwrap();
+        let chats: Vec<Chat> = serde_json::from_value(json!([
+            { "id": "direct", "participants": { "alice": true, "me": true } },
+            {
+                "id": "group",
+                "participants": { "alice": true, "bob": true, "me": true },
+                "isGroup": true,
+                "groupName": "Weekend plans",
+            },
+        ]))
+        .unwrap();
+        store.upsert_chats(&chats).unwrap();
+        store
+            .upsert_messages(
+                "direct",
+                &[
+                    message("d1", "alice", "Dinner at eight?", 1_000),
+                    message("d2", "me", "Dinner sounds good", 2_000),
+                ],
+            )
+            .unwrap();
+        store
+            .upsert_messages(
+                "group",
+                &[
+                    message("g1", "bob", "Hiking on Saturday, then dinner", 3_000),
+                    message("g2", "alice", "100% in", 4_000),
+                ],
+            )
+            .unwrap();
+        store
+    }
+
+    fn ids(store: &Store, input: &str) -> Vec<String> {
+        search(store, &SearchQuery::parse(input, 0), 10)
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.message_id)
+            .collect()
+    }
+
+    #[test]
+    fn finds_terms_by_prefix_with_highlighted_snippets() {
+        let store = store();
+        let mut found = ids(&store, "dinn");
+        found.sort();
+        assert_eq!(found, ["d1", "d2", "g1"]);
+
+        let hits = search(&store, &SearchQuery::parse("hik", 0), 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].chat_name.as_deref(), Some("Weekend plans"));
+        let snippet: Vec<(&str, bool)> = hits[0]
+            .snippet
+            .iter()
+            .map(|part| (part.text.as_str(), part.highlight))
+            .collect();
+        assert_eq!(
+            snippet,
+            [("Hiking", true), (" on Saturday, then dinner", false)]
+        );
+    }
+
+    #[test]
+    fn phrases_match_words_in_order() {
+        let store = store();
+        assert_eq!(ids(&store, "\"dinner sounds\""), ["d2"]);
+        assert!(ids(&store, "\"sounds dinner\"").is_empty());
+    }
+
+    #[test]
+    fn filters_narrow_the_results() {
+        let store = store();
+        assert_eq!(ids(&store, "dinner from:alice"), ["d1"]);
+        assert_eq!(ids(&store, "dinner from:lovelace"), ["d1"]);
+        assert_eq!(ids(&store, "dinner from:ada@example"), ["d1"]);
+        assert_eq!(ids(&store, "dinner in:weekend"), ["g1"]);
+        assert_eq!(ids(&store, "in:group"), ["g2", "g1"]);
+        // Newest first without terms
+        assert_eq!(ids(&store, "from:alice"), ["g2", "d1"]);
+    }
+
+    #[test]
+    fn user_input_is_not_fts_syntax() {
+        let store = store();
+        assert_eq!(ids(&store, "100%"), ["g2"]);
+        assert!(ids(&store, "dinner OR hiking").is_empty());
+        assert!(ids(&store, "from:%").is_empty());
+    }
+}
@@ -0,0 +1,187 @@
+// Parser for the search box syntax
+//
+// Free text is matched against message text; these filters narrow it down:
+//   from:alice            sender name, email or user ID
+//   in:"Weekend plans"    chat ID, group name or participant name
+//   before:2024-05-01     messages sent before that day
+//   after:2024-04-01      messages sent on or after that day
+// Double quotes group words into a phrase, for filters as well as free text.
+use crate::civil::{days_from_civil, days_in_month};
+use crate::models::Timestamp;
+
+const MS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct SearchQuery {
+    /// Words and phrases that must all appear in the message.
+    pub terms: Vec<String>,
+    pub from: Option<String>,
+    pub chat: Option<String>,
+    pub before: Option<Timestamp>,
+    pub after: Option<Timestamp>,
+}
+
+impl SearchQuery {
+    /// Parses `input`. Dates are interpreted in the caller's time zone, given
+    /// as minutes east of UTC. Filters with an unparsable value are treated
+    /// as ordinary search terms.
+    pub fn parse(input: &str, utc_offset_minutes: i32) -> Self {
+        let mut query = SearchQuery::default();
+
+        for token in tokenize(input) {
+            let Some((key, value)) = token.split_once(':').filter(|(_, v)| !v.is_empty()) else {
+                query.terms.push(token);
+                continue;
+            };
+            let value = value.to_string();
+            match key.to_ascii_lowercase().as_str() {
+                "from" => query.from = Some(value),
+                "in" => query.chat = Some(value),
+                "before" => match parse_day(&value, utc_offset_minutes) {
+                    Some(start) => query.before = Some(start),
+                    None => query.terms.push(token),
+                },
+                "after" => match parse_day(&value, utc_offset_minutes) {
+                    Some(start) => query.after = Some(start),
+                    None => query.terms.push(token),
+                },
+                _ => query.terms.push(token),
+            }
+        }
+
+        query
+    }
+
+    /// The terms as an FTS5 match expression: every term is quoted (so user
+    /// input cannot inject FTS syntax) and prefix-matched, and all must match.
+    pub fn fts_expression(&self) -> Option<String> {
+        if self.terms.is_empty() {
+            return None;
+        }
+        let expression = self
+            .terms
+            .iter()
+            .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(" ");
+        Some(expression)
+    }
+}
+
+/// Splits on whitespace, keeping double-quoted runs together and dropping
+/// the quotes (`in:"Team chat"` becomes `in:Team chat`).
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+/// Start of the day `YYYY-MM-DD` in the given UTC offset, in epoch millis,
+/// or `None` if there is no such day.
+fn parse_day(value: &str, utc_offset_minutes: i32) -> Option<Timestamp> {
+    let mut parts = value.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    if day == 0 || day > days_in_month(year, month)? {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    Some(days * MS_PER_DAY - i64::from(utc_offset_minutes) * 60 * 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAY_1: Timestamp = 1_714_521_600_000;
+    const APRIL_1: Timestamp = 1_711_929_600_000;
+
+    #[test]
+    fn parses_filters_and_terms() {
+        assert_eq!(
+            SearchQuery::parse(
+                "dinner from:alice In:\"Weekend plans\" before:2024-05-01 after:2024-04-01 tonight",
+                0
+            ),
+            SearchQuery {
+                terms: vec!["dinner".into(), "tonight".into()],
+                from: Some("alice".into()),
+                chat: Some("Weekend plans".into()),
+                before: Some(MAY_1),
+                after: Some(APRIL_1),
+            }
+        );
+    }
+
+    #[test]
+    fn quotes_group_phrases() {
+        let query = SearchQuery::parse("\"see you  soon\" from:\"Ada Lovelace\" ok", 0);
+        assert_eq!(query.terms, ["see you  soon", "ok"]);
+        assert_eq!(query.from.as_deref(), Some("Ada Lovelace"));
+        // An unclosed quote runs to the end
+        assert_eq!(SearchQuery::parse("\"half open", 0).terms, ["half open"]);
+    }
+
+    #[test]
+    fn days_start_at_the_utc_offset() {
+        let query = SearchQuery::parse("after:2024-05-01", 120);
+        assert_eq!(query.after, Some(MAY_1 - 2 * 60 * 60 * 1000));
+        let query = SearchQuery::parse("before:2024-02-29", -300);
+        assert_eq!(query.before, Some(1_709_164_800_000 + 5 * 60 * 60 * 1000));
+    }
+
+    #[test]
+    fn invalid_filters_are_search_terms() {
+        let query = SearchQuery::parse(
+            "before:2024-02-31 after:2023-02-29 before:2024-13-01 after:yesterday from: to:bob",
+            0,
+        );
+        assert_eq!(
+            query,
+            SearchQuery {
+                terms: vec![
+                    "before:2024-02-31".into(),
+                    "after:2023-02-29".into(),
+                    "before:2024-13-01".into(),
+                    "after:yesterday".into(),
+                    "from:".into(),
+                    "to:bob".into(),
+                ],
+                ..SearchQuery::default()
+            }
+        );
+    }
+
+    #[test]
+    fn fts_expressions_quote_every_term() {
+        assert_eq!(SearchQuery::parse("from:alice", 0).fts_expression(), None);
+        assert_eq!(
+            SearchQuery::parse("din \"say \"\"hi\" OR NEAR(a)", 0).fts_expression(),
+            Some("\"din\"* \"say hi\"* \"OR\"* \"NEAR(a)\"*".into())
+        );
+        let query = SearchQuery {
+            terms: vec!["a\"b".into()],
+            ..SearchQuery::default()
+        };
+        assert_eq!(query.fts_expression(), Some("\"a\"\"b\"*".into()));
+    }
+}
@@ -0,0 +1,105 @@
+// Backend session shared by the Rust-side network subsystems
+//
+// Firebase Auth lives in the webview; it hands us the database URL and a fresh
+// ID token whenever the token rotates so Rust can talk to RTDB on the user's
+// behalf. Database clients read the token from here on every request, so
+// long-running listeners pick up rotated tokens when they reconnect.
+use std::sync::{Arc, RwLock};
+
+use crate::error::{Error, Result};
+use crate::rtdb::{Database, TokenSource};
+
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub database_url: String,
+    pub user_id: String,
+    pub id_token: String,
+    /// Firebase Storage bucket for attachments, e.g. `project.appspot.com`.
+    pub storage_bucket: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Session {
+    credentials: Arc<RwLock<Option<Credentials>>>,
+}
+
+impl Session {
+    pub fn credentials(&self) -> Option<Credentials> {
+        read(&self.credentials).clone()
+    }
+
+    pub fn set(&self, credentials: Option<Credentials>) {
+        *self.credentials.write().unwrap_or_else(|e| e.into_inner()) = credentials;
+    }
+
+    /// A client for the signed-in user's database, authenticated with
+    /// whatever token the session holds at the time of each request.
+    pub fn database(&self, http: &reqwest::Client) -> Result<Database> {
+        let url = read(&self.credentials)
+            .as_ref()
+            .map(|credentials| credentials.database_url.clone())
+            .ok_or(Error::SignedOut)?;
+        Ok(Database::new(&url)?
+            .with_client(http.clone())
+            .with_token_source(Tokens(self.credentials.clone())))
+    }
+}
+
+fn read(
+    credentials: &RwLock<Option<Credentials>>,
+) -> std::sync::RwLockReadGuard<'_, Option<Credentials>> {
+    credentials.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The current ID token of a [`Session`].
+struct Tokens(Arc<RwLock<Option<Credentials>>>);
+
+impl TokenSource for Tokens {
+    fn id_token(&self) -> Option<String> {
+        read(&self.0)
+            .as_ref()
+            .map(|credentials| credentials.id_token.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rtdb::mock::{self, Response};
+
+    use super::*;
+
+    fn sign_in(session: &Session, database_url: &str, id_token: &str) {
+        session.set(Some(Credentials {
+            database_url: database_url.into(),
+            user_id: "u1".into(),
+            id_token: id_token.into(),
+            storage_bucket: None,
+        }));
+    }
+
+    #[test]
+    fn database_needs_a_session() {
+        let session = Session::default();
+        let result = session.database(&reqwest::Client::new());
+        assert!(matches!(result, Err(Error::SignedOut)));
+    }
+
+    #[test]
+    fn database_sends_the_latest_token() {
+        let server = mock::serve(|_| Response::Json(200, "null".into()));
+        let session = Session::default();
+        sign_in(&session, &server.url, "first");
+        let db = session.database(&reqwest::Client::new()).unwrap();
+
+        tauri::async_runtime::block_on(async {
+            db.get::<()>("a").await.unwrap();
+            sign_in(&session, &server.url, "rotated");
+            db.get::<()>("a").await.unwrap();
+            session.set(None);
+            db.get::<()>("a").await.unwrap();
+        });
+        let tokens: Vec<Option<String>> =
+            server.requests().iter().map(|r| r.query("auth")).collect();
+        assert_eq!(tokens, [Some("first".into()), Some("rotated".into()), None]);
+    }
+}
@@ -0,0 +1,91 @@
+// Settings migrations
+//
+// `MIGRATIONS[n]` turns a version `n + 1` file into a version `n + 2` one. They
+// work on the raw JSON, so they can still read settings that `Settings` no
+// longer has. Adding a setting needs no migration, since missing fields take
+// their defaults; renaming, moving or reinterpreting one does. Append a
+// migration for that, which also bumps [`VERSION`], and never change one that
+// has shipped.
+use serde_json::{Map, Value};
+
+type Migration = fn(&mut Map<String, Value>);
+
+/// Version 1 is the first versioned format, so nothing needs migrating yet.
+const MIGRATIONS: &[Migration] = &[];
+
+/// The version settings are written as.
+pub const VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+/// Brings `settings`, written as `version`, up to [`VERSION`].
+pub fn run(settings: &mut Map<String, Value>, version: u32) {
+    apply(MIGRATIONS, settings, version);
+}
+
+fn apply(migrations: &[Migration], settings: &mut Map<String, Value>, version: u32) {
+    let first = version.saturating_sub(1) as usize;
+    for migration in migrations.iter().skip(first) {
+        migration(settings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Version 2 renamed `volume` to `soundVolume`.
+    fn rename_volume(settings: &mut Map<String, Value>) {
+        if let Some(volume) = settings.remove("volume") {
+            settings.insert("soundVolume".into(), volume);
+        }
+    }
+
+    /// Version 3 moved `soundVolume` into `sounds`.
+    fn nest_volume(settings: &mut Map<String, Value>) {
+        if let Some(volume) = settings.remove("soundVolume") {
+            settings.insert("sounds".into(), json!({ "volume": volume }));
+        }
+    }
+
+    const SAMPLE: &[Migration] = &[rename_volume, nest_volume];
+
+    fn migrate(settings: Value, version: u32) -> Value {
+        let Value::Object(mut settings) = settings else {
+            panic!("not an object");
+        };
+        apply(SAMPLE, &mut settings, version);
+        Value::Object(settings)
+    }
+
+    #[test]
+    fn runs_the_migrations_after_the_version() {
+        let expected = json!({ "theme": "dark", "sounds": { "volume": 0.3 } });
+        assert_eq!(
+            migrate(json!({ "theme": "dark", "volume": 0.3 }), 1),
+            expected
+        );
+        assert_eq!(
+            migrate(json!({ "theme": "dark", "soundVolume": 0.3 }), 2),
+            expected
+        );
+        assert_eq!(migrate(expected.clone(), 3), expected);
+    }
+
+    #[test]
+    fn unversioned_files_count_as_the_first_version() {
+        assert_eq!(
+            migrate(json!({ "volume": 1 }), 0),
+            json!({ "sounds": { "volume": 1 } })
+        );
+    }
+
+    #[test]
+    fn current_version_counts_the_migrations() {
+        assert_eq!(VERSION as usize, MIGRATIONS.len() + 1);
+        let mut settings = Map::new();
+        settings.insert("theme".into(), "light".into());
+        run(&mut settings, VERSION);
+        assert_eq!(Value::Object(settings), json!({ "theme": "light" }));
+    }
+}
@@ -0,0 +1,373 @@
+// Settings
+//
+// User preferences live in `settings.json` in the app config directory: the
+// fields of [`Settings`] next to the `version` they were written as. Files
+// from older versions go through the migrations in `migrations.rs` when they
+// are loaded, after a copy of the original is put aside. A file that cannot
+// be read at all is put aside too, and the defaults are used instead.
+//
+// The webview reads the settings with `get_settings` and changes them with
+// `update_settings`; every change is announced with a `settings-changed`
+// event, so all windows stay in sync.
+mod migrations;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::{Error, Result};
+use crate::models::Settings;
+use crate::push_id;
+
+pub use migrations::VERSION;
+
+/// Event emitted with the new settings whenever they change.
+pub const CHANGED_EVENT: &str = "settings-changed";
+
+const FILE_NAME: &str = "settings.json";
+const VERSION_KEY: &str = "version";
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+/// The current settings, managed as state.
+pub struct SettingsStore {
+    path: PathBuf,
+    current: Mutex<Settings>,
+}
+
+impl SettingsStore {
+    /// Loads the settings file in `dir`, or starts from the defaults if there
+    /// is none.
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(FILE_NAME);
+        let settings = match fs::read(&path) {
+            Ok(bytes) => load(&path, &bytes).or_else(|e| {
+                let aside = put_aside(&path, "invalid")?;
+                tracing::warn!(
+                    "unreadable settings copied to {}, using defaults: {e}",
+                    aside.display()
+                );
+                Ok::<_, Error>(Settings::default())
+            })?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Settings::default(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(SettingsStore {
+            path,
+            current: Mutex::new(settings),
+        })
+    }
+
+    pub fn get(&self) -> Settings {
+        self.lock().clone()
+    }
+
+    /// Applies `changes`, an object with the settings to change, and saves
+    /// the result. Nested objects are merged, so `{"sounds": {"volume": 1}}`
+    /// leaves `sounds.enabled` as it is. Returns the new settings if anything
+    /// changed.
+    fn update(&self, changes: Value) -> Result<Option<Settings>> {
+        let mut current = self.lock();
+        let mut value = serde_json::to_value(&*current)?;
+        merge(&mut value, changes, "")?;
+        let settings: Settings =
+            serde_json::from_value(value).map_err(|e| Error::InvalidSettings(e.to_string()))?;
+        validate(&settings)?;
+        if settings == *current {
+            return Ok(None);
+        }
+        save(&self.path, &settings)?;
+        *current = settings.clone();
+        Ok(Some(settings))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Settings> {
+        self.current.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Changes the settings (see [`SettingsStore::update`]) and tells the
+/// webview.
+pub fn update(app: &AppHandle, changes: Value) -> Result<Settings> {
+    let store = app.state::<SettingsStore>();
+    let Some(settings) = store.update(changes)? else {
+        return Ok(store.get());
+    };
+    tracing::info!("settings changed");
+    if let Err(e) = app.emit(CHANGED_EVENT, &settings) {
+        tracing::warn!("failed to emit change event: {e}");
+    }
+    Ok(settings)
+}
+
+fn load(path: &Path, bytes: &[u8]) -> Result<Settings> {
+    let mut settings: Map<String, Value> = serde_json::from_slice(bytes)?;
+    // Files without a version were written by hand
+    let version = match settings.remove(VERSION_KEY) {
+        Some(version) => serde_json::from_value(version)?,
+        None => VERSION,
+    };
+    if version < VERSION {
+        let aside = put_aside(path, &format!("v{version}"))?;
+        tracing::info!(
+            "migrating settings from version {version} to {VERSION}, original kept as {}",
+            aside.display()
+        );
+        migrations::run(&mut settings, version);
+    } else if version > VERSION {
+        // Read what is understood; the rest is lost once something changes
+        tracing::warn!("settings were written by a newer version ({version})");
+    }
+    let settings: Settings = serde_json::from_value(Value::Object(settings))?;
+    validate(&settings)?;
+    Ok(settings)
+}
+
+fn save(path: &Path, settings: &Settings) -> Result<()> {
+    let mut value = serde_json::to_value(settings)?;
+    if let Value::Object(map) = &mut value {
+        map.insert(VERSION_KEY.into(), VERSION.into());
+    }
+    // Write a copy and swap it in, so a crash never leaves half a file
+    let partial = path.with_extension("json.partial");
+    fs::write(&partial, serde_json::to_vec_pretty(&value)?)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// Copies `path` to `settings.<suffix>.json` next to it.
+fn put_aside(path: &Path, suffix: &str) -> Result<PathBuf> {
+    let aside = path.with_extension(format!("{suffix}.json"));
+    fs::copy(path, &aside)?;
+    Ok(aside)
+}
+
+/// Merges `changes` into `target`, rejecting settings that do not exist.
+fn merge(target: &mut Value, changes: Value, prefix: &str) -> Result<()> {
+    let (Value::Object(target), Value::Object(changes)) = (target, changes) else {
+        return Err(Error::InvalidSettings(format!(
+            "expected an object of settings{}",
+            if prefix.is_empty() {
+                String::new()
+            } else {
+                format!(" for {prefix}")
+            }
+        )));
+    };
+    for (key, change) in changes {
+        let name = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        let Some(value) = target.get_mut(&key) else {
+            return Err(Error::InvalidSettings(format!("unknown setting {name}")));
+        };
+        if value.is_object() {
+            merge(value, change, &name)?;
+        } else {
+            *value = change;
+        }
+    }
+    Ok(())
+}
+
+fn validate(settings: &Settings) -> Result<()> {
+    if !(0.0..=1.0).contains(&settings.sounds.volume) {
+        return Err(Error::InvalidSettings(
+            "sounds.volume must be between 0 and 1".into(),
+        ));
+    }
+    let notifications = &settings.notifications;
+    if notifications
+        .quiet_hours
+        .iter()
+        .any(|period| period.start >= MINUTES_PER_DAY || period.end >= MINUTES_PER_DAY)
+    {
+        return Err(Error::InvalidSettings(
+            "notifications.quietHours must start and end within the day".into(),
+        ));
+    }
+    if notifications
+        .keywords
+        .iter()
+        .any(|keyword| keyword.trim().is_empty())
+    {
+        return Err(Error::InvalidSettings(
+            "notifications.keywords must not be empty".into(),
+        ));
+    }
+    if let Some(mute) = notifications
+        .muted_chats
+        .iter()
+        .find(|mute| !push_id::is_valid_key(&mute.chat_id))
+    {
+        return Err(Error::InvalidSettings(format!(
+            "notifications.mutedChats has an invalid chat ID {:?}",
+            mute.chat_id
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::models::{ChatMute, QuietHours, Theme};
+    use crate::temp_dir::TempDir;
+
+    fn open_with(dir: &Path, contents: &str) -> Settings {
+        fs::write(dir.join(FILE_NAME), contents).unwrap();
+        SettingsStore::open(dir).unwrap().get()
+    }
+
+    #[test]
+    fn missing_file_gives_the_defaults() {
+        let dir = TempDir::new();
+        assert_eq!(
+            SettingsStore::open(&dir).unwrap().get(),
+            Settings::default()
+        );
+    }
+
+    #[test]
+    fn unknown_keys_and_missing_fields_are_tolerated() {
+        let dir = TempDir::new();
+        let settings = open_with(
+            &dir,
+            r#"{ "version": 1, "theme": "dark", "sounds": { "volume": 0.2 }, "gone": true }"#,
+        );
+        assert_eq!(settings.theme, Theme::Dark);
+        assert_eq!(settings.sounds.volume, 0.2);
+        assert!(settings.sounds.enabled);
+    }
+
+    #[test]
+    fn unversioned_and_newer_files_are_read_as_they_are() {
+        let dir = TempDir::new();
+        assert_eq!(
+            open_with(&dir, r#"{ "theme": "light" }"#).theme,
+            Theme::Light
+        );
+        let newer = format!(r#"{{ "version": {}, "theme": "dark" }}"#, VERSION + 1);
+        assert_eq!(open_with(&dir, &newer).theme, Theme::Dark);
+        assert!(
+            fs::read_dir(&*dir).unwrap().count() == 1,
+            "nothing is put aside"
+        );
+    }
+
+    #[test]
+    fn older_files_are_copied_before_migrating() {
+        let dir = TempDir::new();
+        let original = r#"{ "version": 0, "theme": "dark" }"#;
+        assert_eq!(open_with(&dir, original).theme, Theme::Dark);
+        assert_eq!(
+            fs::read_to_string(dir.join("settings.v0.json")).unwrap(),
+            original
+        );
+    }
+
+    #[test]
+    fn invalid_files_are_put_aside_for_the_defaults() {
+        for contents in [
+            "not json",
+            r#"{ "theme": "purple" }"#,
+            r#"{ "sounds": { "volume": 2 } }"#,
+        ] {
+            let dir = TempDir::new();
+            assert_eq!(open_with(&dir, contents), Settings::default(), "{contents}");
+            assert_eq!(
+                fs::read_to_string(dir.join("settings.invalid.json")).unwrap(),
+                contents
+            );
+        }
+    }
+
+    #[test]
+    fn updates_merge_nested_settings_and_are_saved() {
+        let dir = TempDir::new();
+        let store = SettingsStore::open(&dir).unwrap();
+        let updated = store
+            .update(json!({ "sounds": { "volume": 1 } }))
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.sounds.volume, 1.0);
+        assert!(updated.sounds.enabled);
+        assert!(store
+            .update(json!({ "sounds": { "volume": 1 } }))
+            .unwrap()
+            .is_none());
+
+        let saved: Value = serde_json::from_slice(&fs::read(dir.join(FILE_NAME)).unwrap()).unwrap();
+        assert_eq!(saved[VERSION_KEY], VERSION);
+        assert_eq!(SettingsStore::open(&dir).unwrap().get(), updated);
+    }
+
+    #[test]
+    fn the_tray_mute_is_kept_until_cleared() {
+        let dir = TempDir::new();
+        let store = SettingsStore::open(&dir).unwrap();
+        let reopened = || SettingsStore::open(&dir).unwrap().get();
+        store
+            .update(json!({ "notifications": { "mutedUntil": 5 } }))
+            .unwrap();
+        assert_eq!(reopened().notifications.muted_until, Some(5));
+        store
+            .update(json!({ "notifications": { "mutedUntil": null } }))
+            .unwrap();
+        assert_eq!(reopened().notifications.muted_until, None);
+    }
+
+    #[test]
+    fn unknown_or_invalid_updates_are_rejected() {
+        let dir = TempDir::new();
+        let store = SettingsStore::open(&dir).unwrap();
+        for changes in [
+            json!({ "colour": "red" }),
+            json!({ "sounds": { "pitch": 1 } }),
+            json!({ "sounds": 1 }),
+            json!({ "sounds": { "volume": -0.1 } }),
+            json!({ "theme": "purple" }),
+        ] {
+            assert!(
+                matches!(
+                    store.update(changes.clone()),
+                    Err(Error::InvalidSettings(_))
+                ),
+                "{changes}"
+            );
+        }
+        assert_eq!(store.get(), Settings::default());
+        assert!(!dir.join(FILE_NAME).exists());
+    }
+
+    #[test]
+    fn validation_checks_ranges_and_keys() {
+        let mut settings = Settings::default();
+        settings.notifications.quiet_hours = vec![QuietHours {
+            days: Vec::new(),
+            start: 22 * 60,
+            end: MINUTES_PER_DAY,
+        }];
+        assert!(validate(&settings).is_err());
+        settings.notifications.quiet_hours[0].end = 7 * 60;
+        assert!(validate(&settings).is_ok());
+
+        settings.notifications.keywords = vec![" ".into()];
+        assert!(validate(&settings).is_err());
+        settings.notifications.keywords = vec!["release".into()];
+
+        settings.notifications.muted_chats = vec![ChatMute {
+            chat_id: "a/b".into(),
+            until: None,
+        }];
+        assert!(validate(&settings).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+// Encryption key for the local store
+//
+// The key is generated once per profile and kept in the OS credential store
+// (Windows Credential Manager, macOS Keychain, Secret Service on Linux). If no
+// credential store is reachable we fall back to a key file next to the
+// database so the app still starts on minimal Linux desktops.
+//
+// A new key is only ever generated for a new store: when neither place has
+// one and there is no database yet. Anything else - a credential store that
+// is briefly unreachable, or a stored key that cannot be read - is an error,
+// since a new key would lock the existing database for good.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rand::RngCore;
+
+use crate::error::{Error, Result};
+use crate::hex;
+use crate::profiles;
+
+const KEYRING_SERVICE: &str = "com.chitchat.desktop";
+const KEYRING_USER: &str = "local-store-key";
+const KEY_FILE: &str = "store.key";
+
+/// A 256-bit SQLCipher key, hex encoded.
+pub struct StoreKey(String);
+
+impl StoreKey {
+    fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::rng().fill_bytes(&mut bytes);
+        StoreKey(hex::encode(&bytes))
+    }
+
+    fn parse(hex: &str) -> Option<Self> {
+        let hex = hex.trim();
+        let valid = hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit());
+        valid.then(|| StoreKey(hex.to_ascii_lowercase()))
+    }
+
+    /// The key formatted as a SQLCipher raw key literal: `x'…'`.
+    pub(crate) fn pragma_value(&self) -> String {
+        format!("x'{}'", self.0)
+    }
+}
+
+/// A place a key can be kept in.
+trait Slot {
+    /// What is stored, if anything. `Ok(None)` when the slot is reachable
+    /// but empty, and `Err(Unavailable)` when it cannot be reached.
+    fn read(&self) -> std::result::Result<Option<String>, Unavailable>;
+    fn write(&self, key: &StoreKey) -> Result<()>;
+}
+
+/// A slot that cannot be reached right now, e.g. a locked credential store.
+struct Unavailable(Error);
+
+/// Loads the store key of `profile`, creating and persisting a new one on
+/// first launch.
+pub fn load_or_create(data_dir: &Path, profile: &str) -> Result<StoreKey> {
+    let keyring = Keyring::new(profile)?;
+    let file = KeyFile(data_dir.join(KEY_FILE));
+    let has_database = data_dir.join(super::DB_FILE).exists();
+    load_or_create_in(&keyring, &file, has_database)
+}
+
+fn load_or_create_in(keyring: &dyn Slot, file: &dyn Slot, has_database: bool) -> Result<StoreKey> {
+    let unavailable = match keyring.read() {
+        Ok(Some(stored)) => return parse(&stored, "the credential store"),
+        Ok(None) => None,
+        Err(Unavailable(e)) => Some(e),
+    };
+    match file.read() {
+        Ok(Some(stored)) => return parse(&stored, KEY_FILE),
+        Ok(None) => {}
+        Err(Unavailable(e)) => return Err(e),
+    }
+
+    if has_database {
+        return Err(match unavailable {
+            Some(e) => Error::StoreKey(format!("the credential store cannot be reached ({e})")),
+            None => Error::StoreKey("the key of the existing local store is missing".into()),
+        });
+    }
+    let key = StoreKey::generate();
+    match unavailable {
+        None => keyring.write(&key)?,
+        Some(e) => {
+            tracing::warn!("no credential store ({e}); keeping the store key in {KEY_FILE}");
+            file.write(&key)?;
+        }
+    }
+    Ok(key)
+}
+
+fn parse(stored: &str, place: &str) -> Result<StoreKey> {
+    StoreKey::parse(stored).ok_or_else(|| Error::StoreKey(format!("the key in {place} is invalid")))
+}
+
+struct Keyring(keyring::Entry);
+
+impl Keyring {
+    fn new(profile: &str) -> Result<Self> {
+        // The default profile keeps the entry from before there were profiles
+        let user = if profile == profiles::DEFAULT {
+            KEYRING_USER.to_string()
+        } else {
+            format!("{KEYRING_USER}:{profile}")
+        };
+        Ok(Keyring(keyring::Entry::new(KEYRING_SERVICE, &user)?))
+    }
+}
+
+impl Slot for Keyring {
+    fn read(&self) -> std::result::Result<Option<String>, Unavailable> {
+        match self.0.get_password() {
+            Ok(stored) => Ok(Some(stored)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(Unavailable(e.into())),
+        }
+    }
+
+    fn write(&self, key: &StoreKey) -> Result<()> {
+        Ok(self.0.set_password(&key.0)?)
+    }
+}
+
+struct KeyFile(PathBuf);
+
+impl Slot for KeyFile {
+    fn read(&self) -> std::result::Result<Option<String>, Unavailable> {
+        match fs::read_to_string(&self.0) {
+            Ok(stored) => Ok(Some(stored)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Unavailable(e.into())),
+        }
+    }
+
+    fn write(&self, key: &StoreKey) -> Result<()> {
+        fs::write(&self.0, &key.0)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&self.0, fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    const KEY: &str = "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff";
+
+    /// A slot in memory; `None` for one that cannot be reached.
+    struct Memory(Option<RefCell<Option<String>>>);
+
+    impl Memory {
+        fn empty() -> Self {
+            Memory(Some(RefCell::new(None)))
+        }
+
+        fn holding(stored: &str) -> Self {
+            Memory(Some(RefCell::new(Some(stored.to_string()))))
+        }
+
+        fn unreachable() -> Self {
+            Memory(None)
+        }
+
+        fn stored(&self) -> Option<String> {
+            self.0.as_ref().and_then(|slot| slot.borrow().clone())
+        }
+    }
+
+    impl Slot for Memory {
+        fn read(&self) -> std::result::Result<Option<String>, Unavailable> {
+            match &self.0 {
+                Some(slot) => Ok(slot.borrow().clone()),
+                None => Err(Unavailable(Error::StoreKey("unreachable".into()))),
+            }
+        }
+
+        fn write(&self, key: &StoreKey) -> Result<()> {
+            let slot = self.0.as_ref().expect("wrote to an unreachable slot");
+            *slot.borrow_mut() = Some(key.0.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn new_store_keeps_its_key_in_the_keyring() {
+        let (keyring, file) = (Memory::empty(), Memory::empty());
+        let key = load_or_create_in(&keyring, &file, false).unwrap();
+        assert_eq!(keyring.stored(), Some(key.0));
+        assert_eq!(file.stored(), None);
+    }
+
+    #[test]
+    fn new_store_falls_back_to_the_key_file() {
+        let (keyring, file) = (Memory::unreachable(), Memory::empty());
+        let key = load_or_create_in(&keyring, &file, false).unwrap();
+        assert_eq!(file.stored(), Some(key.0));
+    }
+
+    #[test]
+    fn loads_the_key_from_either_place() {
+        let key = load_or_create_in(&Memory::holding(KEY), &Memory::empty(), true).unwrap();
+        assert_eq!(key.0, KEY);
+        let key = load_or_create_in(&Memory::unreachable(), &Memory::holding(KEY), true).unwrap();
+        assert_eq!(key.0, KEY);
+        // Written to the file while the keyring was down
+        let key = load_or_create_in(&Memory::empty(), &Memory::holding(KEY), true).unwrap();
+        assert_eq!(key.0, KEY);
+    }
+
+    #[test]
+    fn unreachable_keyring_does_not_replace_the_key() {
+        let file = Memory::empty();
+        assert!(load_or_create_in(&Memory::unreachable(), &file, true).is_err());
+        assert_eq!(file.stored(), None);
+    }
+
+    #[test]
+    fn missing_key_of_an_existing_store_is_an_error() {
+        let keyring = Memory::empty();
+        assert!(load_or_create_in(&keyring, &Memory::empty(), true).is_err());
+        assert_eq!(keyring.stored(), None);
+    }
+
+    #[test]
+    fn bad_stored_keys_are_not_overwritten() {
+        for bad in ["", "not hex", &KEY[..62], &format!("{KEY}00")] {
+            let keyring = Memory::holding(bad);
+            assert!(load_or_create_in(&keyring, &Memory::empty(), false).is_err());
+            assert_eq!(keyring.stored().as_deref(), Some(bad));
+
+            let file = Memory::holding(bad);
+            assert!(load_or_create_in(&Memory::unreachable(), &file, false).is_err());
+            assert_eq!(file.stored().as_deref(), Some(bad));
+        }
+    }
+
+    #[test]
+    fn stored_keys_are_trimmed_and_lowercased() {
+        let stored = format!(" {}\n", KEY.to_ascii_uppercase());
+        let key = load_or_create_in(&Memory::holding(&stored), &Memory::empty(), true).unwrap();
+        assert_eq!(key.0, KEY);
+    }
+}
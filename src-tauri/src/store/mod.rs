@@ -0,0 +1,697 @@
+// Encrypted local store for chats, messages and users
+//
+// Backed by SQLCipher so everything on disk is encrypted at rest. RTDB stays
+// the source of truth; this is a cache the UI can render from at startup and
+// while offline.
+mod key;
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::error::{Error, Result};
+use crate::models::{Chat, Message, Timestamp, User};
+
+const DB_FILE: &str = "chitchat.db";
+
+/// Schema migrations, applied in order. `PRAGMA user_version` records how many
+/// have run, so only ever append to this list.
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE users (
+        id           TEXT PRIMARY KEY,
+        email        TEXT NOT NULL,
+        display_name TEXT NOT NULL,
+        created_at   INTEGER NOT NULL,
+        is_online    INTEGER,
+        last_seen    INTEGER
+    );
+
+    CREATE TABLE chats (
+        id                     TEXT PRIMARY KEY,
+        participants           TEXT NOT NULL,
+        participant_names      TEXT,
+        is_group               INTEGER,
+        group_name             TEXT,
+        owner_id               TEXT,
+        last_message           TEXT NOT NULL,
+        last_message_sender_id TEXT,
+        updated_at             INTEGER NOT NULL
+    );
+
+    CREATE TABLE messages (
+        chat_id     TEXT NOT NULL,
+        id          TEXT NOT NULL,
+        sender_id   TEXT NOT NULL,
+        sender_name TEXT,
+        text        TEXT NOT NULL,
+        timestamp   INTEGER NOT NULL,
+        PRIMARY KEY (chat_id, id)
+    );
+
+    CREATE INDEX messages_by_time ON messages (chat_id, timestamp);
+"#,
+    r#"
+    CREATE TABLE outbox (
+        id              TEXT PRIMARY KEY,
+        chat_id         TEXT NOT NULL,
+        sender_id       TEXT NOT NULL,
+        sender_name     TEXT NOT NULL,
+        text            TEXT NOT NULL,
+        created_at      INTEGER NOT NULL,
+        attempts        INTEGER NOT NULL DEFAULT 0,
+        next_attempt_at INTEGER NOT NULL,
+        state           TEXT NOT NULL,
+        last_error      TEXT
+    );
+
+    CREATE INDEX outbox_by_due ON outbox (state, next_attempt_at);
+"#,
+    // Messages get a rowid of their own to key the search index on; the
+    // implicit one of a table with a composite primary key may be renumbered
+    // by VACUUM
+    r#"
+    CREATE TABLE messages_new (
+        seq         INTEGER PRIMARY KEY,
+        chat_id     TEXT NOT NULL,
+        id          TEXT NOT NULL,
+        sender_id   TEXT NOT NULL,
+        sender_name TEXT,
+        text        TEXT NOT NULL,
+        timestamp   INTEGER NOT NULL,
+        UNIQUE (chat_id, id)
+    );
+    INSERT INTO messages_new (chat_id, id, sender_id, sender_name, text, timestamp)
+        SELECT chat_id, id, sender_id, sender_name, text, timestamp FROM messages;
+    DROP TABLE messages;
+    ALTER TABLE messages_new RENAME TO messages;
+    CREATE INDEX messages_by_time ON messages (chat_id, timestamp);
+
+    CREATE VIRTUAL TABLE messages_fts USING fts5 (
+        text,
+        content = 'messages',
+        content_rowid = 'seq',
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+
+    CREATE TRIGGER messages_fts_insert AFTER INSERT ON messages BEGIN
+        INSERT INTO messages_fts (rowid, text) VALUES (new.seq, new.text);
+    END;
+
+    CREATE TRIGGER messages_fts_delete AFTER DELETE ON messages BEGIN
+        INSERT INTO messages_fts (messages_fts, rowid, text) VALUES ('delete', old.seq, old.text);
+    END;
+
+    CREATE TRIGGER messages_fts_update AFTER UPDATE OF text ON messages BEGIN
+        INSERT INTO messages_fts (messages_fts, rowid, text) VALUES ('delete', old.seq, old.text);
+        INSERT INTO messages_fts (rowid, text) VALUES (new.seq, new.text);
+    END;
+
+    INSERT INTO messages_fts (messages_fts) VALUES ('rebuild');
+"#,
+    r#"
+    CREATE TABLE e2e_identity (
+        user_id    TEXT PRIMARY KEY,
+        device_id  TEXT NOT NULL,
+        identity   TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+
+    CREATE TABLE e2e_pre_keys (
+        user_id TEXT NOT NULL,
+        id      INTEGER NOT NULL,
+        key     TEXT NOT NULL,
+        PRIMARY KEY (user_id, id)
+    );
+
+    CREATE TABLE e2e_peers (
+        address      TEXT PRIMARY KEY,
+        identity_key BLOB NOT NULL,
+        signing_key  BLOB NOT NULL,
+        first_seen   INTEGER NOT NULL
+    );
+
+    CREATE TABLE e2e_sessions (
+        address    TEXT NOT NULL,
+        base_key   BLOB NOT NULL,
+        state      TEXT NOT NULL,
+        updated_at INTEGER NOT NULL,
+        PRIMARY KEY (address, base_key)
+    );
+
+    CREATE TABLE e2e_sender_keys (
+        chat_id    TEXT NOT NULL,
+        sender     TEXT NOT NULL,
+        key_id     INTEGER NOT NULL,
+        state      TEXT NOT NULL,
+        updated_at INTEGER NOT NULL,
+        PRIMARY KEY (chat_id, sender, key_id)
+    );
+
+    CREATE TABLE e2e_sender_key_recipients (
+        chat_id TEXT NOT NULL,
+        key_id  INTEGER NOT NULL,
+        address TEXT NOT NULL,
+        PRIMARY KEY (chat_id, key_id, address)
+    );
+"#,
+    r#"
+    CREATE TABLE chat_reads (
+        chat_id TEXT PRIMARY KEY,
+        read_at INTEGER NOT NULL
+    );
+"#,
+    r#"
+    ALTER TABLE messages ADD COLUMN archived INTEGER NOT NULL DEFAULT 0;
+"#,
+    r#"
+    ALTER TABLE messages ADD COLUMN attachment TEXT;
+    ALTER TABLE outbox ADD COLUMN attachment TEXT;
+"#,
+    // The encrypted message, kept so every attempt sends the same ciphertext
+    r#"
+    ALTER TABLE outbox ADD COLUMN sealed TEXT;
+"#,
+];
+
+pub struct Store {
+    conn: Mutex<Connection>,
+}
+
+impl Store {
+    /// Opens (or creates) the store of `profile` in `data_dir`, unlocking it
+    /// with the profile's key and bringing the schema up to date.
+    pub fn open(data_dir: &Path, profile: &str) -> Result<Self> {
+        fs::create_dir_all(data_dir)?;
+        let key = key::load_or_create(data_dir, profile)?;
+
+        let conn = Connection::open(data_dir.join(DB_FILE))?;
+        conn.pragma_update(None, "key", key.pragma_value())?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        migrate(&conn)?;
+
+        Ok(Store {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub(crate) fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn upsert_users(&self, users: &[User]) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO users (id, email, display_name, created_at, is_online, last_seen)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (id) DO UPDATE SET
+                     email = excluded.email,
+                     display_name = excluded.display_name,
+                     created_at = excluded.created_at,
+                     is_online = excluded.is_online,
+                     last_seen = excluded.last_seen",
+            )?;
+            for user in users {
+                stmt.execute(params![
+                    user.id,
+                    user.email,
+                    user.display_name,
+                    user.created_at,
+                    user.is_online,
+                    user.last_seen,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn user(&self, user_id: &str) -> Result<Option<User>> {
+        let conn = self.conn();
+        let user = conn
+            .query_row(
+                "SELECT id, email, display_name, created_at, is_online, last_seen
+                 FROM users WHERE id = ?1",
+                [user_id],
+                user_from_row,
+            )
+            .optional()?;
+        Ok(user)
+    }
+
+    pub fn upsert_chats(&self, chats: &[Chat]) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO chats (id, participants, participant_names, is_group, group_name,
+                                    owner_id, last_message, last_message_sender_id, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                 ON CONFLICT (id) DO UPDATE SET
+                     participants = excluded.participants,
+                     participant_names = excluded.participant_names,
+                     is_group = excluded.is_group,
+                     group_name = excluded.group_name,
+                     owner_id = excluded.owner_id,
+                     last_message = excluded.last_message,
+                     last_message_sender_id = excluded.last_message_sender_id,
+                     updated_at = excluded.updated_at",
+            )?;
+            for chat in chats {
+                let participant_names = chat
+                    .participant_names
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()?;
+                stmt.execute(params![
+                    chat.id,
+                    serde_json::to_string(&chat.participants)?,
+                    participant_names,
+                    chat.is_group,
+                    chat.group_name,
+                    chat.owner_id,
+                    chat.last_message,
+                    chat.last_message_sender_id,
+                    chat.updated_at,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// All cached chats, most recently updated first.
+    pub fn chats(&self) -> Result<Vec<Chat>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, participants, participant_names, is_group, group_name, owner_id,
+                    last_message, last_message_sender_id, updated_at
+             FROM chats ORDER BY updated_at DESC",
+        )?;
+        let chats = stmt
+            .query_map([], chat_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(chats)
+    }
+
+    /// Drops a chat and its messages, e.g. after the user left a group.
+    pub fn remove_chat(&self, chat_id: &str) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM messages WHERE chat_id = ?1", [chat_id])?;
+        tx.execute("DELETE FROM chats WHERE id = ?1", [chat_id])?;
+        tx.execute("DELETE FROM chat_reads WHERE chat_id = ?1", [chat_id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn upsert_messages(&self, chat_id: &str, messages: &[Message]) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO messages (chat_id, id, sender_id, sender_name, text, timestamp,
+                                       attachment)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                 ON CONFLICT (chat_id, id) DO UPDATE SET
+                     sender_id = excluded.sender_id,
+                     sender_name = excluded.sender_name,
+                     text = excluded.text,
+                     timestamp = excluded.timestamp,
+                     attachment = excluded.attachment
+                 WHERE NOT messages.archived",
+            )?;
+            for message in messages {
+                let attachment = message
+                    .attachment
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()?;
+                stmt.execute(params![
+                    chat_id,
+                    message.id,
+                    message.sender_id,
+                    message.sender_name,
+                    message.text,
+                    message.timestamp,
+                    attachment,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Stores history imported from another messenger. Messages already in
+    /// the store are left alone; returns how many were added.
+    pub fn insert_archived_messages(&self, chat_id: &str, messages: &[Message]) -> Result<u32> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO messages (chat_id, id, sender_id, sender_name, text, timestamp, archived)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1)
+                 ON CONFLICT (chat_id, id) DO NOTHING",
+            )?;
+            for message in messages {
+                inserted += stmt.execute(params![
+                    chat_id,
+                    message.id,
+                    message.sender_id,
+                    message.sender_name,
+                    message.text,
+                    message.timestamp,
+                ])? as u32;
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    /// All archived messages of a chat in chronological order.
+    pub fn archived_messages(&self, chat_id: &str) -> Result<Vec<Message>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, sender_id, sender_name, text, timestamp, archived, attachment
+             FROM messages
+             WHERE chat_id = ?1 AND archived
+             ORDER BY timestamp, id",
+        )?;
+        let messages = stmt
+            .query_map([chat_id], message_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(messages)
+    }
+
+    /// Which of `ids` are messages of the chat already in the store.
+    pub fn existing_message_ids(&self, chat_id: &str, ids: &[&str]) -> Result<HashSet<String>> {
+        let conn = self.conn();
+        let mut stmt =
+            conn.prepare_cached("SELECT 1 FROM messages WHERE chat_id = ?1 AND id = ?2")?;
+        let mut existing = HashSet::new();
+        for id in ids {
+            if stmt.exists(params![chat_id, id])? {
+                existing.insert(id.to_string());
+            }
+        }
+        Ok(existing)
+    }
+
+    /// The newest `limit` live (not archived) messages of a chat older than
+    /// the message `before` (if given), returned in chronological order.
+    /// Messages are ordered by timestamp, then by when they were stored, so
+    /// pages never skip messages that share a timestamp.
+    pub fn messages(
+        &self,
+        chat_id: &str,
+        before: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<Message>> {
+        let conn = self.conn();
+        let (timestamp, seq): (Timestamp, i64) = match before {
+            Some(id) => conn
+                .query_row(
+                    "SELECT timestamp, seq FROM messages WHERE chat_id = ?1 AND id = ?2",
+                    params![chat_id, id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?
+                .ok_or_else(|| Error::UnknownMessage(id.to_string()))?,
+            None => (Timestamp::MAX, i64::MAX),
+        };
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, sender_id, sender_name, text, timestamp, archived, attachment
+             FROM messages
+             WHERE chat_id = ?1 AND (timestamp, seq) < (?2, ?3) AND NOT archived
+             ORDER BY timestamp DESC, seq DESC
+             LIMIT ?4",
+        )?;
+        let mut messages = stmt
+            .query_map(params![chat_id, timestamp, seq, limit], message_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        messages.reverse();
+        Ok(messages)
+    }
+
+    /// Up to `limit` messages of a chat following `after` (a message's
+    /// timestamp and ID), in chronological order. Pages through a whole chat
+    /// without holding the connection in between.
+    pub fn messages_after(
+        &self,
+        chat_id: &str,
+        after: Option<(Timestamp, &str)>,
+        limit: u32,
+    ) -> Result<Vec<Message>> {
+        let (timestamp, id) = after.unwrap_or((Timestamp::MIN, ""));
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, sender_id, sender_name, text, timestamp, archived, attachment
+             FROM messages
+             WHERE chat_id = ?1 AND (timestamp, id) > (?2, ?3)
+             ORDER BY timestamp, id
+             LIMIT ?4",
+        )?;
+        let messages = stmt
+            .query_map(params![chat_id, timestamp, id, limit], message_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(messages)
+    }
+
+    pub fn message(&self, chat_id: &str, id: &str) -> Result<Option<Message>> {
+        let message = self
+            .conn()
+            .query_row(
+                "SELECT id, sender_id, sender_name, text, timestamp, archived, attachment
+                 FROM messages WHERE chat_id = ?1 AND id = ?2",
+                params![chat_id, id],
+                message_from_row,
+            )
+            .optional()?;
+        Ok(message)
+    }
+
+    /// Wipes every cached row, any unsent messages and this device's
+    /// encryption keys, used on sign-out. Imported history is kept: it exists
+    /// nowhere else and is not tied to the account.
+    pub fn clear(&self) -> Result<()> {
+        self.conn().execute_batch(
+            "DELETE FROM outbox; DELETE FROM messages WHERE NOT archived;
+             DELETE FROM chats; DELETE FROM users;
+             DELETE FROM chat_reads;
+             DELETE FROM e2e_identity; DELETE FROM e2e_pre_keys; DELETE FROM e2e_peers;
+             DELETE FROM e2e_sessions; DELETE FROM e2e_sender_keys;
+             DELETE FROM e2e_sender_key_recipients;",
+        )?;
+        Ok(())
+    }
+}
+
+fn migrate(conn: &Connection) -> Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        conn.execute_batch(&format!(
+            "BEGIN; {migration} PRAGMA user_version = {}; COMMIT;",
+            index + 1
+        ))?;
+    }
+    Ok(())
+}
+
+fn user_from_row(row: &Row) -> rusqlite::Result<User> {
+    Ok(User {
+        id: row.get(0)?,
+        email: row.get(1)?,
+        display_name: row.get(2)?,
+        created_at: row.get(3)?,
+        is_online: row.get(4)?,
+        last_seen: row.get(5)?,
+        status: None,
+    })
+}
+
+fn chat_from_row(row: &Row) -> rusqlite::Result<Chat> {
+    Ok(Chat {
+        id: row.get(0)?,
+        participants: parse_json(1, &row.get::<_, String>(1)?)?,
+        participant_names: row
+            .get::<_, Option<String>>(2)?
+            .map(|raw| parse_json(2, &raw))
+            .transpose()?,
+        is_group: row.get(3)?,
+        group_name: row.get(4)?,
+        owner_id: row.get(5)?,
+        last_message: row.get(6)?,
+        last_message_sender_id: row.get(7)?,
+        updated_at: row.get(8)?,
+        typing: None,
+    })
+}
+
+fn message_from_row(row: &Row) -> rusqlite::Result<Message> {
+    Ok(Message {
+        id: row.get(0)?,
+        sender_id: row.get(1)?,
+        sender_name: row.get(2)?,
+        text: row.get(3)?,
+        timestamp: row.get(4)?,
+        archived: row.get::<_, bool>(5)?.then_some(true),
+        attachment: row
+            .get::<_, Option<String>>(6)?
+            .map(|raw| parse_json(6, &raw))
+            .transpose()?,
+    })
+}
+
+/// Reads a JSON column, reporting malformed values as a conversion error.
+pub(crate) fn parse_json<T: serde::de::DeserializeOwned>(
+    index: usize,
+    raw: &str,
+) -> rusqlite::Result<T> {
+    serde_json::from_str(raw).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+#[cfg(test)]
+impl Store {
+    /// An unencrypted store in memory.
+    pub(crate) fn open_in_memory() -> Self {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        Store {
+            conn: Mutex::new(conn),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str, text: &str) -> Message {
+        Message {
+            id: id.into(),
+            sender_id: "alice".into(),
+            sender_name: None,
+            text: text.into(),
+            timestamp: 1,
+            archived: None,
+            attachment: None,
+        }
+    }
+
+    /// IDs of the messages whose text matches `expression`.
+    fn matching(conn: &Connection, expression: &str) -> Vec<String> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT m.id FROM messages_fts JOIN messages m ON m.seq = messages_fts.rowid
+                 WHERE messages_fts MATCH ?1 ORDER BY m.id",
+            )
+            .unwrap();
+        stmt.query_map([expression], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn clear_keeps_imported_history() {
+        let store = Store::open_in_memory();
+        store
+            .upsert_messages("a", &[message("m1", "cached")])
+            .unwrap();
+        store
+            .insert_archived_messages("a", &[message("old1", "imported")])
+            .unwrap();
+
+        store.clear().unwrap();
+        assert!(store.messages("a", None, 10).unwrap().is_empty());
+        let archived = store.archived_messages("a").unwrap();
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].text, "imported");
+        assert_eq!(matching(&store.conn(), "cached"), Vec::<String>::new());
+        assert_eq!(matching(&store.conn(), "imported"), ["old1"]);
+    }
+
+    #[test]
+    fn pages_keep_messages_that_share_a_timestamp() {
+        let store = Store::open_in_memory();
+        let messages: Vec<Message> = (0..5)
+            .map(|i| message(&format!("m{i}"), "same second"))
+            .collect();
+        store.upsert_messages("a", &messages).unwrap();
+
+        let mut pages = Vec::new();
+        let mut before = None;
+        loop {
+            let page = store.messages("a", before.as_deref(), 2).unwrap();
+            let Some(oldest) = page.first() else {
+                break;
+            };
+            before = Some(oldest.id.clone());
+            pages.push(page.into_iter().map(|m| m.id).collect::<Vec<_>>());
+        }
+        assert_eq!(pages, [vec!["m3", "m4"], vec!["m1", "m2"], vec!["m0"]]);
+        assert!(matches!(
+            store.messages("a", Some("gone"), 2),
+            Err(Error::UnknownMessage(_))
+        ));
+    }
+
+    #[test]
+    fn search_index_survives_vacuum() {
+        let store = Store::open_in_memory();
+        let messages: Vec<Message> = (0..50)
+            .map(|i| message(&format!("m{i:02}"), &format!("word{i} common")))
+            .collect();
+        store.upsert_messages("a", &messages).unwrap();
+        store
+            .upsert_messages("b", &[message("m00", "other")])
+            .unwrap();
+        store.remove_chat("b").unwrap();
+        store
+            .conn()
+            .execute("DELETE FROM messages WHERE id < 'm25'", [])
+            .unwrap();
+
+        let conn = store.conn();
+        conn.execute_batch("VACUUM").unwrap();
+        conn.execute(
+            "INSERT INTO messages_fts (messages_fts) VALUES ('integrity-check')",
+            [],
+        )
+        .unwrap();
+        assert_eq!(matching(&conn, "word30"), ["m30"]);
+        assert_eq!(matching(&conn, "word10"), Vec::<String>::new());
+        assert_eq!(matching(&conn, "common").len(), 25);
+    }
+
+    #[test]
+    fn migration_keeps_messages_searchable() {
+        let conn = Connection::open_in_memory().unwrap();
+        // Everything before the search index
+        let before = MIGRATIONS
+            .iter()
+            .position(|migration| migration.contains("content_rowid = 'seq'"))
+            .unwrap();
+        for migration in &MIGRATIONS[..before] {
+            conn.execute_batch(migration).unwrap();
+        }
+        conn.pragma_update(None, "user_version", before).unwrap();
+        conn.execute(
+            "INSERT INTO messages (chat_id, id, sender_id, text, timestamp)
+             VALUES ('a', 'm1', 'alice', 'hello there', 1)",
+            [],
+        )
+        .unwrap();
+
+        migrate(&conn).unwrap();
+        assert_eq!(matching(&conn, "hello"), ["m1"]);
+    }
+}
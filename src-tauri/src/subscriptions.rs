@@ -0,0 +1,517 @@
+// Live RTDB subscriptions
+//
+// Chats, messages and contacts' presence are followed by listeners on the
+// async runtime rather than by the JS SDK, so they keep running while the
+// window is hidden, and are streamed to the webview over an IPC channel.
+// Message snapshots are decrypted before they are sent; when several arrive
+// while one is being decrypted, only the newest is. Each subscription belongs
+// to the webview that asked for it and is stopped when that webview is
+// destroyed or loads a page, since nobody is left to unsubscribe.
+//
+// The signed-in user's chats are also followed for the whole session,
+// independently of any webview; unread counts are worked out from those, and
+// their messages are decrypted as they come in and notified about.
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use tauri::ipc::Channel;
+use tauri::webview::{PageLoadEvent, PageLoadPayload};
+use tauri::{AppHandle, Manager, Runtime, Webview, Window, WindowEvent};
+use tokio::sync::watch;
+
+use crate::e2e::E2e;
+use crate::error::Result;
+use crate::models::{now, Chat, IncomingMessage, LiveUpdate, Message, Timestamp, User};
+use crate::notifications;
+use crate::rtdb::{Database, Event, Listener};
+use crate::session::Session;
+use crate::store::Store;
+use crate::unread;
+
+/// Running subscriptions by ID, managed as state.
+#[derive(Default)]
+pub struct Subscriptions {
+    next_id: AtomicU32,
+    active: Mutex<HashMap<u32, Subscription>>,
+    account: Mutex<Option<Account>>,
+}
+
+/// The session's own subscription to the signed-in user's chats.
+struct Account {
+    /// Newest first; empty until the chats have loaded.
+    chats: Arc<Mutex<Vec<Chat>>>,
+    _listener: Listener,
+}
+
+struct Subscription {
+    /// Label of the webview that subscribed.
+    webview: String,
+    _listener: Listener,
+}
+
+impl Subscriptions {
+    fn add(&self, webview: &str, listener: Listener) -> u32 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let subscription = Subscription {
+            webview: webview.to_string(),
+            _listener: listener,
+        };
+        self.lock().insert(id, subscription);
+        id
+    }
+
+    /// Stops a subscription; unknown IDs are ignored.
+    pub fn remove(&self, id: u32) {
+        self.lock().remove(&id);
+    }
+
+    /// Stops every subscription of the webview labelled `webview`.
+    pub fn remove_webview(&self, webview: &str) {
+        self.lock()
+            .retain(|_, subscription| subscription.webview != webview);
+    }
+
+    /// Stops every subscription, e.g. on sign-out.
+    pub fn clear(&self) {
+        self.lock().clear();
+        *lock(&self.account) = None;
+    }
+
+    /// The signed-in user's chats as the session's subscription last saw
+    /// them, newest first.
+    pub fn account_chats(&self) -> Vec<Chat> {
+        match &*lock(&self.account) {
+            Some(account) => lock(&account.chats).clone(),
+            None => Vec::new(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u32, Subscription>> {
+        self.active.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Stops the subscriptions of a destroyed window's webview.
+pub fn on_window_event<R: Runtime>(window: &Window<R>, event: &WindowEvent) {
+    if let (WindowEvent::Destroyed, Some(subscriptions)) =
+        (event, window.try_state::<Subscriptions>())
+    {
+        subscriptions.remove_webview(window.label());
+    }
+}
+
+/// Stops the subscriptions of a webview that reloads or navigates away; the
+/// new page subscribes afresh.
+pub fn on_page_load<R: Runtime>(webview: &Webview<R>, payload: &PageLoadPayload<'_>) {
+    if let (PageLoadEvent::Started, Some(subscriptions)) =
+        (payload.event(), webview.try_state::<Subscriptions>())
+    {
+        subscriptions.remove_webview(webview.label());
+    }
+}
+
+/// Starts following the signed-in user's chats for the session, in place of
+/// any earlier session's.
+pub fn follow_account(app: &AppHandle) -> Result<()> {
+    let Some(credentials) = app.state::<Session>().credentials() else {
+        return Ok(());
+    };
+    let db = app.state::<Session>().database(&reqwest::Client::new())?;
+    // Chats last updated before the first sign-in on this device start out read
+    let seed_up_to = unread::is_fresh(&app.state::<Store>())?.then(now);
+    // Messages from before the session are not notified about
+    let since = now();
+
+    let chats: Arc<Mutex<Vec<Chat>>> = Arc::default();
+    // Owned by the chats listener, so they stop with it
+    let inboxes: Mutex<HashMap<String, Listener>> = Mutex::default();
+    let (app_handle, latest, messages_db) = (app.clone(), chats.clone(), db.clone());
+    let user_id = credentials.user_id.clone();
+    let listener = watch_chats(&db, &credentials.user_id, move |chats| {
+        // A cancelled index keeps what was last seen until sign-out
+        let Some(chats) = chats else {
+            return;
+        };
+        {
+            let mut inboxes = lock(&inboxes);
+            inboxes.retain(|id, _| chats.contains_key(id));
+            for id in chats.keys() {
+                if !inboxes.contains_key(id) {
+                    let inbox = notify_messages(
+                        &app_handle,
+                        &messages_db,
+                        &user_id,
+                        id,
+                        since,
+                        latest.clone(),
+                    );
+                    inboxes.insert(id.clone(), inbox);
+                }
+            }
+        }
+        let chats = newest_first(chats);
+        if let Some(up_to) = seed_up_to {
+            if let Err(e) = unread::seed(&app_handle.state::<Store>(), &chats, up_to) {
+                tracing::warn!("failed to mark chats read: {e}");
+            }
+        }
+        *lock(&latest) = chats;
+        unread::publish(&app_handle);
+    });
+    *lock(&app.state::<Subscriptions>().account) = Some(Account {
+        chats,
+        _listener: listener,
+    });
+    Ok(())
+}
+
+/// Follows the messages of `chat_id` for notifications. Every snapshot is
+/// decrypted, which also caches it for the webview, and the messages others
+/// sent after `since` are notified about once each. `chats` are the
+/// account's, for the sender's name.
+fn notify_messages(
+    app: &AppHandle,
+    db: &Database,
+    user_id: &str,
+    chat_id: &str,
+    since: Timestamp,
+    chats: Arc<Mutex<Vec<Chat>>>,
+) -> Listener {
+    let (latest, mut updates) = watch::channel(None::<Value>);
+    let path = format!("messages/{chat_id}");
+
+    // Stops once the listener, and with it `latest`, is dropped
+    let (app, user_id, chat_id) = (app.clone(), user_id.to_string(), chat_id.to_string());
+    tauri::async_runtime::spawn(async move {
+        let mut notified_up_to = since;
+        while updates.changed().await.is_ok() {
+            let Some(value) = updates.borrow_and_update().clone() else {
+                continue;
+            };
+            let result = app
+                .state::<E2e>()
+                .decrypt_messages(
+                    &app.state::<Store>(),
+                    Some(&user_id),
+                    &chat_id,
+                    incoming(value),
+                )
+                .await;
+            let messages = match result {
+                Ok(messages) => messages,
+                Err(e) => {
+                    tracing::warn!(chat_id, "failed to decrypt messages: {e}");
+                    continue;
+                }
+            };
+            let chat = lock(&chats).iter().find(|chat| chat.id == chat_id).cloned();
+            let unseen = notifications::unseen(&messages, &user_id, notified_up_to)
+                .map(|message| notifications::for_message(&chat_id, chat.as_ref(), message))
+                .collect();
+            notifications::spawn_messages(app.clone(), unseen);
+            notified_up_to = messages
+                .iter()
+                .map(|message| message.timestamp)
+                .fold(notified_up_to, Timestamp::max);
+        }
+    });
+
+    db.listen(&path, move |event| {
+        if let Event::Value(value) = event {
+            latest.send_replace(Some(value.clone()));
+        }
+    })
+}
+
+/// Follows the chats of `user_id`: the `userChats/{uid}` index, and each chat
+/// listed in it. Sends the whole list, newest first, whenever one changes.
+pub fn chats(
+    webview: &Webview,
+    user_id: &str,
+    channel: Channel<LiveUpdate<Vec<Chat>>>,
+) -> Result<u32> {
+    let app = webview.app_handle();
+    let db = app.state::<Session>().database(&reqwest::Client::new())?;
+    let listener = watch_chats(&db, user_id, move |chats| match chats {
+        Some(chats) => send(&channel, LiveUpdate::Value(newest_first(chats))),
+        None => send(&channel, LiveUpdate::Cancelled),
+    });
+    Ok(app.state::<Subscriptions>().add(webview.label(), listener))
+}
+
+/// Follows the `userChats/{uid}` index of `user_id` and each chat listed in
+/// it. Calls `on_change` with all of them whenever one changes, and with
+/// `None` once the index is cancelled.
+fn watch_chats<F>(db: &Database, user_id: &str, on_change: F) -> Listener
+where
+    F: Fn(Option<&BTreeMap<String, Chat>>) + Send + Sync + 'static,
+{
+    let on_change = Arc::new(on_change);
+    let chats: Arc<Mutex<BTreeMap<String, Chat>>> = Arc::default();
+    // Owned by the index listener, so dropping it stops the chat listeners too
+    let mut listeners: HashMap<String, Listener> = HashMap::new();
+
+    let index_db = db.clone();
+    db.listen(&format!("userChats/{user_id}"), move |event| {
+        let ids: Vec<String> = match event {
+            Event::Value(Value::Object(index)) => index.keys().cloned().collect(),
+            Event::Value(_) => Vec::new(),
+            Event::Cancelled => {
+                on_change(None);
+                return;
+            }
+        };
+
+        let before = listeners.len();
+        listeners.retain(|id, _| ids.contains(id));
+        let removed = listeners.len() < before;
+        lock(&chats).retain(|id, _| ids.contains(id));
+        if removed || ids.is_empty() {
+            on_change(Some(&lock(&chats)));
+        }
+
+        for id in ids {
+            if listeners.contains_key(&id) {
+                continue;
+            }
+            let (chats, on_change) = (chats.clone(), on_change.clone());
+            let chat_id = id.clone();
+            let listener = index_db.listen(&format!("chats/{id}"), move |event| {
+                let mut chats = lock(&chats);
+                // A chat the user may no longer read is as good as gone
+                match event {
+                    Event::Value(value) => match parse::<Chat>(&chat_id, value) {
+                        Some(chat) => chats.insert(chat_id.clone(), chat),
+                        None => chats.remove(&chat_id),
+                    },
+                    Event::Cancelled => chats.remove(&chat_id),
+                };
+                on_change(Some(&chats));
+            });
+            listeners.insert(id, listener);
+        }
+    })
+}
+
+/// Follows a single chat, e.g. for a pop-out window. Sends `None` if the chat
+/// is deleted.
+pub fn chat(
+    webview: &Webview,
+    chat_id: &str,
+    channel: Channel<LiveUpdate<Option<Chat>>>,
+) -> Result<u32> {
+    follow(
+        webview,
+        &format!("chats/{chat_id}"),
+        chat_id.to_string(),
+        channel,
+    )
+}
+
+/// Follows a user's profile and presence. Sends `None` if there is no such
+/// user.
+pub fn user(
+    webview: &Webview,
+    user_id: &str,
+    channel: Channel<LiveUpdate<Option<User>>>,
+) -> Result<u32> {
+    follow(
+        webview,
+        &format!("users/{user_id}"),
+        user_id.to_string(),
+        channel,
+    )
+}
+
+fn follow<T>(
+    webview: &Webview,
+    path: &str,
+    id: String,
+    channel: Channel<LiveUpdate<Option<T>>>,
+) -> Result<u32>
+where
+    T: DeserializeOwned + Serialize + Clone + Send + 'static,
+{
+    let app = webview.app_handle();
+    let db = app.state::<Session>().database(&reqwest::Client::new())?;
+    let listener = db.listen(path, move |event| match event {
+        Event::Value(value) => send(&channel, LiveUpdate::Value(parse::<T>(&id, value))),
+        Event::Cancelled => send(&channel, LiveUpdate::Cancelled),
+    });
+    Ok(app.state::<Subscriptions>().add(webview.label(), listener))
+}
+
+/// Follows the messages of a chat, decrypted and oldest first.
+pub fn messages(
+    webview: &Webview,
+    chat_id: &str,
+    channel: Channel<LiveUpdate<Vec<Message>>>,
+) -> Result<u32> {
+    let app = webview.app_handle();
+    let db = app.state::<Session>().database(&reqwest::Client::new())?;
+    let (latest, mut updates) = watch::channel(None::<Value>);
+
+    // Decrypts whatever snapshot is newest; stops once the listener, and with
+    // it `latest`, is dropped
+    let (app_handle, chat_id_owned, decrypted) =
+        (app.clone(), chat_id.to_string(), channel.clone());
+    tauri::async_runtime::spawn(async move {
+        while updates.changed().await.is_ok() {
+            let Some(value) = updates.borrow_and_update().clone() else {
+                continue;
+            };
+            let user_id = app_handle
+                .state::<Session>()
+                .credentials()
+                .map(|credentials| credentials.user_id);
+            let result = app_handle
+                .state::<E2e>()
+                .decrypt_messages(
+                    &app_handle.state::<Store>(),
+                    user_id.as_deref(),
+                    &chat_id_owned,
+                    incoming(value),
+                )
+                .await;
+            match result {
+                Ok(messages) => send(&decrypted, LiveUpdate::Value(messages)),
+                Err(e) => {
+                    tracing::warn!(chat_id = chat_id_owned, "failed to decrypt messages: {e}")
+                }
+            }
+        }
+    });
+
+    let listener = db.listen(&format!("messages/{chat_id}"), move |event| match event {
+        Event::Value(value) => {
+            latest.send_replace(Some(value.clone()));
+        }
+        Event::Cancelled => send(&channel, LiveUpdate::Cancelled),
+    });
+    Ok(app.state::<Subscriptions>().add(webview.label(), listener))
+}
+
+/// The messages below `messages/{chatId}`, oldest first.
+fn incoming(value: Value) -> Vec<IncomingMessage> {
+    let Value::Object(children) = value else {
+        return Vec::new();
+    };
+    let mut messages: Vec<IncomingMessage> = children
+        .into_iter()
+        .filter_map(|(id, value)| parse(&id, &value))
+        .collect();
+    // RTDB does not keep an order; push IDs break ties chronologically
+    messages.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+    messages
+}
+
+/// Reads a record stored under `id`, which is not part of the stored value.
+fn parse<T: DeserializeOwned>(id: &str, value: &Value) -> Option<T> {
+    let Value::Object(fields) = value else {
+        return None;
+    };
+    let mut fields = fields.clone();
+    fields.insert("id".into(), Value::String(id.into()));
+    serde_json::from_value(Value::Object(fields))
+        .inspect_err(|e| tracing::warn!(id, "ignoring malformed record: {e}"))
+        .ok()
+}
+
+fn newest_first(chats: &BTreeMap<String, Chat>) -> Vec<Chat> {
+    let mut list: Vec<Chat> = chats.values().cloned().collect();
+    list.sort_by_key(|chat| std::cmp::Reverse(chat.updated_at));
+    list
+}
+
+fn send<T: Serialize + Clone>(channel: &Channel<LiveUpdate<T>>, update: LiveUpdate<T>) {
+    if let Err(e) = channel.send(update) {
+        tracing::debug!("failed to send subscription update: {e}");
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::rtdb::mock::{self, Response};
+
+    /// Counts the events a listener reports on `rx`.
+    fn listen(db: &Database, path: &str) -> (Listener, mpsc::Receiver<()>) {
+        let (tx, rx) = mpsc::channel();
+        let listener = db.listen(path, move |_| {
+            let _ = tx.send(());
+        });
+        (listener, rx)
+    }
+
+    /// Whether `rx` disconnects once the events already sent are read.
+    fn disconnects(rx: &mpsc::Receiver<()>) -> bool {
+        loop {
+            match rx.recv_timeout(Duration::from_secs(10)) {
+                Ok(()) => continue,
+                Err(e) => return e == mpsc::RecvTimeoutError::Disconnected,
+            }
+        }
+    }
+
+    #[test]
+    fn removing_a_webview_stops_only_its_subscriptions() {
+        let server = mock::serve(|_| {
+            Response::Events(vec![("put", json!({ "path": "/", "data": 1 }).to_string())])
+        });
+        let db = Database::new(&server.url).unwrap();
+        let subscriptions = Subscriptions::default();
+        let (listener, main) = listen(&db, "chats/c1");
+        subscriptions.add("main", listener);
+        let (listener, popout) = listen(&db, "messages/c1");
+        subscriptions.add("chat-c1", listener);
+        let timeout = Duration::from_secs(10);
+        main.recv_timeout(timeout).unwrap();
+        popout.recv_timeout(timeout).unwrap();
+
+        subscriptions.remove_webview("chat-c1");
+        assert!(disconnects(&popout));
+        assert_ne!(main.try_recv(), Err(mpsc::TryRecvError::Disconnected));
+
+        subscriptions.remove_webview("main");
+        assert!(disconnects(&main));
+    }
+
+    #[test]
+    fn messages_are_sorted_and_keep_their_ids() {
+        let messages = incoming(json!({
+            "-b": { "senderId": "u1", "text": "second", "timestamp": 2 },
+            "-a": { "senderId": "u2", "text": "first", "timestamp": 1 },
+            "-c": { "senderId": "u1", "text": "tie", "timestamp": 2 },
+            "bad": "not a message",
+        }));
+        let ids: Vec<&str> = messages.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["-a", "-b", "-c"]);
+        assert_eq!(messages[0].text.as_deref(), Some("first"));
+        assert!(incoming(Value::Null).is_empty());
+    }
+
+    #[test]
+    fn records_get_their_key_as_id() {
+        let chat: Chat = parse(
+            "c1",
+            &json!({ "participants": { "u1": true }, "updatedAt": 5 }),
+        )
+        .unwrap();
+        assert_eq!((chat.id.as_str(), chat.updated_at), ("c1", 5));
+        assert!(parse::<Chat>("c1", &Value::Null).is_none());
+        assert!(parse::<User>("u1", &json!({ "email": 1 })).is_none());
+    }
+}
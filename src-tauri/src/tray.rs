@@ -0,0 +1,330 @@
+// System tray icon and menu
+//
+// Keeps the app reachable while the main window is hidden: closing the window
+// only hides it, so the session and presence stay up until Quit. The tooltip
+// (and the taskbar badge where the platform has one) shows how many chats are
+// unread. Muting notifications from here is kept in the settings, so it
+// outlasts a restart.
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde_json::json;
+use tauri::menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+use crate::error::Result;
+use crate::models::{now, Timestamp, UnreadSummary, UserStatus};
+use crate::rtdb::ServerTimestamp;
+use crate::session::{Credentials, Session};
+use crate::settings::{self, SettingsStore};
+
+const TRAY_ID: &str = "main";
+
+/// Emitted with the new mute deadline (or `null`) when notifications are
+/// muted or unmuted.
+pub const MUTE_EVENT: &str = "notifications-muted";
+/// Asks the webview to sign out, which owns the Firebase Auth session.
+pub const SIGN_OUT_EVENT: &str = "tray-sign-out";
+
+const MUTE_FOR: Duration = Duration::from_secs(60 * 60);
+/// How long Quit waits for the presence update before exiting anyway.
+const QUIT_TIMEOUT: Duration = Duration::from_secs(3);
+
+const OPEN_ID: &str = "open";
+const MUTE_ID: &str = "mute";
+const SIGN_OUT_ID: &str = "sign-out";
+const QUIT_ID: &str = "quit";
+const STATUS_PREFIX: &str = "status:";
+
+pub struct Tray {
+    icon: TrayIcon,
+    mute: MenuItem<Wry>,
+    statuses: Vec<(UserStatus, CheckMenuItem<Wry>)>,
+    status: Mutex<UserStatus>,
+    unread: Mutex<UnreadSummary>,
+}
+
+impl Tray {
+    pub fn status(&self) -> UserStatus {
+        *self.status.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// When muted notifications resume, if they are muted.
+pub fn muted_until(app: &AppHandle) -> Option<Timestamp> {
+    app.try_state::<SettingsStore>()?
+        .get()
+        .notifications
+        .muted_until
+        .filter(|until| now() < *until)
+}
+
+/// Creates the tray icon. Without one (e.g. no status notifier host on
+/// Linux) the app still works, but closing the window quits it.
+pub fn init(app: &AppHandle) -> tauri::Result<()> {
+    let open = MenuItem::with_id(app, OPEN_ID, "Open Chitchat", true, None::<&str>)?;
+    let muted_until = muted_until(app);
+    let mute = MenuItem::with_id(app, MUTE_ID, mute_text(muted_until), true, None::<&str>)?;
+    let statuses = UserStatus::ALL
+        .into_iter()
+        .map(|status| {
+            let item = CheckMenuItem::with_id(
+                app,
+                format!("{STATUS_PREFIX}{}", status.as_str()),
+                status_label(status),
+                true,
+                status == UserStatus::default(),
+                None::<&str>,
+            )?;
+            Ok((status, item))
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let status_menu = Submenu::with_items(
+        app,
+        "Set status",
+        true,
+        &statuses
+            .iter()
+            .map(|(_, item)| item as &dyn tauri::menu::IsMenuItem<Wry>)
+            .collect::<Vec<_>>(),
+    )?;
+    let sign_out = MenuItem::with_id(app, SIGN_OUT_ID, "Sign out", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, QUIT_ID, "Quit", true, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &open,
+            &PredefinedMenuItem::separator(app)?,
+            &mute,
+            &status_menu,
+            &PredefinedMenuItem::separator(app)?,
+            &sign_out,
+            &quit,
+        ],
+    )?;
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("Chitchat")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(on_menu_event)
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_main_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    let icon = builder.build(app)?;
+
+    app.manage(Tray {
+        icon,
+        mute,
+        statuses,
+        status: Mutex::new(UserStatus::default()),
+        unread: Mutex::new(UnreadSummary::default()),
+    });
+    // A mute from the last run that has not run out yet
+    if let Some(until) = muted_until {
+        spawn_unmute(app.clone(), until);
+    }
+    Ok(())
+}
+
+fn status_label(status: UserStatus) -> &'static str {
+    match status {
+        UserStatus::Available => "Available",
+        UserStatus::Away => "Away",
+        UserStatus::Busy => "Busy",
+    }
+}
+
+fn on_menu_event(app: &AppHandle, event: MenuEvent) {
+    match event.id().as_ref() {
+        OPEN_ID => show_main_window(app),
+        MUTE_ID => toggle_mute(app),
+        SIGN_OUT_ID => {
+            show_main_window(app);
+            if let Err(e) = app.emit(SIGN_OUT_EVENT, ()) {
+                tracing::warn!("failed to request sign-out: {e}");
+            }
+        }
+        QUIT_ID => quit(app.clone()),
+        id => {
+            let chosen = id
+                .strip_prefix(STATUS_PREFIX)
+                .and_then(|name| UserStatus::ALL.into_iter().find(|s| s.as_str() == name));
+            if let Some(status) = chosen {
+                set_status(app, status);
+            }
+        }
+    }
+}
+
+/// Brings the main window back from the tray (or from being minimized).
+pub fn show_main_window(app: &AppHandle) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let _ = window.unminimize();
+    let _ = window.show();
+    let _ = window.set_focus();
+}
+
+/// Whether closing the main window should hide it instead, which only makes
+/// sense when the tray is there to bring it back.
+pub fn hides_on_close(app: &AppHandle) -> bool {
+    app.try_state::<Tray>().is_some()
+}
+
+/// Shows the unread count on the tray and taskbar. Returns false if it did
+/// not change since the last call.
+pub fn show_unread(app: &AppHandle, summary: &UnreadSummary) -> bool {
+    let Some(tray) = app.try_state::<Tray>() else {
+        return true;
+    };
+    {
+        let mut unread = tray.unread.lock().unwrap_or_else(|e| e.into_inner());
+        if *unread == *summary {
+            return false;
+        }
+        *unread = summary.clone();
+    }
+
+    let count = summary.chat_ids.len();
+    let tooltip = match count {
+        0 => "Chitchat".to_string(),
+        1 => "Chitchat: 1 unread chat".to_string(),
+        n => format!("Chitchat: {n} unread chats"),
+    };
+    let label = (count > 0).then(|| count.to_string());
+    if let Err(e) = tray.icon.set_tooltip(Some(tooltip)) {
+        tracing::warn!("failed to update tooltip: {e}");
+    }
+    // Only shown on macOS, next to the icon in the menu bar
+    let _ = tray.icon.set_title(label.as_deref());
+    // Windows has no taskbar badge count, only overlay icons
+    #[cfg(not(target_os = "windows"))]
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.set_badge_count((count > 0).then_some(count as i64));
+    }
+    true
+}
+
+fn toggle_mute(app: &AppHandle) {
+    let until = match muted_until(app) {
+        Some(_) => None,
+        None => Some(now() + MUTE_FOR.as_millis() as Timestamp),
+    };
+    set_muted_until(app, until);
+    if let Some(until) = until {
+        spawn_unmute(app.clone(), until);
+    }
+}
+
+/// Unmutes once `until` has passed, unless notifications were unmuted (or
+/// muted again) in the meantime.
+fn spawn_unmute(app: AppHandle, until: Timestamp) {
+    tauri::async_runtime::spawn(async move {
+        let left = u64::try_from(until - now()).unwrap_or(0);
+        tokio::time::sleep(Duration::from_millis(left)).await;
+        let current = app
+            .try_state::<SettingsStore>()
+            .and_then(|settings| settings.get().notifications.muted_until);
+        if current == Some(until) {
+            set_muted_until(&app, None);
+        }
+    });
+}
+
+fn mute_text(until: Option<Timestamp>) -> &'static str {
+    if until.is_some() {
+        "Unmute notifications"
+    } else {
+        "Mute all for 1 hour"
+    }
+}
+
+fn set_muted_until(app: &AppHandle, until: Option<Timestamp>) {
+    let changes = json!({ "notifications": { "mutedUntil": until } });
+    if let Err(e) = settings::update(app, changes) {
+        tracing::warn!("failed to save the mute: {e}");
+    }
+    if let Err(e) = app.state::<Tray>().mute.set_text(mute_text(until)) {
+        tracing::warn!("failed to update menu: {e}");
+    }
+    if let Err(e) = app.emit(MUTE_EVENT, until) {
+        tracing::warn!("failed to emit mute event: {e}");
+    }
+}
+
+fn set_status(app: &AppHandle, status: UserStatus) {
+    let tray = app.state::<Tray>();
+    *tray.status.lock().unwrap_or_else(|e| e.into_inner()) = status;
+    // Check items toggle themselves on click; keep exactly one checked
+    for (item_status, item) in &tray.statuses {
+        let _ = item.set_checked(*item_status == status);
+    }
+    spawn_publish_status(app.clone());
+}
+
+/// Writes the chosen status to `users/{uid}/status` so contacts see it.
+pub fn spawn_publish_status(app: AppHandle) {
+    let Some(tray) = app.try_state::<Tray>() else {
+        return;
+    };
+    let status = tray.status();
+    tauri::async_runtime::spawn(async move {
+        let session = app.state::<Session>();
+        let Some(credentials) = session.credentials() else {
+            return;
+        };
+        if let Err(e) = publish_status(&session, &credentials, status).await {
+            tracing::warn!("failed to publish status: {e}");
+        }
+    });
+}
+
+async fn publish_status(
+    session: &Session,
+    credentials: &Credentials,
+    status: UserStatus,
+) -> Result<()> {
+    let db = session.database(&reqwest::Client::new())?;
+    db.set(&format!("users/{}/status", credentials.user_id), &status)
+        .await?;
+    Ok(())
+}
+
+/// Marks the user offline, then exits. Presence would also go offline when
+/// the connection drops, but only after the server notices.
+fn quit(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let session = app.state::<Session>();
+        if let Some(credentials) = session.credentials() {
+            match tokio::time::timeout(QUIT_TIMEOUT, go_offline(&session, &credentials)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => tracing::warn!("failed to go offline: {e}"),
+                Err(_) => tracing::warn!("timed out going offline"),
+            }
+        }
+        app.exit(0);
+    });
+}
+
+async fn go_offline(session: &Session, credentials: &Credentials) -> Result<()> {
+    let db = session.database(&reqwest::Client::new())?;
+    db.update(
+        &format!("users/{}", credentials.user_id),
+        &json!({ "isOnline": false, "lastSeen": ServerTimestamp }),
+    )
+    .await?;
+    Ok(())
+}
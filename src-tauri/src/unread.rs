@@ -0,0 +1,153 @@
+// Unread chats, computed from the chats the session follows
+//
+// A chat is unread when its last message came from someone else after the
+// point the user last read it up to. The chats come from the session's own
+// subscription (see [`crate::subscriptions`]), so the count does not depend
+// on a webview. Read markers live in the local store, so the count survives
+// restarts; chats last updated before an account's first sync start out read
+// rather than greeting a fresh install with a wall of unread chats.
+use rusqlite::{params, OptionalExtension};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::Result;
+use crate::models::{Chat, Timestamp, UnreadSummary};
+use crate::session::Session;
+use crate::store::Store;
+use crate::subscriptions::Subscriptions;
+use crate::tray;
+
+/// Event emitted whenever the set of unread chats changes.
+pub const CHANGED_EVENT: &str = "unread-changed";
+
+/// Whether no chat has a read marker yet, as before the first sync after
+/// sign-in.
+pub fn is_fresh(store: &Store) -> Result<bool> {
+    let seeded: bool =
+        store
+            .conn()
+            .query_row("SELECT EXISTS (SELECT 1 FROM chat_reads)", [], |row| {
+                row.get(0)
+            })?;
+    Ok(!seeded)
+}
+
+/// Marks the chats last updated by `up_to` read as of their current summary,
+/// unless they have a read marker already.
+pub fn seed(store: &Store, chats: &[Chat], up_to: Timestamp) -> Result<()> {
+    let mut conn = store.conn();
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO chat_reads (chat_id, read_at) VALUES (?1, ?2)
+             ON CONFLICT (chat_id) DO NOTHING",
+        )?;
+        for chat in chats.iter().filter(|chat| chat.updated_at <= up_to) {
+            stmt.execute(params![chat.id, chat.updated_at])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Records that the user has read `chat_id` up to `up_to` (the chat's
+/// `updatedAt` as they saw it). Markers never move backwards.
+pub fn mark_read(store: &Store, chat_id: &str, up_to: Timestamp) -> Result<()> {
+    store.conn().execute(
+        "INSERT INTO chat_reads (chat_id, read_at) VALUES (?1, ?2)
+         ON CONFLICT (chat_id) DO UPDATE SET read_at = max(read_at, excluded.read_at)",
+        params![chat_id, up_to],
+    )?;
+    Ok(())
+}
+
+/// Unread chats of `user_id` among `chats`, newest first.
+pub fn summary(store: &Store, user_id: &str, chats: &[Chat]) -> Result<UnreadSummary> {
+    let conn = store.conn();
+    let mut stmt = conn.prepare_cached("SELECT read_at FROM chat_reads WHERE chat_id = ?1")?;
+    let mut unread = Vec::new();
+    for chat in chats {
+        let from_other = chat
+            .last_message_sender_id
+            .as_ref()
+            .is_some_and(|sender| sender != user_id);
+        if !from_other {
+            continue;
+        }
+        let read_at: Option<Timestamp> = stmt.query_row([&chat.id], |row| row.get(0)).optional()?;
+        if chat.updated_at > read_at.unwrap_or(0) {
+            unread.push(chat);
+        }
+    }
+    unread.sort_by_key(|chat| std::cmp::Reverse(chat.updated_at));
+    Ok(UnreadSummary {
+        chat_ids: unread.into_iter().map(|chat| chat.id.clone()).collect(),
+    })
+}
+
+/// The current summary for the signed-in user (empty when signed out).
+pub fn current(app: &AppHandle) -> Result<UnreadSummary> {
+    match app.state::<Session>().credentials() {
+        Some(credentials) => summary(
+            &app.state::<Store>(),
+            &credentials.user_id,
+            &app.state::<Subscriptions>().account_chats(),
+        ),
+        None => Ok(UnreadSummary::default()),
+    }
+}
+
+/// Recomputes the unread chats and, if they changed, updates the tray and
+/// notifies the frontend.
+pub fn publish(app: &AppHandle) {
+    let summary = match current(app) {
+        Ok(summary) => summary,
+        Err(e) => {
+            tracing::warn!("failed to count unread chats: {e}");
+            return;
+        }
+    };
+    if !tray::show_unread(app, &summary) {
+        return;
+    }
+    if let Err(e) = app.emit(CHANGED_EVENT, summary) {
+        tracing::warn!("failed to emit change event: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chat(id: &str, sender: Option<&str>, updated_at: Timestamp) -> Chat {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "lastMessageSenderId": sender,
+            "updatedAt": updated_at,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn chats_from_others_are_unread_after_their_marker() {
+        let store = Store::open_in_memory();
+        let chats = [
+            chat("read", Some("u2"), 10),
+            chat("mine", Some("u1"), 30),
+            chat("old", Some("u2"), 20),
+            chat("new", Some("u2"), 40),
+            chat("empty", None, 50),
+        ];
+        assert!(is_fresh(&store).unwrap());
+        seed(&store, &chats, 20).unwrap();
+        assert!(!is_fresh(&store).unwrap());
+        mark_read(&store, "read", 5).unwrap();
+
+        let unread = summary(&store, "u1", &chats).unwrap();
+        assert_eq!(unread.chat_ids, ["new"]);
+
+        // A later message in a seeded chat makes it unread again
+        let chats = [chat("old", Some("u2"), 25), chat("new", Some("u2"), 40)];
+        let unread = summary(&store, "u1", &chats).unwrap();
+        assert_eq!(unread.chat_ids, ["new", "old"]);
+    }
+}
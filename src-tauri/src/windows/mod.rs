@@ -0,0 +1,143 @@
+// Application windows
+//
+// The main window is declared in `tauri.conf.json` but not created from
+// there: it is opened during setup, once it is known whether the web
+// inspector may be enabled for this run. Chats can also be popped out into
+// windows of their own, labelled `chat-<chat id>`, which load the same page
+// and are told which chat to show. Every webview keeps its storage with the
+// profile, and is told which profile it runs in before the page loads.
+//
+// Windows open where they were last closed; see [`state`].
+pub mod state;
+
+use tauri::{
+    AppHandle, Manager, Runtime, WebviewUrl, WebviewWindow, WebviewWindowBuilder, Window,
+    WindowEvent,
+};
+
+use crate::diagnostics;
+use crate::error::{Error, Result};
+use crate::profiles::Profile;
+use crate::push_id;
+
+use self::state::WindowStates;
+
+pub const MAIN: &str = "main";
+
+/// Labels of chat windows start with this, followed by the chat ID.
+pub const CHAT_PREFIX: &str = "chat-";
+
+const CHAT_WIDTH: f64 = 480.0;
+const CHAT_HEIGHT: f64 = 650.0;
+const CHAT_MIN_WIDTH: f64 = 360.0;
+const CHAT_MIN_HEIGHT: f64 = 400.0;
+
+/// Opens the main window, with the web inspector enabled only if `devtools`
+/// is set (debug builds always have it).
+pub fn create_main(app: &AppHandle, devtools: bool) -> Result<WebviewWindow> {
+    let config = app
+        .config()
+        .app
+        .windows
+        .iter()
+        .find(|window| window.label == MAIN)
+        .ok_or(tauri::Error::WindowNotFound)?;
+    let builder = WebviewWindowBuilder::from_config(app, config)?;
+    let builder = with_profile(app, builder, devtools, "")?;
+
+    let profile = app.state::<Profile>();
+    let mut title = config.title.clone();
+    if !profile.is_default() {
+        title = format!("{title} – {}", profile.name());
+    }
+    if devtools {
+        title.push_str(" (diagnostics mode)");
+    }
+    let window = show(app, builder.title(title))?;
+    #[cfg(any(debug_assertions, feature = "devtools"))]
+    if devtools {
+        window.open_devtools();
+    }
+    Ok(window)
+}
+
+/// Opens a window showing only the chat `chat_id`, or brings it to the front
+/// if it is already open. The page sets the title once the chat is loaded.
+pub fn open_chat(app: &AppHandle, chat_id: &str) -> Result<()> {
+    // Push IDs; labels take no other characters
+    if !push_id::is_valid_key(chat_id)
+        || !chat_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(Error::InvalidChatId(chat_id.into()));
+    }
+    let label = format!("{CHAT_PREFIX}{chat_id}");
+    if let Some(window) = app.get_webview_window(&label) {
+        let _ = window.unminimize();
+        window.set_focus()?;
+        return Ok(());
+    }
+
+    let builder = WebviewWindowBuilder::new(app, label, WebviewUrl::default())
+        .title(app.package_info().name.clone())
+        .inner_size(CHAT_WIDTH, CHAT_HEIGHT)
+        .min_inner_size(CHAT_MIN_WIDTH, CHAT_MIN_HEIGHT);
+    let script = format!(
+        "window.__CHITCHAT_CHAT__ = {};",
+        serde_json::to_string(chat_id)?
+    );
+    let builder = with_profile(app, builder, diagnostics::enabled(), &script)?;
+    show(app, builder)?;
+    Ok(())
+}
+
+/// Keeps track of where windows are, and saves it when one is closed.
+pub fn on_event<R: Runtime>(window: &Window<R>, event: &WindowEvent) {
+    let Some(states) = window.try_state::<WindowStates>() else {
+        return;
+    };
+    match event {
+        WindowEvent::Moved(_) | WindowEvent::Resized(_) => states.track(window),
+        WindowEvent::CloseRequested { .. } => {
+            if let Err(e) = states.save() {
+                tracing::warn!("failed to save window placement: {e}");
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Sets up a webview for the current profile, running `script` as well
+/// before the page loads.
+fn with_profile<'a>(
+    app: &AppHandle,
+    builder: WebviewWindowBuilder<'a, tauri::Wry, AppHandle>,
+    devtools: bool,
+    script: &str,
+) -> Result<WebviewWindowBuilder<'a, tauri::Wry, AppHandle>> {
+    let profile = app.state::<Profile>();
+    let mut builder = builder
+        .devtools(devtools || cfg!(debug_assertions))
+        .initialization_script(format!("{}{script}", profile.script()?))
+        .visible(false);
+    if let Some(dir) = profile.webview_dir() {
+        builder = builder
+            .data_directory(dir)
+            .data_store_identifier(profile.webview_store_id());
+    }
+    Ok(builder)
+}
+
+/// Builds a window hidden, puts it where it was last, then shows it.
+fn show(
+    app: &AppHandle,
+    builder: WebviewWindowBuilder<'_, tauri::Wry, AppHandle>,
+) -> Result<WebviewWindow> {
+    let window = builder.build()?;
+    if let Some(states) = app.try_state::<WindowStates>() {
+        states.restore(&window.as_ref().window());
+    }
+    window.show()?;
+    Ok(window)
+}
@@ -0,0 +1,381 @@
+// Window placement
+//
+// Each window's size, position and maximized state is kept per monitor, in
+// `window-state.json` with the profile's settings. A window opens where it
+// last was, on the monitor it was last on; if that monitor is gone, where it
+// was on another connected one; and otherwise at its default size. Monitors
+// are told apart by name, position and resolution, so rearranged screens
+// count as new ones. A placement is fitted into the monitor's work area as
+// it is restored, so a window that was partly off screen, or is larger than
+// the monitor now is, opens where all of it can be seen.
+//
+// Chat windows share one placement, that of the one last moved, so a chat
+// pops out where the last one was and the file does not grow with every chat
+// ever popped out.
+//
+// Placements are in physical pixels and updated in memory as windows move,
+// then written when a window is closed and when the app exits.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Monitor, PhysicalPosition, PhysicalRect, PhysicalSize, Runtime, Window};
+
+use crate::error::Result;
+
+use super::CHAT_PREFIX;
+
+const FILE_NAME: &str = "window-state.json";
+
+/// Key of the placement shared by chat windows.
+const CHAT_KEY: &str = "chat";
+
+/// Placements by window label (see [`state_key`]), managed as state.
+pub struct WindowStates {
+    path: PathBuf,
+    windows: Mutex<BTreeMap<String, WindowState>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WindowState {
+    /// The monitor the window was last on.
+    monitor: Option<String>,
+    monitors: BTreeMap<String, Placement>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Placement {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    maximized: bool,
+}
+
+/// The part of a monitor windows can be placed on, in physical pixels.
+#[derive(Debug, Clone, Copy)]
+struct Area {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+impl WindowStates {
+    /// Loads the placements saved in `dir`. An unreadable file is logged and
+    /// treated as empty; windows then open at their default size.
+    pub fn open(dir: &Path) -> Self {
+        let path = dir.join(FILE_NAME);
+        let windows: BTreeMap<String, WindowState> = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                tracing::warn!("ignoring {}: {e}", path.display());
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        WindowStates {
+            path,
+            windows: Mutex::new(windows),
+        }
+    }
+
+    /// Moves and sizes `window` as it was saved. Call before showing it.
+    pub fn restore<R: Runtime>(&self, window: &Window<R>) {
+        let windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(state) = windows.get(state_key(window.label())) else {
+            return;
+        };
+        let connected: Vec<(String, Area)> = window
+            .available_monitors()
+            .unwrap_or_default()
+            .iter()
+            .map(|monitor| (monitor_key(monitor), Area::from(monitor.work_area())))
+            .collect();
+        let Some(placement) = state.placement(&connected) else {
+            return;
+        };
+
+        let placed = window
+            .set_size(PhysicalSize::new(placement.width, placement.height))
+            .and_then(|()| window.set_position(PhysicalPosition::new(placement.x, placement.y)))
+            .and_then(|()| {
+                if placement.maximized {
+                    window.maximize()
+                } else {
+                    Ok(())
+                }
+            });
+        if let Err(e) = placed {
+            tracing::warn!("failed to place window {}: {e}", window.label());
+        }
+    }
+
+    /// Records where `window` is now, after it moved or was resized.
+    pub fn track<R: Runtime>(&self, window: &Window<R>) {
+        // Minimized windows report made-up positions, and full screen is not
+        // restored
+        if window.is_minimized().unwrap_or(true) || window.is_fullscreen().unwrap_or(true) {
+            return;
+        }
+        let Ok(Some(monitor)) = window.current_monitor() else {
+            return;
+        };
+        let (Ok(position), Ok(size), Ok(maximized)) = (
+            window.outer_position(),
+            window.inner_size(),
+            window.is_maximized(),
+        ) else {
+            return;
+        };
+
+        let key = monitor_key(&monitor);
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        let state = windows
+            .entry(state_key(window.label()).to_string())
+            .or_default();
+        state.monitor = Some(key.clone());
+        let placement = state.monitors.entry(key).or_insert(Placement {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+            maximized,
+        });
+        // Keep the size to return to when it is no longer maximized
+        if !maximized {
+            placement.x = position.x;
+            placement.y = position.y;
+            placement.width = size.width;
+            placement.height = size.height;
+        }
+        placement.maximized = maximized;
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        // Write a copy and swap it in, so a crash never leaves half a file
+        let partial = self.path.with_extension("json.partial");
+        fs::write(&partial, serde_json::to_vec_pretty(&*windows)?)?;
+        fs::rename(&partial, &self.path)?;
+        Ok(())
+    }
+}
+
+impl WindowState {
+    /// Where to put the window on the `connected` monitors: on the one it was
+    /// last on, or else on the first one it has been on before.
+    fn placement(&self, connected: &[(String, Area)]) -> Option<Placement> {
+        let on = |(key, area): &(String, Area)| {
+            self.monitors
+                .get(key)
+                .map(|placement| placement.fitted(area))
+        };
+        self.monitor
+            .as_ref()
+            .and_then(|last| connected.iter().find(|(key, _)| key == last))
+            .and_then(on)
+            .or_else(|| connected.iter().find_map(on))
+    }
+}
+
+impl Placement {
+    /// This placement shrunk and moved as little as needed to fit in `area`.
+    fn fitted(&self, area: &Area) -> Placement {
+        let width = self.width.min(area.width);
+        let height = self.height.min(area.height);
+        let clamp = |at: i32, start: i32, room: u32| {
+            let end = i64::from(start) + i64::from(room);
+            i64::from(at).clamp(i64::from(start), end) as i32
+        };
+        Placement {
+            x: clamp(self.x, area.x, area.width - width),
+            y: clamp(self.y, area.y, area.height - height),
+            width,
+            height,
+            maximized: self.maximized,
+        }
+    }
+}
+
+impl From<&PhysicalRect<i32, u32>> for Area {
+    fn from(rect: &PhysicalRect<i32, u32>) -> Self {
+        Area {
+            x: rect.position.x,
+            y: rect.position.y,
+            width: rect.size.width,
+            height: rect.size.height,
+        }
+    }
+}
+
+/// Where the placement of the window labelled `label` is kept.
+fn state_key(label: &str) -> &str {
+    if label.starts_with(CHAT_PREFIX) {
+        CHAT_KEY
+    } else {
+        label
+    }
+}
+
+fn monitor_key(monitor: &Monitor) -> String {
+    let position = monitor.position();
+    let size = monitor.size();
+    format!(
+        "{} {}x{} at {},{}",
+        monitor.name().map(String::as_str).unwrap_or("unnamed"),
+        size.width,
+        size.height,
+        position.x,
+        position.y
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_windows_share_a_placement() {
+        assert_eq!(state_key("chat--Nx1"), CHAT_KEY);
+        assert_eq!(state_key("chat--Nx2"), CHAT_KEY);
+        assert_eq!(state_key("main"), "main");
+    }
+
+    const LAPTOP: &str = "eDP-1 2560x1600 at 0,0";
+    const EXTERNAL: &str = "DP-2 3840x2160 at 2560,0";
+    const LEFT: &str = "HDMI-1 1920x1080 at -1920,0";
+
+    /// Work areas, less a 40 pixel panel at the top.
+    fn connected(keys: &[&str]) -> Vec<(String, Area)> {
+        keys.iter()
+            .map(|&key| {
+                let area = match key {
+                    LAPTOP => Area {
+                        x: 0,
+                        y: 40,
+                        width: 2560,
+                        height: 1560,
+                    },
+                    EXTERNAL => Area {
+                        x: 2560,
+                        y: 40,
+                        width: 3840,
+                        height: 2120,
+                    },
+                    LEFT => Area {
+                        x: -1920,
+                        y: 40,
+                        width: 1920,
+                        height: 1040,
+                    },
+                    _ => unreachable!(),
+                };
+                (key.to_string(), area)
+            })
+            .collect()
+    }
+
+    fn placement(x: i32, y: i32, width: u32, height: u32) -> Placement {
+        Placement {
+            x,
+            y,
+            width,
+            height,
+            maximized: false,
+        }
+    }
+
+    fn state(last: &str, monitors: &[(&str, Placement)]) -> WindowState {
+        WindowState {
+            monitor: Some(last.to_string()),
+            monitors: monitors
+                .iter()
+                .map(|(key, placement)| (key.to_string(), *placement))
+                .collect(),
+        }
+    }
+
+    fn position(placement: Option<Placement>) -> Option<(i32, i32, u32, u32)> {
+        placement.map(|p| (p.x, p.y, p.width, p.height))
+    }
+
+    #[test]
+    fn restores_on_the_last_monitor() {
+        let state = state(
+            EXTERNAL,
+            &[
+                (LAPTOP, placement(100, 100, 1200, 800)),
+                (EXTERNAL, placement(3000, 200, 1600, 1000)),
+            ],
+        );
+        assert_eq!(
+            position(state.placement(&connected(&[LAPTOP, EXTERNAL]))),
+            Some((3000, 200, 1600, 1000))
+        );
+    }
+
+    #[test]
+    fn falls_back_to_another_monitor_it_was_on() {
+        let state = state(
+            EXTERNAL,
+            &[
+                (EXTERNAL, placement(3000, 200, 1600, 1000)),
+                (LAPTOP, placement(100, 100, 1200, 800)),
+            ],
+        );
+        assert_eq!(
+            position(state.placement(&connected(&[LEFT, LAPTOP]))),
+            Some((100, 100, 1200, 800))
+        );
+    }
+
+    #[test]
+    fn never_restores_a_placement_from_a_missing_monitor() {
+        let state = state(EXTERNAL, &[(EXTERNAL, placement(3000, 200, 1600, 1000))]);
+        assert_eq!(position(state.placement(&connected(&[LAPTOP]))), None);
+        assert_eq!(position(state.placement(&[])), None);
+    }
+
+    #[test]
+    fn pulls_a_window_partly_off_screen_back_in() {
+        let state = state(
+            LAPTOP,
+            &[
+                (LAPTOP, placement(2000, -300, 1200, 800)),
+                (LEFT, placement(-2400, 900, 800, 600)),
+            ],
+        );
+        assert_eq!(
+            position(state.placement(&connected(&[LAPTOP]))),
+            Some((1360, 40, 1200, 800))
+        );
+        assert_eq!(
+            position(state.placement(&connected(&[LEFT]))),
+            Some((-1920, 480, 800, 600))
+        );
+    }
+
+    #[test]
+    fn shrinks_a_window_larger_than_the_monitor() {
+        let mut saved = placement(2700, 100, 3600, 2000);
+        saved.maximized = true;
+        let state = state(
+            EXTERNAL,
+            &[(EXTERNAL, saved), (LAPTOP, placement(-50, 0, 3600, 2000))],
+        );
+        let fitted = state.placement(&connected(&[EXTERNAL])).unwrap();
+        assert_eq!(
+            (fitted.x, fitted.y, fitted.width, fitted.height),
+            (2700, 100, 3600, 2000)
+        );
+        assert!(fitted.maximized);
+        assert_eq!(
+            position(state.placement(&connected(&[LAPTOP]))),
+            Some((0, 40, 2560, 1560))
+        );
+    }
+}
@@ -1,6 +1,6 @@
 import { createSignal, Show, onMount, onCleanup, createEffect, on } from 'solid-js';
 import { DropdownMenu } from '@kobalte/core/dropdown-menu';
-import { getCurrentWindow } from '@tauri-apps/api/window';
+import { listen } from '@tauri-apps/api/event';
 import './App.css';
 import { Login } from './components/Login';
 import { ChatList } from './components/ChatList';
@@ -15,44 +15,46 @@ import {
 } from './components/UpdateChecker';
 import { UpdatePage } from './components/UpdatePage';
 import { ThemeToggle } from './components/ThemeToggle';
+import { ExportMenuItems, ExportStatus } from './components/ExportMenu';
+import { LogViewer } from './components/LogViewer';
+import { SettingsDialog } from './components/SettingsDialog';
+import { ProfileDialog } from './components/ProfileDialog';
 import { user, loading, initAuthListener, cleanupAuthListener } from './stores/auth';
 import { initChatsListener, cleanupChatsListener, cleanupMessagesListener } from './stores/chats';
+import { initDeepLinks, cleanupDeepLinks } from './stores/deepLinks';
 import { signOut } from './services/auth';
 import { initUserPresence, cleanupUserPresence } from './services/messages';
 import { initSounds } from './services/sounds';
+import { clearLocalStore } from './services/localStore';
+import { clearMediaCache, getMediaCacheUsage } from './services/media';
+import { formatFileSize } from './services/attachments';
+import { currentProfile, DEFAULT_PROFILE } from './services/profiles';
+import { UI_LABELS } from './constants/messages';
 // Initialize theme on app load
 import './stores/theme';
 
 function App() {
   const [showNewChat, setShowNewChat] = createSignal(false);
-  let closeUnlisten: (() => void) | null = null;
+  const [mediaCacheBytes, setMediaCacheBytes] = createSignal(0);
+  const [showLogs, setShowLogs] = createSignal(false);
+  const [showSettings, setShowSettings] = createSignal(false);
+  const [showProfiles, setShowProfiles] = createSignal(false);
+  const profile = currentProfile().name;
+  let trayUnlisten: (() => void) | null = null;
 
   onMount(() => {
     initAuthListener();
     initSounds();
+    initDeepLinks().catch((err) => console.error('Failed to listen for links:', err));
 
-    // Use Tauri's onCloseRequested to properly handle window close
-    // This allows us to set user offline before closing
-    getCurrentWindow()
-      .onCloseRequested(async (event) => {
-        const currentUser = user();
-        if (currentUser) {
-          event.preventDefault();
-
-          try {
-            await cleanupUserPresence(currentUser.uid);
-          } catch (error) {
-            console.error('Failed to cleanup presence on close:', error);
-          }
-
-          await getCurrentWindow().destroy();
-        }
-      })
+    // Closing the window only hides it to the tray (handled in Rust), so the
+    // session stays up; signing out from the tray menu is routed here
+    listen('tray-sign-out', () => handleSignOut())
       .then((unlisten) => {
-        closeUnlisten = unlisten;
+        trayUnlisten = unlisten;
       })
       .catch((err) => {
-        console.error('Failed to setup close handler:', err);
+        console.error('Failed to listen for tray sign-out:', err);
       });
 
     // Global keyboard shortcuts
@@ -73,7 +75,7 @@ function App() {
     document.addEventListener('keydown', handleKeyDown);
 
     onCleanup(() => {
-      if (closeUnlisten) closeUnlisten();
+      if (trayUnlisten) trayUnlisten();
       document.removeEventListener('keydown', handleKeyDown);
     });
   });
@@ -82,6 +84,7 @@ function App() {
     cleanupAuthListener();
     cleanupChatsListener();
     cleanupMessagesListener();
+    cleanupDeepLinks();
   });
 
   // Initialize chats and presence when user logs in
@@ -102,9 +105,22 @@ function App() {
     }
     cleanupChatsListener();
     cleanupMessagesListener();
+    await clearLocalStore().catch((error) => console.error('Failed to clear local store:', error));
+    await clearMediaCache().catch((error) => console.error('Failed to clear media cache:', error));
     await signOut();
   }
 
+  function refreshMediaCacheUsage() {
+    getMediaCacheUsage()
+      .then((usage) => setMediaCacheBytes(usage.bytes))
+      .catch((error) => console.error('Failed to read media cache usage:', error));
+  }
+
+  async function handleClearMediaCache() {
+    await clearMediaCache().catch((error) => console.error('Failed to clear media cache:', error));
+    refreshMediaCacheUsage();
+  }
+
   return (
     <>
       {/* Update checker - runs on mount, renders nothing */}
@@ -129,11 +145,20 @@ function App() {
             <aside class="w-[400px] min-w-[300px] flex flex-col bg-wa-sidebar dark:bg-wa-dark-sidebar border-r border-wa-border dark:border-wa-dark-border">
               {/* Sidebar Header */}
               <header class="flex items-center justify-between px-4 py-3 bg-wa-header dark:bg-wa-dark-header min-h-[60px]">
-                <h1 class="text-xl font-semibold text-wa-dark-green">Chitchat</h1>
+                <h1 class="text-xl font-semibold text-wa-dark-green">
+                  Chitchat
+                  <Show when={profile !== DEFAULT_PROFILE}>
+                    <span class="ml-2 text-sm font-normal text-wa-text-secondary dark:text-wa-dark-text-secondary">
+                      {profile}
+                    </span>
+                  </Show>
+                </h1>
                 <div class="flex items-center gap-2">
                   <ThemeToggle />
                   {/* User menu dropdown */}
-                  <DropdownMenu>
+                  <DropdownMenu
+                    onOpenChange={(open) => open && refreshMediaCacheUsage()}
+                  >
                     <DropdownMenu.Trigger class="flex items-center gap-2 px-2 py-1.5 rounded-lg hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover transition-colors">
                       {/* Avatar */}
                       <div class="w-8 h-8 rounded-full bg-wa-teal flex items-center justify-center text-white text-sm font-semibold">
@@ -166,6 +191,42 @@ function App() {
                           {user()?.email}
                         </DropdownMenu.Item>
                         <DropdownMenu.Separator class="h-px bg-wa-border dark:bg-wa-dark-border my-1" />
+                        <DropdownMenu.Sub overlap gutter={4}>
+                          <DropdownMenu.SubTrigger class="px-4 py-2 text-sm text-wa-text-primary dark:text-wa-dark-text-primary hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover cursor-pointer">
+                            {UI_LABELS.EXPORT_ALL_CHATS}
+                          </DropdownMenu.SubTrigger>
+                          <DropdownMenu.Portal>
+                            <DropdownMenu.SubContent class="min-w-[180px] bg-white dark:bg-wa-dark-sidebar rounded-lg shadow-lg border border-wa-border dark:border-wa-dark-border py-1 z-50">
+                              <ExportMenuItems />
+                            </DropdownMenu.SubContent>
+                          </DropdownMenu.Portal>
+                        </DropdownMenu.Sub>
+                        <DropdownMenu.Item
+                          onSelect={handleClearMediaCache}
+                          disabled={mediaCacheBytes() === 0}
+                          class="px-4 py-2 text-sm text-wa-text-primary dark:text-wa-dark-text-primary hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover cursor-pointer data-[disabled]:opacity-50"
+                        >
+                          {UI_LABELS.CLEAR_MEDIA_CACHE} ({formatFileSize(mediaCacheBytes())})
+                        </DropdownMenu.Item>
+                        <DropdownMenu.Item
+                          onSelect={() => setShowProfiles(true)}
+                          class="px-4 py-2 text-sm text-wa-text-primary dark:text-wa-dark-text-primary hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover cursor-pointer"
+                        >
+                          {UI_LABELS.SWITCH_PROFILE}
+                        </DropdownMenu.Item>
+                        <DropdownMenu.Item
+                          onSelect={() => setShowSettings(true)}
+                          class="px-4 py-2 text-sm text-wa-text-primary dark:text-wa-dark-text-primary hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover cursor-pointer"
+                        >
+                          {UI_LABELS.SETTINGS}
+                        </DropdownMenu.Item>
+                        <DropdownMenu.Item
+                          onSelect={() => setShowLogs(true)}
+                          class="px-4 py-2 text-sm text-wa-text-primary dark:text-wa-dark-text-primary hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover cursor-pointer"
+                        >
+                          {UI_LABELS.DIAGNOSTIC_LOGS}
+                        </DropdownMenu.Item>
+                        <DropdownMenu.Separator class="h-px bg-wa-border dark:bg-wa-dark-border my-1" />
                         <DropdownMenu.Item
                           onSelect={handleSignOut}
                           class="px-4 py-2 text-sm text-red-600 dark:text-red-400 hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover cursor-pointer flex items-center gap-2"
@@ -199,6 +260,14 @@ function App() {
             <Show when={showNewChat()}>
               <NewChatDialog onClose={() => setShowNewChat(false)} />
             </Show>
+
+            <LogViewer isOpen={showLogs()} onClose={() => setShowLogs(false)} />
+
+            <SettingsDialog isOpen={showSettings()} onClose={() => setShowSettings(false)} />
+
+            <ProfileDialog isOpen={showProfiles()} onClose={() => setShowProfiles(false)} />
+
+            <ExportStatus />
           </div>
         </Show>
       </Show>
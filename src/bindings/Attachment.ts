@@ -0,0 +1,25 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ImageInfo } from "./ImageInfo";
+
+/**
+ * A file sent with a message, as carried inside the encrypted message. The
+ * file itself is kept, encrypted, by the attachment backend under
+ * `storage_path`.
+ */
+export type Attachment = { name: string, mimeType: string, 
+/**
+ * Size in bytes.
+ */
+size: number, 
+/**
+ * Hex SHA-256 of the contents, checked after every download.
+ */
+sha256: string, storagePath: string, 
+/**
+ * Base64 key the stored file is encrypted with.
+ */
+key: string, 
+/**
+ * Set for images, so chats can show a preview without downloading them.
+ */
+image?: ImageInfo, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TransferDirection } from "./TransferDirection";
+
+/**
+ * Payload of the `attachment-progress` event, emitted after every chunk.
+ */
+export type AttachmentProgress = { sha256: string, direction: TransferDirection, transferred: number, total: number, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A Firebase project, as given to `initializeApp`.
+ */
+export type BackendConfig = { apiKey: string, authDomain: string, projectId: string, storageBucket: string, databaseURL: string, messagingSenderId?: string, appId?: string, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Chat = { id: string, participants: Record<string, boolean>, participantNames?: Record<string, string>, isGroup?: boolean, groupName?: string, ownerId?: string, lastMessage: string, lastMessageSenderId?: string, updatedAt: Date | number, typing?: Record<string, boolean>, };
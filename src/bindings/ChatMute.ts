@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A chat whose messages do not notify.
+ */
+export type ChatMute = { chatId: string, 
+/**
+ * When the chat notifies again; muted until unmuted without one.
+ */
+until?: number, };
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A file the user picked or dropped. The webview refers to it by a one-time
+ * token, never by its path.
+ */
+export type ChosenFile = { token: string, 
+/**
+ * File name, for display.
+ */
+name: string, };
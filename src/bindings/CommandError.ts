@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ErrorKind } from "./ErrorKind";
+
+/**
+ * What every failed command rejects with.
+ */
+export type CommandError = { kind: ErrorKind, 
+/**
+ * Human-readable description, not meant for matching on.
+ */
+message: string, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Where a `chitchat://` link points. Payload of the `deep-link` event.
+ */
+export type DeepLink = { "kind": "chat", id: string, } | { "kind": "user", email: string, } | { "kind": "invite", token: string, };
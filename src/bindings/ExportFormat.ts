@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * File formats chats can be exported to.
+ */
+export type ExportFormat = "json" | "html" | "markdown" | "text";
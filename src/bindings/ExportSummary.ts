@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Result of a finished export.
+ */
+export type ExportSummary = { path: string, chats: number, messages: number, };
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Dimensions and inline preview of an image attachment.
+ */
+export type ImageInfo = { width: number, height: number, 
+/**
+ * Small JPEG or PNG as a `data:` URL.
+ */
+thumbnail: string, };
@@ -0,0 +1,24 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Limits for images prepared for sending. Omitted fields keep their
+ * defaults.
+ */
+export type ImageOptions = { 
+/**
+ * Longest side of the sent image, in pixels.
+ */
+maxDimension: number, 
+/**
+ * Size the encoded image should fit in; quality and then dimensions are
+ * reduced until it does.
+ */
+maxBytes: number, 
+/**
+ * JPEG quality to start from, 1-100.
+ */
+quality: number, 
+/**
+ * Longest side of the thumbnail, in pixels.
+ */
+thumbnailSize: number, };
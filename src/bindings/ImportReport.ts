@@ -0,0 +1,28 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ImportSender } from "./ImportSender";
+import type { ImportSource } from "./ImportSource";
+
+/**
+ * What an import did, or would do on a dry run.
+ */
+export type ImportReport = { source: ImportSource, 
+/**
+ * Name of the chat in the other messenger, when the export has one.
+ */
+title?: string, dryRun: boolean, 
+/**
+ * Messages found in the export.
+ */
+messages: number, 
+/**
+ * Of those, messages imported before and left alone.
+ */
+alreadyImported: number, 
+/**
+ * Lines or entries that are not messages, such as "X joined".
+ */
+skipped: number, firstTimestamp?: number, lastTimestamp?: number, 
+/**
+ * Most active first.
+ */
+senders: Array<ImportSender>, };
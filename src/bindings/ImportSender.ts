@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Someone who wrote messages in an imported export.
+ */
+export type ImportSender = { 
+/**
+ * Name as it appears in the export.
+ */
+name: string, messages: number, 
+/**
+ * The chat participant the sender was mapped to, if any. Messages of
+ * unmapped senders keep the name from the export.
+ */
+userId?: string, };
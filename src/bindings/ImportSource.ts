@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Messengers whose chat exports can be imported.
+ */
+export type ImportSource = "whatsapp" | "telegram";
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A message as stored in RTDB, before decryption. Encrypted messages carry
+ * an `e2e` envelope instead of `text`.
+ */
+export type IncomingMessage = { id: string, senderId: string, senderName?: string, 
+/**
+ * Plaintext of messages sent before encryption was introduced.
+ */
+text?: string, e2e?: unknown, timestamp: number, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * What a live subscription streams to the webview.
+ */
+export type LiveUpdate<T> = { "kind": "value", "value": T } | { "kind": "cancelled" };
@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LogLevel } from "./LogLevel";
+
+/**
+ * An entry of the app log.
+ */
+export type LogEntry = { 
+/**
+ * RFC 3339, in UTC.
+ */
+timestamp: string, level: LogLevel, 
+/**
+ * The Rust module that logged the entry, or `webview` for entries
+ * forwarded by the frontend.
+ */
+target: string, 
+/**
+ * The message followed by its fields, with personal data redacted.
+ */
+message: string, };
@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LogLevel } from "./LogLevel";
+
+/**
+ * Which log entries to return; all given conditions must match.
+ */
+export type LogFilter = { 
+/**
+ * Least severe level to include.
+ */
+level?: LogLevel, 
+/**
+ * Text the target or message must contain, ignoring case.
+ */
+contains?: string, 
+/**
+ * How many of the most recent matching entries to return.
+ */
+limit?: number, };
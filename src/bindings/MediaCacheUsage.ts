@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * How much of the media cache is in use.
+ */
+export type MediaCacheUsage = { files: number, bytes: number, 
+/**
+ * Size cap; least recently used files are evicted beyond it.
+ */
+maxBytes: number, };
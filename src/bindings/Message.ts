@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Attachment } from "./Attachment";
+
+export type Message = { id: string, senderId: string, senderName?: string, text: string, timestamp: Date | number, 
+/**
+ * Set on history imported from another messenger. Archived messages
+ * only exist in the local store and are never edited or sent.
+ */
+archived?: boolean, attachment?: Attachment, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * What became of a notification.
+ */
+export type NotificationOutcome = "shown" | "coalesced" | "disabled" | "muted" | "chatMuted" | "quietHours" | "focused" | "rateLimited";
@@ -0,0 +1,33 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChatMute } from "./ChatMute";
+import type { QuietHours } from "./QuietHours";
+
+/**
+ * Which desktop notifications are shown. Mentions and keywords get through
+ * muted chats and quiet hours.
+ */
+export type NotificationSettings = { 
+/**
+ * New messages.
+ */
+messages: boolean, 
+/**
+ * Contacts coming online or going offline.
+ */
+presence: boolean, mutedChats: Array<ChatMute>, 
+/**
+ * When notifications are held back, e.g. nights and weekends.
+ */
+quietHours: Array<QuietHours>, 
+/**
+ * Notify for messages that mention the user with `@` and their name.
+ */
+mentions: boolean, 
+/**
+ * Notify for messages containing any of these words, ignoring case.
+ */
+keywords: Array<string>, 
+/**
+ * When notifications muted from the tray resume.
+ */
+mutedUntil: number | null, };
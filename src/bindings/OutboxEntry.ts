@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Attachment } from "./Attachment";
+import type { DeliveryState } from "./DeliveryState";
+
+/**
+ * A message waiting in (or failed out of) the outbox.
+ */
+export type OutboxEntry = { id: string, chatId: string, senderId: string, senderName: string, text: string, attachment?: Attachment, createdAt: number, attempts: number, state: DeliveryState, lastError?: string, };
@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DeliveryState } from "./DeliveryState";
+
+/**
+ * Payload of the `outbox-state` event.
+ */
+export type OutboxStateEvent = { id: string, chatId: string, state: DeliveryState, error?: string, 
+/**
+ * Members a sent message was not encrypted for, as they have not set
+ * up encryption.
+ */
+withoutKeys?: Array<string>, };
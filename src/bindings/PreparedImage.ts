@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChosenFile } from "./ChosenFile";
+import type { ImageInfo } from "./ImageInfo";
+
+/**
+ * An image re-encoded for sending, without its metadata.
+ */
+export type PreparedImage = { 
+/**
+ * The processed copy, ready for `upload_attachment`.
+ */
+file: ChosenFile, image: ImageInfo, };
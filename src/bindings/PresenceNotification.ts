@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A contact coming online or going offline.
+ */
+export type PresenceNotification = { userId: string, userName: string, online: boolean, };
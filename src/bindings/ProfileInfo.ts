@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BackendConfig } from "./BackendConfig";
+
+/**
+ * The profile the webview runs in, set as `window.__CHITCHAT_PROFILE__`.
+ */
+export type ProfileInfo = { name: string, 
+/**
+ * The profile's own Firebase project; the build's project is used
+ * without one.
+ */
+backend?: BackendConfig, };
@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Weekday } from "./Weekday";
+
+/**
+ * A weekly period without notifications, in local time. One that ends
+ * before it starts runs past midnight into the next day.
+ */
+export type QuietHours = { 
+/**
+ * The days it starts on.
+ */
+days: Array<Weekday>, 
+/**
+ * Minutes after midnight.
+ */
+start: number, 
+/**
+ * Minutes after midnight; the same as `start` for the whole day.
+ */
+end: number, };
@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SnippetPart } from "./SnippetPart";
+
+/**
+ * A message found by `search_messages`.
+ */
+export type SearchHit = { chatId: string, messageId: string, senderId: string, senderName?: string, 
+/**
+ * Group name, for hits in group chats.
+ */
+chatName?: string, timestamp: number, 
+/**
+ * The matching part of the message, split into plain and highlighted
+ * runs so the UI never has to render HTML from message text.
+ */
+snippet: Array<SnippetPart>, };
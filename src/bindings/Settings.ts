@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { NotificationSettings } from "./NotificationSettings";
+import type { SoundSettings } from "./SoundSettings";
+import type { Theme } from "./Theme";
+
+/**
+ * User preferences, stored in `settings.json` in the config directory.
+ * Fields missing from the file take their defaults.
+ */
+export type Settings = { theme: Theme, sounds: SoundSettings, notifications: NotificationSettings, };
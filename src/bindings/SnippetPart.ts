@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A run of snippet text; `highlight` marks the parts matching the query.
+ */
+export type SnippetPart = { text: string, highlight: boolean, };
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type SoundSettings = { 
+/**
+ * Play sounds when messages are sent and received.
+ */
+enabled: boolean, 
+/**
+ * From 0 (silent) to 1.
+ */
+volume: number, };
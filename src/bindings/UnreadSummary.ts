@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Chats whose last message came from someone else after the user last read
+ * them, most recent first. Payload of the `unread-changed` event.
+ */
+export type UnreadSummary = { chatIds: Array<string>, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { UserStatus } from "./UserStatus";
+
+export type User = { id: string, email: string, displayName: string, createdAt: Date | number, isOnline?: boolean, lastSeen?: Date | number, status?: UserStatus, };
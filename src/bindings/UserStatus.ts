@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Availability a user shows to their contacts, chosen from the tray menu.
+ */
+export type UserStatus = "available" | "away" | "busy";
@@ -0,0 +1,145 @@
+// Attachment chip - file name and size of an attachment (with its thumbnail
+// for images), a Save button when shown in a message and a remove button
+// while composing
+import { createSignal, onCleanup, onMount, Show } from 'solid-js';
+import type { UnlistenFn } from '@tauri-apps/api/event';
+import {
+  formatFileSize,
+  onAttachmentProgress,
+  saveAttachment,
+} from '../services/attachments';
+import { cacheAttachment, mediaUrl } from '../services/media';
+import { ERROR_MESSAGES, UI_LABELS } from '../constants/messages';
+import type { Attachment } from '../types';
+
+/** Only inline images are shown; the metadata comes from other clients */
+function thumbnailUrl(attachment: Attachment): string | undefined {
+  const thumbnail = attachment.image?.thumbnail;
+  return thumbnail?.startsWith('data:image/jpeg;base64,') ||
+    thumbnail?.startsWith('data:image/png;base64,')
+    ? thumbnail
+    : undefined;
+}
+
+/** Larger images only show their thumbnail until saved */
+const AUTO_LOAD_MAX_SIZE = 5 * 1024 * 1024;
+
+interface Props {
+  attachment: Attachment;
+  /** Upload progress in percent while the file is still being sent */
+  progress?: number | null;
+  /** Shows a remove button instead of Save */
+  onRemove?: () => void;
+}
+
+export function AttachmentChip(props: Props) {
+  const [saving, setSaving] = createSignal<number | null>(null);
+  const [error, setError] = createSignal(false);
+  const [fullImage, setFullImage] = createSignal<string | null>(null);
+  let unlisten: UnlistenFn | undefined;
+  onCleanup(() => unlisten?.());
+
+  // Swap the thumbnail for the full image once it is in the media cache
+  onMount(() => {
+    const attachment = props.attachment;
+    if (props.onRemove || !attachment.image || attachment.size > AUTO_LOAD_MAX_SIZE) return;
+    cacheAttachment(attachment)
+      .then(() => setFullImage(mediaUrl(attachment.sha256)))
+      .catch((err) => console.error('Failed to load image:', err));
+  });
+
+  async function handleSave() {
+    setError(false);
+    setSaving(0);
+    unlisten = await onAttachmentProgress((progress) => {
+      if (progress.sha256 === props.attachment.sha256 && progress.direction === 'download') {
+        setSaving(Math.round((progress.transferred / progress.total) * 100));
+      }
+    });
+    try {
+      await saveAttachment(props.attachment);
+    } catch (err) {
+      console.error('Failed to save attachment:', err);
+      setError(true);
+    } finally {
+      unlisten?.();
+      unlisten = undefined;
+      setSaving(null);
+    }
+  }
+
+  const status = () => {
+    if (props.progress != null) return `${UI_LABELS.UPLOADING} ${props.progress}%`;
+    if (saving() != null) return `${UI_LABELS.SAVING_ATTACHMENT} ${saving()}%`;
+    return formatFileSize(props.attachment.size);
+  };
+
+  return (
+    <div class="flex flex-col my-1 rounded-lg bg-black/5 dark:bg-white/5 max-w-xs overflow-hidden">
+      <Show when={fullImage() ?? thumbnailUrl(props.attachment)}>
+        {(url) => (
+          <img
+            src={url()}
+            alt=""
+            width={props.attachment.image?.width}
+            height={props.attachment.image?.height}
+            class="w-full h-auto max-h-64 object-cover"
+          />
+        )}
+      </Show>
+      <div class="flex items-center gap-3 px-3 py-2">
+        <svg
+          viewBox="0 0 24 24"
+          width="24"
+          height="24"
+          fill="currentColor"
+          aria-hidden="true"
+          class="shrink-0 text-wa-text-secondary dark:text-wa-dark-text-secondary"
+        >
+          <path d="M14 2H6c-1.1 0-2 .9-2 2v16c0 1.1.9 2 2 2h12c1.1 0 2-.9 2-2V8l-6-6zm4 18H6V4h7v5h5v11z" />
+        </svg>
+        <div class="flex flex-col min-w-0 flex-1">
+          <span class="text-sm text-wa-text-primary dark:text-wa-dark-text-primary truncate">
+            {props.attachment.name}
+          </span>
+          <span class="text-xs text-wa-text-secondary dark:text-wa-dark-text-secondary">
+            {status()}
+          </span>
+          <Show when={error()}>
+            <span class="text-xs text-red-500" role="alert">
+              {ERROR_MESSAGES.SAVE_ATTACHMENT_FAILED}
+            </span>
+          </Show>
+        </div>
+        <Show
+          when={props.onRemove}
+          fallback={
+            <button
+              type="button"
+              onClick={handleSave}
+              disabled={saving() != null}
+              aria-label={`${UI_LABELS.SAVE_ATTACHMENT} ${props.attachment.name}`}
+              class="text-sm text-wa-teal hover:underline disabled:opacity-50"
+            >
+              {UI_LABELS.SAVE_ATTACHMENT}
+            </button>
+          }
+        >
+          {(onRemove) => (
+            <button
+              type="button"
+              onClick={() => onRemove()()}
+              aria-label={UI_LABELS.REMOVE_ATTACHMENT}
+              title={UI_LABELS.REMOVE_ATTACHMENT}
+              class="w-6 h-6 flex items-center justify-center rounded-full hover:bg-black/10 dark:hover:bg-white/10 text-wa-text-secondary"
+            >
+              <svg viewBox="0 0 24 24" width="16" height="16" fill="currentColor">
+                <path d="M19 6.41L17.59 5 12 10.59 6.41 5 5 6.41 10.59 12 5 17.59 6.41 19 12 13.41 17.59 19 19 17.59 13.41 12z" />
+              </svg>
+            </button>
+          )}
+        </Show>
+      </div>
+    </div>
+  );
+}
@@ -6,9 +6,11 @@
  * - Online/offline status indicators
  * - Typing indicators
  * - Last message preview
+ * - Unread markers
  * - Search/filter contacts
+ * - Full-text message search across all chats
  */
-import { Show, createMemo, createSignal } from 'solid-js';
+import { Show, For, createMemo, createSignal, createEffect, on, onCleanup } from 'solid-js';
 import { Button } from '@kobalte/core/button';
 import { TextField } from '@kobalte/core/text-field';
 import { AccessibleListbox, type ListboxItem } from './AccessibleListbox';
@@ -18,10 +20,12 @@ import {
   currentChatId,
   selectChat,
   otherUserPresence,
+  unreadChatIds,
 } from '../stores/chats';
 import { user } from '../stores/auth';
-import type { Chat } from '../types';
+import type { Chat, SearchHit } from '../types';
 import { UI_LABELS } from '../constants/messages';
+import { searchMessages } from '../services/search';
 
 // ============================================================================
 // Types
@@ -108,6 +112,29 @@ function useChatDerivedInfo() {
 export function ChatList(props: Props) {
   // Search state
   const [searchQuery, setSearchQuery] = createSignal('');
+  const [messageHits, setMessageHits] = createSignal<SearchHit[]>([]);
+
+  // Search message history (debounced) alongside the chat filter
+  let searchTimeout: ReturnType<typeof setTimeout> | null = null;
+  createEffect(
+    on(searchQuery, (query) => {
+      if (searchTimeout) clearTimeout(searchTimeout);
+      if (!query.trim()) {
+        setMessageHits([]);
+        return;
+      }
+      searchTimeout = setTimeout(() => {
+        searchMessages(query)
+          .then((hits) => {
+            if (searchQuery() === query) setMessageHits(hits);
+          })
+          .catch((error) => console.error('Message search failed:', error));
+      }, 250);
+    })
+  );
+  onCleanup(() => {
+    if (searchTimeout) clearTimeout(searchTimeout);
+  });
 
   // Create memoized derived info for all chats
   const chatInfoMap = useChatDerivedInfo();
@@ -152,11 +179,49 @@ export function ChatList(props: Props) {
     selectChat(chat.id);
   };
 
+  // Message hits as listbox items, keyed by message
+  const hitItems = createMemo(() =>
+    messageHits().map((hit) => ({ ...hit, id: `${hit.chatId}/${hit.messageId}` }))
+  );
+
+  const renderHit = (hit: SearchHit & ListboxItem, _index: () => number, isActive: () => boolean) => {
+    const chatName = () => hit.chatName || getChatInfo(hit.chatId).otherName;
+    const snippetText = () => hit.snippet.map((part) => part.text).join('');
+
+    return (
+      <div
+        class={`w-full px-4 py-2 text-left border-b border-wa-border dark:border-wa-dark-border cursor-pointer hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover
+          ${isActive() ? 'bg-wa-sidebar-active dark:bg-wa-dark-sidebar-active' : ''}`}
+      >
+        <span class="sr-only">
+          {hit.senderName || UI_LABELS.UNKNOWN_USER} in {chatName()}: {snippetText()}
+        </span>
+        <div aria-hidden="true">
+          <div class="text-xs text-wa-text-secondary dark:text-wa-dark-text-secondary truncate">
+            {chatName()} · {hit.senderName || UI_LABELS.UNKNOWN_USER}
+          </div>
+          <p class="text-sm text-wa-text-primary dark:text-wa-dark-text-primary truncate">
+            <For each={hit.snippet}>
+              {(part) =>
+                part.highlight ? (
+                  <mark class="bg-wa-light-green/30 text-inherit rounded-sm">{part.text}</mark>
+                ) : (
+                  part.text
+                )
+              }
+            </For>
+          </p>
+        </div>
+      </div>
+    );
+  };
+
   // Render a single chat item
   const renderChatItem = (chat: ChatItem, _index: () => number, isActive: () => boolean) => {
     // All derived state comes from the memoized map - properly reactive
     const info = () => getChatInfo(chat.id);
     const isSelected = () => currentChatId() === chat.id;
+    const isUnread = () => unreadChatIds().includes(chat.id);
 
     return (
       <div
@@ -169,7 +234,9 @@ export function ChatList(props: Props) {
           ${isActive() ? 'bg-wa-sidebar-active dark:bg-wa-dark-sidebar-active' : ''}`}
       >
         {/* Screen reader text */}
-        <span class="sr-only">{info().label}</span>
+        <span class="sr-only">
+          {isUnread() ? `${info().label}, ${UI_LABELS.UNREAD}` : info().label}
+        </span>
 
         {/* Visual content */}
         <div class="flex items-center gap-3" aria-hidden="true">
@@ -188,12 +255,22 @@ export function ChatList(props: Props) {
                 class={`w-2.5 h-2.5 rounded-full flex-shrink-0 ${info().isOnline ? 'bg-wa-light-green' : 'bg-gray-300 dark:bg-gray-600'}`}
               />
             </div>
-            <p class="text-sm text-wa-text-secondary dark:text-wa-dark-text-secondary truncate">
+            <p
+              class={`text-sm truncate ${
+                isUnread()
+                  ? 'font-semibold text-wa-text-primary dark:text-wa-dark-text-primary'
+                  : 'text-wa-text-secondary dark:text-wa-dark-text-secondary'
+              }`}
+            >
               <Show when={info().isTyping} fallback={chat.lastMessage || 'No messages yet'}>
                 <span class="text-wa-teal italic">Typing...</span>
               </Show>
             </p>
           </div>
+
+          <Show when={isUnread()}>
+            <span class="w-3 h-3 rounded-full bg-wa-light-green flex-shrink-0" />
+          </Show>
         </div>
       </div>
     );
@@ -287,7 +364,7 @@ export function ChatList(props: Props) {
             }
           >
             <Show
-              when={items().length > 0}
+              when={items().length > 0 || hitItems().length > 0}
               fallback={
                 <p class="p-8 text-center text-wa-text-secondary dark:text-wa-dark-text-secondary">
                   {UI_LABELS.NO_CONTACTS_MATCH} "{searchQuery()}"
@@ -328,6 +405,22 @@ export function ChatList(props: Props) {
                     {renderChatItem}
                   </AccessibleListbox>
                 </Show>
+
+                <Show when={hitItems().length > 0}>
+                  <div class="px-4 py-2 text-xs font-semibold text-wa-text-secondary uppercase tracking-wider bg-wa-chat-bg/50 dark:bg-wa-dark-chat-bg/50 sticky top-0 z-10 backdrop-blur-sm">
+                    {UI_LABELS.MESSAGES_HEADING}
+                  </div>
+                  <AccessibleListbox
+                    items={hitItems()}
+                    onSelect={(hit) => selectChat(hit.chatId)}
+                    label={UI_LABELS.MESSAGES_HEADING}
+                    id="message-search-list"
+                    class="pb-2"
+                    initialFocusLast={false}
+                  >
+                    {renderHit}
+                  </AccessibleListbox>
+                </Show>
               </div>
             </Show>
           </Show>
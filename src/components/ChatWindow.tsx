@@ -0,0 +1,75 @@
+// Pop-out chat window - shows a single chat, following only that chat
+import { onMount, onCleanup, createEffect, on } from 'solid-js';
+import { getCurrentWindow } from '@tauri-apps/api/window';
+import { MessageView } from './MessageView';
+import { user, loading, initAuthListener, cleanupAuthListener } from '../stores/auth';
+import {
+  initChatWindowListener,
+  cleanupChatsListener,
+  cleanupMessagesListener,
+  currentChat,
+  connectionState,
+} from '../stores/chats';
+import { initSounds } from '../services/sounds';
+import { UI_LABELS } from '../constants/messages';
+
+interface Props {
+  chatId: string;
+}
+
+export function ChatWindow(props: Props) {
+  const appWindow = getCurrentWindow();
+
+  function close() {
+    appWindow.close().catch((error) => console.error('Failed to close chat window:', error));
+  }
+
+  onMount(() => {
+    initAuthListener();
+    initSounds();
+  });
+
+  onCleanup(() => {
+    cleanupAuthListener();
+    cleanupChatsListener();
+    cleanupMessagesListener();
+  });
+
+  // Follow the chat while signed in; signing out in the main window closes it
+  let followingUserId: string | null = null;
+  createEffect(
+    on([user, loading], ([u, isLoading]) => {
+      if (isLoading) return;
+      if (!u) {
+        close();
+      } else if (u.uid !== followingUserId) {
+        followingUserId = u.uid;
+        initChatWindowListener(u.uid, props.chatId);
+      }
+    })
+  );
+
+  // Also close once the chat is gone, e.g. after leaving a group
+  createEffect(() => {
+    if (connectionState() === 'connected' && !currentChat()) close();
+  });
+
+  createEffect(() => {
+    const chat = currentChat();
+    const currentUser = user();
+    if (!chat) return;
+    const otherId = Object.keys(chat.participants || {}).find((id) => id !== currentUser?.uid);
+    const name = chat.isGroup
+      ? chat.groupName || UI_LABELS.GROUP_CHAT_DEFAULT
+      : (otherId && chat.participantNames?.[otherId]) || UI_LABELS.UNKNOWN_USER;
+    appWindow
+      .setTitle(`${name} – ${UI_LABELS.APP_TITLE}`)
+      .catch((error) => console.error('Failed to set window title:', error));
+  });
+
+  return (
+    <div class="flex h-screen bg-wa-chat-bg dark:bg-wa-dark-chat-bg">
+      <MessageView />
+    </div>
+  );
+}
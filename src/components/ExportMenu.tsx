@@ -0,0 +1,73 @@
+// Export menu items and the status toast shown while an export runs
+import { createSignal, For, Show } from 'solid-js';
+import { DropdownMenu } from '@kobalte/core/dropdown-menu';
+import { exportChats } from '../services/export';
+import { ERROR_MESSAGES, UI_LABELS } from '../constants/messages';
+import type { ExportFormat } from '../types';
+
+const FORMATS: { format: ExportFormat; label: string }[] = [
+  { format: 'html', label: UI_LABELS.EXPORT_HTML },
+  { format: 'markdown', label: UI_LABELS.EXPORT_MARKDOWN },
+  { format: 'text', label: UI_LABELS.EXPORT_TEXT },
+  { format: 'json', label: UI_LABELS.EXPORT_JSON },
+];
+
+const [status, setStatus] = createSignal<string | null>(null);
+let clearTimer: ReturnType<typeof setTimeout> | null = null;
+
+function showStatus(text: string | null, clearAfter?: number) {
+  if (clearTimer) clearTimeout(clearTimer);
+  clearTimer = clearAfter ? setTimeout(() => setStatus(null), clearAfter) : null;
+  setStatus(text);
+}
+
+async function runExport(chatId: string | undefined, format: ExportFormat) {
+  showStatus(UI_LABELS.EXPORTING);
+  try {
+    const summary = await exportChats(chatId, format);
+    if (!summary) {
+      showStatus(null);
+      return;
+    }
+    const messages = summary.messages === 1 ? '1 message' : `${summary.messages} messages`;
+    showStatus(`Exported ${messages} to ${summary.path}`, 6000);
+  } catch (error) {
+    console.error('Export failed:', error);
+    showStatus(ERROR_MESSAGES.EXPORT_FAILED, 6000);
+  }
+}
+
+/**
+ * One item per export format, for use inside a dropdown menu. Exports the
+ * given chat, or all chats when `chatId` is omitted.
+ */
+export function ExportMenuItems(props: { chatId?: string }) {
+  return (
+    <For each={FORMATS}>
+      {(item) => (
+        <DropdownMenu.Item
+          onSelect={() => runExport(props.chatId, item.format)}
+          disabled={status() === UI_LABELS.EXPORTING}
+          class="px-4 py-2 text-sm text-wa-text-primary dark:text-wa-dark-text-primary hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover cursor-pointer data-[disabled]:opacity-50"
+        >
+          {item.label}
+        </DropdownMenu.Item>
+      )}
+    </For>
+  );
+}
+
+/**
+ * Progress and result of the current export, announced to screen readers.
+ */
+export function ExportStatus() {
+  return (
+    <div aria-live="polite" aria-atomic="true">
+      <Show when={status()}>
+        <div class="fixed bottom-4 left-1/2 -translate-x-1/2 max-w-[80vw] px-4 py-2 rounded-lg shadow-lg bg-wa-dark-header text-white text-sm truncate z-50">
+          {status()}
+        </div>
+      </Show>
+    </div>
+  );
+}
@@ -7,10 +7,12 @@ import {
   addGroupMember,
   removeGroupMember,
   findUserByEmail,
+  createGroupInvite,
 } from '../services/messages';
+import { inviteLink } from '../services/deepLinks';
 import { user } from '../stores/auth';
 import type { Chat } from '../types';
-import { ERROR_MESSAGES } from '../constants/messages';
+import { ERROR_MESSAGES, UI_LABELS } from '../constants/messages';
 
 interface Props {
   chat: Chat;
@@ -23,6 +25,7 @@ export function GroupInfoDialog(props: Props) {
   const [newMemberEmail, setNewMemberEmail] = createSignal('');
   const [error, setError] = createSignal<string | null>(null);
   const [isProcessing, setIsProcessing] = createSignal(false);
+  const [inviteCopied, setInviteCopied] = createSignal(false);
 
   // Sync group name when chat prop changes
   createEffect(() => {
@@ -103,6 +106,27 @@ export function GroupInfoDialog(props: Props) {
     }
   }
 
+  async function handleCopyInvite() {
+    if (!isOwner() || !currentUser) return;
+
+    setIsProcessing(true);
+    setError(null);
+    try {
+      const token = await createGroupInvite(
+        props.chat.id,
+        props.chat.groupName || UI_LABELS.GROUP_CHAT_DEFAULT,
+        currentUser.uid
+      );
+      await navigator.clipboard.writeText(inviteLink(token));
+      setInviteCopied(true);
+    } catch (err) {
+      console.error('Failed to create invite:', err);
+      setError(ERROR_MESSAGES.INVITE_FAILED);
+    } finally {
+      setIsProcessing(false);
+    }
+  }
+
   async function handleLeaveGroup() {
     if (!confirm('Are you sure you want to leave this group?')) return;
 
@@ -191,6 +215,24 @@ export function GroupInfoDialog(props: Props) {
                 </form>
               </Show>
 
+              {/* Invite Link */}
+              <Show when={isOwner()}>
+                <div class="flex flex-col gap-2">
+                  <Button
+                      onClick={handleCopyInvite}
+                      disabled={isProcessing()}
+                      class="w-full py-2 text-wa-teal hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover rounded-lg border border-wa-border dark:border-wa-dark-border transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
+                  >
+                      {UI_LABELS.COPY_INVITE_LINK}
+                  </Button>
+                  <Show when={inviteCopied()}>
+                      <p class="text-wa-text-secondary dark:text-wa-dark-text-secondary text-sm">
+                        {UI_LABELS.INVITE_LINK_COPIED}
+                      </p>
+                  </Show>
+                </div>
+              </Show>
+
               {/* Members List */}
               <div>
                 <h3 class="text-sm font-semibold text-wa-text-secondary dark:text-wa-dark-text-secondary mb-2">Members ({Object.keys(props.chat.participants || {}).length})</h3>
@@ -0,0 +1,214 @@
+// Import dialog - previews a WhatsApp or Telegram export with a dry run, lets
+// the user map its senders to chat participants, then imports it
+import { createSignal, Show, For } from 'solid-js';
+import { Dialog } from '@kobalte/core/dialog';
+import { Button } from '@kobalte/core/button';
+import { chooseImportFile, importHistory } from '../services/import';
+import { reloadArchivedMessages } from '../stores/chats';
+import { ERROR_MESSAGES, UI_LABELS } from '../constants/messages';
+import type { Chat, ImportReport } from '../types';
+
+interface Props {
+  chat: Chat;
+  onClose: () => void;
+  isOpen: boolean;
+}
+
+const SOURCE_NAMES = { whatsapp: 'WhatsApp', telegram: 'Telegram' } as const;
+
+function formatDate(timestamp?: number): string {
+  return timestamp ? new Date(timestamp).toLocaleDateString() : '';
+}
+
+function errorMessage(err: unknown): string {
+  return typeof err === 'object' && err && 'message' in err
+    ? String((err as { message: unknown }).message)
+    : ERROR_MESSAGES.IMPORT_FAILED;
+}
+
+export function ImportDialog(props: Props) {
+  const [token, setToken] = createSignal<string | null>(null);
+  const [preview, setPreview] = createSignal<ImportReport | null>(null);
+  const [mapping, setMapping] = createSignal<Record<string, string>>({});
+  const [result, setResult] = createSignal<ImportReport | null>(null);
+  const [error, setError] = createSignal<string | null>(null);
+  const [isProcessing, setIsProcessing] = createSignal(false);
+
+  const participants = () =>
+    Object.keys(props.chat.participants || {}).map((id) => ({
+      id,
+      name: props.chat.participantNames?.[id] || UI_LABELS.UNKNOWN_USER,
+    }));
+
+  async function handleChooseFile() {
+    setError(null);
+    setResult(null);
+    try {
+      const chosen = await chooseImportFile();
+      if (!chosen) return;
+      setIsProcessing(true);
+      const report = await importHistory(props.chat.id, chosen.token, {}, true);
+      setToken(chosen.token);
+      setPreview(report);
+      setMapping(
+        Object.fromEntries(report.senders.map((sender) => [sender.name, sender.userId ?? '']))
+      );
+    } catch (err) {
+      console.error('Failed to read export:', err);
+      setError(errorMessage(err));
+    } finally {
+      setIsProcessing(false);
+    }
+  }
+
+  async function handleImport() {
+    const chosen = token();
+    if (!chosen) return;
+    setIsProcessing(true);
+    setError(null);
+    try {
+      const report = await importHistory(props.chat.id, chosen, mapping(), false);
+      setResult(report);
+      setPreview(null);
+      // The import used up the token
+      setToken(null);
+      reloadArchivedMessages(props.chat.id);
+    } catch (err) {
+      console.error('Failed to import history:', err);
+      setError(errorMessage(err));
+    } finally {
+      setIsProcessing(false);
+    }
+  }
+
+  const toImport = () => {
+    const report = preview();
+    return report ? report.messages - report.alreadyImported : 0;
+  };
+
+  return (
+    <Dialog open={props.isOpen} onOpenChange={(open) => !open && props.onClose()}>
+      <Dialog.Portal>
+        <Dialog.Overlay class="fixed inset-0 bg-black/50 z-50" />
+        <div class="fixed inset-0 z-50 flex items-center justify-center">
+          <Dialog.Content class="bg-white dark:bg-wa-dark-sidebar rounded-lg p-6 w-full max-w-lg shadow-xl max-h-[90vh] overflow-y-auto">
+            <div class="flex items-center justify-between mb-4">
+              <Dialog.Title class="text-lg font-semibold text-wa-text-primary dark:text-wa-dark-text-primary">
+                {UI_LABELS.IMPORT_HISTORY}
+              </Dialog.Title>
+              <Dialog.CloseButton class="w-8 h-8 flex items-center justify-center rounded-full hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover transition-colors focus:outline-none focus:ring-2 focus:ring-wa-teal">
+                <svg viewBox="0 0 24 24" width="20" height="20" class="text-wa-text-secondary">
+                  <path
+                    fill="currentColor"
+                    d="M19 6.41L17.59 5 12 10.59 6.41 5 5 6.41 10.59 12 5 17.59 6.41 19 12 13.41 17.59 19 19 17.59 13.41 12z"
+                  />
+                </svg>
+              </Dialog.CloseButton>
+            </div>
+
+            <div class="flex flex-col gap-4">
+              <Dialog.Description class="text-sm text-wa-text-secondary dark:text-wa-dark-text-secondary">
+                {UI_LABELS.IMPORT_DESCRIPTION}
+              </Dialog.Description>
+
+              <Button
+                onClick={handleChooseFile}
+                disabled={isProcessing()}
+                class="self-start px-4 py-2 rounded-lg border border-wa-border dark:border-wa-dark-border text-wa-text-primary dark:text-wa-dark-text-primary hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover disabled:opacity-50"
+              >
+                {UI_LABELS.IMPORT_CHOOSE_FILE}
+              </Button>
+
+              <Show when={preview()}>
+                {(report) => (
+                  <div class="flex flex-col gap-4">
+                    <p class="text-sm text-wa-text-primary dark:text-wa-dark-text-primary">
+                      {SOURCE_NAMES[report().source]}
+                      {report().title ? ` · ${report().title}` : ''}: {report().messages} messages
+                      <Show when={report().firstTimestamp}>
+                        {' '}
+                        from {formatDate(report().firstTimestamp)} to{' '}
+                        {formatDate(report().lastTimestamp)}
+                      </Show>
+                      <Show when={report().alreadyImported > 0}>
+                        , {report().alreadyImported} already imported
+                      </Show>
+                      <Show when={report().skipped > 0}>
+                        , {report().skipped} system notices skipped
+                      </Show>
+                      .
+                    </p>
+
+                    <Show when={report().senders.length > 0}>
+                      <div>
+                        <h3 class="text-sm font-semibold text-wa-text-secondary dark:text-wa-dark-text-secondary mb-2">
+                          {UI_LABELS.IMPORT_SENDERS}
+                        </h3>
+                        <div class="max-h-48 overflow-y-auto border border-wa-border dark:border-wa-dark-border rounded-lg">
+                          <For each={report().senders}>
+                            {(sender) => (
+                              <label class="flex items-center justify-between gap-3 px-3 py-2 border-b border-wa-border dark:border-wa-dark-border last:border-0 text-sm">
+                                <span class="text-wa-text-primary dark:text-wa-dark-text-primary truncate">
+                                  {sender.name}{' '}
+                                  <span class="text-wa-text-muted">({sender.messages})</span>
+                                </span>
+                                <select
+                                  value={mapping()[sender.name] ?? ''}
+                                  onChange={(e) =>
+                                    setMapping({ ...mapping(), [sender.name]: e.currentTarget.value })
+                                  }
+                                  class="px-2 py-1 rounded-lg border border-wa-border dark:border-wa-dark-border bg-wa-header dark:bg-wa-dark-header text-wa-text-primary dark:text-wa-dark-text-primary focus:outline-none focus:border-wa-teal"
+                                >
+                                  <option value="">{UI_LABELS.IMPORT_KEEP_NAME}</option>
+                                  <For each={participants()}>
+                                    {(participant) => (
+                                      <option value={participant.id}>{participant.name}</option>
+                                    )}
+                                  </For>
+                                </select>
+                              </label>
+                            )}
+                          </For>
+                        </div>
+                      </div>
+                    </Show>
+
+                    <p class="text-xs text-wa-text-muted dark:text-wa-dark-text-muted">
+                      {UI_LABELS.IMPORT_LOCAL_ONLY}
+                    </p>
+
+                    <Button
+                      onClick={handleImport}
+                      disabled={isProcessing() || toImport() === 0}
+                      class="px-4 py-2 bg-wa-teal text-white rounded-lg hover:bg-wa-dark-green disabled:opacity-50 disabled:cursor-not-allowed"
+                    >
+                      {toImport() === 1 ? 'Import 1 message' : `Import ${toImport()} messages`}
+                    </Button>
+                  </div>
+                )}
+              </Show>
+
+              <div aria-live="polite">
+                <Show when={result()}>
+                  {(report) => (
+                    <p class="text-sm text-wa-text-primary dark:text-wa-dark-text-primary">
+                      Imported {report().messages - report().alreadyImported} messages
+                      <Show when={report().alreadyImported > 0}>
+                        {' '}
+                        ({report().alreadyImported} were already imported)
+                      </Show>
+                      .
+                    </p>
+                  )}
+                </Show>
+                <Show when={error()}>
+                  <p class="text-red-500 text-sm">{error()}</p>
+                </Show>
+              </div>
+            </div>
+          </Dialog.Content>
+        </div>
+      </Dialog.Portal>
+    </Dialog>
+  );
+}
@@ -0,0 +1,196 @@
+// Log viewer - the diagnostics page: shows the most recent entries of the app
+// log, filtered by level and text, and saves diagnostics bundles for support
+import { createSignal, createEffect, on, Show, For } from 'solid-js';
+import { Dialog } from '@kobalte/core/dialog';
+import { Button } from '@kobalte/core/button';
+import { getLogs } from '../services/logs';
+import { createDiagnosticsBundle } from '../services/diagnostics';
+import { ERROR_MESSAGES, UI_LABELS } from '../constants/messages';
+import type { LogEntry, LogLevel } from '../types';
+
+interface Props {
+  onClose: () => void;
+  isOpen: boolean;
+}
+
+const LEVELS: { value: LogLevel; label: string }[] = [
+  { value: 'debug', label: 'Debug and above' },
+  { value: 'info', label: 'Info and above' },
+  { value: 'warn', label: 'Warnings and errors' },
+  { value: 'error', label: 'Errors only' },
+];
+
+const LEVEL_CLASSES: Record<LogLevel, string> = {
+  trace: 'text-wa-text-muted',
+  debug: 'text-wa-text-muted',
+  info: 'text-wa-text-secondary dark:text-wa-dark-text-secondary',
+  warn: 'text-amber-600 dark:text-amber-400',
+  error: 'text-red-600 dark:text-red-400',
+};
+
+function formatTime(timestamp: string): string {
+  const date = new Date(timestamp);
+  return isNaN(date.getTime()) ? timestamp : date.toLocaleString();
+}
+
+export function LogViewer(props: Props) {
+  const [level, setLevel] = createSignal<LogLevel>('info');
+  const [contains, setContains] = createSignal('');
+  const [entries, setEntries] = createSignal<LogEntry[]>([]);
+  const [error, setError] = createSignal<string | null>(null);
+  const [isLoading, setIsLoading] = createSignal(false);
+  const [bundlePath, setBundlePath] = createSignal<string | null>(null);
+  const [isSavingBundle, setIsSavingBundle] = createSignal(false);
+  let list: HTMLOListElement | undefined;
+
+  async function refresh() {
+    setIsLoading(true);
+    setError(null);
+    try {
+      setEntries(await getLogs({ level: level(), contains: contains().trim() || undefined }));
+      // Newest entries are at the bottom
+      requestAnimationFrame(() => list?.scrollTo({ top: list.scrollHeight }));
+    } catch (err) {
+      console.error('Failed to read logs:', err);
+      setError(ERROR_MESSAGES.LOGS_FAILED);
+    } finally {
+      setIsLoading(false);
+    }
+  }
+
+  async function handleSaveBundle() {
+    setIsSavingBundle(true);
+    setError(null);
+    setBundlePath(null);
+    try {
+      setBundlePath(await createDiagnosticsBundle());
+    } catch (err) {
+      console.error('Failed to create diagnostics bundle:', err);
+      setError(ERROR_MESSAGES.DIAGNOSTICS_FAILED);
+    } finally {
+      setIsSavingBundle(false);
+    }
+  }
+
+  createEffect(
+    on([() => props.isOpen, level], ([isOpen]) => {
+      if (isOpen) refresh();
+    })
+  );
+
+  return (
+    <Dialog open={props.isOpen} onOpenChange={(open) => !open && props.onClose()}>
+      <Dialog.Portal>
+        <Dialog.Overlay class="fixed inset-0 bg-black/50 z-50" />
+        <div class="fixed inset-0 z-50 flex items-center justify-center">
+          <Dialog.Content class="bg-white dark:bg-wa-dark-sidebar rounded-lg p-6 w-full max-w-3xl shadow-xl h-[80vh] flex flex-col">
+            <div class="flex items-center justify-between mb-4">
+              <Dialog.Title class="text-lg font-semibold text-wa-text-primary dark:text-wa-dark-text-primary">
+                {UI_LABELS.DIAGNOSTIC_LOGS}
+              </Dialog.Title>
+              <Dialog.CloseButton class="w-8 h-8 flex items-center justify-center rounded-full hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover transition-colors focus:outline-none focus:ring-2 focus:ring-wa-teal">
+                <svg viewBox="0 0 24 24" width="20" height="20" class="text-wa-text-secondary">
+                  <path
+                    fill="currentColor"
+                    d="M19 6.41L17.59 5 12 10.59 6.41 5 5 6.41 10.59 12 5 17.59 6.41 19 12 13.41 17.59 19 19 17.59 13.41 12z"
+                  />
+                </svg>
+              </Dialog.CloseButton>
+            </div>
+
+            <Dialog.Description class="text-sm text-wa-text-secondary dark:text-wa-dark-text-secondary mb-4">
+              {UI_LABELS.LOGS_DESCRIPTION}
+            </Dialog.Description>
+
+            <form
+              class="flex items-center gap-2 mb-3"
+              onSubmit={(e) => {
+                e.preventDefault();
+                refresh();
+              }}
+            >
+              <select
+                value={level()}
+                onChange={(e) => setLevel(e.currentTarget.value as LogLevel)}
+                aria-label={UI_LABELS.LOG_LEVEL}
+                class="px-2 py-1.5 rounded-lg border border-wa-border dark:border-wa-dark-border bg-wa-header dark:bg-wa-dark-header text-sm text-wa-text-primary dark:text-wa-dark-text-primary focus:outline-none focus:border-wa-teal"
+              >
+                <For each={LEVELS}>
+                  {(option) => <option value={option.value}>{option.label}</option>}
+                </For>
+              </select>
+              <input
+                type="search"
+                value={contains()}
+                onInput={(e) => setContains(e.currentTarget.value)}
+                placeholder={UI_LABELS.FILTER_LOGS}
+                aria-label={UI_LABELS.FILTER_LOGS}
+                class="flex-1 px-3 py-1.5 rounded-lg border border-wa-border dark:border-wa-dark-border bg-wa-header dark:bg-wa-dark-header text-sm text-wa-text-primary dark:text-wa-dark-text-primary focus:outline-none focus:border-wa-teal"
+              />
+              <Button
+                type="submit"
+                disabled={isLoading()}
+                class="px-4 py-1.5 rounded-lg border border-wa-border dark:border-wa-dark-border text-sm text-wa-text-primary dark:text-wa-dark-text-primary hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover disabled:opacity-50"
+              >
+                {UI_LABELS.REFRESH}
+              </Button>
+            </form>
+
+            <Show when={error()}>
+              <p class="text-red-500 text-sm mb-2" role="alert">
+                {error()}
+              </p>
+            </Show>
+
+            <ol
+              ref={list}
+              class="flex-1 overflow-y-auto border border-wa-border dark:border-wa-dark-border rounded-lg p-2 font-mono text-xs"
+            >
+              <For
+                each={entries()}
+                fallback={
+                  <li class="p-2 text-wa-text-muted">
+                    {isLoading() ? UI_LABELS.LOADING : UI_LABELS.NO_LOGS}
+                  </li>
+                }
+              >
+                {(entry) => (
+                  <li class="py-0.5 whitespace-pre-wrap break-words text-wa-text-primary dark:text-wa-dark-text-primary">
+                    <span class="text-wa-text-muted">{formatTime(entry.timestamp)}</span>{' '}
+                    <span class={`uppercase font-semibold ${LEVEL_CLASSES[entry.level]}`}>
+                      {entry.level}
+                    </span>{' '}
+                    <span class="text-wa-text-secondary dark:text-wa-dark-text-secondary">
+                      {entry.target}
+                    </span>{' '}
+                    {entry.message}
+                  </li>
+                )}
+              </For>
+            </ol>
+
+            <div class="flex items-center gap-3 mt-4">
+              <Button
+                onClick={handleSaveBundle}
+                disabled={isSavingBundle()}
+                class="shrink-0 px-4 py-2 bg-wa-teal text-white rounded-lg hover:bg-wa-dark-green disabled:opacity-50 disabled:cursor-not-allowed"
+              >
+                {UI_LABELS.SAVE_DIAGNOSTICS}
+              </Button>
+              <p
+                class="text-xs text-wa-text-secondary dark:text-wa-dark-text-secondary break-all"
+                aria-live="polite"
+              >
+                <Show when={!isSavingBundle()} fallback={UI_LABELS.SAVING_DIAGNOSTICS}>
+                  <Show when={bundlePath()} fallback={UI_LABELS.DIAGNOSTICS_DESCRIPTION}>
+                    {(path) => `${UI_LABELS.DIAGNOSTICS_SAVED} ${path()}`}
+                  </Show>
+                </Show>
+              </p>
+            </div>
+          </Dialog.Content>
+        </div>
+      </Dialog.Portal>
+    </Dialog>
+  );
+}
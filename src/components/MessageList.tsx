@@ -6,11 +6,18 @@
  * - Live region announcements for new messages
  * - Auto-scroll to newest message
  * - Message-specific styling (sent vs received)
+ * - Markdown formatting, rendered and sanitized by the Rust core
  */
 import { createSignal, createEffect, Show, on } from 'solid-js';
+import { openUrl } from '@tauri-apps/plugin-opener';
 import { AccessibleListbox, type ListboxItem } from './AccessibleListbox';
 import type { Message } from '../types';
 import { UI_LABELS } from '../constants/messages';
+import { retryOutboxMessage } from '../services/outbox';
+import { formatFileSize } from '../services/attachments';
+import { AttachmentChip } from './AttachmentChip';
+import { applyHighlightTheme, markdownHtml, renderMarkdown } from '../services/markdown';
+import { isDark } from '../stores/theme';
 
 // ============================================================================
 // Types
@@ -58,11 +65,40 @@ function formatTimestamp(timestamp: Date | number | { toDate?: () => Date } | nu
   );
 }
 
+function getDeliveryLabel(message: Message): string {
+  switch (message.deliveryState) {
+    case 'queued':
+      return UI_LABELS.MESSAGE_QUEUED;
+    case 'failed':
+      return UI_LABELS.MESSAGE_FAILED;
+    default:
+      return '';
+  }
+}
+
 function getMessageLabel(message: Message, isSent: boolean): string {
   const sender = isSent ? UI_LABELS.YOU : message.senderName;
   const timestamp = formatTimestamp(message.timestamp);
   const timeLabel = timestamp ? `, ${timestamp}` : '';
-  return `${sender}: ${message.text}${timeLabel}`;
+  const delivery = getDeliveryLabel(message);
+  const deliveryLabel = delivery ? `, ${delivery}` : '';
+  const importedLabel = message.archived ? `, ${UI_LABELS.IMPORTED.toLowerCase()}` : '';
+  const attachment = message.attachment
+    ? `${message.text ? ', ' : ''}${UI_LABELS.ATTACHMENT.toLowerCase()} ${message.attachment.name}, ${formatFileSize(message.attachment.size)}`
+    : '';
+  return `${sender}: ${message.text}${attachment}${timeLabel}${deliveryLabel}${importedLabel}`;
+}
+
+/**
+ * Links in messages open in the browser instead of replacing the app.
+ */
+function handleLinkClick(e: MouseEvent) {
+  const link = (e.target as HTMLElement).closest('a');
+  if (!link) return;
+  e.preventDefault();
+  if (link.href) {
+    openUrl(link.href).catch((err) => console.error('Failed to open link:', err));
+  }
 }
 
 // ============================================================================
@@ -81,6 +117,18 @@ export function MessageList(props: Props) {
   // Convert messages to ListboxItems (they already have `id`)
   const items = (): MessageItem[] => props.messages as MessageItem[];
 
+  createEffect(() => {
+    renderMarkdown(props.messages.map((message) => message.text)).catch((err) =>
+      console.error('Failed to render messages:', err)
+    );
+  });
+
+  createEffect(() => {
+    applyHighlightTheme(isDark()).catch((err) =>
+      console.error('Failed to load highlight theme:', err)
+    );
+  });
+
   // Auto-scroll to bottom when new messages arrive
   createEffect(
     on(
@@ -183,13 +231,57 @@ export function MessageList(props: Props) {
               {message.senderName}
             </span>
           </Show>
-          <p class="text-wa-text-primary dark:text-wa-dark-text-primary break-words whitespace-pre-wrap">
-            {message.text}
-          </p>
+          <Show when={message.text}>
+            <Show
+              when={markdownHtml(message.text)}
+              fallback={
+                <p class="text-wa-text-primary dark:text-wa-dark-text-primary break-words whitespace-pre-wrap">
+                  {message.text}
+                </p>
+              }
+            >
+              {(html) => (
+                <div
+                  class="message-markdown text-wa-text-primary dark:text-wa-dark-text-primary break-words"
+                  onClick={handleLinkClick}
+                  innerHTML={html()}
+                />
+              )}
+            </Show>
+          </Show>
+        </div>
+        <Show when={message.attachment}>
+          {(attachment) => <AttachmentChip attachment={attachment()} />}
+        </Show>
+        <div aria-hidden="true">
           <time class="text-[11px] text-wa-text-secondary dark:text-wa-dark-text-secondary block text-right mt-0.5 opacity-80">
+            <Show when={message.archived}>
+              {UI_LABELS.IMPORTED}
+              {' · '}
+            </Show>
             {formatTimestamp(message.timestamp)}
+            <Show when={message.deliveryState === 'queued'}>
+              {' · '}
+              {UI_LABELS.MESSAGE_QUEUED}
+            </Show>
           </time>
         </div>
+        <Show when={message.deliveryState === 'failed'}>
+          <div class="flex items-center justify-end gap-2 text-[11px] text-red-500 mt-0.5">
+            <span aria-hidden="true">{UI_LABELS.MESSAGE_FAILED}</span>
+            <button
+              type="button"
+              class="underline hover:no-underline"
+              onClick={() =>
+                retryOutboxMessage(message.id).catch((e) =>
+                  console.error('Failed to retry message:', e)
+                )
+              }
+            >
+              {UI_LABELS.RETRY_SEND}
+            </button>
+          </div>
+        </Show>
       </div>
     );
   };
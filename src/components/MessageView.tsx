@@ -1,24 +1,66 @@
 // Message view component with Kobalte + Tailwind CSS - WhatsApp style
-import { createSignal, createMemo, Show, onCleanup } from 'solid-js';
+import {
+  createSignal,
+  createMemo,
+  createEffect,
+  on,
+  For,
+  Show,
+  onCleanup,
+  onMount,
+} from 'solid-js';
+import { getCurrentWebview } from '@tauri-apps/api/webview';
 import { TextField } from '@kobalte/core/text-field';
 import { Button } from '@kobalte/core/button';
+import { DropdownMenu } from '@kobalte/core/dropdown-menu';
 import {
   messages,
   loadingMessages,
   currentChatId,
   currentChat,
   otherUserPresence,
+  clearCurrentChat,
 } from '../stores/chats';
-import { sendMessage, setTypingStatus } from '../services/messages';
+import { setTypingStatus } from '../services/messages';
+import { enqueueMessage } from '../services/outbox';
+import {
+  chooseAttachment,
+  uploadAttachment,
+  onAttachmentDropped,
+  onAttachmentProgress,
+} from '../services/attachments';
+import { isProcessedImage, prepareImage } from '../services/images';
 import { user } from '../stores/auth';
 import { MessageList } from './MessageList';
 import { GroupInfoDialog } from './GroupInfoDialog';
+import { ExportMenuItems } from './ExportMenu';
+import { ImportDialog } from './ImportDialog';
+import { AttachmentChip } from './AttachmentChip';
 import { playMessageSent } from '../services/sounds';
-import { UI_LABELS } from '../constants/messages';
+import { openChatWindow, poppedOutChatId } from '../services/windows';
+import { isChatMuted, muteChat, unmuteChat } from '../services/notifications';
+import { settings } from '../stores/settings';
+import { ERROR_MESSAGES, UI_LABELS } from '../constants/messages';
+import type { Attachment, ChosenFile } from '../types';
 
 export function MessageView() {
   const [newMessage, setNewMessage] = createSignal('');
   const [showGroupInfo, setShowGroupInfo] = createSignal(false);
+  const [showImport, setShowImport] = createSignal(false);
+
+  // The file attached to the message being composed. It is uploaded as soon
+  // as it is picked or dropped, so sending only has to queue the message.
+  const [attachment, setAttachment] = createSignal<Attachment | null>(null);
+  const [uploadProgress, setUploadProgress] = createSignal<number | null>(null);
+  const [attachError, setAttachError] = createSignal(false);
+  const [isDragging, setIsDragging] = createSignal(false);
+
+  createEffect(
+    on(currentChatId, () => {
+      setAttachment(null);
+      setAttachError(false);
+    })
+  );
 
   type SendState = 'idle' | 'sending' | 'error';
   const [sendState, setSendState] = createSignal<SendState>('idle');
@@ -31,7 +73,8 @@ export function MessageView() {
   const otherUserInfo = createMemo(() => {
     const chat = currentChat();
     const currentUser = user();
-    if (!chat || !currentUser) return { name: 'User', isTyping: false, isOnline: false };
+    if (!chat || !currentUser)
+      return { name: 'User', isTyping: false, isOnline: false, status: undefined };
 
     if (chat.isGroup) {
       const name = chat.groupName || UI_LABELS.GROUP_CHAT_DEFAULT;
@@ -40,20 +83,92 @@ export function MessageView() {
           ([uid, typing]) => uid !== currentUser.uid && typing === true
         )
         : false;
-      return { name, isTyping, isOnline: false };
+      return { name, isTyping, isOnline: false, status: undefined };
     }
 
     // participants is now an object { odId: true }
     const participantIds = Object.keys(chat.participants || {});
     const otherId = participantIds.find((id) => id !== currentUser.uid);
-    if (!otherId) return { name: UI_LABELS.UNKNOWN_USER, isTyping: false, isOnline: false };
+    if (!otherId)
+      return { name: UI_LABELS.UNKNOWN_USER, isTyping: false, isOnline: false, status: undefined };
 
     const name = chat.participantNames?.[otherId] || UI_LABELS.UNKNOWN_USER;
     const typing = chat.typing?.[otherId] === true;
     const presence = otherUserPresence();
     const online = presence[otherId]?.isOnline || false;
+    const status = presence[otherId]?.status;
 
-    return { name, isTyping: typing, isOnline: online };
+    return { name, isTyping: typing, isOnline: online, status };
+  });
+
+  // Move the chat into a window of its own, so it is not followed twice
+  function handlePopOut() {
+    const chatId = currentChatId();
+    if (!chatId) return;
+    openChatWindow(chatId)
+      .then(() => clearCurrentChat())
+      .catch((error) => console.error('Failed to open chat window:', error));
+  }
+
+  const HOUR = 60 * 60 * 1000;
+  const MUTE_OPTIONS: { label: string; duration: number | null }[] = [
+    { label: UI_LABELS.MUTE_1_HOUR, duration: HOUR },
+    { label: UI_LABELS.MUTE_8_HOURS, duration: 8 * HOUR },
+    { label: UI_LABELS.MUTE_1_WEEK, duration: 7 * 24 * HOUR },
+    { label: UI_LABELS.MUTE_UNTIL_UNMUTED, duration: null },
+  ];
+
+  const isMuted = () => {
+    const chatId = currentChatId();
+    return chatId !== null && isChatMuted(settings(), chatId);
+  };
+
+  // The settings store picks up the change from the settings-changed event
+  function handleMute(duration: number | null) {
+    const chatId = currentChatId();
+    if (!chatId) return;
+    muteChat(chatId, duration === null ? null : Date.now() + duration).catch((error) => {
+      console.error('Failed to mute chat:', error);
+      alert(ERROR_MESSAGES.MUTE_FAILED);
+    });
+  }
+
+  function handleUnmute() {
+    const chatId = currentChatId();
+    if (!chatId) return;
+    unmuteChat(chatId).catch((error) => {
+      console.error('Failed to unmute chat:', error);
+      alert(ERROR_MESSAGES.MUTE_FAILED);
+    });
+  }
+
+  // "online" unless the contact picked another status from their tray
+  const presenceLabel = () => {
+    const info = otherUserInfo();
+    if (!info.isOnline) return UI_LABELS.OFFLINE;
+    if (info.status === 'away') return UI_LABELS.AWAY;
+    if (info.status === 'busy') return UI_LABELS.BUSY;
+    return UI_LABELS.ONLINE;
+  };
+
+  onMount(() => {
+    const unlisten = [
+      getCurrentWebview().onDragDropEvent((event) => {
+        if (event.payload.type === 'enter' || event.payload.type === 'over') {
+          setIsDragging(!!currentChatId());
+        } else {
+          setIsDragging(false);
+        }
+      }),
+      // The Rust core picks up the dropped file itself and offers it here
+      onAttachmentDropped((file) => attachFile(file)),
+      onAttachmentProgress((progress) => {
+        if (progress.direction === 'upload' && uploadProgress() != null) {
+          setUploadProgress(Math.round((progress.transferred / progress.total) * 100));
+        }
+      }),
+    ];
+    onCleanup(() => unlisten.forEach((pending) => pending.then((stop) => stop())));
   });
 
   // Cleanup typing timeout on unmount
@@ -83,13 +198,46 @@ export function MessageView() {
     }, 2000);
   }
 
+  async function attachFile(file: ChosenFile) {
+    const chatId = currentChatId();
+    if (!chatId || uploadProgress() != null) return;
+    setAttachError(false);
+    setAttachment(null);
+    setUploadProgress(0);
+    try {
+      // Photos lose their metadata (e.g. location) and excess resolution first
+      const prepared = isProcessedImage(file) ? await prepareImage(file) : null;
+      const uploaded = await uploadAttachment(chatId, prepared?.file ?? file);
+      // Drop it if the user switched chats meanwhile
+      if (currentChatId() === chatId) {
+        setAttachment(prepared ? { ...uploaded, image: prepared.image } : uploaded);
+      }
+    } catch (err) {
+      console.error('Failed to attach file:', err);
+      setAttachError(true);
+    } finally {
+      setUploadProgress(null);
+    }
+  }
+
+  async function handleAttach() {
+    try {
+      const file = await chooseAttachment();
+      if (file) await attachFile(file);
+    } catch (err) {
+      console.error('Failed to choose file:', err);
+      setAttachError(true);
+    }
+  }
+
   async function handleSend(e: Event) {
     e.preventDefault();
     const text = newMessage().trim();
     const chatId = currentChatId();
     const currentUser = user();
+    const file = attachment() ?? undefined;
 
-    if (!text || !chatId || !currentUser) return;
+    if ((!text && !file) || uploadProgress() != null || !chatId || !currentUser) return;
 
     if (typingTimeout) clearTimeout(typingTimeout);
     setIsTyping(false);
@@ -97,9 +245,11 @@ export function MessageView() {
 
     try {
       const senderName = currentUser.displayName || currentUser.email || 'Unknown';
-      await sendMessage(chatId, currentUser.uid, senderName, text);
+      // Queued locally; the outbox delivers it (and retries) in the background
+      await enqueueMessage(chatId, currentUser.uid, senderName, text, file);
       playMessageSent();
       setNewMessage('');
+      setAttachment(null);
       setSendState('idle');
       // Keep focus in the input field after sending
       // Use queueMicrotask for more reliable timing than setTimeout
@@ -147,27 +297,103 @@ export function MessageView() {
                           : 'text-wa-text-muted dark:text-wa-dark-text-muted'
                       }
                     >
-                      {otherUserInfo().isOnline ? UI_LABELS.ONLINE : UI_LABELS.OFFLINE}
+                      {presenceLabel()}
                     </span>
                   }
                 >
                   <span class="text-wa-teal">{UI_LABELS.TYPING}</span>
                 </Show>
+                <Show when={isMuted()}>
+                  <span class="text-wa-text-muted dark:text-wa-dark-text-muted">
+                    {' · '}
+                    {UI_LABELS.MUTED}
+                  </span>
+                </Show>
               </span>
             </div>
           </div>
 
-          <Show when={currentChat()?.isGroup}>
-            <Button
-              onClick={() => setShowGroupInfo(true)}
-              class="p-2 rounded-full hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover text-wa-text-secondary dark:text-wa-dark-text-secondary transition-colors"
-              title="Group Info"
-            >
-              <svg viewBox="0 0 24 24" width="24" height="24" fill="currentColor">
-                <path d="M12 2C6.48 2 2 6.48 2 12s4.48 10 10 10 10-4.48 10-10S17.52 2 12 2zm1 15h-2v-6h2v6zm0-8h-2V7h2v2z" />
-              </svg>
-            </Button>
-          </Show>
+          <div class="flex items-center gap-1">
+            <DropdownMenu>
+              <DropdownMenu.Trigger
+                class="p-2 rounded-full hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover text-wa-text-secondary dark:text-wa-dark-text-secondary transition-colors"
+                title={UI_LABELS.CHAT_OPTIONS}
+                aria-label={UI_LABELS.CHAT_OPTIONS}
+              >
+                <svg viewBox="0 0 24 24" width="24" height="24" fill="currentColor">
+                  <path d="M12 8c1.1 0 2-.9 2-2s-.9-2-2-2-2 .9-2 2 .9 2 2 2zm0 2c-1.1 0-2 .9-2 2s.9 2 2 2 2-.9 2-2-.9-2-2-2zm0 6c-1.1 0-2 .9-2 2s.9 2 2 2 2-.9 2-2-.9-2-2-2z" />
+                </svg>
+              </DropdownMenu.Trigger>
+              <DropdownMenu.Portal>
+                <DropdownMenu.Content class="min-w-[180px] bg-white dark:bg-wa-dark-sidebar rounded-lg shadow-lg border border-wa-border dark:border-wa-dark-border py-1 z-50">
+                  <DropdownMenu.Group>
+                    <DropdownMenu.GroupLabel class="px-4 pt-2 pb-1 text-xs font-semibold text-wa-text-secondary dark:text-wa-dark-text-secondary">
+                      {UI_LABELS.EXPORT_CHAT}
+                    </DropdownMenu.GroupLabel>
+                    <ExportMenuItems chatId={currentChatId() ?? undefined} />
+                  </DropdownMenu.Group>
+                  <DropdownMenu.Separator class="h-px bg-wa-border dark:bg-wa-dark-border my-1" />
+                  <Show
+                    when={isMuted()}
+                    fallback={
+                      <DropdownMenu.Sub overlap gutter={4}>
+                        <DropdownMenu.SubTrigger class="px-4 py-2 text-sm text-wa-text-primary dark:text-wa-dark-text-primary hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover cursor-pointer">
+                          {UI_LABELS.MUTE_NOTIFICATIONS}
+                        </DropdownMenu.SubTrigger>
+                        <DropdownMenu.Portal>
+                          <DropdownMenu.SubContent class="min-w-[160px] bg-white dark:bg-wa-dark-sidebar rounded-lg shadow-lg border border-wa-border dark:border-wa-dark-border py-1 z-50">
+                            <For each={MUTE_OPTIONS}>
+                              {(option) => (
+                                <DropdownMenu.Item
+                                  onSelect={() => handleMute(option.duration)}
+                                  class="px-4 py-2 text-sm text-wa-text-primary dark:text-wa-dark-text-primary hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover cursor-pointer"
+                                >
+                                  {option.label}
+                                </DropdownMenu.Item>
+                              )}
+                            </For>
+                          </DropdownMenu.SubContent>
+                        </DropdownMenu.Portal>
+                      </DropdownMenu.Sub>
+                    }
+                  >
+                    <DropdownMenu.Item
+                      onSelect={handleUnmute}
+                      class="px-4 py-2 text-sm text-wa-text-primary dark:text-wa-dark-text-primary hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover cursor-pointer"
+                    >
+                      {UI_LABELS.UNMUTE_NOTIFICATIONS}
+                    </DropdownMenu.Item>
+                  </Show>
+                  <DropdownMenu.Item
+                    onSelect={() => setShowImport(true)}
+                    class="px-4 py-2 text-sm text-wa-text-primary dark:text-wa-dark-text-primary hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover cursor-pointer"
+                  >
+                    {UI_LABELS.IMPORT_HISTORY_ITEM}
+                  </DropdownMenu.Item>
+                  <Show when={!poppedOutChatId()}>
+                    <DropdownMenu.Item
+                      onSelect={handlePopOut}
+                      class="px-4 py-2 text-sm text-wa-text-primary dark:text-wa-dark-text-primary hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover cursor-pointer"
+                    >
+                      {UI_LABELS.OPEN_IN_NEW_WINDOW}
+                    </DropdownMenu.Item>
+                  </Show>
+                </DropdownMenu.Content>
+              </DropdownMenu.Portal>
+            </DropdownMenu>
+
+            <Show when={currentChat()?.isGroup}>
+              <Button
+                onClick={() => setShowGroupInfo(true)}
+                class="p-2 rounded-full hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover text-wa-text-secondary dark:text-wa-dark-text-secondary transition-colors"
+                title="Group Info"
+              >
+                <svg viewBox="0 0 24 24" width="24" height="24" fill="currentColor">
+                  <path d="M12 2C6.48 2 2 6.48 2 12s4.48 10 10 10 10-4.48 10-10S17.52 2 12 2zm1 15h-2v-6h2v6zm0-8h-2V7h2v2z" />
+                </svg>
+              </Button>
+            </Show>
+          </div>
         </header>
 
         {/* Messages area with accessible navigation */}
@@ -178,12 +404,40 @@ export function MessageView() {
           onEscape={() => inputRef?.focus()}
         />
 
+        {/* Pending attachment */}
+        <Show when={attachment() || uploadProgress() != null || attachError()}>
+          <div class="px-4 pt-3 bg-wa-header dark:bg-wa-dark-header" aria-live="polite">
+            <Show when={attachment()}>
+              {(file) => <AttachmentChip attachment={file()} onRemove={() => setAttachment(null)} />}
+            </Show>
+            <Show when={uploadProgress() != null}>
+              <p class="text-sm text-wa-text-secondary dark:text-wa-dark-text-secondary">
+                {UI_LABELS.UPLOADING} {uploadProgress()}%
+              </p>
+            </Show>
+            <Show when={attachError()}>
+              <p class="text-sm text-red-500">{ERROR_MESSAGES.ATTACH_FAILED}</p>
+            </Show>
+          </div>
+        </Show>
+
         {/* Message input */}
         <form
-          class="flex items-center gap-3 p-4 bg-wa-header dark:bg-wa-dark-header"
+          class={`flex items-center gap-3 p-4 bg-wa-header dark:bg-wa-dark-header ${isDragging() ? 'ring-2 ring-inset ring-wa-teal' : ''}`}
           onSubmit={handleSend}
           autocomplete="off"
         >
+          <Button
+            onClick={handleAttach}
+            disabled={uploadProgress() != null}
+            aria-label={UI_LABELS.ATTACH_FILE}
+            title={UI_LABELS.ATTACH_FILE}
+            class="p-2 rounded-full hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover text-wa-text-secondary dark:text-wa-dark-text-secondary transition-colors disabled:opacity-50"
+          >
+            <svg viewBox="0 0 24 24" width="24" height="24" fill="currentColor">
+              <path d="M16.5 6v11.5c0 2.21-1.79 4-4 4s-4-1.79-4-4V5c0-1.38 1.12-2.5 2.5-2.5s2.5 1.12 2.5 2.5v10.5c0 .55-.45 1-1 1s-1-.45-1-1V6H10v9.5c0 1.38 1.12 2.5 2.5 2.5s2.5-1.12 2.5-2.5V5c0-2.21-1.79-4-4-4S7 2.79 7 5v12.5c0 3.04 2.46 5.5 5.5 5.5s5.5-2.46 5.5-5.5V6h-1.5z" />
+            </svg>
+          </Button>
           <TextField
             value={newMessage()}
             onChange={(value) => {
@@ -195,10 +449,12 @@ export function MessageView() {
             <TextField.Label class="sr-only">{UI_LABELS.TYPE_MESSAGE_PLACEHOLDER}</TextField.Label>
             <TextField.Input
               ref={(el: HTMLInputElement) => (inputRef = el)}
-              placeholder={
+                placeholder={
                 sendState() === 'error'
                   ? UI_LABELS.FAILED_TO_SEND
-                  : UI_LABELS.TYPE_MESSAGE_PLACEHOLDER
+                  : isDragging()
+                    ? UI_LABELS.DROP_TO_ATTACH
+                    : UI_LABELS.TYPE_MESSAGE_PLACEHOLDER
               }
               autocomplete="off"
               class={`w-full px-4 py-3 rounded-lg border-none bg-white dark:bg-wa-dark-sidebar text-wa-text-primary dark:text-wa-dark-text-primary placeholder:text-wa-text-muted focus:outline-none focus:ring-1 focus:ring-wa-teal/50 ${sendState() === 'error' ? 'ring-2 ring-red-500' : ''}`}
@@ -206,7 +462,11 @@ export function MessageView() {
           </TextField>
           <Button
             type="submit"
-            disabled={sendState() === 'sending' || !newMessage().trim()}
+            disabled={
+              sendState() === 'sending' ||
+              uploadProgress() != null ||
+              (!newMessage().trim() && !attachment())
+            }
             aria-label={UI_LABELS.SEND_MESSAGE_LABEL}
             class={`w-12 h-12 rounded-full text-white flex items-center justify-center transition-colors focus:outline-none focus:ring-2 focus:ring-wa-teal focus:ring-offset-2 ${sendState() === 'error' ? 'bg-red-500 hover:bg-red-600' : 'bg-wa-teal hover:bg-wa-dark-green'} disabled:bg-gray-300 dark:disabled:bg-gray-600 disabled:cursor-not-allowed`}
           >
@@ -235,6 +495,15 @@ export function MessageView() {
           onClose={() => setShowGroupInfo(false)}
         />
       </Show>
+
+      {/* Import Dialog */}
+      <Show when={showImport() && currentChat()}>
+        <ImportDialog
+          chat={currentChat()!}
+          isOpen={showImport()}
+          onClose={() => setShowImport(false)}
+        />
+      </Show>
     </main>
   );
 }
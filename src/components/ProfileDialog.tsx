@@ -0,0 +1,140 @@
+// Profile dialog - lists the profiles and switches to one, or to a new one.
+// Switching restarts the app, so the window closes right after.
+import { createSignal, createEffect, on, Show, For } from 'solid-js';
+import { Dialog } from '@kobalte/core/dialog';
+import { Button } from '@kobalte/core/button';
+import { currentProfile, listProfiles, switchProfile } from '../services/profiles';
+import { ERROR_MESSAGES, UI_LABELS } from '../constants/messages';
+
+interface Props {
+  onClose: () => void;
+  isOpen: boolean;
+}
+
+const PROFILE_NAME = /^[A-Za-z0-9_-]{1,32}$/;
+
+export function ProfileDialog(props: Props) {
+  const [profiles, setProfiles] = createSignal<string[]>([]);
+  const [newName, setNewName] = createSignal('');
+  const [error, setError] = createSignal<string | null>(null);
+  const [isSwitching, setIsSwitching] = createSignal(false);
+  const current = currentProfile().name;
+
+  createEffect(
+    on(
+      () => props.isOpen,
+      async (isOpen) => {
+        if (!isOpen) return;
+        setError(null);
+        setNewName('');
+        try {
+          setProfiles(await listProfiles());
+        } catch (err) {
+          console.error('Failed to list profiles:', err);
+          setError(ERROR_MESSAGES.PROFILES_FAILED);
+        }
+      }
+    )
+  );
+
+  async function handleSwitch(name: string) {
+    if (!PROFILE_NAME.test(name)) {
+      setError(ERROR_MESSAGES.INVALID_PROFILE_NAME);
+      return;
+    }
+    setIsSwitching(true);
+    setError(null);
+    try {
+      await switchProfile(name);
+    } catch (err) {
+      console.error('Failed to switch profiles:', err);
+      setError(ERROR_MESSAGES.SWITCH_PROFILE_FAILED);
+      setIsSwitching(false);
+    }
+  }
+
+  return (
+    <Dialog open={props.isOpen} onOpenChange={(open) => !open && props.onClose()}>
+      <Dialog.Portal>
+        <Dialog.Overlay class="fixed inset-0 bg-black/50 z-50" />
+        <div class="fixed inset-0 z-50 flex items-center justify-center">
+          <Dialog.Content class="bg-white dark:bg-wa-dark-sidebar rounded-lg p-6 w-full max-w-md shadow-xl">
+            <div class="flex items-center justify-between mb-4">
+              <Dialog.Title class="text-lg font-semibold text-wa-text-primary dark:text-wa-dark-text-primary">
+                {UI_LABELS.PROFILES}
+              </Dialog.Title>
+              <Dialog.CloseButton class="w-8 h-8 flex items-center justify-center rounded-full hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover transition-colors focus:outline-none focus:ring-2 focus:ring-wa-teal">
+                <svg viewBox="0 0 24 24" width="20" height="20" class="text-wa-text-secondary">
+                  <path
+                    fill="currentColor"
+                    d="M19 6.41L17.59 5 12 10.59 6.41 5 5 6.41 10.59 12 5 17.59 6.41 19 12 13.41 17.59 19 19 17.59 13.41 12z"
+                  />
+                </svg>
+              </Dialog.CloseButton>
+            </div>
+
+            <Dialog.Description class="text-sm text-wa-text-secondary dark:text-wa-dark-text-secondary mb-4">
+              {UI_LABELS.PROFILES_DESCRIPTION}
+            </Dialog.Description>
+
+            <ul class="mb-4 border border-wa-border dark:border-wa-dark-border rounded-lg divide-y divide-wa-border dark:divide-wa-dark-border">
+              <For each={profiles()}>
+                {(name) => (
+                  <li class="flex items-center justify-between px-3 py-2 text-sm text-wa-text-primary dark:text-wa-dark-text-primary">
+                    <span>
+                      {name}
+                      <Show when={name === current}>
+                        <span class="ml-2 text-xs text-wa-text-muted">
+                          ({UI_LABELS.CURRENT_PROFILE})
+                        </span>
+                      </Show>
+                    </span>
+                    <Show when={name !== current}>
+                      <Button
+                        onClick={() => handleSwitch(name)}
+                        disabled={isSwitching()}
+                        class="px-3 py-1 rounded-lg border border-wa-border dark:border-wa-dark-border text-sm hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover disabled:opacity-50"
+                      >
+                        {UI_LABELS.SWITCH}
+                      </Button>
+                    </Show>
+                  </li>
+                )}
+              </For>
+            </ul>
+
+            <form
+              class="flex items-center gap-2"
+              onSubmit={(e) => {
+                e.preventDefault();
+                handleSwitch(newName().trim());
+              }}
+            >
+              <input
+                type="text"
+                value={newName()}
+                onInput={(e) => setNewName(e.currentTarget.value)}
+                placeholder={UI_LABELS.NEW_PROFILE_PLACEHOLDER}
+                aria-label={UI_LABELS.NEW_PROFILE}
+                class="flex-1 px-3 py-2 rounded-lg border border-wa-border dark:border-wa-dark-border bg-wa-header dark:bg-wa-dark-header text-sm text-wa-text-primary dark:text-wa-dark-text-primary focus:outline-none focus:border-wa-teal"
+              />
+              <Button
+                type="submit"
+                disabled={isSwitching() || !newName().trim()}
+                class="px-4 py-2 bg-wa-teal text-white rounded-lg hover:bg-wa-dark-green disabled:opacity-50 disabled:cursor-not-allowed"
+              >
+                {isSwitching() ? UI_LABELS.SWITCHING_PROFILE : UI_LABELS.CREATE_AND_SWITCH}
+              </Button>
+            </form>
+
+            <Show when={error()}>
+              <p class="text-red-500 text-sm mt-2" role="alert">
+                {error()}
+              </p>
+            </Show>
+          </Dialog.Content>
+        </div>
+      </Dialog.Portal>
+    </Dialog>
+  );
+}
@@ -0,0 +1,275 @@
+// Settings dialog - theme, sounds and notifications. Changes are saved as
+// they are made, and show up in every open window.
+import { For } from 'solid-js';
+import { Dialog } from '@kobalte/core/dialog';
+import { settings, changeSettings } from '../stores/settings';
+import { UI_LABELS } from '../constants/messages';
+import type { QuietHours, Theme, Weekday } from '../types';
+
+interface Props {
+  onClose: () => void;
+  isOpen: boolean;
+}
+
+const THEMES: { value: Theme; label: string }[] = [
+  { value: 'light', label: UI_LABELS.THEME_LIGHT },
+  { value: 'dark', label: UI_LABELS.THEME_DARK },
+  { value: 'system', label: UI_LABELS.THEME_SYSTEM },
+];
+
+const WEEKDAYS: { value: Weekday; label: string }[] = [
+  { value: 'monday', label: 'Mo' },
+  { value: 'tuesday', label: 'Tu' },
+  { value: 'wednesday', label: 'We' },
+  { value: 'thursday', label: 'Th' },
+  { value: 'friday', label: 'Fr' },
+  { value: 'saturday', label: 'Sa' },
+  { value: 'sunday', label: 'Su' },
+];
+
+// Weeknights, 22:00 to 07:00
+const NEW_QUIET_HOURS: QuietHours = {
+  days: ['monday', 'tuesday', 'wednesday', 'thursday', 'friday'],
+  start: 22 * 60,
+  end: 7 * 60,
+};
+
+// Quiet hours are kept in minutes after midnight; time inputs use "HH:MM"
+function toTime(minutes: number): string {
+  const pad = (n: number) => String(n).padStart(2, '0');
+  return `${pad(Math.floor(minutes / 60))}:${pad(minutes % 60)}`;
+}
+
+function fromTime(value: string): number | null {
+  const match = /^(\d{2}):(\d{2})/.exec(value);
+  return match ? Number(match[1]) * 60 + Number(match[2]) : null;
+}
+
+function parseKeywords(value: string): string[] {
+  return value
+    .split(',')
+    .map((keyword) => keyword.trim())
+    .filter((keyword) => keyword.length > 0);
+}
+
+function changeQuietHours(quietHours: QuietHours[]) {
+  changeSettings({ notifications: { quietHours } });
+}
+
+function updateQuietHours(index: number, changes: Partial<QuietHours>) {
+  changeQuietHours(
+    settings().notifications.quietHours.map((period, i) =>
+      i === index ? { ...period, ...changes } : period
+    )
+  );
+}
+
+function toggleDay(index: number, day: Weekday) {
+  const days = settings().notifications.quietHours[index].days;
+  updateQuietHours(index, {
+    days: days.includes(day) ? days.filter((d) => d !== day) : [...days, day],
+  });
+}
+
+const HEADING_CLASS =
+  'text-sm font-semibold text-wa-text-primary dark:text-wa-dark-text-primary mb-2';
+const LABEL_CLASS =
+  'flex items-center gap-3 py-1 text-sm text-wa-text-primary dark:text-wa-dark-text-primary';
+
+export function SettingsDialog(props: Props) {
+  return (
+    <Dialog open={props.isOpen} onOpenChange={(open) => !open && props.onClose()}>
+      <Dialog.Portal>
+        <Dialog.Overlay class="fixed inset-0 bg-black/50 z-50" />
+        <div class="fixed inset-0 z-50 flex items-center justify-center">
+          <Dialog.Content class="bg-white dark:bg-wa-dark-sidebar rounded-lg p-6 w-full max-w-md max-h-[90vh] overflow-y-auto shadow-xl">
+            <div class="flex items-center justify-between mb-4">
+              <Dialog.Title class="text-lg font-semibold text-wa-text-primary dark:text-wa-dark-text-primary">
+                {UI_LABELS.SETTINGS}
+              </Dialog.Title>
+              <Dialog.CloseButton class="w-8 h-8 flex items-center justify-center rounded-full hover:bg-wa-sidebar-hover dark:hover:bg-wa-dark-sidebar-hover transition-colors focus:outline-none focus:ring-2 focus:ring-wa-teal">
+                <svg viewBox="0 0 24 24" width="20" height="20" class="text-wa-text-secondary">
+                  <path
+                    fill="currentColor"
+                    d="M19 6.41L17.59 5 12 10.59 6.41 5 5 6.41 10.59 12 5 17.59 6.41 19 12 13.41 17.59 19 19 17.59 13.41 12z"
+                  />
+                </svg>
+              </Dialog.CloseButton>
+            </div>
+
+            <fieldset class="mb-5">
+              <legend class={HEADING_CLASS}>{UI_LABELS.THEME}</legend>
+              <For each={THEMES}>
+                {(option) => (
+                  <label class={LABEL_CLASS}>
+                    <input
+                      type="radio"
+                      name="theme"
+                      value={option.value}
+                      checked={settings().theme === option.value}
+                      onChange={() => changeSettings({ theme: option.value })}
+                      class="accent-wa-teal"
+                    />
+                    {option.label}
+                  </label>
+                )}
+              </For>
+            </fieldset>
+
+            <fieldset class="mb-5">
+              <legend class={HEADING_CLASS}>{UI_LABELS.SOUNDS}</legend>
+              <label class={LABEL_CLASS}>
+                <input
+                  type="checkbox"
+                  checked={settings().sounds.enabled}
+                  onChange={(e) => changeSettings({ sounds: { enabled: e.currentTarget.checked } })}
+                  class="accent-wa-teal"
+                />
+                {UI_LABELS.PLAY_SOUNDS}
+              </label>
+              <label class={LABEL_CLASS}>
+                <span class="w-16">{UI_LABELS.VOLUME}</span>
+                <input
+                  type="range"
+                  min="0"
+                  max="1"
+                  step="0.05"
+                  value={settings().sounds.volume}
+                  disabled={!settings().sounds.enabled}
+                  onChange={(e) =>
+                    changeSettings({ sounds: { volume: e.currentTarget.valueAsNumber } })
+                  }
+                  class="flex-1 accent-wa-teal disabled:opacity-50"
+                />
+              </label>
+            </fieldset>
+
+            <fieldset>
+              <legend class={HEADING_CLASS}>{UI_LABELS.NOTIFICATIONS}</legend>
+              <label class={LABEL_CLASS}>
+                <input
+                  type="checkbox"
+                  checked={settings().notifications.messages}
+                  onChange={(e) =>
+                    changeSettings({ notifications: { messages: e.currentTarget.checked } })
+                  }
+                  class="accent-wa-teal"
+                />
+                {UI_LABELS.NOTIFY_MESSAGES}
+              </label>
+              <label class={LABEL_CLASS}>
+                <input
+                  type="checkbox"
+                  checked={settings().notifications.presence}
+                  onChange={(e) =>
+                    changeSettings({ notifications: { presence: e.currentTarget.checked } })
+                  }
+                  class="accent-wa-teal"
+                />
+                {UI_LABELS.NOTIFY_PRESENCE}
+              </label>
+              <label class={LABEL_CLASS}>
+                <input
+                  type="checkbox"
+                  checked={settings().notifications.mentions}
+                  onChange={(e) =>
+                    changeSettings({ notifications: { mentions: e.currentTarget.checked } })
+                  }
+                  class="accent-wa-teal"
+                />
+                {UI_LABELS.NOTIFY_MENTIONS}
+              </label>
+              <label class="flex flex-col gap-1 py-1 text-sm text-wa-text-primary dark:text-wa-dark-text-primary">
+                {UI_LABELS.KEYWORDS}
+                <input
+                  type="text"
+                  value={settings().notifications.keywords.join(', ')}
+                  placeholder={UI_LABELS.KEYWORDS_PLACEHOLDER}
+                  onChange={(e) =>
+                    changeSettings({
+                      notifications: { keywords: parseKeywords(e.currentTarget.value) },
+                    })
+                  }
+                  class="px-3 py-2 rounded-lg border border-wa-border dark:border-wa-dark-border bg-wa-header dark:bg-wa-dark-header text-sm text-wa-text-primary dark:text-wa-dark-text-primary focus:outline-none focus:border-wa-teal"
+                />
+              </label>
+            </fieldset>
+
+            <fieldset class="mt-5">
+              <legend class={HEADING_CLASS}>{UI_LABELS.QUIET_HOURS}</legend>
+              <p class="text-xs text-wa-text-secondary dark:text-wa-dark-text-secondary mb-2">
+                {UI_LABELS.QUIET_HOURS_DESCRIPTION}
+              </p>
+              <For each={settings().notifications.quietHours}>
+                {(period, index) => (
+                  <div class="py-2 border-b border-wa-border dark:border-wa-dark-border">
+                    <div class={LABEL_CLASS}>
+                      <span>{UI_LABELS.QUIET_HOURS_FROM}</span>
+                      <input
+                        type="time"
+                        value={toTime(period.start)}
+                        onChange={(e) => {
+                          const start = fromTime(e.currentTarget.value);
+                          if (start !== null) updateQuietHours(index(), { start });
+                        }}
+                        class="px-2 py-1 rounded-lg border border-wa-border dark:border-wa-dark-border bg-wa-header dark:bg-wa-dark-header focus:outline-none focus:border-wa-teal"
+                      />
+                      <span>{UI_LABELS.QUIET_HOURS_TO}</span>
+                      <input
+                        type="time"
+                        value={toTime(period.end)}
+                        onChange={(e) => {
+                          const end = fromTime(e.currentTarget.value);
+                          if (end !== null) updateQuietHours(index(), { end });
+                        }}
+                        class="px-2 py-1 rounded-lg border border-wa-border dark:border-wa-dark-border bg-wa-header dark:bg-wa-dark-header focus:outline-none focus:border-wa-teal"
+                      />
+                      <button
+                        type="button"
+                        onClick={() =>
+                          changeQuietHours(
+                            settings().notifications.quietHours.filter((_, i) => i !== index())
+                          )
+                        }
+                        class="ml-auto text-xs text-red-500 hover:underline"
+                      >
+                        {UI_LABELS.REMOVE_QUIET_HOURS}
+                      </button>
+                    </div>
+                    <div class="flex gap-1">
+                      <For each={WEEKDAYS}>
+                        {(day) => (
+                          <button
+                            type="button"
+                            aria-pressed={period.days.includes(day.value)}
+                            onClick={() => toggleDay(index(), day.value)}
+                            class={`w-8 h-8 rounded-full text-xs transition-colors ${
+                              period.days.includes(day.value)
+                                ? 'bg-wa-teal text-white'
+                                : 'bg-wa-sidebar-hover dark:bg-wa-dark-sidebar-hover text-wa-text-secondary dark:text-wa-dark-text-secondary'
+                            }`}
+                          >
+                            {day.label}
+                          </button>
+                        )}
+                      </For>
+                    </div>
+                  </div>
+                )}
+              </For>
+              <button
+                type="button"
+                onClick={() =>
+                  changeQuietHours([...settings().notifications.quietHours, NEW_QUIET_HOURS])
+                }
+                class="mt-2 text-sm text-wa-teal hover:underline"
+              >
+                {UI_LABELS.ADD_QUIET_HOURS}
+              </button>
+            </fieldset>
+          </Dialog.Content>
+        </div>
+      </Dialog.Portal>
+    </Dialog>
+  );
+}
@@ -8,6 +8,20 @@ export const ERROR_MESSAGES = {
   CREATE_GROUP_FAILED: 'Failed to create group.',
   FIND_USER_FAILED: 'Failed to find user.',
   AUTH_FAILED: 'Authentication failed',
+  EXPORT_FAILED: 'Export failed.',
+  IMPORT_FAILED: 'Import failed.',
+  ATTACH_FAILED: 'Could not attach the file.',
+  SAVE_ATTACHMENT_FAILED: 'Could not save the attachment.',
+  LOGS_FAILED: 'Could not read the logs.',
+  DIAGNOSTICS_FAILED: 'Could not create the diagnostics bundle.',
+  PROFILES_FAILED: 'Could not load the profiles.',
+  SWITCH_PROFILE_FAILED: 'Could not switch profiles.',
+  INVALID_PROFILE_NAME: 'Use up to 32 letters, digits, - and _.',
+  CHAT_NOT_FOUND: 'That chat does not exist, or you are not in it.',
+  INVITE_NOT_FOUND: 'That invite link is no longer valid.',
+  OPEN_LINK_FAILED: 'Could not open the link.',
+  INVITE_FAILED: 'Could not create an invite link.',
+  MUTE_FAILED: 'Could not change notifications for this chat.',
 } as const;
 
 export const UI_LABELS = {
@@ -27,10 +41,14 @@ export const UI_LABELS = {
   LOADING_CHATS: 'Loading chats...',
   NO_CHATS: 'No chats yet. Start a new conversation!',
   NO_CONTACTS_MATCH: 'No contacts match',
+  MESSAGES_HEADING: 'Messages',
   TYPING: 'Typing...',
   SOMEONE_TYPING: 'someone is typing',
   ONLINE: 'online',
   OFFLINE: 'offline',
+  AWAY: 'away',
+  BUSY: 'busy',
+  UNREAD: 'unread',
   UNKNOWN_USER: 'Unknown',
   GROUP_CHAT_DEFAULT: 'Group Chat',
   SELECT_CHAT_PROMPT: 'Select a chat to start messaging',
@@ -43,6 +61,9 @@ export const UI_LABELS = {
   MESSAGE_LIST_LABEL: 'Chat messages. Use arrow keys to navigate between messages.',
   YOU: 'You',
   FAILED_TO_SEND: 'Failed to send. Try again.',
+  MESSAGE_QUEUED: 'Sending…',
+  MESSAGE_FAILED: 'Not sent',
+  RETRY_SEND: 'Retry',
   APP_TITLE: 'Chitchat',
   CREATE_ACCOUNT: 'Create Account',
   SIGN_IN: 'Sign In',
@@ -53,4 +74,83 @@ export const UI_LABELS = {
   LOADING: 'Loading...',
   ALREADY_HAVE_ACCOUNT: 'Already have an account?',
   DONT_HAVE_ACCOUNT: "Don't have an account?",
+  EXPORT_CHAT: 'Export chat',
+  EXPORT_ALL_CHATS: 'Export all chats',
+  EXPORT_JSON: 'JSON',
+  EXPORT_HTML: 'Web page (HTML)',
+  EXPORT_MARKDOWN: 'Markdown',
+  EXPORT_TEXT: 'Plain text',
+  EXPORTING: 'Exporting…',
+  CHAT_OPTIONS: 'Chat options',
+  IMPORT_HISTORY: 'Import history',
+  IMPORT_HISTORY_ITEM: 'Import history…',
+  IMPORT_DESCRIPTION:
+    'Bring in messages from a WhatsApp chat export (.txt or .zip) or a Telegram Desktop export (result.json).',
+  IMPORT_CHOOSE_FILE: 'Choose export file…',
+  IMPORT_SENDERS: 'Match senders to chat members',
+  IMPORT_KEEP_NAME: 'Keep name from export',
+  IMPORT_LOCAL_ONLY:
+    'Imported messages are read-only and stay on this device; other members will not see them.',
+  IMPORTED: 'Imported',
+  ATTACH_FILE: 'Attach a file',
+  REMOVE_ATTACHMENT: 'Remove attachment',
+  DROP_TO_ATTACH: 'Drop a file to attach it',
+  ATTACHMENT: 'Attachment',
+  SAVE_ATTACHMENT: 'Save',
+  SAVING_ATTACHMENT: 'Saving…',
+  UPLOADING: 'Uploading…',
+  CLEAR_MEDIA_CACHE: 'Clear media cache',
+  DIAGNOSTIC_LOGS: 'Diagnostic logs',
+  LOGS_DESCRIPTION:
+    'Recent entries of the app log. Email addresses and message text are never written to it.',
+  LOG_LEVEL: 'Log level',
+  FILTER_LOGS: 'Filter logs',
+  REFRESH: 'Refresh',
+  NO_LOGS: 'No matching log entries',
+  SAVE_DIAGNOSTICS: 'Save diagnostics bundle…',
+  SAVING_DIAGNOSTICS: 'Checking for updates and collecting logs…',
+  DIAGNOSTICS_SAVED: 'Saved to',
+  DIAGNOSTICS_DESCRIPTION:
+    'Attach the bundle to your bug report. It holds logs, versions and settings, but no messages, contacts or passwords.',
+  SETTINGS: 'Settings',
+  THEME: 'Theme',
+  THEME_LIGHT: 'Light',
+  THEME_DARK: 'Dark',
+  THEME_SYSTEM: 'Same as system',
+  SOUNDS: 'Sounds',
+  PLAY_SOUNDS: 'Play a sound when messages are sent or received',
+  VOLUME: 'Volume',
+  NOTIFICATIONS: 'Notifications',
+  NOTIFY_MESSAGES: 'New messages',
+  NOTIFY_PRESENCE: 'Contacts coming online or going offline',
+  NOTIFY_MENTIONS: 'Always notify when someone @mentions me',
+  KEYWORDS: 'Always notify for these words',
+  KEYWORDS_PLACEHOLDER: 'e.g. deploy, lunch',
+  QUIET_HOURS: 'Quiet hours',
+  QUIET_HOURS_DESCRIPTION:
+    'No notifications during these hours, except for mentions and your words. An end before the start runs into the next day.',
+  ADD_QUIET_HOURS: 'Add quiet hours',
+  REMOVE_QUIET_HOURS: 'Remove',
+  QUIET_HOURS_FROM: 'From',
+  QUIET_HOURS_TO: 'to',
+  PROFILES: 'Profiles',
+  SWITCH_PROFILE: 'Switch profile…',
+  PROFILES_DESCRIPTION:
+    'Each profile has its own account, messages and settings. Chitchat restarts to switch.',
+  CURRENT_PROFILE: 'current',
+  NEW_PROFILE: 'New profile',
+  NEW_PROFILE_PLACEHOLDER: 'e.g. work',
+  SWITCH: 'Switch',
+  CREATE_AND_SWITCH: 'Create and switch',
+  SWITCHING_PROFILE: 'Restarting…',
+  COPY_INVITE_LINK: 'Copy invite link',
+  OPEN_IN_NEW_WINDOW: 'Open in new window',
+  MUTE_NOTIFICATIONS: 'Mute notifications',
+  MUTE_1_HOUR: 'For 1 hour',
+  MUTE_8_HOURS: 'For 8 hours',
+  MUTE_1_WEEK: 'For 1 week',
+  MUTE_UNTIL_UNMUTED: 'Until I unmute',
+  UNMUTE_NOTIFICATIONS: 'Unmute notifications',
+  MUTED: 'Muted',
+  INVITE_LINK_COPIED: 'Invite link copied. Anyone with the link can join the group.',
 } as const;
@@ -1,6 +1,17 @@
 /* @refresh reload */
 import { render } from 'solid-js/web';
 import App from './App';
+import { ChatWindow } from './components/ChatWindow';
+import { initLogForwarding } from './services/logs';
+import { initConnectionTracking } from './services/diagnostics';
+import { initSettings } from './stores/settings';
+import { poppedOutChatId } from './services/windows';
+
+// Keep the webview's warnings and errors in the app log, and connection
+// changes for diagnostics bundles
+initLogForwarding();
+initConnectionTracking();
+initSettings();
 
 // Disable browser-like navigation (back/forward with backspace, alt+arrows, etc.)
 // This is a desktop app, not a browser
@@ -26,4 +37,9 @@ document.addEventListener('contextmenu', (e) => {
   e.preventDefault();
 });
 
-render(() => <App />, document.getElementById('root') as HTMLElement);
+// Pop-out chat windows load the same page, showing just their chat
+const chatId = poppedOutChatId();
+render(
+  () => (chatId ? <ChatWindow chatId={chatId} /> : <App />),
+  document.getElementById('root') as HTMLElement
+);
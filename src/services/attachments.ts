@@ -0,0 +1,63 @@
+// Attachment service - files are hashed, encrypted, uploaded and downloaded
+// by the Rust core; the webview only ever handles their metadata, and refers
+// to files the user chose by one-time tokens rather than paths
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import { getCurrentWebview } from '@tauri-apps/api/webview';
+import type { Attachment, AttachmentProgress, ChosenFile } from '../types';
+
+/**
+ * Ask for a file to attach. Resolves to `null` if the user cancels.
+ */
+export function chooseAttachment(): Promise<ChosenFile | null> {
+  return invoke<ChosenFile | null>('choose_attachment');
+}
+
+/**
+ * Subscribe to files dropped on this window, ready for attaching.
+ */
+export function onAttachmentDropped(callback: (file: ChosenFile) => void): Promise<UnlistenFn> {
+  return getCurrentWebview().listen<ChosenFile>('attachment-dropped', (event) =>
+    callback(event.payload)
+  );
+}
+
+/**
+ * Encrypt and upload a chosen file for sending in a chat. Pass the result to
+ * `enqueueMessage`. Each chosen file can be uploaded once.
+ */
+export function uploadAttachment(chatId: string, file: ChosenFile): Promise<Attachment> {
+  return invoke<Attachment>('upload_attachment', { chatId, token: file.token });
+}
+
+/**
+ * Download an attachment and ask where to save it. Resolves to the saved
+ * path, or `null` if the user cancels.
+ */
+export function saveAttachment(attachment: Attachment): Promise<string | null> {
+  return invoke<string | null>('save_attachment', { attachment });
+}
+
+/**
+ * Subscribe to upload and download progress.
+ */
+export function onAttachmentProgress(
+  callback: (progress: AttachmentProgress) => void
+): Promise<UnlistenFn> {
+  return listen<AttachmentProgress>('attachment-progress', (event) => callback(event.payload));
+}
+
+/**
+ * Human-readable file size, e.g. "1.4 MB".
+ */
+export function formatFileSize(bytes: number): string {
+  if (bytes < 1024) return `${bytes} B`;
+  const units = ['KB', 'MB', 'GB'];
+  let size = bytes / 1024;
+  let unit = 0;
+  while (size >= 1024 && unit < units.length - 1) {
+    size /= 1024;
+    unit++;
+  }
+  return `${size.toFixed(size < 10 ? 1 : 0)} ${units[unit]}`;
+}
@@ -4,6 +4,7 @@ import {
   signInWithEmailAndPassword,
   signOut as firebaseSignOut,
   onAuthStateChanged,
+  onIdTokenChanged,
   updateProfile,
   type User,
 } from 'firebase/auth';
@@ -81,3 +82,13 @@ export async function signOut(): Promise<void> {
 export function onAuthChange(callback: (user: User | null) => void): () => void {
   return onAuthStateChanged(auth, callback);
 }
+
+/**
+ * Subscribes to ID token changes (sign-in, sign-out and hourly refreshes).
+ *
+ * @param callback - Function called with the user whose token changed
+ * @returns Unsubscribe function
+ */
+export function onIdTokenChange(callback: (user: User | null) => void): () => void {
+  return onIdTokenChanged(auth, callback);
+}
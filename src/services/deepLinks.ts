@@ -0,0 +1,22 @@
+// Deep link service - the Rust core parses `chitchat://` links the app is
+// opened with and passes them on once the webview is listening
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import type { DeepLink } from '../types';
+
+export const LINK_SCHEME = 'chitchat';
+
+/**
+ * Call `callback` with every link opened, starting with those that arrived
+ * before the webview was listening.
+ */
+export async function onDeepLink(callback: (link: DeepLink) => void): Promise<UnlistenFn> {
+  const unlisten = await listen<DeepLink>('deep-link', (event) => callback(event.payload));
+  const pending = await invoke<DeepLink[]>('take_deep_links');
+  pending.forEach(callback);
+  return unlisten;
+}
+
+export function inviteLink(token: string): string {
+  return `${LINK_SCHEME}://invite/${token}`;
+}
@@ -0,0 +1,36 @@
+// Diagnostics service - connection state history and diagnostics bundles for
+// support tickets, both kept by the Rust core
+import { invoke } from '@tauri-apps/api/core';
+import { ref, onValue } from 'firebase/database';
+import { db } from './firebase';
+import type { ConnectionSource } from '../types';
+
+function recordConnectionState(source: ConnectionSource, connected: boolean) {
+  invoke('record_connection_state', { source, connected }).catch((err) =>
+    console.error('Failed to record connection state:', err)
+  );
+}
+
+/**
+ * Report changes of the database connection and the network to the core
+ * for as long as the app runs.
+ */
+export function initConnectionTracking() {
+  recordConnectionState('network', navigator.onLine);
+  window.addEventListener('online', () => recordConnectionState('network', true));
+  window.addEventListener('offline', () => recordConnectionState('network', false));
+  onValue(ref(db, '.info/connected'), (snapshot) =>
+    recordConnectionState('database', snapshot.val() === true)
+  );
+}
+
+/**
+ * Ask where to save, then write a diagnostics bundle (redacted logs, versions,
+ * connection history, configuration without secrets) for a bug report.
+ * Resolves to the path written, or `null` if the user cancels.
+ */
+export function createDiagnosticsBundle(): Promise<string | null> {
+  return invoke<string | null>('create_diagnostics_bundle', {
+    userAgent: navigator.userAgent,
+  });
+}
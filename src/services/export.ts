@@ -0,0 +1,19 @@
+// Chat history export - the Rust core asks where to save and writes the file
+import { invoke } from '@tauri-apps/api/core';
+import type { ExportFormat, ExportSummary } from '../types';
+
+/**
+ * Export one chat, or every chat when `chatId` is omitted. Times in the file
+ * are shown in the local time zone. Resolves to `null` if the user cancels
+ * the save dialog.
+ */
+export function exportChats(
+  chatId: string | undefined,
+  format: ExportFormat
+): Promise<ExportSummary | null> {
+  return invoke<ExportSummary | null>('export_chats', {
+    chatId,
+    format,
+    utcOffsetMinutes: -new Date().getTimezoneOffset(),
+  });
+}
@@ -1,10 +1,11 @@
 // Firebase initialization
 // Replace with your Firebase config from Firebase Console
 import { initializeApp } from 'firebase/app';
-import { getAuth } from 'firebase/auth';
-import { getDatabase } from 'firebase/database';
+import { browserLocalPersistence, indexedDBLocalPersistence, initializeAuth } from 'firebase/auth';
+import { forceWebSockets, getDatabase } from 'firebase/database';
+import { currentProfile } from './profiles';
 
-const firebaseConfig = {
+const buildConfig = {
   apiKey: import.meta.env.VITE_FIREBASE_API_KEY,
   authDomain: import.meta.env.VITE_FIREBASE_AUTH_DOMAIN,
   projectId: import.meta.env.VITE_FIREBASE_PROJECT_ID,
@@ -14,8 +15,19 @@ const firebaseConfig = {
   databaseURL: import.meta.env.VITE_FIREBASE_DATABASE_URL,
 };
 
+// A profile can use its own Firebase project instead of the one built in
+export const firebaseConfig = currentProfile().backend ?? buildConfig;
+
 const app = initializeApp(firebaseConfig);
-export const auth = getAuth(app);
 
-// Realtime Database instance
+// Same persistence as getAuth(), but without the popup/redirect resolver: sign-in is
+// email/password only, and the resolver would load scripts and an iframe from Google
+// that the Content Security Policy does not allow
+export const auth = initializeAuth(app, {
+  persistence: [indexedDBLocalPersistence, browserLocalPersistence],
+});
+
+// Realtime Database instance. The long-polling fallback injects <script> tags, which
+// the Content Security Policy blocks, so always use WebSockets
+forceWebSockets();
 export const db = getDatabase(app);
@@ -0,0 +1,35 @@
+// Image service - outgoing images are cleaned of metadata, downscaled and
+// thumbnailed by the Rust core before they are uploaded
+import { invoke } from '@tauri-apps/api/core';
+import type { ChosenFile, ImageOptions, PreparedImage } from '../types';
+
+/** Extensions of images that are processed before sending */
+const PROCESSED_EXTENSIONS = ['jpg', 'jpeg', 'png', 'webp'];
+
+/**
+ * Whether a file will be processed by {@link prepareImage} before it is sent.
+ * Other images (e.g. animated GIFs) are sent as they are.
+ */
+export function isProcessedImage(file: ChosenFile): boolean {
+  const extension = file.name.split('.').pop()?.toLowerCase() ?? '';
+  return PROCESSED_EXTENSIONS.includes(extension);
+}
+
+/**
+ * Strip EXIF/XMP metadata from a chosen image and scale it down. Resolves to
+ * the processed copy, to upload instead of the original, plus its
+ * dimensions and thumbnail.
+ */
+export function prepareImage(
+  file: ChosenFile,
+  options?: Partial<ImageOptions>
+): Promise<PreparedImage> {
+  return invoke<PreparedImage>('prepare_image', { token: file.token, options });
+}
+
+/**
+ * A thumbnail of a chosen image as a `data:` URL.
+ */
+export function imageThumbnail(file: ChosenFile, size?: number): Promise<string> {
+  return invoke<string>('image_thumbnail', { token: file.token, size });
+}
@@ -0,0 +1,40 @@
+// History import from WhatsApp and Telegram exports - parsed by the Rust core
+// and kept in the local store as archived messages
+import { invoke } from '@tauri-apps/api/core';
+import type { ChosenFile, ImportReport, Message } from '../types';
+
+/**
+ * Ask for an export file. Resolves to `null` if the user cancels.
+ */
+export function chooseImportFile(): Promise<ChosenFile | null> {
+  return invoke<ChosenFile | null>('choose_import_file');
+}
+
+/**
+ * Import a chosen export into a chat, or with `dryRun` only report what would
+ * be imported. The token stays valid until an import succeeds. Exports
+ * without time zone information are read in the local time zone.
+ * @param mapping - Export sender name to participant user ID or email; an
+ *   empty string keeps that sender unmapped
+ */
+export function importHistory(
+  chatId: string,
+  token: string,
+  mapping: Record<string, string>,
+  dryRun: boolean
+): Promise<ImportReport> {
+  return invoke<ImportReport>('import_history', {
+    chatId,
+    token,
+    mapping,
+    dryRun,
+    utcOffsetMinutes: -new Date().getTimezoneOffset(),
+  });
+}
+
+/**
+ * All imported messages of a chat in chronological order.
+ */
+export function getArchivedMessages(chatId: string): Promise<Message[]> {
+  return invoke<Message[]>('get_archived_messages', { chatId });
+}
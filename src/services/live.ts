@@ -0,0 +1,46 @@
+// Live subscriptions - RTDB locations are followed by the Rust core, which
+// streams every change over an IPC channel until unsubscribed
+import { Channel, invoke } from '@tauri-apps/api/core';
+import { backendSessionReady } from './session';
+import type { LiveUpdate } from '../types';
+
+export type Unsubscribe = () => void;
+
+/**
+ * Start the subscription command `command` and pass each value it streams to
+ * `callback`. `onError` is called if the subscription cannot start or the
+ * server cancels it, e.g. after losing read access.
+ */
+export function subscribe<T>(
+  command: string,
+  args: Record<string, unknown>,
+  callback: (value: T) => void,
+  onError?: (error: Error) => void
+): Unsubscribe {
+  let stopped = false;
+  const onUpdate = new Channel<LiveUpdate<T>>();
+  onUpdate.onmessage = (update) => {
+    if (stopped) return;
+    if (update.kind === 'value') {
+      callback(update.value);
+    } else {
+      console.error(`Subscription ${command} was cancelled`);
+      onError?.(new Error('Permission denied'));
+    }
+  };
+
+  const id = backendSessionReady()
+    .then(() => (stopped ? null : invoke<number>(command, { ...args, onUpdate })))
+    .catch((error) => {
+      console.error(`Failed to start ${command}:`, error);
+      if (!stopped) onError?.(error instanceof Error ? error : new Error(String(error)));
+      return null;
+    });
+
+  return () => {
+    stopped = true;
+    id.then((id) => {
+      if (id !== null) return invoke('unsubscribe', { id });
+    }).catch((error) => console.error('Failed to unsubscribe:', error));
+  };
+}
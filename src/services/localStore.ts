@@ -0,0 +1,59 @@
+// Encrypted local cache service (backed by the Rust core's SQLCipher store)
+import { invoke } from '@tauri-apps/api/core';
+import type { Chat, Message, User } from '../types';
+
+/**
+ * Load cached chats, most recently updated first.
+ */
+export function getCachedChats(): Promise<Chat[]> {
+  return invoke<Chat[]>('get_cached_chats');
+}
+
+/**
+ * Write chats to the local cache (insert or replace).
+ */
+export function cacheChats(chats: Chat[]): Promise<void> {
+  return invoke('cache_chats', { chats });
+}
+
+/**
+ * Remove a chat and its messages from the local cache.
+ */
+export function removeCachedChat(chatId: string): Promise<void> {
+  return invoke('remove_cached_chat', { chatId });
+}
+
+/**
+ * Load the newest cached messages of a chat in chronological order.
+ * @param before - Only return messages older than the message with this ID (for paging)
+ */
+export function getCachedMessages(
+  chatId: string,
+  before?: string,
+  limit?: number
+): Promise<Message[]> {
+  return invoke<Message[]>('get_cached_messages', { chatId, before, limit });
+}
+
+/**
+ * Write messages of a chat to the local cache (insert or replace).
+ */
+export function cacheMessages(chatId: string, messages: Message[]): Promise<void> {
+  return invoke('cache_messages', { chatId, messages });
+}
+
+export function getCachedUser(userId: string): Promise<User | null> {
+  return invoke<User | null>('get_cached_user', { userId });
+}
+
+export function cacheUsers(users: User[]): Promise<void> {
+  return invoke('cache_users', { users });
+}
+
+/**
+ * Wipe the local cache, keeping imported history. Call this when the user
+ * signs out.
+ */
+export function clearLocalStore(): Promise<void> {
+  return invoke('clear_local_store');
+}
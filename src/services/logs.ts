@@ -0,0 +1,62 @@
+// Log service - the Rust core keeps the app log (rotating files, with personal
+// data redacted); the webview forwards its warnings and errors to it
+import { invoke } from '@tauri-apps/api/core';
+import type { LogEntry, LogFilter, LogLevel } from '../types';
+
+/**
+ * Add an entry to the app log. Values under keys such as `text` or `email`
+ * in `context` are redacted by the core.
+ */
+export function logMessage(level: LogLevel, message: string, context?: unknown): Promise<void> {
+  return invoke('log_message', { level, message, context });
+}
+
+/**
+ * The most recent log entries matching `filter`, oldest first.
+ */
+export function getLogs(filter: LogFilter = {}): Promise<LogEntry[]> {
+  return invoke<LogEntry[]>('get_logs', { filter });
+}
+
+function describe(value: unknown): string {
+  if (value instanceof Error) return value.stack || `${value.name}: ${value.message}`;
+  if (typeof value === 'string') return value;
+  if (typeof value === 'object' && value && 'message' in value) {
+    return String((value as { message: unknown }).message);
+  }
+  return String(value);
+}
+
+/**
+ * Forward `console.warn`/`console.error` calls and uncaught errors to the app
+ * log, so they outlive the webview. Plain objects are sent as context.
+ */
+export function initLogForwarding() {
+  const forward = (level: LogLevel, args: unknown[]) => {
+    const context = args.filter(
+      (arg) =>
+        typeof arg === 'object' && arg !== null && !(arg instanceof Error) && !('message' in arg)
+    );
+    const message = args
+      .filter((arg) => !context.includes(arg))
+      .map(describe)
+      .join(' ');
+    // Never through console, which would forward again
+    logMessage(level, message, context.length > 0 ? context : undefined).catch(() => {});
+  };
+
+  for (const level of ['warn', 'error'] as const) {
+    const original = console[level].bind(console);
+    console[level] = (...args: unknown[]) => {
+      original(...args);
+      forward(level, args);
+    };
+  }
+
+  window.addEventListener('error', (event) => {
+    forward('error', ['Uncaught error:', event.error ?? event.message]);
+  });
+  window.addEventListener('unhandledrejection', (event) => {
+    forward('error', ['Unhandled rejection:', event.reason]);
+  });
+}
@@ -0,0 +1,57 @@
+// Markdown service - message text is rendered to sanitized HTML by the Rust
+// core, in batches, and kept in a bounded cache keyed by the text
+import { invoke } from '@tauri-apps/api/core';
+import { createSignal } from 'solid-js';
+
+/** Rendered texts kept in memory; the oldest are dropped first */
+const MAX_CACHED = 2000;
+
+const rendered = new Map<string, string>();
+const pending = new Set<string>();
+const [version, setVersion] = createSignal(0);
+
+/**
+ * The rendered HTML for a message text, or `undefined` until
+ * {@link renderMarkdown} has processed it. Reactive.
+ */
+export function markdownHtml(text: string): string | undefined {
+  version();
+  return rendered.get(text);
+}
+
+/**
+ * Render every text that is not cached yet, in a single call.
+ */
+export async function renderMarkdown(texts: string[]): Promise<void> {
+  const missing = [...new Set(texts)].filter(
+    (text) => text && !rendered.has(text) && !pending.has(text)
+  );
+  if (missing.length === 0) return;
+  missing.forEach((text) => pending.add(text));
+  try {
+    const html = await invoke<string[]>('render_markdown', { texts: missing });
+    missing.forEach((text, i) => rendered.set(text, html[i]));
+    for (const oldest of rendered.keys()) {
+      if (rendered.size <= MAX_CACHED) break;
+      rendered.delete(oldest);
+    }
+    setVersion(version() + 1);
+  } finally {
+    missing.forEach((text) => pending.delete(text));
+  }
+}
+
+let highlightStyle: HTMLStyleElement | null = null;
+
+/**
+ * Load the code highlighting stylesheet for the light or dark theme.
+ */
+export async function applyHighlightTheme(dark: boolean): Promise<void> {
+  const css = await invoke<string>('get_highlight_css', { dark });
+  if (!highlightStyle) {
+    highlightStyle = document.createElement('style');
+    highlightStyle.id = 'highlight-theme';
+    document.head.appendChild(highlightStyle);
+  }
+  highlightStyle.textContent = css;
+}
@@ -0,0 +1,30 @@
+// Media cache service - downloaded media is kept by the Rust core under its
+// SHA-256 and served to the webview through the `chitchat-media` scheme
+import { convertFileSrc, invoke } from '@tauri-apps/api/core';
+import type { Attachment, MediaCacheUsage } from '../types';
+
+/**
+ * URL of a cached file, for use as an `<img>` source. Only resolves once the
+ * file is in the cache, e.g. after {@link cacheAttachment}.
+ */
+export function mediaUrl(sha256: string): string {
+  return convertFileSrc(sha256, 'chitchat-media');
+}
+
+/**
+ * Download an attachment into the cache unless it is already there.
+ */
+export function cacheAttachment(attachment: Attachment): Promise<void> {
+  return invoke('cache_attachment', { attachment });
+}
+
+export function getMediaCacheUsage(): Promise<MediaCacheUsage> {
+  return invoke<MediaCacheUsage>('get_media_cache_usage');
+}
+
+/**
+ * Remove all cached media; it is downloaded again when next shown.
+ */
+export function clearMediaCache(): Promise<void> {
+  return invoke('clear_media_cache');
+}
@@ -14,147 +14,37 @@ import {
   type Unsubscribe,
 } from 'firebase/database';
 import { db } from './firebase';
+import { subscribe } from './live';
 import type { Message, Chat, User } from '../types';
 
-// Subscribe to messages in a chat (real-time)
+// Subscribe to messages in a chat (real-time), oldest first. Messages are
+// end-to-end encrypted in RTDB; the Rust core follows the chat and decrypts
+// them before they reach the callback.
 export function subscribeToMessages(
   chatId: string,
   callback: (messages: Message[]) => void
 ): Unsubscribe {
-  const messagesRef = ref(db, `messages/${chatId}`);
-
-  const unsubscribe = onValue(messagesRef, (snapshot) => {
-    const messages: Message[] = [];
-    if (snapshot.exists()) {
-      snapshot.forEach((child) => {
-        messages.push({
-          id: child.key!,
-          ...child.val(),
-        });
-      });
-      // Sort by timestamp (RTDB doesn't guarantee order)
-      messages.sort((a, b) => {
-        const timeA = typeof a.timestamp === 'number' ? a.timestamp : a.timestamp?.getTime?.() || 0;
-        const timeB = typeof b.timestamp === 'number' ? b.timestamp : b.timestamp?.getTime?.() || 0;
-        return timeA - timeB;
-      });
-    }
-    callback(messages);
-  });
-
-  return unsubscribe;
+  return subscribe<Message[]>('subscribe_messages', { chatId }, callback);
 }
 
-// Send a new message
-export async function sendMessage(
-  chatId: string,
-  senderId: string,
-  senderName: string,
-  text: string
-): Promise<string> {
-  // Messages are stored at /messages/{chatId}/ (separate from chat metadata)
-  const messagesRef = ref(db, `messages/${chatId}`);
-  const newMessageRef = push(messagesRef);
-
-  await set(newMessageRef, {
-    senderId,
-    senderName,
-    text,
-    timestamp: serverTimestamp(),
-  });
-
-  // Update chat's last message, sender info, and clear typing indicator
-  const chatRef = ref(db, `chats/${chatId}`);
-  await update(chatRef, {
-    lastMessage: text,
-    lastMessageSenderId: senderId,
-    updatedAt: serverTimestamp(),
-    [`typing/${senderId}`]: false,
-  });
-
-  return newMessageRef.key!;
-}
-
-// Subscribe to user's chats using userChats index (real-time)
-// This subscribes to the userChats/{odId} index, then subscribes to each individual chat
+// Subscribe to a user's chats (real-time), newest first. The Rust core follows
+// the userChats/{userId} index and each chat listed in it.
 export function subscribeToChats(
   userId: string,
   callback: (chats: Chat[]) => void,
   onError?: (error: Error) => void
 ): Unsubscribe {
-  const userChatsRef = ref(db, `userChats/${userId}`);
-  const chatSubscriptions: Map<string, Unsubscribe> = new Map();
-  const chatData: Map<string, Chat> = new Map();
-
-  // Function to update and notify
-  const notifyUpdate = () => {
-    const chats = Array.from(chatData.values());
-    // Sort by updatedAt descending
-    chats.sort((a, b) => {
-      const timeA = typeof a.updatedAt === 'number' ? a.updatedAt : a.updatedAt?.getTime?.() || 0;
-      const timeB = typeof b.updatedAt === 'number' ? b.updatedAt : b.updatedAt?.getTime?.() || 0;
-      return timeB - timeA;
-    });
-    callback(chats);
-  };
-
-  // Subscribe to userChats index
-  const userChatsUnsubscribe = onValue(
-    userChatsRef,
-    (snapshot) => {
-      const currentChatIds = new Set<string>();
-
-      if (snapshot.exists()) {
-        snapshot.forEach((child) => {
-          const chatId = child.key!;
-          currentChatIds.add(chatId);
-
-          // Subscribe to this chat if not already subscribed
-          if (!chatSubscriptions.has(chatId)) {
-            const chatRef = ref(db, `chats/${chatId}`);
-            const chatUnsub = onValue(chatRef, (chatSnapshot) => {
-              if (chatSnapshot.exists()) {
-                chatData.set(chatId, {
-                  id: chatId,
-                  ...chatSnapshot.val(),
-                });
-              } else {
-                chatData.delete(chatId);
-              }
-              notifyUpdate();
-            });
-            chatSubscriptions.set(chatId, chatUnsub);
-          }
-        });
-      }
-
-      // Unsubscribe from chats that were removed
-      chatSubscriptions.forEach((unsub, chatId) => {
-        if (!currentChatIds.has(chatId)) {
-          unsub();
-          chatSubscriptions.delete(chatId);
-          chatData.delete(chatId);
-        }
-      });
+  return subscribe<Chat[]>('subscribe_chats', { userId }, callback, onError);
+}
 
-      // If no chats, notify with empty array
-      if (currentChatIds.size === 0) {
-        notifyUpdate();
-      }
-    },
-    (error) => {
-      console.error('RTDB subscription error:', error);
-      if (onError) onError(error);
-    }
-  );
-
-  // Return cleanup function
-  return () => {
-    userChatsUnsubscribe();
-    chatSubscriptions.forEach((unsub) => unsub());
-    chatSubscriptions.clear();
-    chatData.clear();
-  };
+// Subscribe to a single chat (real-time), e.g. for a pop-out chat window.
+// The callback gets null if the chat is deleted or the user was removed from it.
+export function subscribeToChat(
+  chatId: string,
+  callback: (chat: Chat | null) => void,
+  onError?: (error: Error) => void
+): Unsubscribe {
+  return subscribe<Chat | null>('subscribe_chat', { chatId }, callback, onError);
 }
 
 // Get user by ID
@@ -388,15 +278,7 @@ export function subscribeToUserPresence(
   odId: string,
   callback: (user: User | null) => void
 ): Unsubscribe {
-  const userRef = ref(db, `users/${odId}`);
-
-  return onValue(userRef, (snapshot) => {
-    if (snapshot.exists()) {
-      callback({ id: odId, ...snapshot.val() } as User);
-    } else {
-      callback(null);
-    }
-  });
+  return subscribe<User | null>('subscribe_user', { userId: odId }, callback);
 }
 
 // Rename a group
@@ -432,3 +314,49 @@ export async function removeGroupMember(chatId: string, memberId: string): Promi
 
   await update(ref(db), updates);
 }
+
+// Whether a user is in a chat; false as well if the chat does not exist
+export async function isChatParticipant(chatId: string, userId: string): Promise<boolean> {
+  const snapshot = await get(ref(db, `chats/${chatId}/participants/${userId}`));
+  return snapshot.exists();
+}
+
+export interface GroupInvite {
+  chatId: string;
+  groupName: string;
+  createdBy: string;
+}
+
+/**
+ * Creates an invite to a group, returning its token. Anyone holding the token
+ * can join the group until the group is deleted.
+ */
+export async function createGroupInvite(
+  chatId: string,
+  groupName: string,
+  createdBy: string
+): Promise<string> {
+  const bytes = crypto.getRandomValues(new Uint8Array(16));
+  // URL-safe base64, so the token can go into a link as it is
+  const token = btoa(String.fromCharCode(...bytes))
+    .replace(/\+/g, '-')
+    .replace(/\//g, '_')
+    .replace(/=+$/, '');
+
+  await set(ref(db, `invites/${token}`), {
+    chatId,
+    groupName,
+    createdBy,
+    createdAt: serverTimestamp(),
+  });
+  return token;
+}
+
+// Look up an invite by its token
+export async function getGroupInvite(token: string): Promise<GroupInvite | null> {
+  const snapshot = await get(ref(db, `invites/${token}`));
+  if (!snapshot.exists()) {
+    return null;
+  }
+  return snapshot.val() as GroupInvite;
+}
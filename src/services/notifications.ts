@@ -1,17 +1,19 @@
-// Notification service using Tauri's native notification plugin
-import {
-  isPermissionGranted,
-  requestPermission,
-  sendNotification,
-} from '@tauri-apps/plugin-notification';
+// Notification service - the Rust core decides from the notification settings,
+// chat mutes, quiet hours and the tray mute whether a notification is shown,
+// and shows it: through notify-rust and D-Bus on Linux and the BSDs, so a
+// chat's notification is replaced and closed in place, and with the native
+// notification plugin elsewhere. New messages are picked up and decrypted by
+// the core itself; the webview reports presence changes, and checks and asks
+// for the permission
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import { isPermissionGranted, requestPermission } from '@tauri-apps/plugin-notification';
+import type { NotificationOutcome, Settings } from '../types';
 
 // Track whether notifications are enabled and initialized
 let notificationsEnabled = false;
 let initialized = false;
 
-// Notification counter for unique IDs (must be 32-bit integer)
-let notificationIdCounter = 1;
-
 /**
  * Initialize the notification system.
  * Checks/requests permission and sets up the notification state.
@@ -67,118 +69,81 @@ export function resetNotifications(): void {
 }
 
 /**
- * Get unique notification ID
+ * Minutes local time is ahead of UTC, for quiet hours
  */
-function getNotificationId(): number {
-  return notificationIdCounter++;
+function utcOffsetMinutes(): number {
+  return -new Date().getTimezoneOffset();
 }
 
 /**
- * Generate a deterministic 32-bit integer ID from a string
+ * Subscribe to what became of each new message's notification. Only the main
+ * window hears about them.
  */
-function getNotificationIdForChat(chatId: string): number {
-  let hash = 0;
-  for (let i = 0; i < chatId.length; i++) {
-    const char = chatId.charCodeAt(i);
-    hash = (hash << 5) - hash + char;
-    hash = hash | 0; // Convert to 32bit integer
-  }
-  return Math.abs(hash);
+export function onMessageNotified(
+  callback: (outcome: NotificationOutcome) => void
+): Promise<UnlistenFn> {
+  return listen<NotificationOutcome>('message-notified', (event) => callback(event.payload));
 }
 
-/**
- * Show a notification for a new message
- * @param chatId - The ID of the chat (used for grouping notifications)
- * @param senderName - The name of the message sender
- * @param messageText - The message content (will be truncated if too long)
- */
-export async function notifyNewMessage(
-  chatId: string,
-  senderName: string,
-  messageText: string
-): Promise<void> {
-  if (!notificationsEnabled) {
-    return;
-  }
-
+// Presence notifications are rate limited per contact and overall
+async function notifyPresence(userId: string, userName: string, online: boolean): Promise<void> {
   try {
-    // Truncate message if too long
-    const truncatedMessage =
-      messageText.length > 100 ? messageText.substring(0, 97) + '...' : messageText;
-
-    // Send notification with deterministic ID based on chat
-    // This ensures updates to the same chat replace the previous notification
-    sendNotification({
-      id: getNotificationIdForChat(chatId),
-      title: `New message from ${senderName}`,
-      body: truncatedMessage,
-      sound: 'Default',
+    await invoke<NotificationOutcome>('notify_presence', {
+      notification: { userId, userName, online },
+      utcOffsetMinutes: utcOffsetMinutes(),
     });
   } catch (error) {
-    console.error('Failed to send message notification:', error);
+    console.error(`Failed to send ${online ? 'online' : 'offline'} notification:`, error);
   }
 }
 
 /**
  * Show a notification when a user comes online
+ * @param userId - The ID of the user who came online
  * @param userName - The name of the user who came online
  */
-export async function notifyUserOnline(userName: string): Promise<void> {
-  if (!notificationsEnabled) {
-    return;
-  }
-
-  try {
-    sendNotification({
-      id: getNotificationId(),
-      title: 'Contact Online',
-      body: `${userName} is now online`,
-      sound: 'Default',
-    });
-  } catch (error) {
-    console.error('Failed to send online notification:', error);
-  }
+export function notifyUserOnline(userId: string, userName: string): Promise<void> {
+  return notifyPresence(userId, userName, true);
 }
 
 /**
  * Show a notification when a user goes offline
+ * @param userId - The ID of the user who went offline
  * @param userName - The name of the user who went offline
  */
-export async function notifyUserOffline(userName: string): Promise<void> {
-  if (!notificationsEnabled) {
-    return;
-  }
+export function notifyUserOffline(userId: string, userName: string): Promise<void> {
+  return notifyPresence(userId, userName, false);
+}
 
-  try {
-    sendNotification({
-      id: getNotificationId(),
-      title: 'Contact Offline',
-      body: `${userName} is now offline`,
-      sound: 'Default',
-    });
-  } catch (error) {
-    console.error('Failed to send offline notification:', error);
-  }
+/**
+ * Tell the Rust core which chat this window shows, so no notifications are
+ * shown for it while the window has focus. Opening a chat also removes its
+ * notification.
+ */
+export function setOpenChat(chatId: string | null): void {
+  invoke('set_open_chat', { chatId }).catch((error) =>
+    console.error('Failed to report the open chat:', error)
+  );
 }
 
 /**
- * Show a generic notification
- * @param title - Notification title
- * @param body - Notification body text
+ * Mute a chat until `until` (milliseconds since the epoch), or until it is
+ * unmuted. Resolves to the updated settings.
  */
-export async function showNotification(title: string, body: string): Promise<void> {
-  if (!notificationsEnabled) {
-    return;
-  }
+export function muteChat(chatId: string, until: number | null): Promise<Settings> {
+  return invoke<Settings>('mute_chat', { chatId, until });
+}
 
-  try {
-    sendNotification({
-      id: getNotificationId(),
-      title,
-      body,
-      sound: 'Default',
-    });
-  } catch (error) {
-    console.error('Failed to send notification:', error);
-  }
+export function unmuteChat(chatId: string): Promise<Settings> {
+  return invoke<Settings>('unmute_chat', { chatId });
+}
+
+/**
+ * Whether a chat is muted, per the given settings
+ */
+export function isChatMuted(settings: Settings, chatId: string): boolean {
+  const now = Date.now();
+  return settings.notifications.mutedChats.some(
+    (mute) => mute.chatId === chatId && (mute.until == null || now < mute.until)
+  );
 }
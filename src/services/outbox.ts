@@ -0,0 +1,54 @@
+// Outbox service - outgoing messages are queued in the Rust core and delivered
+// in the background with retries, so sending works offline
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import type { Attachment, OutboxEntry, OutboxStateEvent } from '../types';
+
+/**
+ * Queue a message for delivery. Resolves as soon as the message is stored
+ * locally; delivery progress arrives through {@link onOutboxState}.
+ * @param attachment - A file uploaded with `uploadAttachment`, if any
+ */
+export function enqueueMessage(
+  chatId: string,
+  senderId: string,
+  senderName: string,
+  text: string,
+  attachment?: Attachment
+): Promise<OutboxEntry> {
+  return invoke<OutboxEntry>('enqueue_message', {
+    chatId,
+    senderId,
+    senderName,
+    text,
+    attachment,
+  });
+}
+
+/**
+ * List messages that have not been delivered yet (queued or failed).
+ */
+export function listOutbox(chatId?: string): Promise<OutboxEntry[]> {
+  return invoke<OutboxEntry[]>('list_outbox', { chatId });
+}
+
+/**
+ * Put a failed message back in the queue.
+ */
+export function retryOutboxMessage(id: string): Promise<void> {
+  return invoke('retry_outbox_message', { id });
+}
+
+/**
+ * Drop an undelivered message.
+ */
+export function discardOutboxMessage(id: string): Promise<void> {
+  return invoke('discard_outbox_message', { id });
+}
+
+/**
+ * Subscribe to queued/sent/failed/discarded transitions.
+ */
+export function onOutboxState(callback: (event: OutboxStateEvent) => void): Promise<UnlistenFn> {
+  return listen<OutboxStateEvent>('outbox-state', (event) => callback(event.payload));
+}
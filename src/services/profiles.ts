@@ -0,0 +1,30 @@
+// Profile service - each profile has its own local data, settings, sign-in and
+// optionally its own Firebase project. Switching restarts the app with the
+// chosen profile.
+import { invoke } from '@tauri-apps/api/core';
+import type { ProfileInfo } from '../types';
+
+export const DEFAULT_PROFILE = 'default';
+
+/**
+ * The profile this window runs in, as set by the Rust core before the page
+ * loads.
+ */
+export function currentProfile(): ProfileInfo {
+  return window.__CHITCHAT_PROFILE__ ?? { name: DEFAULT_PROFILE };
+}
+
+/**
+ * The names of all profiles, the default one first.
+ */
+export function listProfiles(): Promise<string[]> {
+  return invoke<string[]>('list_profiles');
+}
+
+/**
+ * Restart the app with another profile, creating it if it does not exist.
+ * Names may use letters, digits, `-` and `_`.
+ */
+export function switchProfile(name: string): Promise<void> {
+  return invoke('switch_profile', { name });
+}
@@ -0,0 +1,16 @@
+// Search service - full-text search over the local message cache
+import { invoke } from '@tauri-apps/api/core';
+import type { SearchHit } from '../types';
+
+/**
+ * Search cached messages across all chats.
+ *
+ * Supports free text plus `from:`, `in:`, `before:YYYY-MM-DD` and
+ * `after:YYYY-MM-DD` filters; quote multi-word values (`in:"Team chat"`).
+ * Results are ranked best match first.
+ */
+export function searchMessages(query: string, limit?: number): Promise<SearchHit[]> {
+  // getTimezoneOffset() is minutes *behind* UTC, the command wants minutes ahead
+  const utcOffsetMinutes = -new Date().getTimezoneOffset();
+  return invoke<SearchHit[]>('search_messages', { query, limit, utcOffsetMinutes });
+}
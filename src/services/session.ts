@@ -0,0 +1,35 @@
+// Hands the Firebase session to the Rust core so it can reach RTDB directly
+import { invoke } from '@tauri-apps/api/core';
+import { firebaseConfig } from './firebase';
+
+const databaseUrl = firebaseConfig.databaseURL;
+const storageBucket = firebaseConfig.storageBucket;
+
+// Settles once the Rust core holds a session, which it needs to reach RTDB
+let resolveReady: () => void = () => {};
+let ready = new Promise<void>((resolve) => (resolveReady = resolve));
+
+/**
+ * Share the current ID token with the Rust core.
+ * Call on sign-in and whenever Firebase rotates the token.
+ */
+export async function setBackendSession(userId: string, idToken: string): Promise<void> {
+  await invoke('set_backend_session', { databaseUrl, userId, idToken, storageBucket });
+  resolveReady();
+}
+
+/**
+ * Forget the session in the Rust core. Call on sign-out.
+ */
+export async function clearBackendSession(): Promise<void> {
+  ready = new Promise<void>((resolve) => (resolveReady = resolve));
+  await invoke('clear_backend_session');
+}
+
+/**
+ * Wait until the Rust core has a session, i.e. until the first
+ * {@link setBackendSession} after sign-in.
+ */
+export function backendSessionReady(): Promise<void> {
+  return ready;
+}
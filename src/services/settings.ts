@@ -0,0 +1,31 @@
+// Settings service - preferences are stored by the Rust core, which migrates
+// them between versions and announces every change to all windows
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import type { Settings } from '../types';
+
+/**
+ * Settings to change; groups such as `sounds` can be changed in part.
+ */
+export type SettingsChanges = {
+  [K in keyof Settings]?: Settings[K] extends object ? Partial<Settings[K]> : Settings[K];
+};
+
+export function getSettings(): Promise<Settings> {
+  return invoke<Settings>('get_settings');
+}
+
+/**
+ * Change some settings, returning all of them. Unknown or invalid settings
+ * are rejected.
+ */
+export function updateSettings(changes: SettingsChanges): Promise<Settings> {
+  return invoke<Settings>('update_settings', { changes });
+}
+
+/**
+ * Subscribe to settings changes, made from any window.
+ */
+export function onSettingsChanged(callback: (settings: Settings) => void): Promise<UnlistenFn> {
+  return listen<Settings>('settings-changed', (event) => callback(event.payload));
+}
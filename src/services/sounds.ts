@@ -1,14 +1,11 @@
 import sendSoundUrl from '../assets/sounds/send.wav';
 import receiveSoundUrl from '../assets/sounds/receive.wav';
+import { settings } from '../stores/settings';
 
 // Preload audio objects
 const sendAudio = new Audio(sendSoundUrl);
 const receiveAudio = new Audio(receiveSoundUrl);
 
-// Set volume
-sendAudio.volume = 0.5;
-receiveAudio.volume = 0.5;
-
 // Track if audio context is unlocked
 let isUnlocked = false;
 
@@ -43,6 +40,8 @@ export function initSounds() {
  * Play the "message sent" sound
  */
 export function playMessageSent() {
+  if (!settings().sounds.enabled) return;
+  sendAudio.volume = settings().sounds.volume;
   sendAudio.currentTime = 0;
   sendAudio.play().catch((e) => console.error('Error playing sent sound:', e));
 }
@@ -51,6 +50,8 @@ export function playMessageSent() {
  * Play the "message received" sound
  */
 export function playMessageReceived() {
+  if (!settings().sounds.enabled) return;
+  receiveAudio.volume = settings().sounds.volume;
   receiveAudio.currentTime = 0;
   receiveAudio.play().catch((e) => console.error('Error playing received sound:', e));
 }
@@ -0,0 +1,23 @@
+// Unread chat tracking - the Rust core works out which chats are unread from
+// the chats it follows for the session and keeps the tray badge in sync
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import type { UnreadSummary } from '../types';
+
+/**
+ * Record that the user has seen a chat up to its current `updatedAt`.
+ */
+export function markChatRead(chatId: string, upTo: number): Promise<void> {
+  return invoke('mark_chat_read', { chatId, upTo });
+}
+
+export function getUnreadChats(): Promise<UnreadSummary> {
+  return invoke<UnreadSummary>('get_unread_chats');
+}
+
+/**
+ * Subscribe to changes of the unread chat set.
+ */
+export function onUnreadChanged(callback: (summary: UnreadSummary) => void): Promise<UnlistenFn> {
+  return listen<UnreadSummary>('unread-changed', (event) => callback(event.payload));
+}
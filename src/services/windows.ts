@@ -0,0 +1,18 @@
+// Window service - chats can be popped out of the main window into windows of
+// their own, which the Rust core opens and places where they were last
+import { invoke } from '@tauri-apps/api/core';
+
+/**
+ * The chat this window shows if it is a pop-out chat window, as set by the
+ * Rust core before the page loads; null in the main window.
+ */
+export function poppedOutChatId(): string | null {
+  return window.__CHITCHAT_CHAT__ ?? null;
+}
+
+/**
+ * Open a chat in a window of its own, or bring its window to the front.
+ */
+export function openChatWindow(chatId: string): Promise<void> {
+  return invoke('open_chat_window', { chatId });
+}
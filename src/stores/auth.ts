@@ -1,7 +1,8 @@
 // Auth store - reactive state for authentication
 // Wrapped in createRoot to ensure proper signal disposal
 import { createSignal, createRoot } from 'solid-js';
-import { onAuthChange, type AuthUser } from '../services/auth';
+import { onAuthChange, onIdTokenChange, type AuthUser } from '../services/auth';
+import { setBackendSession, clearBackendSession } from '../services/session';
 
 // Create signals within a root to ensure proper lifecycle management
 const { user, loading, error, setUser, setLoading, setError } = createRoot(() => {
@@ -13,6 +14,7 @@ const { user, loading, error, setUser, setLoading, setError } = createRoot(() =>
 
 // Initialize auth listener
 let unsubscribe: (() => void) | null = null;
+let tokenUnsubscribe: (() => void) | null = null;
 
 export function initAuthListener() {
   if (unsubscribe) return; // Already initialized
@@ -21,6 +23,19 @@ export function initAuthListener() {
     setUser(authUser);
     setLoading(false);
   });
+
+  // Keep the Rust core's session in sync so the outbox can deliver messages
+  tokenUnsubscribe = onIdTokenChange(async (authUser) => {
+    try {
+      if (authUser) {
+        await setBackendSession(authUser.uid, await authUser.getIdToken());
+      } else {
+        await clearBackendSession();
+      }
+    } catch (err) {
+      console.error('Failed to update backend session:', err);
+    }
+  });
 }
 
 export function cleanupAuthListener() {
@@ -28,6 +43,10 @@ export function cleanupAuthListener() {
     unsubscribe();
     unsubscribe = null;
   }
+  if (tokenUnsubscribe) {
+    tokenUnsubscribe();
+    tokenUnsubscribe = null;
+  }
 }
 
 export function setAuthError(err: string | null) {
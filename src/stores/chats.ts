@@ -1,22 +1,29 @@
 // Chats store - reactive state for chat list and messages
 // Wrapped in createRoot to ensure proper signal disposal
 import { createSignal, createRoot } from 'solid-js';
-import type { Chat, Message, User } from '../types';
+import type { UnlistenFn } from '@tauri-apps/api/event';
+import type { Chat, Message, OutboxEntry, User } from '../types';
 
 export type ConnectionState = 'idle' | 'connecting' | 'connected' | 'error';
 
 import {
+  subscribeToChat,
   subscribeToChats,
   subscribeToMessages,
   subscribeToUserPresence,
 } from '../services/messages';
 import { playMessageReceived } from '../services/sounds';
+import { cacheChats, getCachedChats, getCachedMessages } from '../services/localStore';
+import { listOutbox, onOutboxState } from '../services/outbox';
+import { getArchivedMessages } from '../services/import';
+import { getUnreadChats, markChatRead, onUnreadChanged } from '../services/unread';
 import {
   initNotifications,
-  notifyNewMessage,
+  onMessageNotified,
   notifyUserOnline,
   notifyUserOffline,
   resetNotifications,
+  setOpenChat,
 } from '../services/notifications';
 
 // Create signals within a root to ensure proper lifecycle management
@@ -25,9 +32,12 @@ const store = createRoot(() => {
   const [currentChatId, setCurrentChatId] = createSignal<string | null>(null);
   const [currentChat, setCurrentChat] = createSignal<Chat | null>(null);
   const [messages, setMessages] = createSignal<Message[]>([]);
+  const [archived, setArchived] = createSignal<Message[]>([]);
+  const [outgoing, setOutgoing] = createSignal<OutboxEntry[]>([]);
   const [connectionState, setConnectionState] = createSignal<ConnectionState>('idle');
   const [loadingMessages, setLoadingMessages] = createSignal(false);
   const [otherUserPresence, setOtherUserPresence] = createSignal<Record<string, User>>({});
+  const [unreadChatIds, setUnreadChatIds] = createSignal<string[]>([]);
 
   return {
     chats,
@@ -38,12 +48,18 @@ const store = createRoot(() => {
     setCurrentChat,
     messages,
     setMessages,
+    archived,
+    setArchived,
+    outgoing,
+    setOutgoing,
     connectionState,
     setConnectionState,
     loadingMessages,
     setLoadingMessages,
     otherUserPresence,
     setOtherUserPresence,
+    unreadChatIds,
+    setUnreadChatIds,
   };
 });
 
@@ -57,23 +73,28 @@ const {
   setCurrentChat,
   messages,
   setMessages,
+  archived,
+  setArchived,
+  outgoing,
+  setOutgoing,
   connectionState,
   setConnectionState,
   loadingMessages,
   setLoadingMessages,
   otherUserPresence,
   setOtherUserPresence,
+  unreadChatIds,
+  setUnreadChatIds,
 } = store;
 
 let chatsUnsubscribe: (() => void) | null = null;
 let messagesUnsubscribe: (() => void) | null = null;
+let outboxUnlisten: UnlistenFn | null = null;
+let unreadUnlisten: UnlistenFn | null = null;
+let notifiedUnlisten: UnlistenFn | null = null;
 let presenceUnsubscribes: Map<string, () => void> = new Map();
 let loggedInUserId: string | null = null;
 
-// Track notified messages and chat states to avoid duplicate notifications
-let notifiedMessageTimestamps: Map<string, number> = new Map(); // chatId -> lastNotifiedTimestamp
-let isInitialChatsLoad = true;
-
 // Track presence initialization state per user
 let presenceInitialStates: Set<string> = new Set(); // Set of userIds that have been loaded initially
 
@@ -82,83 +103,58 @@ export async function initChatsListener(userId: string) {
   cleanupChatsListener();
   setConnectionState('connecting');
   loggedInUserId = userId;
-  isInitialChatsLoad = true;
-  notifiedMessageTimestamps.clear();
   presenceInitialStates.clear();
 
+  // Render cached chats immediately while RTDB catches up
+  getCachedChats()
+    .then((cached) => {
+      if (loggedInUserId === userId && connectionState() === 'connecting') {
+        setChats(cached);
+      }
+    })
+    .catch((error) => console.error('Failed to load cached chats:', error));
+
+  listenForOutbox(userId);
+
+  // Unread chats are worked out by the Rust core from the chats it follows
+  getUnreadChats()
+    .then((summary) => {
+      if (loggedInUserId === userId) setUnreadChatIds(summary.chatIds);
+    })
+    .catch((error) => console.error('Failed to load unread chats:', error));
+  onUnreadChanged((summary) => setUnreadChatIds(summary.chatIds))
+    .then((unlisten) => {
+      if (loggedInUserId === userId) {
+        unreadUnlisten = unlisten;
+      } else {
+        unlisten();
+      }
+    })
+    .catch((error) => console.error('Failed to listen for unread changes:', error));
+  window.addEventListener('focus', markCurrentChatRead);
+
+  // The Rust core notifies about new messages; play a sound when a
+  // notification popped up or the chat is being looked at
+  onMessageNotified((outcome) => {
+    if (outcome === 'shown' || outcome === 'focused') playMessageReceived();
+  })
+    .then((unlisten) => {
+      if (loggedInUserId === userId) {
+        notifiedUnlisten = unlisten;
+      } else {
+        unlisten();
+      }
+    })
+    .catch((error) => console.error('Failed to listen for notifications:', error));
+
   // Initialize notifications when user logs in
   await initNotifications();
 
   chatsUnsubscribe = subscribeToChats(
     userId,
     (newChats) => {
-      // On initial load, just record the current timestamps - don't notify
-      if (isInitialChatsLoad) {
-        newChats.forEach((chat) => {
-          const timestamp = typeof chat.updatedAt === 'number' ? chat.updatedAt : Date.now();
-          notifiedMessageTimestamps.set(chat.id, timestamp);
-        });
-        isInitialChatsLoad = false;
-        setChats(newChats);
-        setConnectionState('connected');
-
-        // Update current chat if selected
-        const currentId = currentChatId();
-        if (currentId) {
-          const updated = newChats.find((c) => c.id === currentId);
-          if (updated) setCurrentChat(updated);
-        }
-
-        // Subscribe to presence for all other users in chats
-        updatePresenceSubscriptions(newChats, userId);
-        return;
-      }
-
-      // Not initial load - check for new messages
-      newChats.forEach((chat) => {
-        const chatTimestamp = typeof chat.updatedAt === 'number' ? chat.updatedAt : 0;
-        const lastNotifiedTimestamp = notifiedMessageTimestamps.get(chat.id) || 0;
-
-        // Only notify if:
-        // 1. The chat timestamp is newer than what we last notified
-        // 2. We know who sent it (lastMessageSenderId is defined)
-        // 3. It's not from the current user
-        // 4. Not viewing this chat with window visible
-        const isNewerMessage = chatTimestamp > lastNotifiedTimestamp;
-        const hasSenderId =
-          chat.lastMessageSenderId !== undefined && chat.lastMessageSenderId !== null;
-        const isFromOther = chat.lastMessageSenderId !== loggedInUserId;
-        const isViewingChat =
-          chat.id === currentChatId() && !document.hidden && document.hasFocus();
-
-        if (isNewerMessage && hasSenderId && isFromOther && !isViewingChat && chat.lastMessage) {
-          const senderId = chat.lastMessageSenderId;
-          const senderName = senderId
-            ? chat.participantNames?.[senderId] ||
-            otherUserPresence()[senderId]?.displayName ||
-            otherUserPresence()[senderId]?.email ||
-            'Someone'
-            : 'Someone';
-
-          notifyNewMessage(chat.id, senderName, chat.lastMessage);
-
-          // Update the last notified timestamp for this chat
-          notifiedMessageTimestamps.set(chat.id, chatTimestamp);
-
-          // Play sound for new message
-          playMessageReceived();
-        } else if (isNewerMessage) {
-          // Still update the timestamp even if we didn't notify (e.g., own message or viewing chat)
-          notifiedMessageTimestamps.set(chat.id, chatTimestamp);
-
-          // Also play sound if we're viewing the chat but it's from someone else
-          if (isViewingChat && isFromOther) {
-            playMessageReceived();
-          }
-        }
-      });
-
       setChats(newChats);
+      persistChats(newChats);
       setConnectionState('connected');
 
       // Update current chat if selected
@@ -167,6 +163,7 @@ export async function initChatsListener(userId: string) {
         const updated = newChats.find((c) => c.id === currentId);
         if (updated) setCurrentChat(updated);
       }
+      markCurrentChatRead();
 
       // Subscribe to presence for all other users in chats
       updatePresenceSubscriptions(newChats, userId);
@@ -178,8 +175,82 @@ export async function initChatsListener(userId: string) {
   );
 }
 
-// Subscribe to presence for other users in chats
-function updatePresenceSubscriptions(chatList: Chat[], currentUserIdParam: string) {
+// Pop-out chat windows follow only their own chat; notifications and sounds
+// for new messages are left to the main window
+export function initChatWindowListener(userId: string, chatId: string) {
+  cleanupChatsListener();
+  setConnectionState('connecting');
+  loggedInUserId = userId;
+  listenForOutbox(userId);
+  window.addEventListener('focus', markCurrentChatRead);
+
+  selectChat(chatId);
+  chatsUnsubscribe = subscribeToChat(
+    chatId,
+    (chat) => {
+      const chatList = chat ? [chat] : [];
+      setChats(chatList);
+      setCurrentChat(chat);
+      setConnectionState('connected');
+      markCurrentChatRead();
+      updatePresenceSubscriptions(chatList, userId, false);
+    },
+    (error) => {
+      console.error('Failed to subscribe to chat:', error);
+      setConnectionState('error');
+    }
+  );
+}
+
+// Track delivery of our own outgoing messages
+function listenForOutbox(userId: string) {
+  onOutboxState((event) => {
+    if (event.withoutKeys) {
+      console.warn(`Message ${event.id} was not encrypted for members without keys:`, event.withoutKeys);
+    }
+    if (event.chatId !== currentChatId()) return;
+    if (event.state === 'queued') {
+      refreshOutgoing(event.chatId);
+    } else if (event.state === 'discarded') {
+      setOutgoing((prev) => prev.filter((entry) => entry.id !== event.id));
+    } else {
+      setOutgoing((prev) =>
+        prev.map((entry) =>
+          entry.id === event.id ? { ...entry, state: event.state, lastError: event.error } : entry
+        )
+      );
+    }
+  })
+    .then((unlisten) => {
+      if (loggedInUserId === userId) {
+        outboxUnlisten = unlisten;
+      } else {
+        unlisten();
+      }
+    })
+    .catch((error) => console.error('Failed to listen for outbox updates:', error));
+}
+
+// Mark the open chat read, as long as the user can actually see it
+function markCurrentChatRead() {
+  const chat = currentChat();
+  if (!chat || document.hidden || !document.hasFocus()) return;
+  const upTo = typeof chat.updatedAt === 'number' ? chat.updatedAt : chat.updatedAt.getTime();
+  markChatRead(chat.id, upTo).catch((error) => console.error('Failed to mark chat read:', error));
+}
+
+// Write the latest chat list to the local cache (best effort)
+function persistChats(chatList: Chat[]) {
+  cacheChats(chatList).catch((error) => console.error('Failed to cache chats:', error));
+}
+
+// Subscribe to presence for other users in chats, notifying when they come
+// online or go offline unless `notify` is false
+function updatePresenceSubscriptions(
+  chatList: Chat[],
+  currentUserIdParam: string,
+  notify = true
+) {
   const otherUserIds = new Set<string>();
 
   chatList.forEach((chat) => {
@@ -216,19 +287,19 @@ function updatePresenceSubscriptions(chatList: Chat[], currentUserIdParam: strin
           if (isInitialLoad) {
             // First time seeing this user - just store state, don't notify
             presenceInitialStates.add(odId);
-          } else if (prevUser !== undefined) {
+          } else if (notify && prevUser !== undefined) {
             // We have previous state - check for changes
             const wasOnline = prevUser.isOnline === true;
             const isNowOnline = user.isOnline === true;
 
             // Check if user just came online (was offline before)
             if (!wasOnline && isNowOnline) {
-              notifyUserOnline(userName);
+              notifyUserOnline(odId, userName);
             }
 
             // Check if user just went offline (was online before)
             if (wasOnline && !isNowOnline) {
-              notifyUserOffline(userName);
+              notifyUserOffline(odId, userName);
             }
           }
 
@@ -240,34 +311,79 @@ function updatePresenceSubscriptions(chatList: Chat[], currentUserIdParam: strin
   });
 }
 
+// Reload undelivered messages of a chat from the outbox
+function refreshOutgoing(chatId: string) {
+  listOutbox(chatId)
+    .then((entries) => {
+      if (currentChatId() !== chatId) return;
+      // Keep entries that were just sent until the live listener delivers them
+      setOutgoing((prev) => [
+        ...prev.filter((entry) => entry.state === 'sent' && !entries.some((e) => e.id === entry.id)),
+        ...entries,
+      ]);
+    })
+    .catch((error) => console.error('Failed to load outbox:', error));
+}
+
 export function cleanupChatsListener() {
   if (chatsUnsubscribe) {
     chatsUnsubscribe();
     chatsUnsubscribe = null;
   }
+  if (outboxUnlisten) {
+    outboxUnlisten();
+    outboxUnlisten = null;
+  }
+  if (unreadUnlisten) {
+    unreadUnlisten();
+    unreadUnlisten = null;
+  }
+  if (notifiedUnlisten) {
+    notifiedUnlisten();
+    notifiedUnlisten = null;
+  }
+  window.removeEventListener('focus', markCurrentChatRead);
+  setUnreadChatIds([]);
   // Cleanup presence subscriptions
   presenceUnsubscribes.forEach((unsub) => unsub());
   presenceUnsubscribes.clear();
   presenceInitialStates.clear();
   setOtherUserPresence({});
   loggedInUserId = null;
-  isInitialChatsLoad = true;
-  notifiedMessageTimestamps.clear();
   // Reset notification state so it re-initializes on next login
   resetNotifications();
+  setOpenChat(null);
 }
 
 // Subscribe to messages in a specific chat
 export function selectChat(chatId: string) {
   cleanupMessagesListener();
   setCurrentChatId(chatId);
+  setOpenChat(chatId);
   setLoadingMessages(true);
 
   // Set current chat
   const chat = chats().find((c) => c.id === chatId);
   setCurrentChat(chat || null);
+  markCurrentChatRead();
+
+  setOutgoing([]);
+  refreshOutgoing(chatId);
+  reloadArchivedMessages(chatId);
+
+  // Show cached history instantly; the live subscription replaces it once it fires
+  let receivedLive = false;
+  getCachedMessages(chatId)
+    .then((cached) => {
+      if (!receivedLive && currentChatId() === chatId && cached.length > 0) {
+        setMessages(cached);
+        setLoadingMessages(false);
+      }
+    })
+    .catch((error) => console.error('Failed to load cached messages:', error));
 
   messagesUnsubscribe = subscribeToMessages(chatId, (newMessages) => {
+    receivedLive = true;
     setMessages(newMessages);
     setLoadingMessages(false);
   });
@@ -279,11 +395,23 @@ export function cleanupMessagesListener() {
     messagesUnsubscribe = null;
   }
   setMessages([]);
+  setArchived([]);
+  setOutgoing([]);
+}
+
+// Load history imported from other messengers, e.g. after an import finished
+export function reloadArchivedMessages(chatId: string) {
+  getArchivedMessages(chatId)
+    .then((loaded) => {
+      if (currentChatId() === chatId) setArchived(loaded);
+    })
+    .catch((error) => console.error('Failed to load imported messages:', error));
 }
 
 export function clearCurrentChat() {
   cleanupMessagesListener();
   setCurrentChatId(null);
+  setOpenChat(null);
   setCurrentChat(null);
 }
 
@@ -291,10 +419,11 @@ export {
   chats,
   currentChatId,
   currentChat,
-  messages,
+  chatMessages as messages,
   connectionState,
   loadingMessages,
   otherUserPresence,
+  unreadChatIds,
   derivedContacts,
 };
 
@@ -302,6 +431,28 @@ import { createMemo } from 'solid-js';
 import { user } from './auth';
 import type { Contact } from '../types';
 
+// Messages of the current chat merged with its imported history, followed by
+// our own messages still in the outbox
+const chatMessages = createMemo((): Message[] => {
+  const imported = archived();
+  const live =
+    imported.length > 0
+      ? [...imported, ...messages()].sort((a, b) => Number(a.timestamp) - Number(b.timestamp))
+      : messages();
+  const liveIds = new Set(live.map((m) => m.id));
+  const pending = outgoing()
+    .filter((entry) => !liveIds.has(entry.id))
+    .map((entry) => ({
+      id: entry.id,
+      senderId: entry.senderId,
+      senderName: entry.senderName,
+      text: entry.text,
+      timestamp: entry.createdAt,
+      deliveryState: entry.state,
+    }));
+  return pending.length > 0 ? [...live, ...pending] : live;
+});
+
 // Derived unique contacts from existing chats
 const derivedContacts = createMemo(() => {
   const currentUser = user();
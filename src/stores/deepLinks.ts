@@ -0,0 +1,98 @@
+// Deep links store - opens `chitchat://` links, holding them back until a
+// user is signed in
+// Wrapped in createRoot to ensure proper signal disposal
+import { createSignal, createRoot, createEffect, on } from 'solid-js';
+import type { UnlistenFn } from '@tauri-apps/api/event';
+import type { DeepLink } from '../types';
+import type { AuthUser } from '../services/auth';
+import { onDeepLink } from '../services/deepLinks';
+import {
+  addGroupMember,
+  createChat,
+  findUserByEmail,
+  getGroupInvite,
+  isChatParticipant,
+} from '../services/messages';
+import { user } from './auth';
+import { selectChat } from './chats';
+import { ERROR_MESSAGES, UI_LABELS } from '../constants/messages';
+
+const [pending, setPending] = createRoot(() => createSignal<DeepLink[]>([]));
+
+let unlisten: UnlistenFn | null = null;
+
+export async function initDeepLinks() {
+  if (unlisten) return; // Already initialized
+  unlisten = await onDeepLink((link) => setPending((links) => [...links, link]));
+}
+
+export function cleanupDeepLinks() {
+  if (unlisten) {
+    unlisten();
+    unlisten = null;
+  }
+}
+
+// Open links once a user is signed in, in the order they arrived
+createRoot(() => {
+  createEffect(
+    on([user, pending], async ([currentUser, links]) => {
+      if (!currentUser || links.length === 0) return;
+      setPending([]);
+      for (const link of links) {
+        try {
+          await openLink(link, currentUser);
+        } catch (err) {
+          console.error('Failed to open link:', err);
+          alert(ERROR_MESSAGES.OPEN_LINK_FAILED);
+        }
+      }
+    })
+  );
+});
+
+async function openLink(link: DeepLink, currentUser: AuthUser) {
+  const currentUserName = currentUser.displayName || currentUser.email || UI_LABELS.UNKNOWN_USER;
+  switch (link.kind) {
+    case 'chat': {
+      if (!(await isChatParticipant(link.id, currentUser.uid))) {
+        alert(ERROR_MESSAGES.CHAT_NOT_FOUND);
+        return;
+      }
+      selectChat(link.id);
+      return;
+    }
+    case 'user': {
+      if (link.email === currentUser.email?.toLowerCase()) {
+        alert(ERROR_MESSAGES.SELF_CHAT);
+        return;
+      }
+      const targetUser = await findUserByEmail(link.email);
+      if (!targetUser) {
+        alert(ERROR_MESSAGES.USER_NOT_FOUND);
+        return;
+      }
+      const chatId = await createChat(
+        currentUser.uid,
+        targetUser.id,
+        currentUserName,
+        targetUser.displayName || targetUser.email || UI_LABELS.UNKNOWN_USER
+      );
+      selectChat(chatId);
+      return;
+    }
+    case 'invite': {
+      const invite = await getGroupInvite(link.token);
+      if (!invite) {
+        alert(ERROR_MESSAGES.INVITE_NOT_FOUND);
+        return;
+      }
+      if (!(await isChatParticipant(invite.chatId, currentUser.uid))) {
+        if (!confirm(`Join the group "${invite.groupName}"?`)) return;
+        await addGroupMember(invite.chatId, currentUser.uid, currentUserName);
+      }
+      selectChat(invite.chatId);
+      return;
+    }
+  }
+}
@@ -0,0 +1,84 @@
+// Settings store - reactive copy of the preferences kept by the Rust core
+import { createRoot, createSignal } from 'solid-js';
+import {
+  getSettings,
+  onSettingsChanged,
+  updateSettings,
+  type SettingsChanges,
+} from '../services/settings';
+import type { Settings } from '../types';
+
+// Shown until the stored settings have loaded; the same as the core's defaults
+const DEFAULT_SETTINGS: Settings = {
+  theme: 'system',
+  sounds: { enabled: true, volume: 0.5 },
+  notifications: {
+    messages: true,
+    presence: true,
+    mutedChats: [],
+    quietHours: [],
+    mentions: true,
+    keywords: [],
+    mutedUntil: null,
+  },
+};
+
+// Where the theme was kept before settings moved to the core
+const LEGACY_THEME_KEY = 'chitchat-theme';
+
+const [settings, setSettings] = createRoot(() => createSignal<Settings>(DEFAULT_SETTINGS));
+
+let initialized = false;
+
+/**
+ * Load the stored settings and follow changes made in other windows.
+ */
+export async function initSettings(): Promise<void> {
+  if (initialized) return;
+  initialized = true;
+
+  onSettingsChanged(setSettings).catch((error) =>
+    console.error('Failed to listen for settings changes:', error)
+  );
+
+  try {
+    setSettings(await getSettings());
+    await importLegacyTheme();
+  } catch (error) {
+    console.error('Failed to load settings:', error);
+  }
+}
+
+async function importLegacyTheme(): Promise<void> {
+  const theme = localStorage.getItem(LEGACY_THEME_KEY);
+  if (theme === null) return;
+  localStorage.removeItem(LEGACY_THEME_KEY);
+  if (theme === 'light' || theme === 'dark') {
+    await changeSettings({ theme });
+  }
+}
+
+function merge(current: Settings, changes: SettingsChanges): Settings {
+  return {
+    theme: changes.theme ?? current.theme,
+    sounds: { ...current.sounds, ...changes.sounds },
+    notifications: { ...current.notifications, ...changes.notifications },
+  };
+}
+
+/**
+ * Change some settings. The change shows at once and is undone if it cannot
+ * be saved.
+ */
+export async function changeSettings(changes: SettingsChanges): Promise<void> {
+  const previous = settings();
+  setSettings(merge(previous, changes));
+  try {
+    setSettings(await updateSettings(changes));
+  } catch (error) {
+    console.error('Failed to save settings:', error);
+    setSettings(previous);
+  }
+}
+
+export { settings };
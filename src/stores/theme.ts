@@ -1,41 +1,28 @@
-// Theme store - reactive state for dark/light mode
+// Theme store - reactive state for dark/light mode, kept in the settings
 // Wrapped in createRoot to ensure proper signal disposal
-import { createSignal, createEffect, createRoot, on } from 'solid-js';
+import { createEffect, createRoot, on } from 'solid-js';
+import { settings, changeSettings } from './settings';
+import type { Theme } from '../types';
 
-type Theme = 'light' | 'dark' | 'system';
+const theme = (): Theme => settings().theme;
 
-// Create signals within a root to ensure proper lifecycle management
-const store = createRoot(() => {
-  // Get initial theme from localStorage or default to 'system'
-  const getInitialTheme = (): Theme => {
-    if (typeof window === 'undefined') return 'system';
-    const stored = localStorage.getItem('chitchat-theme') as Theme | null;
-    return stored || 'system';
-  };
-
-  const [theme, setTheme] = createSignal<Theme>(getInitialTheme());
+const setThemeInternal = (newTheme: Theme) => {
+  changeSettings({ theme: newTheme });
+};
 
-  // Computed: is dark mode actually active?
-  const isDark = (): boolean => {
-    const currentTheme = theme();
-    if (currentTheme === 'dark') return true;
-    if (currentTheme === 'light') return false;
-    // System preference
-    return window.matchMedia('(prefers-color-scheme: dark)').matches;
-  };
+// Computed: is dark mode actually active?
+const isDark = (): boolean => {
+  const currentTheme = theme();
+  if (currentTheme === 'dark') return true;
+  if (currentTheme === 'light') return false;
+  // System preference
+  return window.matchMedia('(prefers-color-scheme: dark)').matches;
+};
 
-  return { theme, setTheme, isDark };
-});
-
-const { theme, setTheme: setThemeInternal, isDark } = store;
-
-// Apply theme to document and persist to localStorage
+// Apply theme to document
 createRoot(() => {
   createEffect(
     on(theme, (currentTheme) => {
-      // Persist to localStorage
-      localStorage.setItem('chitchat-theme', currentTheme);
-
       // Apply dark class to document
       const dark =
         currentTheme === 'dark' ||
@@ -1,12 +1,55 @@
 // TypeScript interfaces for the application
+//
+// Types exchanged with the Rust core are generated from `src-tauri/src/models.rs`
+// into `src/bindings/` on every Rust build; never edit those by hand.
+import type { Message as MessageBinding } from '../bindings/Message';
+import type { DeliveryState } from '../bindings/DeliveryState';
 
-export interface User {
-  id: string;
-  email: string;
-  displayName: string;
-  createdAt: Date | number;
-  isOnline?: boolean;
-  lastSeen?: Date | number;
+export type { User } from '../bindings/User';
+export type { UserStatus } from '../bindings/UserStatus';
+export type { Chat } from '../bindings/Chat';
+export type { IncomingMessage } from '../bindings/IncomingMessage';
+export type { DeliveryState } from '../bindings/DeliveryState';
+export type { OutboxEntry } from '../bindings/OutboxEntry';
+export type { OutboxStateEvent } from '../bindings/OutboxStateEvent';
+export type { LiveUpdate } from '../bindings/LiveUpdate';
+export type { SnippetPart } from '../bindings/SnippetPart';
+export type { SearchHit } from '../bindings/SearchHit';
+export type { UnreadSummary } from '../bindings/UnreadSummary';
+export type { ExportFormat } from '../bindings/ExportFormat';
+export type { ExportSummary } from '../bindings/ExportSummary';
+export type { ImportSource } from '../bindings/ImportSource';
+export type { ImportSender } from '../bindings/ImportSender';
+export type { ImportReport } from '../bindings/ImportReport';
+export type { Attachment } from '../bindings/Attachment';
+export type { ChosenFile } from '../bindings/ChosenFile';
+export type { TransferDirection } from '../bindings/TransferDirection';
+export type { AttachmentProgress } from '../bindings/AttachmentProgress';
+export type { ImageInfo } from '../bindings/ImageInfo';
+export type { ImageOptions } from '../bindings/ImageOptions';
+export type { PreparedImage } from '../bindings/PreparedImage';
+export type { MediaCacheUsage } from '../bindings/MediaCacheUsage';
+export type { LogLevel } from '../bindings/LogLevel';
+export type { LogEntry } from '../bindings/LogEntry';
+export type { LogFilter } from '../bindings/LogFilter';
+export type { ConnectionSource } from '../bindings/ConnectionSource';
+export type { Settings } from '../bindings/Settings';
+export type { Theme } from '../bindings/Theme';
+export type { SoundSettings } from '../bindings/SoundSettings';
+export type { NotificationSettings } from '../bindings/NotificationSettings';
+export type { ChatMute } from '../bindings/ChatMute';
+export type { QuietHours } from '../bindings/QuietHours';
+export type { Weekday } from '../bindings/Weekday';
+export type { NotificationOutcome } from '../bindings/NotificationOutcome';
+export type { ProfileInfo } from '../bindings/ProfileInfo';
+export type { BackendConfig } from '../bindings/BackendConfig';
+export type { DeepLink } from '../bindings/DeepLink';
+export type { CommandError } from '../bindings/CommandError';
+export type { ErrorKind } from '../bindings/ErrorKind';
+
+export interface Message extends MessageBinding {
+  /** Set on own messages that are still in the outbox */
+  deliveryState?: DeliveryState;
 }
 
 export interface Contact {
@@ -15,27 +58,3 @@ export interface Contact {
   displayName: string;
   photoURL?: string | null;
 }
-
-export interface Chat {
-  id: string;
-  participants: Record<string, boolean>; // { odId1: true, odId2: true } for RTDB rules
-  participantNames?: Record<string, string>; // { odId1: "Name1", odId2: "Name2" }
-  /** Whether this is a group chat */
-  isGroup?: boolean;
-  /** Name of the group (required if isGroup is true) */
-  groupName?: string;
-  /** ID of the group owner (who can manage members) */
-  ownerId?: string;
-  lastMessage: string;
-  lastMessageSenderId?: string; // ID of user who sent the last message
-  updatedAt: Date | number;
-  typing?: Record<string, boolean>; // { odId: true/false }
-}
-
-export interface Message {
-  id: string;
-  senderId: string;
-  senderName?: string;
-  text: string;
-  timestamp: Date | number;
-}
@@ -12,3 +12,10 @@ interface ImportMetaEnv {
 interface ImportMeta {
   readonly env: ImportMetaEnv;
 }
+
+interface Window {
+  /** Set by the Rust core before the page loads; see `services/profiles.ts` */
+  readonly __CHITCHAT_PROFILE__?: import('./bindings/ProfileInfo').ProfileInfo;
+  /** Set in pop-out chat windows to the chat they show; see `services/windows.ts` */
+  readonly __CHITCHAT_CHAT__?: string;
+}